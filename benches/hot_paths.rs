@@ -0,0 +1,84 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use loglog::{
+    fields::{json_fields, parse_fields},
+    search::{fuzzy_rank, Cancellation},
+    shared::LogEvent,
+    table::Table,
+};
+use ratatui::{buffer::Buffer, layout::Rect, widgets::Widget};
+
+fn group_names(count: usize) -> Vec<String> {
+    let kinds = ["lambda", "ecs", "apigateway", "rds", "vendedlogs"];
+    (0..count)
+        .map(|i| {
+            format!(
+                "/aws/{}/service-{}/handler-{}",
+                kinds[i % kinds.len()],
+                i / 7,
+                i
+            )
+        })
+        .collect()
+}
+
+fn json_events(count: usize) -> Vec<LogEvent> {
+    (0..count)
+        .map(|i| LogEvent {
+            timestamp: i as i64 * 1000,
+            message: format!(
+                r#"{{"level":"info","requestId":"req-{}","path":"/orders/{}","duration":{}.5,"status":200}}"#,
+                i,
+                i % 97,
+                i % 300
+            )
+            .into(),
+            log_group: "/aws/lambda/checkout".into(),
+            log_stream: "2024/01/01/[$LATEST]abc".into(),
+            ..Default::default()
+        })
+        .collect()
+}
+
+fn fuzzy_search(c: &mut Criterion) {
+    let names = group_names(50_000);
+    let cancellation = Cancellation::never();
+    c.bench_function("fuzzy search over 50k group names", |b| {
+        b.iter(|| {
+            fuzzy_rank(
+                black_box("lambsvc12hand"),
+                names.iter().map(String::as_str),
+                &cancellation,
+            )
+        })
+    });
+}
+
+fn table_render(c: &mut Criterion) {
+    let table = Table::new(json_events(100_000));
+    let area = Rect::new(0, 0, 200, 50);
+    let mut buf = Buffer::empty(area);
+    c.bench_function("render a table of 100k rows", |b| {
+        b.iter(|| {
+            buf.reset();
+            (&table).render(area, &mut buf);
+        })
+    });
+}
+
+fn json_parsing(c: &mut Criterion) {
+    let events = json_events(10_000);
+    c.bench_function("parse the fields of 10k json events", |b| {
+        b.iter(|| {
+            events
+                .iter()
+                .map(|event| parse_fields(black_box(&event.message)).len())
+                .sum::<usize>()
+        })
+    });
+    c.bench_function("collect the json fields of 10k events", |b| {
+        b.iter(|| json_fields(events.iter().cloned()))
+    });
+}
+
+criterion_group!(benches, fuzzy_search, table_render, json_parsing);
+criterion_main!(benches);
@@ -0,0 +1,165 @@
+use std::sync::Arc;
+
+use crossterm::event::{Event, KeyEventKind};
+use ratatui::{buffer::Buffer, layout::Rect, widgets::Widget};
+use tokio::sync::mpsc;
+
+use crate::{
+    backend::LogBackend,
+    keymap::{Action, Context, Keymap},
+    popup::ListPopup,
+    shared::{Account, LoadingState},
+    tasks::{TaskKey, TaskSupervisor},
+    theme::Theme,
+};
+
+pub enum AccountsOutboundMessage {
+    SetAccounts(Result<Vec<Account>, String>),
+    Switched(Account, Result<(), String>),
+}
+
+/// A popup listing the profiles and SSO account roles the credentials can be switched to
+#[derive(Debug)]
+pub struct AccountsComponent {
+    accounts: Vec<Account>,
+    loading_state: LoadingState,
+    selected: usize,
+    /// the account switched to last, marked in the list
+    current: Option<Account>,
+    is_open: bool,
+    pub keymap: Arc<Keymap>,
+    pub theme: Theme,
+    accounts_tx: mpsc::UnboundedSender<AccountsOutboundMessage>,
+    backend: Arc<dyn LogBackend>,
+    tasks: TaskSupervisor,
+}
+
+impl AccountsComponent {
+    pub fn new(
+        accounts_tx: mpsc::UnboundedSender<AccountsOutboundMessage>,
+        backend: Arc<dyn LogBackend>,
+        tasks: TaskSupervisor,
+    ) -> Self {
+        Self {
+            accounts: vec![],
+            loading_state: LoadingState::Idle,
+            selected: 0,
+            current: None,
+            is_open: false,
+            keymap: Arc::default(),
+            theme: Theme::default(),
+            accounts_tx,
+            backend,
+            tasks,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    /// Shows the popup and lists the accounts again, SSO logins may have changed since
+    pub fn open(&mut self) {
+        self.is_open = true;
+        self.loading_state = LoadingState::Loading;
+        let backend = self.backend.clone();
+        let accounts_tx = self.accounts_tx.clone();
+        self.tasks.spawn(TaskKey::Accounts, async move {
+            let result = backend.fetch_accounts().await;
+            let _ = accounts_tx.send(AccountsOutboundMessage::SetAccounts(result));
+        });
+    }
+
+    pub fn close(&mut self) {
+        self.is_open = false;
+    }
+
+    pub fn set_accounts(&mut self, result: Result<Vec<Account>, String>) {
+        match result {
+            Ok(accounts) => {
+                self.selected = self
+                    .current
+                    .as_ref()
+                    .and_then(|current| accounts.iter().position(|account| account == current))
+                    .unwrap_or_default();
+                self.accounts = accounts;
+                self.loading_state = LoadingState::Loaded;
+            }
+            Err(e) => self.loading_state = LoadingState::Error(e),
+        }
+    }
+
+    /// Marks `account` as the one in use once the backend has switched to it
+    pub fn switched(&mut self, account: Account) {
+        self.current = Some(account);
+        self.is_open = false;
+    }
+
+    fn switch_to_selected(&self) {
+        let Some(account) = self.accounts.get(self.selected).cloned() else {
+            return;
+        };
+        let backend = self.backend.clone();
+        let accounts_tx = self.accounts_tx.clone();
+        self.tasks.spawn(TaskKey::SwitchAccount, async move {
+            let result = backend.switch_account(account.clone()).await;
+            let _ = accounts_tx.send(AccountsOutboundMessage::Switched(account, result));
+        });
+    }
+
+    /// Takes every key while open
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        if !self.is_open {
+            return false;
+        }
+        let Event::Key(key) = event else {
+            return true;
+        };
+        if key.kind != KeyEventKind::Press {
+            return true;
+        }
+        match (
+            self.keymap.action(Context::Groups, key),
+            self.keymap.action(Context::Global, key),
+        ) {
+            (Some(Action::ScrollDown), _) => {
+                self.selected = (self.selected + 1).min(self.accounts.len().saturating_sub(1));
+            }
+            (Some(Action::ScrollUp), _) => self.selected = self.selected.saturating_sub(1),
+            (Some(Action::Open), _) => self.switch_to_selected(),
+            (_, Some(Action::Back | Action::Accounts | Action::Quit)) => self.close(),
+            _ => {}
+        }
+        true
+    }
+}
+
+impl Widget for &AccountsComponent {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let entries = self
+            .accounts
+            .iter()
+            .map(|account| {
+                if self.current.as_ref() == Some(account) {
+                    format!("{} (current)", account.summary())
+                } else {
+                    account.summary()
+                }
+            })
+            .collect::<Vec<_>>();
+        let empty = match &self.loading_state {
+            LoadingState::Error(e) => e.as_str(),
+            LoadingState::Loaded => "no profiles or SSO sessions in the AWS config",
+            _ => "listing accounts…",
+        };
+        ListPopup {
+            title: "Accounts",
+            entries: &entries,
+            selected: self.selected,
+            empty,
+            hint: "enter to switch, esc to close",
+            theme: &self.theme,
+        }
+        .render(area, buf);
+    }
+}
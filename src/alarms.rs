@@ -0,0 +1,258 @@
+use std::sync::Arc;
+
+use crossterm::event::Event;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    style::Style,
+    text::Line,
+    widgets::{Block, HighlightSpacing, Paragraph, Row, StatefulWidget, Table, TableState, Widget},
+};
+use tokio::sync::mpsc;
+
+use crate::{
+    backend::LogBackend,
+    keymap::{Action, Context, Keymap},
+    shared::{format_elapsed, Alarm, LoadingState},
+    tasks::{TaskKey, TaskSupervisor},
+    theme::Theme,
+};
+
+/// Logs are opened from this long before an alarm went off
+const BEFORE_ALARM_MS: i64 = 30 * 60 * 1000;
+
+/// to this long after it, or now when that's sooner
+const AFTER_ALARM_MS: i64 = 15 * 60 * 1000;
+
+pub enum AlarmsOutboundMessage {
+    SetAlarms(Result<Vec<Alarm>, String>),
+    /// the groups whose metric filters publish the alarm's metric
+    OpenLogs(Alarm, Result<Vec<String>, String>),
+}
+
+/// Alarms in the ALARM state, opening one shows the logs behind its metric around when it fired
+#[derive(Debug)]
+pub struct AlarmsComponent {
+    alarms: Vec<Alarm>,
+    loading_state: LoadingState,
+    table_state: TableState,
+    /// the alarm to highlight once they've loaded, listed even when it isn't going off
+    focus: Option<Alarm>,
+    /// loads alarms from every region instead of the configured one
+    pub all_regions: bool,
+    pub keymap: Arc<Keymap>,
+    pub theme: Theme,
+    alarms_tx: mpsc::UnboundedSender<AlarmsOutboundMessage>,
+    backend: Arc<dyn LogBackend>,
+    tasks: TaskSupervisor,
+}
+
+impl AlarmsComponent {
+    pub fn new(
+        alarms_tx: mpsc::UnboundedSender<AlarmsOutboundMessage>,
+        backend: Arc<dyn LogBackend>,
+        tasks: TaskSupervisor,
+    ) -> Self {
+        Self {
+            alarms: vec![],
+            loading_state: LoadingState::Idle,
+            table_state: TableState::default(),
+            focus: None,
+            all_regions: false,
+            keymap: Arc::default(),
+            theme: Theme::default(),
+            alarms_tx,
+            backend,
+            tasks,
+        }
+    }
+
+    pub fn run(&mut self) {
+        self.loading_state = LoadingState::Loading;
+        let regions = if self.all_regions {
+            self.backend.regions().into_iter().map(Some).collect()
+        } else {
+            vec![None]
+        };
+        let backend = self.backend.clone();
+        let alarms_tx = self.alarms_tx.clone();
+        self.tasks.spawn(TaskKey::Alarms, async move {
+            let results = futures::future::join_all(
+                regions
+                    .into_iter()
+                    .map(|region| backend.fetch_alarms(region)),
+            )
+            .await;
+            // regions that aren't enabled fail, only report an error when every region did
+            let mut alarms = vec![];
+            let mut error = None;
+            for result in results {
+                match result {
+                    Ok(more) => alarms.extend(more),
+                    Err(e) => error = error.or(Some(e)),
+                }
+            }
+            let result = match error {
+                Some(e) if alarms.is_empty() => Err(e),
+                _ => {
+                    // the most recent first, it's most likely the one being looked into
+                    alarms.sort_by_key(|alarm: &Alarm| std::cmp::Reverse(alarm.state_updated));
+                    Ok(alarms)
+                }
+            };
+            let _ = alarms_tx.send(AlarmsOutboundMessage::SetAlarms(result));
+        });
+    }
+
+    pub fn loading_state(&self) -> &LoadingState {
+        &self.loading_state
+    }
+
+    pub fn set_alarms(&mut self, result: Result<Vec<Alarm>, String>) {
+        match result {
+            Ok(alarms) => {
+                self.alarms = alarms;
+                self.loading_state = LoadingState::Loaded;
+                let focused = self.focus.take().map(|focus| {
+                    let same =
+                        |alarm: &Alarm| alarm.name == focus.name && alarm.region == focus.region;
+                    self.alarms.iter().position(same).unwrap_or_else(|| {
+                        self.alarms.insert(0, focus);
+                        0
+                    })
+                });
+                self.table_state
+                    .select(focused.or((!self.alarms.is_empty()).then_some(0)));
+            }
+            Err(e) => self.loading_state = LoadingState::Error(e),
+        }
+    }
+
+    /// Highlights `alarm` the next time the alarms load
+    pub fn focus(&mut self, alarm: Alarm) {
+        self.focus = Some(alarm);
+    }
+
+    /// Looks up the groups behind the selected alarm's metric, the app opens them once known
+    fn open_selected(&self) {
+        let Some(alarm) = self
+            .table_state
+            .selected()
+            .and_then(|selected| self.alarms.get(selected))
+            .cloned()
+        else {
+            return;
+        };
+        let (Some(metric_name), Some(namespace)) =
+            (alarm.metric_name.clone(), alarm.namespace.clone())
+        else {
+            let result = Err(format!("{} doesn't watch a single metric", alarm.name));
+            let _ = self
+                .alarms_tx
+                .send(AlarmsOutboundMessage::OpenLogs(alarm, result));
+            return;
+        };
+        let backend = self.backend.clone();
+        let alarms_tx = self.alarms_tx.clone();
+        self.tasks.spawn(TaskKey::AlarmGroups, async move {
+            let result = backend
+                .fetch_metric_filter_groups(alarm.region.clone(), metric_name, namespace)
+                .await;
+            let _ = alarms_tx.send(AlarmsOutboundMessage::OpenLogs(alarm, result));
+        });
+    }
+
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        let Event::Key(key) = event else {
+            return false;
+        };
+        let last = self.alarms.len().saturating_sub(1);
+        let selected = self.table_state.selected().unwrap_or_default();
+        match self.keymap.action(Context::Alarms, key) {
+            Some(Action::ScrollUp) => self.table_state.select(Some(selected.saturating_sub(1))),
+            Some(Action::ScrollDown) => self.table_state.select(Some((selected + 1).min(last))),
+            Some(Action::Open) => self.open_selected(),
+            Some(Action::Reload) if self.loading_state != LoadingState::Loading => self.run(),
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// The window logs are opened with for an alarm that went off at `state_updated`
+pub fn time_range_around(state_updated: i64, now: i64) -> (i64, i64) {
+    (
+        state_updated - BEFORE_ALARM_MS,
+        (state_updated + AFTER_ALARM_MS).min(now),
+    )
+}
+
+impl Widget for &mut AlarmsComponent {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::bordered()
+            .title("Alarms")
+            .title(Line::from(format!("{:?}", self.loading_state)).right_aligned())
+            .title_bottom(
+                Line::from(format!(
+                    "{} to open the logs, {}",
+                    self.keymap.keys(Context::Alarms, Action::Open),
+                    self.keymap.hint()
+                ))
+                .right_aligned(),
+            );
+        if self.alarms.is_empty() {
+            let message = match &self.loading_state {
+                LoadingState::Error(e) => {
+                    Line::styled(e.clone(), Style::new().fg(self.theme.error))
+                }
+                LoadingState::Loaded => {
+                    Line::styled("no alarms are going off", Style::new().fg(self.theme.muted))
+                }
+                _ => Line::from(""),
+            };
+            Paragraph::new(message)
+                .centered()
+                .block(block)
+                .render(area, buf);
+            return;
+        }
+        let now = chrono::Utc::now().timestamp_millis();
+        let rows = self.alarms.iter().map(|alarm| {
+            let metric = match (&alarm.namespace, &alarm.metric_name) {
+                (Some(namespace), Some(metric_name)) => format!("{}/{}", namespace, metric_name),
+                _ => String::new(),
+            };
+            let since = std::time::Duration::from_millis((now - alarm.state_updated).max(0) as u64);
+            let name = if alarm.is_going_off() {
+                alarm.name.clone()
+            } else {
+                format!("{} ({})", alarm.name, alarm.state.to_lowercase())
+            };
+            Row::new(vec![
+                Line::from(name),
+                Line::styled(metric, Style::new().fg(self.theme.muted)),
+                Line::styled(
+                    format!("{} ago", format_elapsed(since)),
+                    Style::new().fg(if alarm.is_going_off() {
+                        self.theme.error
+                    } else {
+                        self.theme.muted
+                    }),
+                ),
+                Line::styled(alarm.reason.as_str(), Style::new().fg(self.theme.muted)),
+            ])
+        });
+        let widths = [
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+            Constraint::Length(12),
+            Constraint::Fill(1),
+        ];
+        let table = Table::new(rows, widths)
+            .block(block)
+            .highlight_spacing(HighlightSpacing::Always)
+            .highlight_symbol("🚨")
+            .highlight_style(Style::new().fg(self.theme.accent));
+        StatefulWidget::render(table, area, buf, &mut self.table_state);
+    }
+}
@@ -0,0 +1,1625 @@
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use color_eyre::Result;
+use futures::StreamExt;
+
+use crate::accounts::{AccountsComponent, AccountsOutboundMessage};
+use crate::alarms::{time_range_around, AlarmsComponent, AlarmsOutboundMessage};
+use crate::backend::LogBackend;
+use crate::config::{MessageTimestamps, ViewerQueries};
+use crate::input::Input;
+use crate::investigation::{Bookmark, Investigation, QueryRecord};
+use crate::keymap::{Action, Context, Keymap};
+use crate::log_detail::{LogDetailComponent, LogDetailOutboundMessage};
+use crate::log_groups::{LogGroupListComponent, LogGroupSelectionOutboundMessage};
+use crate::log_viewer::{LogViewerOutboundMessage, LogVieweromponent, Pane};
+use crate::logging;
+use crate::popup::{centered_area, ConfirmPopup, DebugPopup, HelpPopup, ListPopup, PromptPopup};
+use crate::shared::{
+    format_bytes, format_count, format_elapsed, is_offline, Account, Alarm, LoadingState, LogEvent,
+    LogGroup, Notification, OFFLINE,
+};
+use crate::state::StateStore;
+use crate::tasks::{TaskKey, TaskStatus, TaskSupervisor};
+use crate::theme::Theme;
+use ratatui::{
+    crossterm::{
+        self,
+        event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind},
+        style::Print,
+        terminal::SetTitle,
+    },
+    layout::{Constraint, Layout, Rect},
+    style::Style,
+    text::{Line, Span},
+    widgets::{Paragraph, Wrap},
+    DefaultTerminal, Frame,
+};
+use regex_lite::Regex;
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+/// Below this the screens can't lay themselves out, a message asking for more room is shown
+const MIN_WIDTH: u16 = 40;
+const MIN_HEIGHT: u16 = 8;
+
+/// Frames slower than this are logged with the time each part took, 60 fps leaves about 16ms
+const FRAME_BUDGET: Duration = Duration::from_millis(16);
+
+/// How often AWS is tried again while offline
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A query left running with the viewer's background key
+#[derive(Debug)]
+struct InboxEntry {
+    /// the main viewer's generation when it was left, its results are tagged with it
+    generation: u64,
+    log_groups: Vec<LogGroup>,
+    started: Instant,
+    /// how long it took and what it returned, `None` while it's running
+    result: Option<(Duration, Result<Vec<LogEvent>, String>)>,
+}
+
+impl InboxEntry {
+    fn summary(&self) -> String {
+        let group_names = self
+            .log_groups
+            .iter()
+            .map(|group| group.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        match &self.result {
+            None => format!(
+                "● {}  running {}",
+                group_names,
+                format_elapsed(self.started.elapsed())
+            ),
+            Some((took, Ok(events))) => format!(
+                "✓ {}  {} events in {}",
+                group_names,
+                format_count(events.len()),
+                format_elapsed(*took)
+            ),
+            Some((_, Err(e))) => format!("✗ {}  {}", group_names, e),
+        }
+    }
+}
+
+/// Times the parts of a frame one after another
+struct Stopwatch {
+    started: Instant,
+    lap: Instant,
+    laps: Vec<(&'static str, Duration)>,
+}
+
+impl Stopwatch {
+    fn start() -> Stopwatch {
+        let now = Instant::now();
+        Stopwatch {
+            started: now,
+            lap: now,
+            laps: vec![],
+        }
+    }
+
+    /// Records the time since the previous lap as `name`'s
+    fn lap(&mut self, name: &'static str) {
+        let now = Instant::now();
+        self.laps.push((name, now - self.lap));
+        self.lap = now;
+    }
+
+    /// The total time and every lap
+    fn stop(self) -> (Duration, Vec<(&'static str, Duration)>) {
+        (self.started.elapsed(), self.laps)
+    }
+}
+
+/// Screens are stacked as the user drills down, Esc pops back to the previous one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Screen {
+    LogGroups,
+    LogViewer,
+    LogDetail,
+    Alarms,
+}
+
+#[derive(Debug)]
+pub struct App {
+    should_quit: bool,
+    /// ask before quitting while queries are still running
+    pub confirm_quit: bool,
+    confirming_quit: bool,
+    keymap: Arc<Keymap>,
+    theme: Theme,
+    /// the `?` overlay listing the keys of the current screen, scrolled this many lines
+    help_scroll: Option<u16>,
+    /// the debug overlay with timings, queues and the newest log lines
+    show_debug: bool,
+    /// how long drawing the previous frame took, and each part of it
+    frame_time: Duration,
+    widget_times: Vec<(&'static str, Duration)>,
+    /// never empty, `Screen::LogGroups` always sits at the bottom
+    screens: Vec<Screen>,
+    log_groups_component: LogGroupListComponent,
+    log_group_selection_rx: mpsc::UnboundedReceiver<LogGroupSelectionOutboundMessage>,
+    log_viewer_component: LogVieweromponent,
+    log_viewer_rx: mpsc::UnboundedReceiver<LogViewerOutboundMessage>,
+    /// a second viewer shown to the right of the main one, opened with `v`
+    split_viewer_component: LogVieweromponent,
+    split_viewer_rx: mpsc::UnboundedReceiver<LogViewerOutboundMessage>,
+    split_open: bool,
+    /// the group list was opened to pick the groups for the split viewer
+    picking_split_groups: bool,
+    focus: Pane,
+    state_store: StateStore,
+    /// the latest notification, shown at the right of the status bar
+    notification: Option<Notification>,
+    /// queries running longer than this raise a desktop notification when they finish
+    notify_after: Option<Duration>,
+    /// notify even when the terminal has focus
+    notify_when_focused: bool,
+    /// whether the terminal has focus, it says when it gains or loses it
+    focused: bool,
+    /// waiting to be sent to the terminal after the next draw
+    desktop_notification: Option<String>,
+    /// queries left running in the background, the newest first
+    inbox: Vec<InboxEntry>,
+    /// the highlighted entry while the inbox is open
+    inbox_selected: Option<usize>,
+    /// the queries run and events bookmarked this session
+    investigation: Investigation,
+    /// where the investigation is exported to
+    export_dir: PathBuf,
+    /// the highlighted bookmark while the bookmarks pane is open
+    bookmarks_selected: Option<usize>,
+    /// the highlighted query while the history is open, counted from the newest
+    history_selected: Option<usize>,
+    /// the note being typed for the bookmark at this index
+    note_input: Option<(usize, Input)>,
+    log_detail_component: LogDetailComponent,
+    log_detail_rx: mpsc::UnboundedReceiver<LogDetailOutboundMessage>,
+    alarms_component: AlarmsComponent,
+    alarms_rx: mpsc::UnboundedReceiver<AlarmsOutboundMessage>,
+    /// the popup switching profiles and SSO accounts, over whichever screen is open
+    accounts_component: AccountsComponent,
+    accounts_rx: mpsc::UnboundedReceiver<AccountsOutboundMessage>,
+    /// a request couldn't reach AWS, it's tried again until one gets through
+    offline: bool,
+    online_tx: mpsc::UnboundedSender<()>,
+    online_rx: mpsc::UnboundedReceiver<()>,
+    tasks: TaskSupervisor,
+    task_status_rx: mpsc::UnboundedReceiver<()>,
+    /// asked for how many Insights queries are queued or running, for the status bar
+    backend: Arc<dyn LogBackend>,
+}
+
+impl App {
+    pub async fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
+        self.start();
+
+        let mut events = EventStream::new();
+        // redraws running queries so their elapsed time keeps counting
+        let mut clock = tokio::time::interval(Duration::from_secs(1));
+
+        let mut window_title = String::new();
+        while !self.should_quit {
+            terminal.draw(|frame| self.draw(frame))?;
+            if self.window_title() != window_title {
+                window_title = self.window_title();
+                crossterm::execute!(terminal.backend_mut(), SetTitle(&window_title))?;
+            }
+            if let Some(message) = self.take_desktop_notification() {
+                // OSC 9, terminals that support it pass it on as a desktop notification
+                let osc = format!("\x1b]9;{}\x07", message.replace(['\x1b', '\x07'], ""));
+                crossterm::execute!(terminal.backend_mut(), Print(osc))?;
+            }
+            tokio::select! {
+                Some(message) = self.log_group_selection_rx.recv() => {
+                    self.handle_log_group_message(message);
+                },
+                Some(message) = self.log_viewer_rx.recv() => {
+                    self.handle_log_viewer_message(Pane::Main, message);
+                },
+                Some(message) = self.split_viewer_rx.recv() => {
+                    self.handle_log_viewer_message(Pane::Split, message);
+                },
+                Some(message) = self.log_detail_rx.recv() => {
+                    self.handle_log_detail_message(message);
+                },
+                Some(message) = self.alarms_rx.recv() => {
+                    self.handle_alarms_message(message);
+                },
+                Some(message) = self.accounts_rx.recv() => {
+                    self.handle_accounts_message(message);
+                },
+                Some(()) = self.online_rx.recv() => self.back_online(),
+                Some(()) = self.task_status_rx.recv() => {},
+                _ = clock.tick(), if self.is_querying() || self.is_fading() => {},
+                Some(Ok(event)) = events.next() => self.handle_event(&event),
+            }
+            self.drain_messages();
+        }
+        Ok(())
+    }
+
+    /// Applies everything already queued so a burst of results costs a single redraw,
+    /// consecutive appends are merged so they're only sorted/searched once
+    fn drain_messages(&mut self) {
+        while self.task_status_rx.try_recv().is_ok() {}
+
+        let mut log_groups = vec![];
+        while let Ok(message) = self.log_group_selection_rx.try_recv() {
+            match message {
+                LogGroupSelectionOutboundMessage::AppendLogGroups(page) => log_groups.extend(page),
+                message => {
+                    if !log_groups.is_empty() {
+                        self.log_groups_component
+                            .append_log_groups(std::mem::take(&mut log_groups));
+                    }
+                    self.handle_log_group_message(message);
+                }
+            }
+        }
+        if !log_groups.is_empty() {
+            self.log_groups_component.append_log_groups(log_groups);
+        }
+
+        self.drain_log_viewer_messages(Pane::Main);
+        self.drain_log_viewer_messages(Pane::Split);
+        while let Ok(message) = self.log_detail_rx.try_recv() {
+            self.handle_log_detail_message(message);
+        }
+        while let Ok(message) = self.alarms_rx.try_recv() {
+            self.handle_alarms_message(message);
+        }
+        while let Ok(message) = self.accounts_rx.try_recv() {
+            self.handle_accounts_message(message);
+        }
+    }
+
+    fn drain_log_viewer_messages(&mut self, pane: Pane) {
+        // batches of the same live tail are appended together
+        let mut appended: Option<(u64, Vec<LogEvent>)> = None;
+        while let Ok(message) = self.log_viewer_rx(pane).try_recv() {
+            match message {
+                LogViewerOutboundMessage::AppendLogs(tail_generation, events) => {
+                    match &mut appended {
+                        Some((batched, log_messages)) if *batched == tail_generation => {
+                            log_messages.extend(events)
+                        }
+                        _ => {
+                            if let Some((batched, log_messages)) =
+                                appended.replace((tail_generation, events))
+                            {
+                                self.log_viewer(pane).append_logs(batched, log_messages);
+                            }
+                        }
+                    }
+                }
+                message => {
+                    if let Some((tail_generation, log_messages)) = appended.take() {
+                        self.log_viewer(pane)
+                            .append_logs(tail_generation, log_messages);
+                    }
+                    self.handle_log_viewer_message(pane, message);
+                }
+            }
+        }
+        if let Some((tail_generation, log_messages)) = appended {
+            self.log_viewer(pane)
+                .append_logs(tail_generation, log_messages);
+        }
+    }
+
+    fn log_viewer(&mut self, pane: Pane) -> &mut LogVieweromponent {
+        match pane {
+            Pane::Main => &mut self.log_viewer_component,
+            Pane::Split => &mut self.split_viewer_component,
+        }
+    }
+
+    fn log_viewer_rx(
+        &mut self,
+        pane: Pane,
+    ) -> &mut mpsc::UnboundedReceiver<LogViewerOutboundMessage> {
+        match pane {
+            Pane::Main => &mut self.log_viewer_rx,
+            Pane::Split => &mut self.split_viewer_rx,
+        }
+    }
+
+    /// Kicks off the initial background work, `run` does this itself
+    pub fn start(&mut self) {
+        self.log_groups_component.run();
+    }
+
+    /// Waits for the next message from a background task and applies it
+    pub async fn handle_next_message(&mut self) {
+        tokio::select! {
+            Some(message) = self.log_group_selection_rx.recv() => {
+                self.handle_log_group_message(message);
+            },
+            Some(message) = self.log_viewer_rx.recv() => {
+                self.handle_log_viewer_message(Pane::Main, message);
+            },
+            Some(message) = self.split_viewer_rx.recv() => {
+                self.handle_log_viewer_message(Pane::Split, message);
+            },
+            Some(message) = self.log_detail_rx.recv() => {
+                self.handle_log_detail_message(message);
+            },
+            Some(message) = self.alarms_rx.recv() => {
+                self.handle_alarms_message(message);
+            },
+            Some(message) = self.accounts_rx.recv() => {
+                self.handle_accounts_message(message);
+            },
+            Some(()) = self.online_rx.recv() => self.back_online(),
+        }
+    }
+
+    /// Lists groups from every region, call before `start`
+    pub fn set_all_regions(&mut self, all_regions: bool) {
+        self.log_groups_component.all_regions = all_regions;
+        self.alarms_component.all_regions = all_regions;
+    }
+
+    /// Leaves groups matching any of `patterns` out of the list, `H` shows them anyway
+    pub fn set_exclude_patterns(&mut self, patterns: Vec<Regex>) {
+        self.log_groups_component.exclude = patterns;
+    }
+
+    /// Replaces the default keys, e.g. with user overrides from the config
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = Arc::new(keymap);
+        self.log_groups_component.keymap = self.keymap.clone();
+        self.log_viewer_component.keymap = self.keymap.clone();
+        self.split_viewer_component.keymap = self.keymap.clone();
+        self.log_detail_component.keymap = self.keymap.clone();
+        self.alarms_component.keymap = self.keymap.clone();
+        self.accounts_component.keymap = self.keymap.clone();
+    }
+
+    /// Colors for the terminal's background, see `theme::detect_background`
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.log_groups_component.theme = theme;
+        self.log_viewer_component.set_theme(theme);
+        self.split_viewer_component.set_theme(theme);
+        self.alarms_component.theme = theme;
+        self.accounts_component.theme = theme;
+    }
+
+    /// Asks before running queries estimated to scan more than `bytes`, 0 never asks
+    pub fn set_confirm_query_bytes(&mut self, bytes: i64) {
+        self.log_viewer_component.confirm_query_bytes = bytes;
+        self.split_viewer_component.confirm_query_bytes = bytes;
+    }
+
+    /// Keeps at most `events` per viewer in memory, older ones go to a temp file, 0 keeps them all
+    pub fn set_memory_events(&mut self, events: usize) {
+        self.log_viewer_component.set_memory_events(events);
+        self.split_viewer_component.set_memory_events(events);
+    }
+
+    /// Runs a per hour error count next to every query, charted above the events
+    pub fn set_error_summary(&mut self, enabled: bool) {
+        self.log_viewer_component.error_summary = enabled;
+        self.split_viewer_component.error_summary = enabled;
+    }
+
+    /// Raises a desktop notification when a query running longer than `after` finishes while
+    /// the terminal is in the background, or also in the foreground when `when_focused`
+    pub fn set_query_notifications(&mut self, after: Option<Duration>, when_focused: bool) {
+        self.notify_after = after;
+        self.notify_when_focused = when_focused;
+    }
+
+    /// The desktop notification to send, once
+    pub fn take_desktop_notification(&mut self) -> Option<String> {
+        self.desktop_notification.take()
+    }
+
+    /// Writes investigation reports to `dir` instead of the working directory
+    pub fn set_export_dir(&mut self, dir: PathBuf) {
+        self.export_dir = dir;
+    }
+
+    /// Insights queries the viewers run instead of the built in one, by group
+    pub fn set_viewer_queries(&mut self, queries: ViewerQueries) {
+        self.log_viewer_component.queries = queries.clone();
+        self.split_viewer_component.queries = queries;
+    }
+
+    /// Where in each group's messages the time they were logged at is, by group
+    pub fn set_message_timestamps(&mut self, message_timestamps: MessageTimestamps) {
+        self.log_viewer_component
+            .set_message_timestamps(message_timestamps.clone());
+        self.split_viewer_component
+            .set_message_timestamps(message_timestamps.clone());
+        self.log_detail_component.message_timestamps = message_timestamps;
+    }
+
+    /// Remembers the last opened groups in `state_store` and preselects them, call before `start`.
+    /// With `open_last` they're opened right away
+    pub fn set_state_store(&mut self, state_store: StateStore, open_last: bool) {
+        let state = state_store.load();
+        self.log_groups_component.preselect = state.last_log_groups;
+        self.log_groups_component.hidden_log_groups = state.hidden_log_groups;
+        self.log_groups_component.open_preselected = open_last;
+        self.state_store = state_store;
+    }
+
+    fn remember_log_groups(&self, groups: &[LogGroup]) {
+        let mut state = self.state_store.load();
+        state.last_log_groups = groups.iter().map(|group| group.name.clone()).collect();
+        // losing this only costs a preselection next time, not worth interrupting for
+        let _ = self.state_store.save(&state);
+    }
+
+    fn remember_hidden_groups(&self, hidden_log_groups: Vec<String>) {
+        let mut state = self.state_store.load();
+        state.hidden_log_groups = hidden_log_groups;
+        let _ = self.state_store.save(&state);
+    }
+
+    fn current_context(&self) -> Context {
+        match self.current_screen() {
+            Screen::LogGroups => Context::Groups,
+            Screen::LogViewer => Context::Viewer,
+            Screen::LogDetail => Context::Detail,
+            Screen::Alarms => Context::Alarms,
+        }
+    }
+
+    pub fn should_quit(&self) -> bool {
+        self.should_quit
+    }
+
+    /// A search is still matching in the background, the screen shows the previous results
+    pub fn is_search_pending(&self) -> bool {
+        self.log_groups_component.is_search_pending()
+            || self.log_viewer_component.is_search_pending()
+            || self.split_viewer_component.is_search_pending()
+    }
+
+    fn is_querying(&self) -> bool {
+        self.log_viewer_component.is_querying()
+            || (self.split_open && self.split_viewer_component.is_querying())
+    }
+
+    fn is_fading(&self) -> bool {
+        self.log_viewer_component.is_fading()
+            || (self.split_open && self.split_viewer_component.is_fading())
+    }
+
+    /// Work that would be lost by quitting right now
+    fn has_pending_work(&self) -> bool {
+        self.log_groups_component.is_busy()
+            || self.inbox.iter().any(|entry| entry.result.is_none())
+            || (self.screens.contains(&Screen::LogViewer) && self.log_viewer_component.is_busy())
+            || (self.split_open && self.split_viewer_component.is_busy())
+    }
+
+    fn quit(&mut self) {
+        if self.confirm_quit && self.has_pending_work() {
+            self.confirming_quit = true;
+        } else {
+            self.should_quit = true;
+        }
+    }
+
+    pub fn current_screen(&self) -> Screen {
+        self.screens.last().copied().unwrap_or(Screen::LogGroups)
+    }
+
+    /// Tells loglog sessions in several tabs apart, e.g. `loglog — /aws/lambda/checkout [24h]`
+    pub fn window_title(&self) -> String {
+        match self.current_screen() {
+            Screen::LogGroups => "loglog".to_string(),
+            Screen::Alarms => "loglog — alarms".to_string(),
+            Screen::LogViewer | Screen::LogDetail => {
+                format!("loglog — {}", self.log_viewer_component.window_title())
+            }
+        }
+    }
+
+    fn push_screen(&mut self, screen: Screen) {
+        debug!(?screen, "opened screen");
+        self.screens.push(screen);
+    }
+
+    fn pop_screen(&mut self) {
+        if self.screens.len() <= 1 {
+            return;
+        }
+        let screen = self.screens.pop();
+        debug!(?screen, "closed screen");
+        match screen {
+            Some(Screen::LogViewer) => {
+                self.close_split();
+                self.log_viewer_component.close();
+            }
+            Some(Screen::LogGroups) => self.picking_split_groups = false,
+            _ => {}
+        }
+    }
+
+    /// Goes back to the group list to pick what the split viewer shows
+    fn open_split(&mut self) {
+        self.picking_split_groups = true;
+        self.push_screen(Screen::LogGroups);
+    }
+
+    fn close_split(&mut self) {
+        if !self.split_open {
+            return;
+        }
+        self.split_open = false;
+        self.split_viewer_component.close();
+        self.set_focus(Pane::Main);
+    }
+
+    fn set_focus(&mut self, pane: Pane) {
+        self.focus = pane;
+        self.log_viewer_component.focused = pane == Pane::Main;
+        self.split_viewer_component.focused = pane == Pane::Split;
+    }
+
+    fn handle_log_group_message(&mut self, message: LogGroupSelectionOutboundMessage) {
+        match message {
+            LogGroupSelectionOutboundMessage::SelectedGroups(groups)
+                if self.picking_split_groups =>
+            {
+                self.pop_screen();
+                self.split_viewer_component.log_groups = groups;
+                self.split_viewer_component.window_ms = self.log_groups_component.window_ms();
+                self.split_viewer_component.run();
+                self.split_open = true;
+                self.set_focus(Pane::Split);
+            }
+            LogGroupSelectionOutboundMessage::SelectedGroups(groups) => {
+                self.remember_log_groups(&groups);
+                self.log_viewer_component.log_groups = groups;
+                self.log_viewer_component.window_ms = self.log_groups_component.window_ms();
+                // TODO handle reselecvtion and stuff
+                self.log_viewer_component.run();
+                self.push_screen(Screen::LogViewer);
+            }
+            LogGroupSelectionOutboundMessage::AppendLogGroups(log_groups) => {
+                self.log_groups_component.append_log_groups(log_groups);
+            }
+            LogGroupSelectionOutboundMessage::SetLoadingState(loading_state) => {
+                self.check_offline(&loading_state);
+                self.log_groups_component.set_loading_state(loading_state);
+            }
+            LogGroupSelectionOutboundMessage::SetHiddenGroups(hidden_log_groups) => {
+                self.remember_hidden_groups(hidden_log_groups);
+            }
+            LogGroupSelectionOutboundMessage::Notify(notification) => {
+                self.notification = Some(notification);
+            }
+            LogGroupSelectionOutboundMessage::ChangesOffline(log_groups, change) => {
+                self.notification = Some(Notification {
+                    message: format!(
+                        "{}, {} groups left to change once back online",
+                        OFFLINE,
+                        log_groups.len()
+                    ),
+                    is_error: true,
+                });
+                self.log_groups_component
+                    .set_offline_changes(log_groups, change);
+                self.went_offline();
+            }
+            LogGroupSelectionOutboundMessage::LogGroupDeleted(log_group) => {
+                self.log_groups_component.remove_log_group(&log_group);
+            }
+            LogGroupSelectionOutboundMessage::SearchResults(generation, results) => {
+                self.log_groups_component
+                    .set_search_results(generation, results);
+            }
+            LogGroupSelectionOutboundMessage::GroupInfo(name, field_indexes, metric_filters) => {
+                self.log_groups_component
+                    .set_group_info(name, field_indexes, metric_filters);
+            }
+            LogGroupSelectionOutboundMessage::GroupMetadata(arn, result) => {
+                self.log_groups_component.set_metadata(arn, result);
+            }
+            LogGroupSelectionOutboundMessage::ShowAlarm(alarm) => {
+                self.alarms_component.focus(alarm);
+                self.open_alarms();
+            }
+        }
+    }
+
+    fn handle_alarms_message(&mut self, message: AlarmsOutboundMessage) {
+        match message {
+            AlarmsOutboundMessage::SetAlarms(result) => {
+                self.alarms_component.set_alarms(result);
+                let loading_state = self.alarms_component.loading_state().clone();
+                self.check_offline(&loading_state);
+            }
+            AlarmsOutboundMessage::OpenLogs(alarm, result) => self.open_alarm_logs(alarm, result),
+        }
+    }
+
+    fn handle_accounts_message(&mut self, message: AccountsOutboundMessage) {
+        match message {
+            AccountsOutboundMessage::SetAccounts(result) => {
+                self.accounts_component.set_accounts(result);
+            }
+            AccountsOutboundMessage::Switched(account, Ok(())) => self.account_switched(account),
+            AccountsOutboundMessage::Switched(account, Err(e)) => {
+                self.notification = Some(Notification {
+                    message: format!("can't switch to {}: {}", account.summary(), e),
+                    is_error: true,
+                });
+            }
+        }
+    }
+
+    /// Starts over from the group list, whatever was open belonged to the previous account
+    fn account_switched(&mut self, account: Account) {
+        self.notification = Some(Notification {
+            message: format!("switched to {}", account.summary()),
+            is_error: false,
+        });
+        self.accounts_component.switched(account);
+        while self.current_screen() != Screen::LogGroups {
+            self.pop_screen();
+        }
+        self.log_groups_component.run();
+    }
+
+    fn check_offline(&mut self, loading_state: &LoadingState) {
+        if loading_state.is_offline() {
+            self.went_offline();
+        }
+    }
+
+    /// Tries AWS every few seconds until a request gets through
+    fn went_offline(&mut self) {
+        if self.offline {
+            return;
+        }
+        self.offline = true;
+        let backend = self.backend.clone();
+        let online_tx = self.online_tx.clone();
+        self.tasks.spawn(TaskKey::Reconnect, async move {
+            loop {
+                tokio::time::sleep(RECONNECT_INTERVAL).await;
+                match backend.fetch_log_groups(None, None).await {
+                    Err(e) if is_offline(&e) => {}
+                    // any answer from AWS, even an error, means the network is back
+                    _ => break,
+                }
+            }
+            let _ = online_tx.send(());
+        });
+    }
+
+    /// Loads again whatever failed for being offline
+    fn back_online(&mut self) {
+        self.offline = false;
+        self.notification = Some(Notification {
+            message: "back online".to_string(),
+            is_error: false,
+        });
+        if self.log_groups_component.loading_state().is_offline() {
+            self.log_groups_component.run();
+        }
+        self.log_groups_component.retry_changes();
+        if self.screens.contains(&Screen::LogViewer)
+            && self.log_viewer_component.loading_state().is_offline()
+        {
+            self.log_viewer_component.run();
+        }
+        if self.split_open && self.split_viewer_component.loading_state().is_offline() {
+            self.split_viewer_component.run();
+        }
+        if self.screens.contains(&Screen::Alarms)
+            && self.alarms_component.loading_state().is_offline()
+        {
+            self.alarms_component.run();
+        }
+    }
+
+    /// Shows the alarms going off, the screens opened before it are closed
+    fn open_alarms(&mut self) {
+        if self.current_screen() == Screen::Alarms {
+            return;
+        }
+        while self.current_screen() != Screen::LogGroups {
+            self.pop_screen();
+        }
+        self.alarms_component.run();
+        self.push_screen(Screen::Alarms);
+    }
+
+    /// Opens the groups behind an alarm's metric around the time it went off
+    fn open_alarm_logs(&mut self, alarm: Alarm, result: Result<Vec<String>, String>) {
+        // the alarms were left while the groups were looked up
+        if self.current_screen() != Screen::Alarms {
+            return;
+        }
+        let names = match result {
+            Ok(names) if names.is_empty() => Err(format!(
+                "no metric filter publishes the metric of {}",
+                alarm.name
+            )),
+            result => result,
+        };
+        let names = match names {
+            Ok(names) => names,
+            Err(e) => {
+                self.notification = Some(Notification {
+                    message: e,
+                    is_error: true,
+                });
+                return;
+            }
+        };
+        let region = alarm.region.as_deref();
+        let account_id = self.log_groups_component.account_id();
+        let log_groups = names
+            .into_iter()
+            .map(|name| {
+                self.log_groups_component
+                    .find_log_group(&name, region)
+                    .cloned()
+                    .unwrap_or_else(|| LogGroup {
+                        // a group that isn't listed only gets an arn when it can be a real one
+                        arn: region
+                            .zip(account_id)
+                            .map(|(region, account_id)| {
+                                format!("arn:aws:logs:{}:{}:log-group:{}", region, account_id, name)
+                            })
+                            .unwrap_or_default(),
+                        name,
+                        ..Default::default()
+                    })
+            })
+            .collect();
+        let now = chrono::Utc::now().timestamp_millis();
+        self.log_viewer_component.log_groups = log_groups;
+        self.log_viewer_component.time_range = Some(time_range_around(alarm.state_updated, now));
+        self.log_viewer_component.run();
+        self.push_screen(Screen::LogViewer);
+    }
+
+    fn handle_log_detail_message(&mut self, message: LogDetailOutboundMessage) {
+        match message {
+            LogDetailOutboundMessage::Fields(pointer, result) => {
+                self.log_detail_component.set_fields(pointer, result);
+            }
+        }
+    }
+
+    fn handle_log_viewer_message(&mut self, pane: Pane, message: LogViewerOutboundMessage) {
+        let message = match (pane, message) {
+            (Pane::Main, message) => match self.deliver_to_inbox(message) {
+                Some(message) => message,
+                None => return,
+            },
+            (_, message) => message,
+        };
+        let log_viewer = self.log_viewer(pane);
+        let query_elapsed = log_viewer.query_elapsed();
+        match message {
+            LogViewerOutboundMessage::SetLogs(generation, log_messages) => {
+                log_viewer.set_logs(generation, log_messages);
+            }
+            LogViewerOutboundMessage::AppendLogs(tail_generation, log_messages) => {
+                log_viewer.append_logs(tail_generation, log_messages);
+            }
+            LogViewerOutboundMessage::SetComparisonLogs(generation, result) => {
+                log_viewer.set_comparison_logs(generation, result);
+            }
+            LogViewerOutboundMessage::SetLoadingState(generation, loading_state) => {
+                let offline = loading_state.is_offline();
+                log_viewer.set_loading_state(generation, loading_state);
+                if offline {
+                    self.went_offline();
+                }
+            }
+            LogViewerOutboundMessage::TailEnded(tail_generation, error) => {
+                log_viewer.tail_ended(tail_generation, error);
+            }
+            LogViewerOutboundMessage::TailSampled(tail_generation, sampled) => {
+                log_viewer.set_tail_sampled(tail_generation, sampled);
+            }
+            LogViewerOutboundMessage::SearchResults(generation, matches) => {
+                log_viewer.set_search_results(generation, matches);
+            }
+            LogViewerOutboundMessage::Unmasked(pointer, result) => {
+                log_viewer.set_unmasked(pointer, result);
+            }
+            LogViewerOutboundMessage::Notify(notification) => {
+                self.notification = Some(notification);
+            }
+            LogViewerOutboundMessage::ErrorCounts(generation, counts) => {
+                log_viewer.set_error_counts(generation, counts);
+            }
+            LogViewerOutboundMessage::IndexedFields(generation, fields) => {
+                log_viewer.set_indexed_fields(generation, fields);
+            }
+            LogViewerOutboundMessage::MetricFilters(generation, patterns) => {
+                log_viewer.set_metric_filters(generation, patterns);
+            }
+            LogViewerOutboundMessage::MetricFilterMatches(generation, pattern, result) => {
+                log_viewer.set_metric_filter_matches(generation, pattern, result);
+            }
+            LogViewerOutboundMessage::ShowDetail(event, region) => {
+                self.log_detail_component.show(event, region);
+                self.push_screen(Screen::LogDetail);
+            }
+        }
+        if let Some(record) = self.log_viewer(pane).take_finished_query() {
+            self.investigation.queries.push(record);
+        }
+        if let Some(elapsed) = query_elapsed {
+            self.query_finished(pane, elapsed);
+        }
+    }
+
+    /// Keeps the results of a query left running in the background, any other message is
+    /// handed back
+    fn deliver_to_inbox(
+        &mut self,
+        message: LogViewerOutboundMessage,
+    ) -> Option<LogViewerOutboundMessage> {
+        let (generation, result) = match message {
+            LogViewerOutboundMessage::SetLogs(generation, log_messages) => {
+                (generation, Ok(log_messages))
+            }
+            LogViewerOutboundMessage::SetLoadingState(generation, LoadingState::Error(e)) => {
+                (generation, Err(e))
+            }
+            message => return Some(message),
+        };
+        let Some(entry) = self
+            .inbox
+            .iter_mut()
+            .find(|entry| entry.generation == generation && entry.result.is_none())
+        else {
+            return Some(match result {
+                Ok(log_messages) => LogViewerOutboundMessage::SetLogs(generation, log_messages),
+                Err(e) => {
+                    LogViewerOutboundMessage::SetLoadingState(generation, LoadingState::Error(e))
+                }
+            });
+        };
+        let took = entry.started.elapsed();
+        debug!(?took, "background query finished");
+        entry.result = Some((took, result));
+        self.notification = Some(Notification {
+            message: format!(
+                "background query finished, {} for the inbox",
+                self.keymap.keys(Context::Global, Action::Inbox)
+            ),
+            is_error: false,
+        });
+        None
+    }
+
+    /// Bookmarks the focused viewer's selected event, or takes it out of the bookmarks again
+    fn toggle_bookmark(&mut self) {
+        let focus = self.focus;
+        let Some(bookmark) = self.log_viewer(focus).selected_bookmark() else {
+            return;
+        };
+        match self.investigation.toggle_bookmark(bookmark) {
+            Some(index) => self.note_input = Some((index, Input::default())),
+            None => {
+                self.notification = Some(Notification {
+                    message: "bookmark removed".to_string(),
+                    is_error: false,
+                });
+            }
+        }
+        self.mark_bookmarks();
+    }
+
+    /// Shows the viewers which of their events are bookmarked
+    fn mark_bookmarks(&mut self) {
+        let bookmarks = &self.investigation.bookmarks;
+        self.log_viewer_component.set_bookmarks(bookmarks);
+        self.split_viewer_component.set_bookmarks(bookmarks);
+    }
+
+    fn handle_note_key(&mut self, key: &KeyEvent) {
+        let Some((index, input)) = &mut self.note_input else {
+            return;
+        };
+        match key.code {
+            KeyCode::Enter => {
+                if let Some(bookmark) = self.investigation.bookmarks.get_mut(*index) {
+                    bookmark.note = input.value().trim().to_string();
+                }
+            }
+            KeyCode::Esc => {}
+            _ => {
+                input.handle_key(key);
+                return;
+            }
+        }
+        self.note_input = None;
+        if self.bookmarks_selected.is_none() {
+            self.notification = Some(Notification {
+                message: format!(
+                    "bookmarked, {} to list the bookmarks, {} to export them",
+                    self.keymap.keys(Context::Global, Action::Bookmarks),
+                    self.keymap.keys(Context::Global, Action::Export)
+                ),
+                is_error: false,
+            });
+        }
+    }
+
+    fn handle_bookmarks_key(&mut self, key: &KeyEvent) {
+        let Some(selected) = self.bookmarks_selected else {
+            return;
+        };
+        let bookmarks = &mut self.investigation.bookmarks;
+        match (
+            self.keymap.action(Context::BookmarkList, key),
+            self.keymap.action(Context::Global, key),
+        ) {
+            (Some(Action::ScrollDown), _) => {
+                self.bookmarks_selected =
+                    Some((selected + 1).min(bookmarks.len().saturating_sub(1)));
+            }
+            (Some(Action::ScrollUp), _) => {
+                self.bookmarks_selected = Some(selected.saturating_sub(1));
+            }
+            (Some(Action::Open), _) => {
+                if let Some(bookmark) = bookmarks.get(selected) {
+                    self.note_input = Some((selected, Input::new(&bookmark.note)));
+                }
+            }
+            (Some(Action::Remove), _) if selected < bookmarks.len() => {
+                bookmarks.remove(selected);
+                self.bookmarks_selected = Some(selected.min(bookmarks.len().saturating_sub(1)));
+                self.mark_bookmarks();
+            }
+            (_, Some(Action::Back | Action::Bookmarks | Action::Quit)) => {
+                self.bookmarks_selected = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_history_key(&mut self, key: &KeyEvent) {
+        let Some(selected) = self.history_selected else {
+            return;
+        };
+        let last = self.investigation.queries.len().saturating_sub(1);
+        match (
+            self.keymap.action(Context::Groups, key),
+            self.keymap.action(Context::Global, key),
+        ) {
+            (Some(Action::ScrollDown), _) => self.history_selected = Some((selected + 1).min(last)),
+            (Some(Action::ScrollUp), _) => self.history_selected = Some(selected.saturating_sub(1)),
+            (Some(Action::Open), _) => self.run_from_history(selected),
+            (_, Some(Action::Back | Action::History | Action::Quit)) => {
+                self.history_selected = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Opens the groups of the query `back` from the newest and queries them the same way
+    fn run_from_history(&mut self, back: usize) {
+        let queries = &self.investigation.queries;
+        let Some(record) = queries
+            .len()
+            .checked_sub(back + 1)
+            .map(|index| &queries[index])
+        else {
+            return;
+        };
+        let record = record.clone();
+        let log_groups = record
+            .log_groups
+            .iter()
+            .zip(&record.regions)
+            .filter_map(|(name, region)| {
+                self.log_groups_component
+                    .find_log_group(name, region.as_deref())
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        if log_groups.len() < record.log_groups.len() {
+            self.notification = Some(Notification {
+                message: "its groups aren't listed anymore".to_string(),
+                is_error: true,
+            });
+            return;
+        }
+        self.history_selected = None;
+        while self.current_screen() != Screen::LogGroups {
+            self.pop_screen();
+        }
+        self.remember_log_groups(&log_groups);
+        self.log_viewer_component.run_again(log_groups, &record);
+        self.push_screen(Screen::LogViewer);
+    }
+
+    /// Writes the investigation as markdown and json next to each other
+    fn export_investigation(&mut self) {
+        let notification = if self.investigation.is_empty() {
+            Notification {
+                message: "nothing to export yet, run a query or bookmark an event".to_string(),
+                is_error: true,
+            }
+        } else {
+            let name = chrono::Utc::now()
+                .format("loglog-investigation-%Y%m%d-%H%M%S")
+                .to_string();
+            match self.investigation.write(&self.export_dir, &name) {
+                Ok(path) => Notification {
+                    message: format!("wrote {} and its .json", path.display()),
+                    is_error: false,
+                },
+                Err(e) => Notification {
+                    message: e,
+                    is_error: true,
+                },
+            }
+        };
+        self.notification = Some(notification);
+    }
+
+    /// Leaves the main viewer's query running and goes back to the groups, its results are
+    /// kept in the inbox
+    fn background_query(&mut self) {
+        let elapsed = self.log_viewer_component.query_elapsed();
+        let Some(generation) = self.log_viewer_component.detach_query() else {
+            return;
+        };
+        self.inbox.insert(
+            0,
+            InboxEntry {
+                generation,
+                log_groups: self.log_viewer_component.log_groups.clone(),
+                started: Instant::now() - elapsed.unwrap_or_default(),
+                result: None,
+            },
+        );
+        while self.current_screen() != Screen::LogGroups {
+            self.pop_screen();
+        }
+    }
+
+    /// Opens the highlighted inbox entry in the viewer once its query is done
+    fn open_inbox_entry(&mut self, index: usize) {
+        if self
+            .inbox
+            .get(index)
+            .is_none_or(|entry| entry.result.is_none())
+        {
+            return;
+        }
+        let entry = self.inbox.remove(index);
+        let Some((_, result)) = entry.result else {
+            return;
+        };
+        self.inbox_selected = None;
+        while self.current_screen() != Screen::LogGroups {
+            self.pop_screen();
+        }
+        self.log_viewer_component
+            .show_results(entry.log_groups, result);
+        self.push_screen(Screen::LogViewer);
+    }
+
+    fn handle_inbox_key(&mut self, key: &KeyEvent) {
+        let Some(selected) = self.inbox_selected else {
+            return;
+        };
+        match (
+            self.keymap.action(Context::Groups, key),
+            self.keymap.action(Context::Global, key),
+        ) {
+            (Some(Action::ScrollDown), _) => {
+                self.inbox_selected = Some((selected + 1).min(self.inbox.len().saturating_sub(1)));
+            }
+            (Some(Action::ScrollUp), _) => self.inbox_selected = Some(selected.saturating_sub(1)),
+            (Some(Action::Open), _) => self.open_inbox_entry(selected),
+            (_, Some(Action::Back | Action::Inbox | Action::Quit)) => self.inbox_selected = None,
+            _ => {}
+        }
+    }
+
+    /// Queues a desktop notification for a long query that just finished
+    fn query_finished(&mut self, pane: Pane, elapsed: Duration) {
+        if self.notify_after.is_none_or(|after| elapsed < after)
+            || (self.focused && !self.notify_when_focused)
+        {
+            return;
+        }
+        let log_viewer = self.log_viewer(pane);
+        let outcome = match log_viewer.loading_state() {
+            LoadingState::Loading => return,
+            LoadingState::Error(_) => "failed",
+            _ => "finished",
+        };
+        self.desktop_notification = Some(format!(
+            "loglog: {} {} after {}",
+            log_viewer.window_title(),
+            outcome,
+            format_elapsed(elapsed)
+        ));
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame) {
+        let size = frame.area();
+        if size.width < MIN_WIDTH || size.height < MIN_HEIGHT {
+            let message = Paragraph::new(vec![
+                Line::from("terminal too small"),
+                Line::from(format!(
+                    "please enlarge to at least {}x{}",
+                    MIN_WIDTH, MIN_HEIGHT
+                )),
+                Line::styled(
+                    format!("now {}x{}", size.width, size.height),
+                    Style::new().fg(self.theme.muted),
+                ),
+            ])
+            .centered()
+            .wrap(Wrap { trim: true });
+            // room for the lines to wrap on a narrow terminal
+            frame.render_widget(message, centered_area(size, size.width, 5));
+            return;
+        }
+        let mut stopwatch = Stopwatch::start();
+        let [area, status_area] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(frame.area());
+        match self.current_screen() {
+            Screen::LogGroups => {
+                frame.render_widget(&mut self.log_groups_component, area);
+                stopwatch.lap("groups");
+            }
+            Screen::LogViewer if self.split_open => {
+                let [main_area, split_area] =
+                    Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).areas(area);
+                frame.render_widget(&self.log_viewer_component, main_area);
+                stopwatch.lap("viewer");
+                frame.render_widget(&self.split_viewer_component, split_area);
+                stopwatch.lap("split");
+            }
+            Screen::LogViewer => {
+                frame.render_widget(&self.log_viewer_component, area);
+                stopwatch.lap("viewer");
+            }
+            Screen::LogDetail => {
+                frame.render_widget(&self.log_detail_component, area);
+                stopwatch.lap("detail");
+            }
+            Screen::Alarms => {
+                frame.render_widget(&mut self.alarms_component, area);
+                stopwatch.lap("alarms");
+            }
+        }
+        self.draw_status_bar(frame, status_area);
+        stopwatch.lap("status bar");
+        if let Some(scroll) = self.help_scroll {
+            let mut popup = HelpPopup {
+                keymap: &self.keymap,
+                context: self.current_context(),
+                scroll,
+                theme: &self.theme,
+            };
+            popup.scroll = scroll.min(popup.max_scroll(area));
+            self.help_scroll = Some(popup.scroll);
+            frame.render_widget(popup, area);
+        }
+        if let Some(selected) = self.inbox_selected {
+            let entries = self
+                .inbox
+                .iter()
+                .map(InboxEntry::summary)
+                .collect::<Vec<_>>();
+            frame.render_widget(
+                ListPopup {
+                    title: "Inbox",
+                    entries: &entries,
+                    selected,
+                    empty: "nothing running in the background",
+                    hint: "enter to open, esc to close",
+                    theme: &self.theme,
+                },
+                area,
+            );
+        }
+        if let Some(selected) = self.bookmarks_selected {
+            let entries = self
+                .investigation
+                .bookmarks
+                .iter()
+                .map(Bookmark::summary)
+                .collect::<Vec<_>>();
+            let hint = format!(
+                "{} to edit the note, {} to remove, {} to close",
+                self.keymap.keys(Context::BookmarkList, Action::Open),
+                self.keymap.keys(Context::BookmarkList, Action::Remove),
+                self.keymap.keys(Context::Global, Action::Back)
+            );
+            frame.render_widget(
+                ListPopup {
+                    title: "Bookmarks",
+                    entries: &entries,
+                    selected,
+                    empty: "nothing bookmarked yet",
+                    hint: &hint,
+                    theme: &self.theme,
+                },
+                area,
+            );
+        }
+        if let Some(selected) = self.history_selected {
+            let entries = self
+                .investigation
+                .queries
+                .iter()
+                .rev()
+                .map(QueryRecord::summary)
+                .collect::<Vec<_>>();
+            frame.render_widget(
+                ListPopup {
+                    title: "History",
+                    entries: &entries,
+                    selected,
+                    empty: "no queries run yet",
+                    hint: "enter to run again, esc to close",
+                    theme: &self.theme,
+                },
+                area,
+            );
+        }
+        if self.accounts_component.is_open() {
+            frame.render_widget(&self.accounts_component, area);
+        }
+        if let Some((_, input)) = &self.note_input {
+            frame.render_widget(
+                PromptPopup {
+                    title: "Bookmark",
+                    message: input.line("Note: ", ""),
+                    hint: "enter to save, esc to leave it",
+                },
+                area,
+            );
+        }
+        if self.confirming_quit {
+            frame.render_widget(
+                ConfirmPopup {
+                    title: "Quit",
+                    message: "Queries are still running, quit anyway?",
+                    keymap: &self.keymap,
+                    theme: &self.theme,
+                },
+                frame.area(),
+            );
+        }
+        // the overlay shows the previous frame, so its own time is left out of the budget
+        let (frame_time, widget_times) = stopwatch.stop();
+        if frame_time > FRAME_BUDGET {
+            warn!(
+                ?frame_time,
+                ?widget_times,
+                "frame took longer than its budget"
+            );
+        }
+        if self.show_debug {
+            self.draw_debug(frame, area);
+        }
+        self.frame_time = frame_time;
+        self.widget_times = widget_times;
+    }
+
+    /// The previous frame's time, then each part's
+    fn frame_summary(&self) -> String {
+        let parts = self
+            .widget_times
+            .iter()
+            .map(|(name, time)| format!("{} {:.1?}", name, time))
+            .collect::<Vec<_>>();
+        let over = if self.frame_time > FRAME_BUDGET {
+            " over budget"
+        } else {
+            ""
+        };
+        format!("{:.1?}{}: {}", self.frame_time, over, parts.join(", "))
+    }
+
+    fn draw_debug(&self, frame: &mut Frame, area: Rect) {
+        let statuses = self.tasks.statuses();
+        let running = statuses
+            .iter()
+            .filter(|(_, status)| *status == TaskStatus::Running)
+            .count();
+        let viewers = [&self.log_viewer_component, &self.split_viewer_component];
+        let (events, bytes, spilled) = viewers
+            .into_iter()
+            .map(|viewer| viewer.stored_events())
+            .fold(
+                (0, 0, 0),
+                |(events, bytes, spilled), (more_events, more_bytes, more_spilled)| {
+                    (
+                        events + more_events,
+                        bytes + more_bytes,
+                        spilled + more_spilled,
+                    )
+                },
+            );
+        let stats = [
+            ("frame", self.frame_summary()),
+            (
+                "tasks",
+                format!("{} running, {} queued", running, statuses.len() - running),
+            ),
+            (
+                "queues",
+                format!(
+                    "groups {}, viewer {}, split {}, detail {}, status {}",
+                    self.log_group_selection_rx.len(),
+                    self.log_viewer_rx.len(),
+                    self.split_viewer_rx.len(),
+                    self.log_detail_rx.len(),
+                    self.task_status_rx.len(),
+                ),
+            ),
+            (
+                "events",
+                format!(
+                    "{}, {} in memory, {} on disk",
+                    events,
+                    format_bytes(bytes as i64),
+                    spilled
+                ),
+            ),
+        ];
+        frame.render_widget(
+            DebugPopup {
+                stats: &stats,
+                log: &logging::recent_lines(),
+                theme: &self.theme,
+            },
+            area,
+        );
+    }
+
+    fn draw_status_bar(&self, frame: &mut Frame, area: Rect) {
+        let mut statuses = self.tasks.statuses();
+        statuses.sort_by_key(|(key, _)| key.label());
+        let mut spans = vec![];
+        // CloudWatch queues queries past the account's limit, which is why one may not start
+        let queries = self.backend.query_counts();
+        if queries.running > 0 || queries.queued > 0 {
+            spans.push(Span::raw(format!("insights {} running", queries.running)));
+            if queries.queued > 0 {
+                spans.push(Span::styled(
+                    format!(", {} queued", queries.queued),
+                    Style::new().fg(self.theme.warning),
+                ));
+            }
+            spans.push(Span::raw("  "));
+        }
+        spans.extend(statuses.into_iter().flat_map(|(key, status)| {
+            let (marker, color) = match status {
+                TaskStatus::Running => ("● ", self.theme.ok),
+                TaskStatus::Queued => ("◌ queued ", self.theme.warning),
+            };
+            [
+                Span::styled(marker, Style::new().fg(color)),
+                Span::raw(format!("{}  ", key.label())),
+            ]
+        }));
+        frame.render_widget(Line::from(spans), area);
+        if let Some(notification) = &self.notification {
+            let color = if notification.is_error {
+                self.theme.error
+            } else {
+                self.theme.ok
+            };
+            frame.render_widget(
+                Line::styled(notification.message.as_str(), Style::new().fg(color)).right_aligned(),
+                area,
+            );
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        match event {
+            Event::FocusGained => self.focused = true,
+            Event::FocusLost => self.focused = false,
+            _ => {}
+        }
+        if self.confirming_quit {
+            if let Event::Key(key) = event {
+                if key.kind == KeyEventKind::Press {
+                    self.confirming_quit = false;
+                    self.should_quit =
+                        self.keymap.action(Context::Global, key) == Some(Action::Confirm);
+                }
+            }
+            return;
+        }
+        if let Some(scroll) = self.help_scroll {
+            if let Event::Key(key) = event {
+                if key.kind == KeyEventKind::Press {
+                    // drawing keeps it from scrolling past the last binding
+                    self.help_scroll = match self.keymap.action(self.current_context(), key) {
+                        Some(Action::ScrollUp) => Some(scroll.saturating_sub(1)),
+                        Some(Action::ScrollDown) => Some(scroll.saturating_add(1)),
+                        Some(Action::PageUp) => Some(scroll.saturating_sub(20)),
+                        Some(Action::PageDown) => Some(scroll.saturating_add(20)),
+                        _ => None,
+                    };
+                }
+            }
+            return;
+        }
+        if let Some((_, input)) = &mut self.note_input {
+            match event {
+                Event::Key(key) if key.kind == KeyEventKind::Press => self.handle_note_key(key),
+                Event::Paste(text) => input.paste(text),
+                _ => {}
+            }
+            return;
+        }
+        if self.inbox_selected.is_some() {
+            if let Event::Key(key) = event {
+                if key.kind == KeyEventKind::Press {
+                    self.handle_inbox_key(key);
+                }
+            }
+            return;
+        }
+        if self.bookmarks_selected.is_some() {
+            if let Event::Key(key) = event {
+                if key.kind == KeyEventKind::Press {
+                    self.handle_bookmarks_key(key);
+                }
+            }
+            return;
+        }
+        if self.history_selected.is_some() {
+            if let Event::Key(key) = event {
+                if key.kind == KeyEventKind::Press {
+                    self.handle_history_key(key);
+                }
+            }
+            return;
+        }
+        if self.accounts_component.handle_event(event) {
+            return;
+        }
+        // components return true when they consumed the event, e.g. while typing into an input
+        let handled = match self.current_screen() {
+            Screen::LogGroups => self.log_groups_component.handle_event(event),
+            Screen::LogViewer => {
+                let focus = self.focus;
+                self.log_viewer(focus).handle_event(event)
+            }
+            Screen::LogDetail => self.log_detail_component.handle_event(event),
+            Screen::Alarms => self.alarms_component.handle_event(event),
+        };
+        if handled {
+            return;
+        }
+        if let Event::Key(key) = event {
+            if key.kind == KeyEventKind::Press {
+                let in_viewer = self.current_screen() == Screen::LogViewer;
+                // the split is driven from here since it spans both viewers
+                let viewer_action = in_viewer
+                    .then(|| self.keymap.action(Context::Viewer, key))
+                    .flatten();
+                match (viewer_action, self.keymap.action(Context::Global, key)) {
+                    (Some(Action::OpenSplit), _) if !self.split_open => self.open_split(),
+                    (Some(Action::SwitchPane), _) if self.split_open => {
+                        self.set_focus(match self.focus {
+                            Pane::Main => Pane::Split,
+                            Pane::Split => Pane::Main,
+                        });
+                    }
+                    (_, Some(Action::Help)) => self.help_scroll = Some(0),
+                    (Some(Action::Background), _) if !self.split_open => self.background_query(),
+                    (Some(Action::Bookmark), _) => self.toggle_bookmark(),
+                    (_, Some(Action::Debug)) => self.show_debug = !self.show_debug,
+                    (_, Some(Action::Inbox)) => self.inbox_selected = Some(0),
+                    (_, Some(Action::Export)) => self.export_investigation(),
+                    (_, Some(Action::Bookmarks)) => self.bookmarks_selected = Some(0),
+                    (_, Some(Action::History)) => self.history_selected = Some(0),
+                    (_, Some(Action::ShowAlarms)) => self.open_alarms(),
+                    (_, Some(Action::Accounts)) => self.accounts_component.open(),
+                    (_, Some(Action::Back)) if in_viewer && self.split_open => self.close_split(),
+                    (_, Some(Action::Back)) if self.screens.len() > 1 => self.pop_screen(),
+                    (_, Some(Action::Back | Action::Quit)) => self.quit(),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+impl App {
+    pub fn new(backend: Arc<dyn LogBackend>) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel::<LogGroupSelectionOutboundMessage>();
+        let (log_viewer_tx, log_viewer_rx) = mpsc::unbounded_channel::<LogViewerOutboundMessage>();
+        let (split_viewer_tx, split_viewer_rx) =
+            mpsc::unbounded_channel::<LogViewerOutboundMessage>();
+        let (log_detail_tx, log_detail_rx) = mpsc::unbounded_channel::<LogDetailOutboundMessage>();
+        let (alarms_tx, alarms_rx) = mpsc::unbounded_channel::<AlarmsOutboundMessage>();
+        let (accounts_tx, accounts_rx) = mpsc::unbounded_channel::<AccountsOutboundMessage>();
+        let (online_tx, online_rx) = mpsc::unbounded_channel::<()>();
+        let (task_status_tx, task_status_rx) = mpsc::unbounded_channel::<()>();
+        let tasks = TaskSupervisor::new(task_status_tx);
+        Self {
+            should_quit: false,
+            confirm_quit: true,
+            confirming_quit: false,
+            keymap: Arc::default(),
+            theme: Theme::default(),
+            help_scroll: None,
+            show_debug: false,
+            frame_time: Duration::ZERO,
+            widget_times: vec![],
+            screens: vec![Screen::LogGroups],
+            log_groups_component: LogGroupListComponent::new(tx, backend.clone(), tasks.clone()),
+            log_viewer_component: LogVieweromponent::new(
+                Pane::Main,
+                log_viewer_tx,
+                backend.clone(),
+                tasks.clone(),
+            ),
+            log_viewer_rx,
+            split_viewer_component: LogVieweromponent::new(
+                Pane::Split,
+                split_viewer_tx,
+                backend.clone(),
+                tasks.clone(),
+            ),
+            split_viewer_rx,
+            split_open: false,
+            picking_split_groups: false,
+            focus: Pane::Main,
+            state_store: StateStore::default(),
+            notification: None,
+            notify_after: None,
+            notify_when_focused: false,
+            focused: true,
+            desktop_notification: None,
+            inbox: vec![],
+            inbox_selected: None,
+            investigation: Investigation::default(),
+            export_dir: PathBuf::from("."),
+            bookmarks_selected: None,
+            history_selected: None,
+            note_input: None,
+            log_group_selection_rx: rx,
+            log_detail_component: LogDetailComponent::new(
+                log_detail_tx,
+                backend.clone(),
+                tasks.clone(),
+            ),
+            log_detail_rx,
+            alarms_component: AlarmsComponent::new(alarms_tx, backend.clone(), tasks.clone()),
+            alarms_rx,
+            accounts_component: AccountsComponent::new(accounts_tx, backend.clone(), tasks.clone()),
+            accounts_rx,
+            offline: false,
+            online_tx,
+            online_rx,
+            tasks,
+            task_status_rx,
+            backend,
+        }
+    }
+}
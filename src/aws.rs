@@ -1,27 +1,283 @@
-use aws_sdk_cloudwatchlogs::{error::SdkError, types::QueryStatus};
+use aws_config::{timeout::TimeoutConfig, Region};
+use aws_credential_types::provider::SharedCredentialsProvider;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
+use aws_sdk_cloudwatch::types::{AlarmType, MetricAlarm, StateValue};
+use aws_sdk_cloudwatchlogs::{
+    error::SdkError,
+    primitives::event_stream::EventReceiver,
+    types::{
+        error::StartLiveTailResponseStreamError, DataProtectionStatus, OrderBy, QueryStatus,
+        ResultField, StartLiveTailResponseStream,
+    },
+};
+use chrono::NaiveDateTime;
+use tracing::{debug, instrument};
+
+use crate::query;
+use crate::shared::{
+    Alarm, GroupMetadata, LogEvent, LogGroup, LogGroupPage, MetricFilter, QueryCounts, QueryRow,
+    MAX_FILTERED_EVENTS, OFFLINE,
+};
+
+/// Regions searched by `--all-regions`, opt-in regions that aren't enabled just fail
+pub const REGIONS: &[&str] = &[
+    "us-east-1",
+    "us-east-2",
+    "us-west-1",
+    "us-west-2",
+    "af-south-1",
+    "ap-east-1",
+    "ap-south-1",
+    "ap-south-2",
+    "ap-southeast-1",
+    "ap-southeast-2",
+    "ap-southeast-3",
+    "ap-southeast-4",
+    "ap-northeast-1",
+    "ap-northeast-2",
+    "ap-northeast-3",
+    "ca-central-1",
+    "ca-west-1",
+    "eu-central-1",
+    "eu-central-2",
+    "eu-west-1",
+    "eu-west-2",
+    "eu-west-3",
+    "eu-south-1",
+    "eu-south-2",
+    "eu-north-1",
+    "il-central-1",
+    "me-south-1",
+    "me-central-1",
+    "sa-east-1",
+];
+
+/// Where a client connects, anything unset comes from the environment
+#[derive(Debug, Clone, Default)]
+pub struct Connection {
+    pub profile: Option<String>,
+    pub region: Option<String>,
+    /// used instead of the profile's credentials, e.g. a role reached through SSO
+    pub credentials: Option<SharedCredentialsProvider>,
+}
+
+/// How long to wait for a connection, a dead VPN otherwise hangs until the OS gives up
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait for AWS to answer once connected, filtering a large group can take a while.
+/// Set per attempt rather than as a read timeout so it fails as a `TimeoutError`, while a
+/// connector timeout can only be the connection
+const ATTEMPT_TIMEOUT: Duration = Duration::from_secs(60);
+
+async fn sdk_config(connection: Connection) -> aws_config::SdkConfig {
+    let mut loader = aws_config::from_env().timeout_config(
+        TimeoutConfig::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .operation_attempt_timeout(ATTEMPT_TIMEOUT)
+            .build(),
+    );
+    if let Some(profile) = connection.profile {
+        loader = loader.profile_name(profile);
+    }
+    if let Some(region) = connection.region {
+        loader = loader.region(Region::new(region));
+    }
+    if let Some(credentials) = connection.credentials {
+        loader = loader.credentials_provider(credentials);
+    }
+    loader.load().await
+}
+
+/// What went wrong with a call, starting with `OFFLINE` when the request never reached AWS
+pub fn error_message<E, R>(e: SdkError<E, R>) -> String {
+    match &e {
+        SdkError::DispatchFailure(failure) if failure.is_io() || failure.is_timeout() => {
+            OFFLINE.to_string()
+        }
+        // AWS was reached and is slow to answer, trying again straight away won't help
+        SdkError::TimeoutError(_) => {
+            format!(
+                "AWS took longer than {}s to answer",
+                ATTEMPT_TIMEOUT.as_secs()
+            )
+        }
+        _ => e.to_string(),
+    }
+}
+
+async fn client(connection: Connection) -> aws_sdk_cloudwatchlogs::Client {
+    aws_sdk_cloudwatchlogs::Client::new(&sdk_config(connection).await)
+}
+
+#[instrument(level = "debug", skip_all, fields(region = ?connection.region), err)]
+pub async fn fetch_log_groups(
+    connection: Connection,
+    next_token: Option<String>,
+) -> Result<LogGroupPage, String> {
+    let client = client(connection).await;
+    let response = match client
+        .describe_log_groups()
+        .set_next_token(next_token)
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => return Err(error_message(e)),
+    };
+    let log_groups = response
+        .log_groups
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|group| {
+            Some(LogGroup {
+                name: group.log_group_name?,
+                arn: group
+                    .log_group_arn
+                    .or_else(|| group.arn.map(|arn| arn.trim_end_matches(":*").into()))
+                    .unwrap_or_default(),
+                stored_bytes: group.stored_bytes,
+                retention_in_days: group.retention_in_days,
+                creation_time: group.creation_time,
+                data_protected: group.data_protection_status
+                    == Some(DataProtectionStatus::Activated),
+            })
+        })
+        .collect::<Vec<LogGroup>>();
+    Ok(LogGroupPage {
+        log_groups,
+        next_token: response.next_token,
+    })
+}
+
+/// Fields the viewer needs from every event
+const VIEWER_FIELDS: &str = "fields @timestamp, @message, @log, @logStream, @ingestionTime";
+
+/// Runs the viewer's query, a configured `query` is run with the fields the viewer needs added.
+/// Events come back in time order, unless `query` sorts them itself
 pub async fn fetch_logs(
-    log_group_name: String,
+    connection: Connection,
+    log_group_names: Vec<String>,
+    query: Option<String>,
     start: i64,
     end: i64,
-) -> Result<Vec<String>, String> {
-    let config = aws_config::load_from_env().await;
-    let client = aws_sdk_cloudwatchlogs::Client::new(&config);
+) -> Result<Vec<LogEvent>, String> {
+    let sorted = query.as_deref().is_some_and(query::sorts);
+    let query_string = match query {
+        Some(query) => format!("{} | {}", query, VIEWER_FIELDS),
+        None => VIEWER_FIELDS.to_string(),
+    };
+    let rows = query_rows(connection, log_group_names, query_string, start, end).await?;
+    let mut events = rows
+        .into_iter()
+        .map(log_event_from_row)
+        .collect::<Vec<LogEvent>>();
+    // results from several groups come back interleaved, merge them by time
+    if !sorted {
+        events.sort_by_key(|event| event.timestamp);
+    }
+    Ok(events)
+}
+
+/// Runs a Logs Insights query and waits for every row of its results
+pub async fn run_query(
+    connection: Connection,
+    log_group_names: Vec<String>,
+    query_string: String,
+    start: i64,
+    end: i64,
+) -> Result<Vec<QueryRow>, String> {
+    let rows = query_rows(connection, log_group_names, query_string, start, end).await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                // @ptr is an internal pointer to the event, never useful to show
+                .filter(|(field, _)| field != "@ptr")
+                .collect()
+        })
+        .collect())
+}
+
+/// Insights stops queries that run for longer than this
+pub const QUERY_TIME_LIMIT_MINUTES: u64 = 60;
+
+static QUERIES_QUEUED: AtomicUsize = AtomicUsize::new(0);
+static QUERIES_RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+/// Insights queries started by any call that are queued or running right now
+pub fn query_counts() -> QueryCounts {
+    QueryCounts {
+        queued: QUERIES_QUEUED.load(Ordering::Relaxed),
+        running: QUERIES_RUNNING.load(Ordering::Relaxed),
+    }
+}
+
+/// Counts a started query as queued, then running, until it's dropped. Dropped covers both
+/// finishing and the task polling it being cancelled
+struct CountedQuery {
+    running: bool,
+}
+
+impl CountedQuery {
+    fn start() -> CountedQuery {
+        QUERIES_QUEUED.fetch_add(1, Ordering::Relaxed);
+        CountedQuery { running: false }
+    }
+
+    fn set_running(&mut self) {
+        if !self.running {
+            QUERIES_QUEUED.fetch_sub(1, Ordering::Relaxed);
+            QUERIES_RUNNING.fetch_add(1, Ordering::Relaxed);
+            self.running = true;
+        }
+    }
+}
+
+impl Drop for CountedQuery {
+    fn drop(&mut self) {
+        let counter = if self.running {
+            &QUERIES_RUNNING
+        } else {
+            &QUERIES_QUEUED
+        };
+        counter.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Like `run_query`, keeping the `@ptr` of every row
+#[instrument(level = "debug", skip_all, fields(region = ?connection.region, groups = ?log_group_names, query = %query_string), err)]
+async fn query_rows(
+    connection: Connection,
+    log_group_names: Vec<String>,
+    query_string: String,
+    start: i64,
+    end: i64,
+) -> Result<Vec<QueryRow>, String> {
+    let client = client(connection).await;
     let query_id = match client
         .start_query()
         .set_start_time(Some(start))
         .set_end_time(Some(end))
-        .set_query_string(Some("fields @message".into()))
-        .set_log_group_name(log_group_name.into())
+        .set_query_string(Some(query_string))
+        .set_log_group_names(Some(log_group_names))
         .send()
         .await
     {
         Ok(response) => response.query_id,
-        Err(e) => return Err(e.to_string()),
+        Err(e) => return Err(error_message(e)),
     };
+    let mut counted = CountedQuery::start();
 
     loop {
-        tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+        tokio::time::sleep(Duration::from_millis(250)).await;
 
         match client
             .get_query_results()
@@ -30,25 +286,570 @@ pub async fn fetch_logs(
             .await
         {
             Ok(response) => {
-                let messages = response
+                let rows = response
                     .results
                     .unwrap_or_default()
                     .into_iter()
-                    .flatten()
-                    .filter(|result| result.field == Some("@message".to_string()))
-                    .map(|result| result.value.unwrap_or_default())
-                    .rev()
-                    .collect::<Vec<String>>();
+                    .map(row_from_fields)
+                    .collect::<Vec<QueryRow>>();
 
                 match response.status {
-                    Some(QueryStatus::Complete) => return Ok(messages),
-                    Some(status @ (QueryStatus::Failed | QueryStatus::Timeout)) => {
-                        return Err(status.to_string())
+                    Some(QueryStatus::Complete) => return Ok(rows),
+                    Some(QueryStatus::Timeout) => {
+                        return Err(format!(
+                            "the query ran into the {} minute limit, try a narrower time range",
+                            QUERY_TIME_LIMIT_MINUTES
+                        ))
+                    }
+                    Some(QueryStatus::Failed) => return Err("the query failed".to_string()),
+                    Some(QueryStatus::Cancelled) => {
+                        return Err("the query was cancelled".to_string())
+                    }
+                    Some(QueryStatus::Running) if !counted.running => {
+                        debug!(?query_id, "query started running");
+                        counted.set_running();
                     }
                     _ => {}
                 }
             }
-            Err(e) => panic!("Error: {:?}", e),
+            Err(e) => return Err(error_message(e)),
         };
     }
 }
+
+/// Fetches through FilterLogEvents so streams can be narrowed by prefix server-side
+#[instrument(level = "debug", skip_all, fields(region = ?connection.region, groups = ?log_group_names, stream_prefix = %log_stream_name_prefix), err)]
+pub async fn filter_logs(
+    connection: Connection,
+    log_group_names: Vec<String>,
+    log_stream_name_prefix: String,
+    start: i64,
+    end: i64,
+) -> Result<Vec<LogEvent>, String> {
+    let client = client(connection).await;
+
+    let mut events = vec![];
+    for log_group_name in log_group_names {
+        let log_group: Arc<str> = log_group_name.as_str().into();
+        let mut group_events = vec![];
+        let mut next_token = None;
+        loop {
+            let response = match client
+                .filter_log_events()
+                .log_group_name(&log_group_name)
+                .log_stream_name_prefix(&log_stream_name_prefix)
+                .start_time(start)
+                .end_time(end)
+                .set_next_token(next_token)
+                .send()
+                .await
+            {
+                Ok(response) => response,
+                Err(e) => return Err(error_message(e)),
+            };
+            group_events.extend(
+                response
+                    .events
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|event| LogEvent {
+                        timestamp: event.timestamp.unwrap_or_default(),
+                        message: event.message.unwrap_or_default().into(),
+                        log_group: log_group.clone(),
+                        log_stream: event.log_stream_name.unwrap_or_default().into(),
+                        pointer: None,
+                        ingestion_time: event.ingestion_time,
+                        event_id: event.event_id.map(Into::into),
+                        envelope: None,
+                        fields: vec![],
+                    }),
+            );
+            // pages come oldest first, the viewer says when the later ones were left out
+            if group_events.len() >= MAX_FILTERED_EVENTS {
+                group_events.truncate(MAX_FILTERED_EVENTS);
+                break;
+            }
+            if response.next_token.is_none() {
+                break;
+            }
+            next_token = response.next_token;
+        }
+        events.extend(group_events);
+    }
+    events.sort_by_key(|event| event.timestamp);
+    Ok(events)
+}
+
+#[instrument(level = "debug", skip_all, fields(region = ?connection.region, group = %log_group_name, retention_in_days), err)]
+pub async fn put_retention_policy(
+    connection: Connection,
+    log_group_name: String,
+    retention_in_days: i32,
+) -> Result<(), String> {
+    client(connection)
+        .await
+        .put_retention_policy()
+        .log_group_name(log_group_name)
+        .retention_in_days(retention_in_days)
+        .send()
+        .await
+        .map(|_| ())
+        .map_err(error_message)
+}
+
+#[instrument(level = "debug", skip_all, fields(region = ?connection.region, group = %log_group_arn, key = %key), err)]
+pub async fn tag_log_group(
+    connection: Connection,
+    log_group_arn: String,
+    key: String,
+    value: String,
+) -> Result<(), String> {
+    client(connection)
+        .await
+        .tag_resource()
+        .resource_arn(log_group_arn)
+        .set_tags(Some(HashMap::from([(key, value)])))
+        .send()
+        .await
+        .map(|_| ())
+        .map_err(error_message)
+}
+
+#[instrument(level = "debug", skip_all, fields(region = ?connection.region, group = %log_group_name), err)]
+pub async fn delete_log_group(
+    connection: Connection,
+    log_group_name: String,
+) -> Result<(), String> {
+    client(connection)
+        .await
+        .delete_log_group()
+        .log_group_name(log_group_name)
+        .send()
+        .await
+        .map(|_| ())
+        .map_err(error_message)
+}
+
+/// Every metric filter on the group
+#[instrument(level = "debug", skip_all, fields(region = ?connection.region, group = %log_group_name), err)]
+pub async fn fetch_metric_filters(
+    connection: Connection,
+    log_group_name: String,
+) -> Result<Vec<MetricFilter>, String> {
+    let client = client(connection).await;
+    let mut filters = vec![];
+    let mut next_token = None;
+    loop {
+        let response = client
+            .describe_metric_filters()
+            .log_group_name(&log_group_name)
+            .set_next_token(next_token)
+            .send()
+            .await
+            .map_err(error_message)?;
+        filters.extend(
+            response
+                .metric_filters
+                .unwrap_or_default()
+                .into_iter()
+                .map(|filter| {
+                    // a filter publishes a single metric, the API only takes one transformation
+                    let transformation = filter
+                        .metric_transformations
+                        .and_then(|transformations| transformations.into_iter().next());
+                    MetricFilter {
+                        name: filter.filter_name.unwrap_or_default(),
+                        pattern: filter.filter_pattern.unwrap_or_default(),
+                        metric_name: transformation.as_ref().map(|t| t.metric_name.clone()),
+                        namespace: transformation.map(|t| t.metric_namespace),
+                    }
+                }),
+        );
+        if response.next_token.is_none() {
+            return Ok(filters);
+        }
+        next_token = response.next_token;
+    }
+}
+
+/// Metric alarms in the ALARM state, composite alarms have no metric to trace back to logs
+#[instrument(level = "debug", skip_all, fields(region = ?connection.region), err)]
+pub async fn fetch_alarms(connection: Connection) -> Result<Vec<Alarm>, String> {
+    let region = connection.region.clone();
+    let client = aws_sdk_cloudwatch::Client::new(&sdk_config(connection).await);
+    let mut alarms = vec![];
+    let mut next_token = None;
+    loop {
+        let response = client
+            .describe_alarms()
+            .state_value(StateValue::Alarm)
+            .alarm_types(AlarmType::MetricAlarm)
+            .set_next_token(next_token)
+            .send()
+            .await
+            .map_err(error_message)?;
+        alarms.extend(
+            response
+                .metric_alarms
+                .unwrap_or_default()
+                .into_iter()
+                .map(|alarm| to_alarm(alarm, region.clone())),
+        );
+        if response.next_token.is_none() {
+            return Ok(alarms);
+        }
+        next_token = response.next_token;
+    }
+}
+
+/// Alarms watching `metric_name` in `namespace`, whatever their state
+#[instrument(level = "debug", skip_all, fields(region = ?connection.region, metric = %metric_name), err)]
+pub async fn fetch_metric_alarms(
+    connection: Connection,
+    metric_name: String,
+    namespace: String,
+) -> Result<Vec<Alarm>, String> {
+    let region = connection.region.clone();
+    let client = aws_sdk_cloudwatch::Client::new(&sdk_config(connection).await);
+    let response = client
+        .describe_alarms_for_metric()
+        .metric_name(metric_name)
+        .namespace(namespace)
+        .send()
+        .await
+        .map_err(error_message)?;
+    Ok(response
+        .metric_alarms
+        .unwrap_or_default()
+        .into_iter()
+        .map(|alarm| to_alarm(alarm, region.clone()))
+        .collect())
+}
+
+fn to_alarm(alarm: MetricAlarm, region: Option<String>) -> Alarm {
+    Alarm {
+        name: alarm.alarm_name.unwrap_or_default(),
+        region,
+        metric_name: alarm.metric_name,
+        namespace: alarm.namespace,
+        state: alarm
+            .state_value
+            .map(|state| state.as_str().to_string())
+            .unwrap_or_default(),
+        reason: alarm.state_reason.unwrap_or_default(),
+        state_updated: alarm
+            .state_updated_timestamp
+            .and_then(|updated| updated.to_millis().ok())
+            .unwrap_or_default(),
+    }
+}
+
+/// Names of the groups with a metric filter publishing `metric_name` in `namespace`
+#[instrument(level = "debug", skip_all, fields(region = ?connection.region, metric = %metric_name), err)]
+pub async fn fetch_metric_filter_groups(
+    connection: Connection,
+    metric_name: String,
+    namespace: String,
+) -> Result<Vec<String>, String> {
+    let client = client(connection).await;
+    let mut names = vec![];
+    let mut next_token = None;
+    loop {
+        let response = client
+            .describe_metric_filters()
+            .metric_name(&metric_name)
+            .metric_namespace(&namespace)
+            .set_next_token(next_token)
+            .send()
+            .await
+            .map_err(error_message)?;
+        for name in response
+            .metric_filters
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|filter| filter.log_group_name)
+        {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        if response.next_token.is_none() {
+            return Ok(names);
+        }
+        next_token = response.next_token;
+    }
+}
+
+/// The group's tags and when its newest event was logged, two calls for every group
+#[instrument(level = "debug", skip_all, fields(region = ?connection.region, group = %log_group_name), err)]
+pub async fn fetch_group_metadata(
+    connection: Connection,
+    log_group_name: String,
+    log_group_arn: String,
+) -> Result<GroupMetadata, String> {
+    let client = client(connection).await;
+    let (tags, streams) = futures::future::join(
+        client
+            .list_tags_for_resource()
+            .resource_arn(log_group_arn)
+            .send(),
+        client
+            .describe_log_streams()
+            .log_group_name(log_group_name)
+            .order_by(OrderBy::LastEventTime)
+            .descending(true)
+            .limit(1)
+            .send(),
+    )
+    .await;
+    let mut tags = tags
+        .map_err(error_message)?
+        .tags
+        .unwrap_or_default()
+        .into_iter()
+        .collect::<Vec<_>>();
+    tags.sort();
+    let last_event = streams
+        .map_err(error_message)?
+        .log_streams
+        .unwrap_or_default()
+        .into_iter()
+        .find_map(|stream| stream.last_event_timestamp);
+    Ok(GroupMetadata { last_event, tags })
+}
+
+/// Names of the fields indexed on the group, queries filtering on them scan less
+#[instrument(level = "debug", skip_all, fields(region = ?connection.region, group = %log_group_name), err)]
+pub async fn fetch_field_indexes(
+    connection: Connection,
+    log_group_name: String,
+) -> Result<Vec<String>, String> {
+    let client = client(connection).await;
+    let mut fields = vec![];
+    let mut next_token = None;
+    loop {
+        let response = client
+            .describe_field_indexes()
+            .log_group_identifiers(&log_group_name)
+            .set_next_token(next_token)
+            .send()
+            .await
+            .map_err(error_message)?;
+        fields.extend(
+            response
+                .field_indexes
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|index| index.field_index_name),
+        );
+        if response.next_token.is_none() {
+            return Ok(fields);
+        }
+        next_token = response.next_token;
+    }
+}
+
+/// TestMetricFilter takes at most this many messages per call
+const METRIC_FILTER_BATCH: usize = 50;
+
+/// Indices into `messages` of the ones `pattern` matches
+#[instrument(level = "debug", skip_all, fields(region = ?connection.region, pattern = %pattern, messages = messages.len()), err)]
+pub async fn test_metric_filter(
+    connection: Connection,
+    pattern: String,
+    messages: Vec<String>,
+) -> Result<Vec<usize>, String> {
+    let client = client(connection).await;
+    let mut matches = vec![];
+    for (batch_index, batch) in messages.chunks(METRIC_FILTER_BATCH).enumerate() {
+        let response = client
+            .test_metric_filter()
+            .filter_pattern(&pattern)
+            .set_log_event_messages(Some(batch.to_vec()))
+            .send()
+            .await
+            .map_err(error_message)?;
+        let matched = response
+            .matches
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|record| record.event_message)
+            .collect::<Vec<_>>();
+        // matched by message, identical messages always match alike
+        matches.extend(
+            batch
+                .iter()
+                .enumerate()
+                .filter(|(_, message)| matched.contains(message))
+                .map(|(index, _)| batch_index * METRIC_FILTER_BATCH + index),
+        );
+    }
+    Ok(matches)
+}
+
+/// The event's message with masked values revealed, needs the `logs:Unmask` permission
+#[instrument(level = "debug", skip_all, fields(region = ?connection.region, pointer = %pointer), err)]
+pub async fn get_unmasked_message(
+    connection: Connection,
+    pointer: String,
+) -> Result<String, String> {
+    let response = client(connection)
+        .await
+        .get_log_record()
+        .log_record_pointer(pointer)
+        .unmask(true)
+        .send()
+        .await
+        .map_err(error_message)?;
+    response
+        .log_record
+        .and_then(|mut record| record.remove("@message"))
+        .ok_or_else(|| "the event has no message".to_string())
+}
+
+/// Every field of the event, including the ones Logs Insights discovered, sorted by name
+#[instrument(level = "debug", skip_all, fields(region = ?connection.region, pointer = %pointer), err)]
+pub async fn get_log_record(connection: Connection, pointer: String) -> Result<QueryRow, String> {
+    let response = client(connection)
+        .await
+        .get_log_record()
+        .log_record_pointer(pointer)
+        .send()
+        .await
+        .map_err(error_message)?;
+    let mut fields = response
+        .log_record
+        .unwrap_or_default()
+        .into_iter()
+        .collect::<QueryRow>();
+    fields.sort();
+    Ok(fields)
+}
+
+fn row_from_fields(fields: Vec<ResultField>) -> QueryRow {
+    fields
+        .into_iter()
+        .map(|field| {
+            (
+                field.field.unwrap_or_default(),
+                field.value.unwrap_or_default(),
+            )
+        })
+        .collect()
+}
+
+/// Milliseconds since the epoch from a time in query results, e.g. `2024-01-01 10:00:00.000`
+pub fn parse_query_timestamp(value: &str) -> Option<i64> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.3f")
+        .ok()
+        .map(|timestamp| timestamp.and_utc().timestamp_millis())
+}
+
+/// Fields the viewer has no column for are kept apart from the message, which stays as logged
+fn log_event_from_row(row: QueryRow) -> LogEvent {
+    let mut event = LogEvent::default();
+    for (field, value) in row {
+        match field.as_str() {
+            "@timestamp" => event.timestamp = parse_query_timestamp(&value).unwrap_or_default(),
+            "@message" => event.message = value.into(),
+            // @log is "<account id>:<log group name>"
+            "@log" => {
+                event.log_group = value
+                    .split_once(':')
+                    .map_or(value.as_str(), |(_, group)| group)
+                    .into()
+            }
+            "@logStream" => event.log_stream = value.into(),
+            "@ptr" => event.pointer = Some(value.into()),
+            "@ingestionTime" => event.ingestion_time = parse_query_timestamp(&value),
+            _ => event.fields.push((field.into(), value.into())),
+        }
+    }
+    event
+}
+
+pub struct LiveTail {
+    stream: EventReceiver<StartLiveTailResponseStream, StartLiveTailResponseStreamError>,
+    /// the last update held a sample of the events rather than all of them
+    pub sampled: bool,
+}
+
+#[instrument(level = "debug", skip_all, fields(region = ?connection.region, groups = ?log_group_arns), err)]
+pub async fn start_live_tail(
+    connection: Connection,
+    log_group_arns: Vec<String>,
+    log_stream_name_prefix: String,
+    filter_pattern: String,
+) -> Result<LiveTail, String> {
+    let log_stream_name_prefixes = if log_stream_name_prefix.is_empty() {
+        None
+    } else if log_group_arns.len() > 1 {
+        return Err("stream prefixes can only be tailed for a single log group".into());
+    } else {
+        Some(vec![log_stream_name_prefix])
+    };
+
+    let client = client(connection).await;
+    match client
+        .start_live_tail()
+        .set_log_group_identifiers(Some(log_group_arns))
+        .set_log_stream_name_prefixes(log_stream_name_prefixes)
+        .set_log_event_filter_pattern(Some(filter_pattern).filter(|pattern| !pattern.is_empty()))
+        .send()
+        .await
+    {
+        Ok(response) => Ok(LiveTail {
+            stream: response.response_stream,
+            sampled: false,
+        }),
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+impl LiveTail {
+    /// Waits for the next batch of tailed events, `None` once the session has ended.
+    pub async fn next_events(&mut self) -> Result<Option<Vec<LogEvent>>, String> {
+        loop {
+            match self.stream.recv().await {
+                Ok(Some(StartLiveTailResponseStream::SessionUpdate(update))) => {
+                    self.sampled = update
+                        .session_metadata
+                        .is_some_and(|metadata| metadata.sampled);
+                    let mut events = update
+                        .session_results
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|event| LogEvent {
+                            timestamp: event.timestamp.unwrap_or_default(),
+                            message: event.message.unwrap_or_default().into(),
+                            log_group: log_group_name_from_identifier(
+                                &event.log_group_identifier.unwrap_or_default(),
+                            )
+                            .into(),
+                            log_stream: event.log_stream_name.unwrap_or_default().into(),
+                            pointer: None,
+                            ingestion_time: event.ingestion_time,
+                            event_id: None,
+                            envelope: None,
+                            fields: vec![],
+                        })
+                        .collect::<Vec<LogEvent>>();
+                    events.sort_by_key(|event| event.timestamp);
+                    return Ok(Some(events));
+                }
+                Ok(Some(_)) => {}
+                Ok(None) => return Ok(None),
+                Err(e) => return Err(error_message(e)),
+            }
+        }
+    }
+}
+
+/// Live tail identifies groups by ARN, turn it back into the plain group name
+fn log_group_name_from_identifier(identifier: &str) -> String {
+    identifier
+        .split_once(":log-group:")
+        .map(|(_, name)| name.trim_end_matches(":*"))
+        .unwrap_or(identifier)
+        .to_string()
+}
@@ -0,0 +1,434 @@
+use std::{
+    fmt::Debug,
+    sync::{Arc, Mutex},
+};
+
+use futures::{future::BoxFuture, FutureExt};
+use serde::Serialize;
+
+use crate::{
+    aws,
+    shared::{
+        Account, Alarm, GroupMetadata, LogEvent, LogGroup, LogGroupPage, MetricFilter, QueryCounts,
+        QueryRow,
+    },
+    sso,
+};
+
+/// Everything the UI needs from CloudWatch, so components can be driven by a fake in tests
+/// `region` is `None` for the region configured in the environment
+pub trait LogBackend: Debug + Send + Sync {
+    /// Every region groups can be discovered in
+    fn regions(&self) -> Vec<String>;
+
+    fn fetch_log_groups(
+        &self,
+        region: Option<String>,
+        next_token: Option<String>,
+    ) -> BoxFuture<'_, Result<LogGroupPage, String>>;
+
+    /// The viewer's events, `query` is a configured Insights query to run instead of the built in one
+    fn fetch_logs(
+        &self,
+        region: Option<String>,
+        log_group_names: Vec<String>,
+        query: Option<String>,
+        start: i64,
+        end: i64,
+    ) -> BoxFuture<'_, Result<Vec<LogEvent>, String>>;
+
+    fn filter_logs(
+        &self,
+        region: Option<String>,
+        log_group_names: Vec<String>,
+        log_stream_name_prefix: String,
+        start: i64,
+        end: i64,
+    ) -> BoxFuture<'_, Result<Vec<LogEvent>, String>>;
+
+    /// Runs an arbitrary Logs Insights query, used by `loglog query`
+    fn run_query(
+        &self,
+        region: Option<String>,
+        log_group_names: Vec<String>,
+        query: String,
+        start: i64,
+        end: i64,
+    ) -> BoxFuture<'_, Result<Vec<QueryRow>, String>>;
+
+    /// Empty `log_stream_name_prefix` and `filter_pattern` follow every stream and event, the
+    /// pattern is CloudWatch's filter syntax and applied before events are sent
+    fn start_live_tail(
+        &self,
+        region: Option<String>,
+        log_group_arns: Vec<String>,
+        log_stream_name_prefix: String,
+        filter_pattern: String,
+    ) -> BoxFuture<'_, Result<Box<dyn LiveTailSession>, String>>;
+
+    fn change_log_group(
+        &self,
+        log_group: LogGroup,
+        change: GroupChange,
+    ) -> BoxFuture<'_, Result<(), String>>;
+
+    /// The metric filters already set up on the group
+    fn fetch_metric_filters(
+        &self,
+        region: Option<String>,
+        log_group_name: String,
+    ) -> BoxFuture<'_, Result<Vec<MetricFilter>, String>>;
+
+    /// Names of the fields indexed on the group
+    fn fetch_field_indexes(
+        &self,
+        region: Option<String>,
+        log_group_name: String,
+    ) -> BoxFuture<'_, Result<Vec<String>, String>>;
+
+    /// The group's tags and when its newest event was logged
+    fn fetch_group_metadata(
+        &self,
+        region: Option<String>,
+        log_group_name: String,
+        log_group_arn: String,
+    ) -> BoxFuture<'_, Result<GroupMetadata, String>>;
+
+    /// Alarms that are going off right now
+    fn fetch_alarms(&self, region: Option<String>) -> BoxFuture<'_, Result<Vec<Alarm>, String>>;
+
+    /// Names of the groups whose metric filters publish the metric an alarm watches
+    fn fetch_metric_filter_groups(
+        &self,
+        region: Option<String>,
+        metric_name: String,
+        namespace: String,
+    ) -> BoxFuture<'_, Result<Vec<String>, String>>;
+
+    /// Alarms watching a metric, in any state
+    fn fetch_metric_alarms(
+        &self,
+        region: Option<String>,
+        metric_name: String,
+        namespace: String,
+    ) -> BoxFuture<'_, Result<Vec<Alarm>, String>>;
+
+    /// The message behind `pointer` with its masked values revealed
+    fn fetch_unmasked(
+        &self,
+        region: Option<String>,
+        pointer: String,
+    ) -> BoxFuture<'_, Result<String, String>>;
+
+    /// Indices into `messages` of the ones the metric filter `pattern` would match
+    fn test_metric_filter(
+        &self,
+        region: Option<String>,
+        pattern: String,
+        messages: Vec<String>,
+    ) -> BoxFuture<'_, Result<Vec<usize>, String>>;
+
+    /// Every field of the event behind `pointer`, sorted by name
+    fn fetch_log_record(
+        &self,
+        region: Option<String>,
+        pointer: String,
+    ) -> BoxFuture<'_, Result<QueryRow, String>>;
+
+    /// Profiles in the AWS config and the roles its SSO sessions reach, to switch between
+    fn fetch_accounts(&self) -> BoxFuture<'_, Result<Vec<Account>, String>>;
+
+    /// Makes every later call with `account`'s credentials
+    fn switch_account(&self, account: Account) -> BoxFuture<'_, Result<(), String>>;
+
+    /// Insights queries started through this backend that are queued or running right now
+    fn query_counts(&self) -> QueryCounts;
+}
+
+/// Changes to a group, applied to several at once from the group list
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum GroupChange {
+    SetRetention(i32),
+    Tag(String, String),
+    Delete,
+}
+
+pub trait LiveTailSession: Send {
+    /// Waits for the next batch of tailed events, `None` once the session has ended.
+    fn next_events(&mut self) -> BoxFuture<'_, Result<Option<Vec<LogEvent>>, String>>;
+
+    /// Whether the last batch was a sample, CloudWatch stops sending every event once the
+    /// tailed groups log more than it streams
+    fn is_sampled(&self) -> bool {
+        false
+    }
+}
+
+/// Talks to CloudWatch, `region` is used whenever a call doesn't name one
+#[derive(Debug, Clone, Default)]
+pub struct AwsBackend {
+    pub profile: Option<String>,
+    pub region: Option<String>,
+    /// the account switched to, used instead of `profile`
+    switched: Arc<Mutex<Option<aws::Connection>>>,
+}
+
+impl AwsBackend {
+    pub fn new(profile: Option<String>, region: Option<String>) -> Self {
+        Self {
+            profile,
+            region,
+            switched: Arc::default(),
+        }
+    }
+
+    fn connection(&self, region: Option<String>) -> aws::Connection {
+        let switched = self.switched.lock().unwrap().clone();
+        let connection = switched.unwrap_or_else(|| aws::Connection {
+            profile: self.profile.clone(),
+            ..Default::default()
+        });
+        aws::Connection {
+            region: region.or_else(|| self.region.clone()),
+            ..connection
+        }
+    }
+}
+
+impl LogBackend for AwsBackend {
+    fn regions(&self) -> Vec<String> {
+        aws::REGIONS
+            .iter()
+            .map(|region| region.to_string())
+            .collect()
+    }
+
+    fn fetch_log_groups(
+        &self,
+        region: Option<String>,
+        next_token: Option<String>,
+    ) -> BoxFuture<'_, Result<LogGroupPage, String>> {
+        aws::fetch_log_groups(self.connection(region), next_token).boxed()
+    }
+
+    fn fetch_logs(
+        &self,
+        region: Option<String>,
+        log_group_names: Vec<String>,
+        query: Option<String>,
+        start: i64,
+        end: i64,
+    ) -> BoxFuture<'_, Result<Vec<LogEvent>, String>> {
+        aws::fetch_logs(self.connection(region), log_group_names, query, start, end).boxed()
+    }
+
+    fn filter_logs(
+        &self,
+        region: Option<String>,
+        log_group_names: Vec<String>,
+        log_stream_name_prefix: String,
+        start: i64,
+        end: i64,
+    ) -> BoxFuture<'_, Result<Vec<LogEvent>, String>> {
+        aws::filter_logs(
+            self.connection(region),
+            log_group_names,
+            log_stream_name_prefix,
+            start,
+            end,
+        )
+        .boxed()
+    }
+
+    fn run_query(
+        &self,
+        region: Option<String>,
+        log_group_names: Vec<String>,
+        query: String,
+        start: i64,
+        end: i64,
+    ) -> BoxFuture<'_, Result<Vec<QueryRow>, String>> {
+        aws::run_query(self.connection(region), log_group_names, query, start, end).boxed()
+    }
+
+    fn start_live_tail(
+        &self,
+        region: Option<String>,
+        log_group_arns: Vec<String>,
+        log_stream_name_prefix: String,
+        filter_pattern: String,
+    ) -> BoxFuture<'_, Result<Box<dyn LiveTailSession>, String>> {
+        let connection = self.connection(region);
+        async move {
+            let live_tail = aws::start_live_tail(
+                connection,
+                log_group_arns,
+                log_stream_name_prefix,
+                filter_pattern,
+            )
+            .await?;
+            Ok(Box::new(live_tail) as Box<dyn LiveTailSession>)
+        }
+        .boxed()
+    }
+
+    fn change_log_group(
+        &self,
+        log_group: LogGroup,
+        change: GroupChange,
+    ) -> BoxFuture<'_, Result<(), String>> {
+        let connection = self.connection(log_group.region().map(str::to_string));
+        match change {
+            GroupChange::SetRetention(days) => {
+                aws::put_retention_policy(connection, log_group.name, days).boxed()
+            }
+            GroupChange::Tag(key, value) => {
+                aws::tag_log_group(connection, log_group.arn, key, value).boxed()
+            }
+            GroupChange::Delete => aws::delete_log_group(connection, log_group.name).boxed(),
+        }
+    }
+
+    fn fetch_metric_filters(
+        &self,
+        region: Option<String>,
+        log_group_name: String,
+    ) -> BoxFuture<'_, Result<Vec<MetricFilter>, String>> {
+        aws::fetch_metric_filters(self.connection(region), log_group_name).boxed()
+    }
+
+    fn fetch_field_indexes(
+        &self,
+        region: Option<String>,
+        log_group_name: String,
+    ) -> BoxFuture<'_, Result<Vec<String>, String>> {
+        aws::fetch_field_indexes(self.connection(region), log_group_name).boxed()
+    }
+
+    fn fetch_group_metadata(
+        &self,
+        region: Option<String>,
+        log_group_name: String,
+        log_group_arn: String,
+    ) -> BoxFuture<'_, Result<GroupMetadata, String>> {
+        aws::fetch_group_metadata(self.connection(region), log_group_name, log_group_arn).boxed()
+    }
+
+    fn fetch_alarms(&self, region: Option<String>) -> BoxFuture<'_, Result<Vec<Alarm>, String>> {
+        aws::fetch_alarms(self.connection(region)).boxed()
+    }
+
+    fn fetch_metric_filter_groups(
+        &self,
+        region: Option<String>,
+        metric_name: String,
+        namespace: String,
+    ) -> BoxFuture<'_, Result<Vec<String>, String>> {
+        aws::fetch_metric_filter_groups(self.connection(region), metric_name, namespace).boxed()
+    }
+
+    fn fetch_metric_alarms(
+        &self,
+        region: Option<String>,
+        metric_name: String,
+        namespace: String,
+    ) -> BoxFuture<'_, Result<Vec<Alarm>, String>> {
+        aws::fetch_metric_alarms(self.connection(region), metric_name, namespace).boxed()
+    }
+
+    fn fetch_unmasked(
+        &self,
+        region: Option<String>,
+        pointer: String,
+    ) -> BoxFuture<'_, Result<String, String>> {
+        aws::get_unmasked_message(self.connection(region), pointer).boxed()
+    }
+
+    fn test_metric_filter(
+        &self,
+        region: Option<String>,
+        pattern: String,
+        messages: Vec<String>,
+    ) -> BoxFuture<'_, Result<Vec<usize>, String>> {
+        aws::test_metric_filter(self.connection(region), pattern, messages).boxed()
+    }
+
+    fn fetch_log_record(
+        &self,
+        region: Option<String>,
+        pointer: String,
+    ) -> BoxFuture<'_, Result<QueryRow, String>> {
+        aws::get_log_record(self.connection(region), pointer).boxed()
+    }
+
+    fn fetch_accounts(&self) -> BoxFuture<'_, Result<Vec<Account>, String>> {
+        async {
+            let file = sso::AwsConfigFile::load()?;
+            let results =
+                futures::future::join_all(file.sso_sessions.iter().map(sso::list_accounts)).await;
+            let mut accounts = file
+                .profiles
+                .iter()
+                .cloned()
+                .map(Account::Profile)
+                .collect::<Vec<_>>();
+            // a session that isn't logged in shouldn't hide the others
+            let mut error = None;
+            for result in results {
+                match result {
+                    Ok(more) => accounts.extend(more),
+                    Err(e) => error = error.or(Some(e)),
+                }
+            }
+            match error {
+                Some(e) if accounts.is_empty() => Err(e),
+                _ => Ok(accounts),
+            }
+        }
+        .boxed()
+    }
+
+    fn switch_account(&self, account: Account) -> BoxFuture<'_, Result<(), String>> {
+        async move {
+            let connection = match account {
+                Account::Profile(name) => aws::Connection {
+                    profile: Some(name),
+                    ..Default::default()
+                },
+                Account::Sso {
+                    session,
+                    account_id,
+                    role_name,
+                    ..
+                } => {
+                    let file = sso::AwsConfigFile::load()?;
+                    let session = file
+                        .sso_session(&session)
+                        .ok_or_else(|| format!("no sso-session {} in the AWS config", session))?;
+                    aws::Connection {
+                        profile: self.profile.clone(),
+                        credentials: Some(sso::credentials(session, &account_id, &role_name)),
+                        ..Default::default()
+                    }
+                }
+            };
+            *self.switched.lock().unwrap() = Some(connection);
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn query_counts(&self) -> QueryCounts {
+        aws::query_counts()
+    }
+}
+
+impl LiveTailSession for aws::LiveTail {
+    fn next_events(&mut self) -> BoxFuture<'_, Result<Option<Vec<LogEvent>>, String>> {
+        aws::LiveTail::next_events(self).boxed()
+    }
+
+    fn is_sampled(&self) -> bool {
+        self.sampled
+    }
+}
@@ -0,0 +1,233 @@
+use std::{collections::BTreeMap, fs, io, path::PathBuf};
+
+use regex_lite::Regex;
+use serde::Deserialize;
+
+use crate::{
+    paths::{self, Dir},
+    timestamp::parse_timestamp,
+};
+
+/// User settings read from `config.json`, every field is optional
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// groups to hide from the list, globs like `*/test/*` or regexes wrapped in slashes
+    pub exclude_groups: Vec<String>,
+    /// queries estimated to scan more than this ask first, 0 never asks
+    pub confirm_query_bytes: i64,
+    /// events a viewer keeps in memory, older ones go to a temp file, 0 keeps them all
+    pub memory_events: usize,
+    /// count errors per hour next to every query and chart them in the viewer title
+    pub error_summary: bool,
+    pub region: Option<String>,
+    pub profile: Option<String>,
+    /// how far back `loglog query` looks, e.g. `2h`
+    pub since: Option<String>,
+    /// the query `loglog query` runs without `-q`
+    pub query: Option<String>,
+    /// the Insights query the viewer runs, e.g. `fields @message | filter @message not like /health/`
+    pub viewer_query: Option<String>,
+    /// viewer queries for groups matching a pattern, the first match wins over `viewer_query`
+    pub group_queries: Vec<GroupQuery>,
+    /// where the application's own time is in the messages of groups matching a pattern,
+    /// shipping agents that buffer skew `@timestamp` away from it
+    pub message_timestamps: Vec<MessageTimestamp>,
+    /// `text`, `json`, `ndjson` or `csv`
+    pub output: Option<String>,
+    /// `dark` or `light`, detected from the terminal when unset
+    pub theme: Option<String>,
+    /// queries running longer than this many seconds raise a desktop notification when they
+    /// finish while the terminal is in the background, 0 never notifies
+    pub notify_after_seconds: u64,
+    /// also notify when the terminal is in the foreground
+    pub notify_when_focused: bool,
+    /// investigation reports are written here, the directory loglog started in when unset
+    pub export_dir: Option<PathBuf>,
+    /// key overrides like `"viewer.search_token": ["g"]`, see `loglog keys` for every name
+    pub keys: BTreeMap<String, Vec<String>>,
+}
+
+/// A viewer query for the groups matching `groups`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct GroupQuery {
+    /// a glob like `/aws/lambda/*` or a regex wrapped in slashes
+    pub groups: String,
+    pub query: String,
+}
+
+/// How to read the time an application logged in the messages of the groups matching `groups`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct MessageTimestamp {
+    /// a glob like `/aws/lambda/*` or a regex wrapped in slashes
+    pub groups: String,
+    /// a regex whose first capture group is the time, e.g. `"time":"([^"]+)"`
+    pub pattern: String,
+    /// a strftime format like `%d/%m/%Y %H:%M:%S%.f`, any time `@` reads when unset
+    pub format: Option<String>,
+}
+
+/// The compiled `message_timestamps`, the first one matching a group is used
+#[derive(Debug, Clone, Default)]
+pub struct MessageTimestamps {
+    groups: Vec<(Regex, Regex, Option<String>)>,
+}
+
+impl MessageTimestamps {
+    pub fn from_config(config: &Config) -> Result<MessageTimestamps, String> {
+        let groups = config
+            .message_timestamps
+            .iter()
+            .map(|timestamp| {
+                let pattern = Regex::new(&timestamp.pattern)
+                    .map_err(|e| format!("invalid pattern {}: {}", timestamp.pattern, e))?;
+                if pattern.captures_len() < 2 {
+                    return Err(format!(
+                        "{} has no capture group for the time",
+                        timestamp.pattern
+                    ));
+                }
+                Ok((
+                    compile_pattern(&timestamp.groups)?,
+                    pattern,
+                    timestamp.format.clone(),
+                ))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(MessageTimestamps { groups })
+    }
+
+    /// Milliseconds since the epoch of the time in `message`, `None` when its group has no
+    /// pattern or the message has no time in it
+    pub fn extract(&self, log_group_name: &str, message: &str) -> Option<i64> {
+        let (_, pattern, format) = self
+            .groups
+            .iter()
+            .find(|(groups, _, _)| groups.is_match(log_group_name))?;
+        let text = pattern.captures(message)?.get(1)?.as_str();
+        match format {
+            Some(format) => chrono::DateTime::parse_from_str(text, format)
+                .map(|time| time.timestamp_millis())
+                .or_else(|_| {
+                    chrono::NaiveDateTime::parse_from_str(text, format)
+                        .map(|time| time.and_utc().timestamp_millis())
+                })
+                .ok(),
+            None => parse_timestamp(text, chrono::Utc::now().timestamp_millis()),
+        }
+    }
+}
+
+/// Which Insights query the viewer runs for each group, none keeps the built in one
+#[derive(Debug, Clone, Default)]
+pub struct ViewerQueries {
+    default: Option<String>,
+    groups: Vec<(Regex, String)>,
+}
+
+impl ViewerQueries {
+    pub fn from_config(config: &Config) -> Result<ViewerQueries, String> {
+        let groups = config
+            .group_queries
+            .iter()
+            .map(|group_query| {
+                Ok((
+                    compile_pattern(&group_query.groups)?,
+                    group_query.query.clone(),
+                ))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(ViewerQueries {
+            default: config.viewer_query.clone(),
+            groups,
+        })
+    }
+
+    pub fn for_group(&self, log_group_name: &str) -> Option<&str> {
+        self.groups
+            .iter()
+            .find(|(pattern, _)| pattern.is_match(log_group_name))
+            .map(|(_, query)| query.as_str())
+            .or(self.default.as_deref())
+    }
+}
+
+/// 100 GB, well past anything a day of a busy service usually stores
+pub const DEFAULT_CONFIRM_QUERY_BYTES: i64 = 100 * 1024 * 1024 * 1024;
+
+/// A million events is a few hundred MB with typical message sizes
+pub const DEFAULT_MEMORY_EVENTS: usize = 1_000_000;
+
+/// Long enough to have switched to something else while waiting
+pub const DEFAULT_NOTIFY_AFTER_SECONDS: u64 = 30;
+
+/// What the viewer asks for when no query is configured
+pub const DEFAULT_VIEWER_QUERY: &str = "fields @timestamp, @message";
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            exclude_groups: vec![],
+            confirm_query_bytes: DEFAULT_CONFIRM_QUERY_BYTES,
+            memory_events: DEFAULT_MEMORY_EVENTS,
+            error_summary: false,
+            region: None,
+            profile: None,
+            since: None,
+            query: None,
+            viewer_query: None,
+            group_queries: vec![],
+            message_timestamps: vec![],
+            output: None,
+            theme: None,
+            notify_after_seconds: DEFAULT_NOTIFY_AFTER_SECONDS,
+            notify_when_focused: false,
+            export_dir: None,
+            keys: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    /// `config.json` in the platform's config directory
+    pub fn default_path() -> Option<PathBuf> {
+        Some(paths::dir(Dir::Config)?.join("config.json"))
+    }
+
+    /// A missing file is an empty config, a broken one is an error worth stopping for
+    pub fn load(path: Option<PathBuf>) -> Result<Config, String> {
+        let Some(path) = path else {
+            return Ok(Config::default());
+        };
+        match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| format!("invalid config {}: {}", path.display(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(format!("can't read config {}: {}", path.display(), e)),
+        }
+    }
+}
+
+/// Compiles a group pattern, `/.../` is a regex and anything else a glob matching the whole name
+pub fn compile_pattern(pattern: &str) -> Result<Regex, String> {
+    let regex = match pattern
+        .strip_prefix('/')
+        .and_then(|pattern| pattern.strip_suffix('/'))
+    {
+        Some(regex) if !regex.is_empty() => regex.to_string(),
+        _ => {
+            let glob = pattern
+                .split('*')
+                .map(|part| {
+                    part.split('?')
+                        .map(regex_lite::escape)
+                        .collect::<Vec<_>>()
+                        .join(".")
+                })
+                .collect::<Vec<_>>()
+                .join(".*");
+            format!("^{}$", glob)
+        }
+    };
+    Regex::new(&regex).map_err(|e| format!("invalid pattern {}: {}", pattern, e))
+}
@@ -0,0 +1,593 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use chrono::DateTime;
+use futures::{future::BoxFuture, FutureExt};
+
+use crate::{
+    backend::{GroupChange, LiveTailSession, LogBackend},
+    shared::{
+        Account, Alarm, GroupMetadata, LogEvent, LogGroup, LogGroupPage, MetricFilter, QueryCounts,
+        QueryRow,
+    },
+};
+
+const REGIONS: [&str; 2] = ["eu-west-1", "us-east-1"];
+
+/// Groups of every region, the ones under `/secure/` have a data protection policy
+const GROUPS: [&str; 8] = [
+    "/aws/lambda/checkout",
+    "/aws/lambda/payments",
+    "/aws/lambda/image-resizer",
+    "/ecs/orders-api",
+    "/ecs/inventory-worker",
+    "/aws/apigateway/storefront",
+    "/secure/customers",
+    "/aws/rds/instance/orders-db/postgresql",
+];
+
+/// Events of a group are this far apart, some are left out so they don't look evenly spaced
+const STEP_MS: i64 = 15_000;
+
+/// As many events as a Logs Insights query returns at most
+const MAX_EVENTS: usize = 10_000;
+
+const PATHS: [&str; 5] = ["/orders", "/orders/42", "/cart", "/checkout", "/health"];
+
+const CUSTOMERS: [&str; 3] = ["ada@example.com", "grace@example.com", "linus@example.com"];
+
+/// Alarms going off in every region: name, metric, the groups publishing it and how many
+/// minutes ago it fired
+const ALARMS: [(&str, &str, &[&str], i64); 2] = [
+    (
+        "checkout-errors",
+        "CheckoutErrors",
+        &["/aws/lambda/checkout", "/aws/lambda/payments"],
+        40,
+    ),
+    ("orders-api-5xx", "Orders5xx", &["/ecs/orders-api"], 185),
+];
+
+const ALARM_NAMESPACE: &str = "Demo";
+
+/// Accounts the demo's SSO session reaches: id, name and the roles in it
+const ACCOUNTS: [(&str, &str, &[&str]); 2] = [
+    ("111111111111", "shop-production", &["ReadOnly", "Admin"]),
+    ("222222222222", "shop-staging", &["Admin"]),
+];
+
+/// Serves made up groups and events without AWS, for `--demo`. Every event is generated
+/// from its group and timestamp, so fetching a range twice returns the same events
+#[derive(Debug)]
+pub struct DemoBackend {
+    /// changed by retention, tags and deletes from the group list
+    log_groups: Mutex<Vec<LogGroup>>,
+}
+
+impl Default for DemoBackend {
+    fn default() -> Self {
+        let log_groups = REGIONS
+            .iter()
+            .flat_map(|region| {
+                GROUPS
+                    .iter()
+                    .enumerate()
+                    .map(move |(index, name)| LogGroup {
+                        name: name.to_string(),
+                        arn: format!("arn:aws:logs:{}:123456789012:log-group:{}", region, name),
+                        stored_bytes: Some((mix(index as u64) % 50_000_000_000) as i64),
+                        retention_in_days: [None, Some(7), Some(30), Some(365)]
+                            .get(index % 4)
+                            .copied()
+                            .flatten(),
+                        creation_time: Some(1_600_000_000_000 + index as i64 * 86_400_000),
+                        data_protected: name.starts_with("/secure/"),
+                    })
+            })
+            .collect();
+        DemoBackend {
+            log_groups: Mutex::new(log_groups),
+        }
+    }
+}
+
+impl DemoBackend {
+    fn region(region: Option<String>) -> String {
+        region.unwrap_or_else(|| REGIONS[0].to_string())
+    }
+
+    /// The newest events of `log_group_names` between `start` and `end`, oldest first
+    fn events(&self, log_group_names: &[String], start: i64, end: i64) -> Vec<LogEvent> {
+        let mut events = vec![];
+        let last_slot = end.div_euclid(STEP_MS);
+        let first_slot = start.div_euclid(STEP_MS);
+        for slot in (first_slot..=last_slot).rev() {
+            for group in log_group_names {
+                let timestamp = slot * STEP_MS + (mix(seed(group, slot)) % STEP_MS as u64) as i64;
+                if (start..=end).contains(&timestamp)
+                    && !mix(seed(group, slot) ^ 1).is_multiple_of(3)
+                {
+                    events.push(event(group, timestamp));
+                }
+            }
+            if events.len() >= MAX_EVENTS {
+                break;
+            }
+        }
+        events.truncate(MAX_EVENTS);
+        events.sort_by_key(|event| event.timestamp);
+        events
+    }
+}
+
+impl LogBackend for DemoBackend {
+    fn regions(&self) -> Vec<String> {
+        REGIONS.iter().map(|region| region.to_string()).collect()
+    }
+
+    fn fetch_log_groups(
+        &self,
+        region: Option<String>,
+        _next_token: Option<String>,
+    ) -> BoxFuture<'_, Result<LogGroupPage, String>> {
+        let region = DemoBackend::region(region);
+        let log_groups = self
+            .log_groups
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|group| group.region() == Some(region.as_str()))
+            .cloned()
+            .collect();
+        futures::future::ready(Ok(LogGroupPage {
+            log_groups,
+            next_token: None,
+        }))
+        .boxed()
+    }
+
+    fn fetch_logs(
+        &self,
+        _region: Option<String>,
+        log_group_names: Vec<String>,
+        _query: Option<String>,
+        start: i64,
+        end: i64,
+    ) -> BoxFuture<'_, Result<Vec<LogEvent>, String>> {
+        futures::future::ready(Ok(self.events(&log_group_names, start, end))).boxed()
+    }
+
+    fn filter_logs(
+        &self,
+        _region: Option<String>,
+        log_group_names: Vec<String>,
+        log_stream_name_prefix: String,
+        start: i64,
+        end: i64,
+    ) -> BoxFuture<'_, Result<Vec<LogEvent>, String>> {
+        let mut events = self.events(&log_group_names, start, end);
+        events.retain(|event| event.log_stream.starts_with(&log_stream_name_prefix));
+        for event in &mut events {
+            event.event_id = event.pointer.take();
+        }
+        futures::future::ready(Ok(events)).boxed()
+    }
+
+    fn run_query(
+        &self,
+        _region: Option<String>,
+        log_group_names: Vec<String>,
+        _query: String,
+        start: i64,
+        end: i64,
+    ) -> BoxFuture<'_, Result<Vec<QueryRow>, String>> {
+        let rows = self
+            .events(&log_group_names, start, end)
+            .iter()
+            .rev()
+            .map(row)
+            .collect();
+        futures::future::ready(Ok(rows)).boxed()
+    }
+
+    fn start_live_tail(
+        &self,
+        _region: Option<String>,
+        log_group_arns: Vec<String>,
+        log_stream_name_prefix: String,
+        filter_pattern: String,
+    ) -> BoxFuture<'_, Result<Box<dyn LiveTailSession>, String>> {
+        let log_group_names = log_group_arns
+            .iter()
+            .map(|arn| arn.split(":log-group:").last().unwrap_or(arn).to_string())
+            .collect();
+        let tail = DemoTail {
+            log_group_names,
+            log_stream_name_prefix,
+            filter_pattern,
+            since: now(),
+        };
+        futures::future::ready(Ok(Box::new(tail) as Box<dyn LiveTailSession>)).boxed()
+    }
+
+    fn change_log_group(
+        &self,
+        log_group: LogGroup,
+        change: GroupChange,
+    ) -> BoxFuture<'_, Result<(), String>> {
+        let mut log_groups = self.log_groups.lock().unwrap();
+        match change {
+            GroupChange::SetRetention(days) => log_groups
+                .iter_mut()
+                .filter(|group| group.arn == log_group.arn)
+                .for_each(|group| group.retention_in_days = Some(days)),
+            GroupChange::Tag(..) => {}
+            GroupChange::Delete => log_groups.retain(|group| group.arn != log_group.arn),
+        }
+        futures::future::ready(Ok(())).boxed()
+    }
+
+    fn fetch_metric_filters(
+        &self,
+        _region: Option<String>,
+        log_group_name: String,
+    ) -> BoxFuture<'_, Result<Vec<MetricFilter>, String>> {
+        let filter = |name: &str, pattern: &str, metric_name: &str| MetricFilter {
+            name: name.to_string(),
+            pattern: pattern.to_string(),
+            metric_name: Some(metric_name.to_string()),
+            namespace: Some(ALARM_NAMESPACE.to_string()),
+        };
+        // the groups behind an alarm publish its metric, other functions count their errors
+        let mut filters: Vec<MetricFilter> = ALARMS
+            .iter()
+            .filter(|(_, _, groups, _)| groups.contains(&log_group_name.as_str()))
+            .map(|(name, metric_name, _, _)| filter(name, "ERROR", metric_name))
+            .collect();
+        if log_group_name.starts_with("/aws/lambda/") {
+            if filters.is_empty() {
+                filters.push(filter("errors", "ERROR", "Errors"));
+            }
+            filters.push(filter(
+                "slow-invocations",
+                "{ $.duration > 1000 }",
+                "SlowInvocations",
+            ));
+        }
+        futures::future::ready(Ok(filters)).boxed()
+    }
+
+    fn fetch_field_indexes(
+        &self,
+        _region: Option<String>,
+        _log_group_name: String,
+    ) -> BoxFuture<'_, Result<Vec<String>, String>> {
+        futures::future::ready(Ok(vec!["requestId".to_string()])).boxed()
+    }
+
+    /// Tagged with the service in the group's name, the RDS group hasn't been written to in a
+    /// while
+    fn fetch_group_metadata(
+        &self,
+        _region: Option<String>,
+        log_group_name: String,
+        _log_group_arn: String,
+    ) -> BoxFuture<'_, Result<GroupMetadata, String>> {
+        let service = log_group_name.rsplit('/').next().unwrap_or_default();
+        let idle_ms = if log_group_name.starts_with("/aws/rds/") {
+            9 * 24 * 3600 * 1000
+        } else {
+            STEP_MS
+        };
+        let metadata = GroupMetadata {
+            last_event: Some(now() - idle_ms),
+            tags: vec![
+                ("env".to_string(), "demo".to_string()),
+                ("service".to_string(), service.to_string()),
+            ],
+        };
+        futures::future::ready(Ok(metadata)).boxed()
+    }
+
+    fn fetch_alarms(&self, region: Option<String>) -> BoxFuture<'_, Result<Vec<Alarm>, String>> {
+        let region = DemoBackend::region(region);
+        let alarms = ALARMS
+            .iter()
+            .map(|alarm| demo_alarm(alarm, &region))
+            .collect();
+        futures::future::ready(Ok(alarms)).boxed()
+    }
+
+    fn fetch_metric_filter_groups(
+        &self,
+        _region: Option<String>,
+        metric_name: String,
+        namespace: String,
+    ) -> BoxFuture<'_, Result<Vec<String>, String>> {
+        let names = ALARMS
+            .iter()
+            .filter(|(_, metric, _, _)| *metric == metric_name && namespace == ALARM_NAMESPACE)
+            .flat_map(|(_, _, groups, _)| groups.iter().map(|group| group.to_string()))
+            .collect();
+        futures::future::ready(Ok(names)).boxed()
+    }
+
+    fn fetch_metric_alarms(
+        &self,
+        region: Option<String>,
+        metric_name: String,
+        namespace: String,
+    ) -> BoxFuture<'_, Result<Vec<Alarm>, String>> {
+        let region = DemoBackend::region(region);
+        let alarms = ALARMS
+            .iter()
+            .filter(|(_, metric, _, _)| *metric == metric_name && namespace == ALARM_NAMESPACE)
+            .map(|alarm| demo_alarm(alarm, &region))
+            .collect();
+        futures::future::ready(Ok(alarms)).boxed()
+    }
+
+    fn fetch_unmasked(
+        &self,
+        _region: Option<String>,
+        pointer: String,
+    ) -> BoxFuture<'_, Result<String, String>> {
+        let result = parse_pointer(&pointer)
+            .map(|(group, timestamp)| message(group, timestamp, true))
+            .ok_or_else(|| format!("no demo event {}", pointer));
+        futures::future::ready(result).boxed()
+    }
+
+    fn test_metric_filter(
+        &self,
+        _region: Option<String>,
+        pattern: String,
+        messages: Vec<String>,
+    ) -> BoxFuture<'_, Result<Vec<usize>, String>> {
+        // only plain terms are understood, every one of them has to appear
+        let terms: Vec<&str> = pattern
+            .split_whitespace()
+            .map(|term| term.trim_matches('"'))
+            .collect();
+        let matches = messages
+            .iter()
+            .enumerate()
+            .filter(|(_, message)| terms.iter().all(|term| message.contains(term)))
+            .map(|(index, _)| index)
+            .collect();
+        futures::future::ready(Ok(matches)).boxed()
+    }
+
+    fn fetch_log_record(
+        &self,
+        _region: Option<String>,
+        pointer: String,
+    ) -> BoxFuture<'_, Result<QueryRow, String>> {
+        let result = parse_pointer(&pointer)
+            .map(|(group, timestamp)| {
+                let mut row = row(&event(group, timestamp));
+                row.push(("@ingestionTime".to_string(), (timestamp + 800).to_string()));
+                row.sort();
+                row
+            })
+            .ok_or_else(|| format!("no demo event {}", pointer));
+        futures::future::ready(result).boxed()
+    }
+
+    fn fetch_accounts(&self) -> BoxFuture<'_, Result<Vec<Account>, String>> {
+        let roles = ACCOUNTS
+            .iter()
+            .flat_map(|(account_id, account_name, roles)| {
+                roles.iter().map(|role_name| Account::Sso {
+                    session: "demo".to_string(),
+                    account_id: account_id.to_string(),
+                    account_name: account_name.to_string(),
+                    role_name: role_name.to_string(),
+                })
+            });
+        let accounts = std::iter::once(Account::Profile("default".to_string()))
+            .chain(roles)
+            .collect();
+        futures::future::ready(Ok(accounts)).boxed()
+    }
+
+    /// Every account sees the same made up groups
+    fn switch_account(&self, _account: Account) -> BoxFuture<'_, Result<(), String>> {
+        futures::future::ready(Ok(())).boxed()
+    }
+
+    fn query_counts(&self) -> QueryCounts {
+        QueryCounts::default()
+    }
+}
+
+/// Every second a few events from the tailed groups, timestamped when they're made
+struct DemoTail {
+    log_group_names: Vec<String>,
+    log_stream_name_prefix: String,
+    /// only its plain terms, each has to be in the message
+    filter_pattern: String,
+    since: i64,
+}
+
+impl LiveTailSession for DemoTail {
+    fn next_events(&mut self) -> BoxFuture<'_, Result<Option<Vec<LogEvent>>, String>> {
+        async move {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            let until = now();
+            let events = (self.since..until)
+                .step_by(250)
+                .flat_map(|timestamp| {
+                    self.log_group_names
+                        .iter()
+                        .filter(move |group| mix(seed(group, timestamp)).is_multiple_of(4))
+                        .map(move |group| event(group, timestamp))
+                })
+                .filter(|event| event.log_stream.starts_with(&self.log_stream_name_prefix))
+                .filter(|event| {
+                    self.filter_pattern
+                        .split_whitespace()
+                        .all(|term| event.message.contains(term.trim_matches('"')))
+                })
+                .collect();
+            self.since = until;
+            Ok(Some(events))
+        }
+        .boxed()
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+/// A well spread number for `value`, so neighbouring slots don't look alike
+/// One of `ALARMS`, every one of them is going off
+fn demo_alarm(
+    (name, metric_name, _, minutes_ago): &(&str, &str, &[&str], i64),
+    region: &str,
+) -> Alarm {
+    Alarm {
+        name: name.to_string(),
+        region: Some(region.to_string()),
+        metric_name: Some(metric_name.to_string()),
+        namespace: Some(ALARM_NAMESPACE.to_string()),
+        state: "ALARM".to_string(),
+        reason: format!(
+            "Threshold Crossed: 1 datapoint was greater than the threshold (5.0) for {}",
+            metric_name
+        ),
+        state_updated: now() - minutes_ago * 60_000,
+    }
+}
+
+fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+fn seed(group: &str, value: i64) -> u64 {
+    group
+        .bytes()
+        .fold(value as u64, |seed, byte| mix(seed ^ byte as u64))
+}
+
+/// `<group>@<timestamp>`, everything needed to generate the event again
+fn parse_pointer(pointer: &str) -> Option<(&str, i64)> {
+    let (group, timestamp) = pointer.rsplit_once('@')?;
+    Some((group, timestamp.parse().ok()?))
+}
+
+fn event(group: &str, timestamp: i64) -> LogEvent {
+    let random = mix(seed(group, timestamp));
+    let log_stream = if group.starts_with("/aws/lambda/") {
+        format!("2026/10/16/[$LATEST]{:08x}", random % 3)
+    } else if group.starts_with("/ecs/") {
+        format!("ecs/app/{:08x}", random % 2)
+    } else {
+        format!("stream-{}", random % 2)
+    };
+    LogEvent {
+        timestamp,
+        message: message(group, timestamp, false).into(),
+        log_group: group.into(),
+        log_stream: log_stream.into(),
+        pointer: Some(format!("{}@{}", group, timestamp).into()),
+        ingestion_time: Some(timestamp + 800),
+        ..Default::default()
+    }
+}
+
+/// What `group` logged at `timestamp`, with masked values revealed when `unmasked`
+fn message(group: &str, timestamp: i64, unmasked: bool) -> String {
+    let random = mix(seed(group, timestamp) ^ 2);
+    let request_id = format!("{:08x}-{:04x}", random >> 32, random & 0xffff);
+    let path = PATHS[(random % PATHS.len() as u64) as usize];
+    let duration = random % 1500;
+    let status = if random.is_multiple_of(13) { 500 } else { 200 };
+    match group {
+        "/secure/customers" => {
+            let customer = CUSTOMERS[(random % CUSTOMERS.len() as u64) as usize];
+            let email = if unmasked { customer } else { "*****************" };
+            format!("customer {} updated their address, request {}", email, request_id)
+        }
+        group if group.starts_with("/aws/lambda/") => match random % 10 {
+            0 => format!("START RequestId: {} Version: $LATEST", request_id),
+            1 => format!(
+                "REPORT RequestId: {}\tDuration: {}.42 ms\tBilled Duration: {} ms\tMemory Size: 512 MB",
+                request_id,
+                duration,
+                duration + 1
+            ),
+            2 => format!(
+                r#"{{"_aws":{{"Timestamp":{},"CloudWatchMetrics":[{{"Namespace":"Demo","Dimensions":[["Service"]],"Metrics":[{{"Name":"Latency","Unit":"Milliseconds"}}]}}]}},"Service":"{}","Latency":{}}}"#,
+                timestamp,
+                group.trim_start_matches("/aws/lambda/"),
+                duration
+            ),
+            3 if status == 500 => format!(
+                "ERROR\t{}\tUnhandled error: connection to orders-db timed out after {}ms",
+                request_id, duration
+            ),
+            _ => format!(
+                r#"{{"level":"{}","requestId":"{}","path":"{}","duration":{},"status":{}}}"#,
+                if status == 500 { "error" } else { "info" },
+                request_id,
+                path,
+                duration,
+                status
+            ),
+        },
+        group if group.starts_with("/ecs/") => {
+            let log = if status == 500 {
+                format!("WARN retrying {} for request {}", path, request_id)
+            } else {
+                format!("INFO handled {} in {}ms", path, duration)
+            };
+            format!(
+                r#"{{"log":"{}","stream":"stdout","container_name":"{}"}}"#,
+                log,
+                group.trim_start_matches("/ecs/")
+            )
+        }
+        group if group.starts_with("/aws/apigateway/") => format!(
+            r#"10.0.{}.{} - - "GET {} HTTP/1.1" {} {} "{}""#,
+            random % 255,
+            random / 255 % 255,
+            path,
+            status,
+            duration * 10,
+            request_id
+        ),
+        _ => format!(
+            "LOG:  duration: {}.{:03} ms  statement: SELECT * FROM orders WHERE id = {}",
+            duration,
+            random % 1000,
+            random % 10_000
+        ),
+    }
+}
+
+/// The fields Logs Insights returns for `event`, the timestamp formatted the way it formats them
+fn row(event: &LogEvent) -> QueryRow {
+    let timestamp = DateTime::from_timestamp_millis(event.timestamp).unwrap_or_default();
+    vec![
+        (
+            "@timestamp".to_string(),
+            timestamp.format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+        ),
+        ("@message".to_string(), event.message.to_string()),
+        ("@logStream".to_string(), event.log_stream.to_string()),
+        (
+            "@log".to_string(),
+            format!("123456789012:{}", event.log_group),
+        ),
+    ]
+}
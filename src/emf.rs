@@ -0,0 +1,80 @@
+use serde_json::{Map, Value};
+
+/// One `CloudWatchMetrics` entry of an embedded metric format event, with the values it logged
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricDirective {
+    pub namespace: String,
+    /// name, value and unit of every metric, a metric logging several values lists them all
+    pub metrics: Vec<(String, String, Option<String>)>,
+    /// each set of dimensions the metrics are published under, with their values
+    pub dimension_sets: Vec<Vec<(String, String)>>,
+}
+
+/// The metrics of an embedded metric format message, `None` when it isn't one
+pub fn parse(message: &str) -> Option<Vec<MetricDirective>> {
+    let trimmed = message.trim();
+    if !trimmed.starts_with('{') {
+        return None;
+    }
+    let Ok(Value::Object(object)) = serde_json::from_str::<Value>(trimmed) else {
+        return None;
+    };
+    let directives = object.get("_aws")?.get("CloudWatchMetrics")?.as_array()?;
+    Some(
+        directives
+            .iter()
+            .map(|directive| MetricDirective {
+                namespace: directive
+                    .get("Namespace")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string(),
+                metrics: directive
+                    .get("Metrics")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|metric| {
+                        let name = metric.get("Name")?.as_str()?;
+                        let unit = metric.get("Unit").and_then(Value::as_str);
+                        Some((
+                            name.to_string(),
+                            value_of(&object, name),
+                            unit.map(str::to_string),
+                        ))
+                    })
+                    .collect(),
+                dimension_sets: directive
+                    .get("Dimensions")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_array)
+                    .map(|set| {
+                        set.iter()
+                            .filter_map(Value::as_str)
+                            .map(|name| (name.to_string(), value_of(&object, name)))
+                            .collect()
+                    })
+                    .collect(),
+            })
+            .collect(),
+    )
+}
+
+/// The logged value of a metric or dimension, several values are joined with commas
+fn value_of(object: &Map<String, Value>, name: &str) -> String {
+    match object.get(name) {
+        Some(Value::String(value)) => value.clone(),
+        Some(Value::Array(values)) => values
+            .iter()
+            .map(|value| match value {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+        Some(value) => value.to_string(),
+        None => "missing".to_string(),
+    }
+}
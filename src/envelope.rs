@@ -0,0 +1,50 @@
+use serde_json::Value;
+
+use crate::shared::LogEvent;
+
+/// Keys a container log agent adds next to `log`, e.g. docker's json-file driver, Fluent Bit
+/// or FireLens
+const AGENT_KEYS: [&str; 7] = [
+    "stream",
+    "time",
+    "source",
+    "kubernetes",
+    "container_id",
+    "container_name",
+    "ecs_task_arn",
+];
+
+/// The message a logging agent or the embedded metric format wrapped, `None` when `message`
+/// isn't wrapped
+pub fn unwrap(message: &str) -> Option<String> {
+    let trimmed = message.trim();
+    if !trimmed.starts_with('{') {
+        return None;
+    }
+    let Ok(Value::Object(mut object)) = serde_json::from_str::<Value>(trimmed) else {
+        return None;
+    };
+    if let Some(Value::String(log)) = object.get("log") {
+        if AGENT_KEYS.iter().any(|key| object.contains_key(*key)) {
+            return Some(log.trim_end().to_string());
+        }
+    }
+    // EMF keeps the metric definitions under `_aws`, what's left is what was logged
+    if object
+        .get("_aws")
+        .is_some_and(|aws| aws.get("CloudWatchMetrics").is_some())
+    {
+        object.remove("_aws");
+        return Some(Value::Object(object).to_string());
+    }
+    None
+}
+
+/// Swaps every wrapped message for the one inside, the wrapper is kept as the event's envelope
+pub fn unwrap_events(events: &mut [LogEvent]) {
+    for event in events {
+        if let Some(message) = unwrap(&event.message) {
+            event.envelope = Some(std::mem::replace(&mut event.message, message.into()));
+        }
+    }
+}
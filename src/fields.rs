@@ -0,0 +1,92 @@
+use std::collections::BTreeSet;
+
+use serde_json::Value;
+
+use crate::shared::LogEvent;
+
+/// Top level fields of a structured message, json objects and `key=value` pairs are understood
+pub fn parse_fields(message: &str) -> Vec<(String, String)> {
+    let trimmed = message.trim();
+    if trimmed.starts_with('{') {
+        if let Ok(Value::Object(object)) = serde_json::from_str::<Value>(trimmed) {
+            return object
+                .into_iter()
+                .map(|(key, value)| {
+                    let value = match value {
+                        Value::String(value) => value,
+                        value => value.to_string(),
+                    };
+                    (key, value)
+                })
+                .collect();
+        }
+    }
+    trimmed
+        .split_whitespace()
+        .filter_map(|pair| pair.split_once('='))
+        .filter(|(key, _)| !key.is_empty())
+        .map(|(key, value)| (key.to_string(), value.trim_matches('"').to_string()))
+        .collect()
+}
+
+/// Top level keys of every json message, Insights discovers these as fields. Sorted by name
+pub fn json_fields(events: impl IntoIterator<Item = LogEvent>) -> Vec<String> {
+    events
+        .into_iter()
+        .filter(|event| event.message.trim_start().starts_with('{'))
+        .flat_map(|event| parse_fields(&event.message))
+        .map(|(key, _)| key)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+fn numeric_value(message: &str, field: &str) -> Option<f64> {
+    parse_fields(message)
+        .into_iter()
+        .find(|(key, _)| key == field)
+        .and_then(|(_, value)| value.parse::<f64>().ok())
+        .filter(|value| value.is_finite())
+}
+
+/// Every field that holds a number in at least one event, sorted by name
+pub fn numeric_fields(events: impl IntoIterator<Item = LogEvent>) -> Vec<String> {
+    events
+        .into_iter()
+        .flat_map(|event| parse_fields(&event.message))
+        .filter(|(_, value)| value.parse::<f64>().is_ok_and(|value| value.is_finite()))
+        .map(|(key, _)| key)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldStats {
+    pub count: usize,
+    pub min: f64,
+    pub p50: f64,
+    pub p95: f64,
+    pub max: f64,
+}
+
+/// Summary of `field` over every event that has it as a number
+pub fn field_stats(events: impl IntoIterator<Item = LogEvent>, field: &str) -> Option<FieldStats> {
+    let mut values = events
+        .into_iter()
+        .filter_map(|event| numeric_value(&event.message, field))
+        .collect::<Vec<_>>();
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+    // nearest rank, so every percentile is a value that was actually logged
+    let percentile = |p: f64| values[((p * values.len() as f64).ceil() as usize).max(1) - 1];
+    Some(FieldStats {
+        count: values.len(),
+        min: values[0],
+        p50: percentile(0.5),
+        p95: percentile(0.95),
+        max: values[values.len() - 1],
+    })
+}
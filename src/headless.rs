@@ -0,0 +1,331 @@
+use std::io::Write;
+
+use futures::{stream, StreamExt};
+
+use crate::{
+    backend::LogBackend,
+    keymap::{format_keys, Context, Keymap},
+    output::{event_record, group_record, Printer},
+    shared::LogGroup,
+};
+
+/// Prints every group in `regions`, `None` being the configured region. Regions that fail,
+/// e.g. opt-in ones that aren't enabled, are reported on `errors` and left out
+pub async fn list_groups(
+    backend: &dyn LogBackend,
+    regions: Vec<Option<String>>,
+    printer: Printer<impl Write>,
+    errors: impl Write,
+) -> Result<(), String> {
+    let mut printer = printer.with_text_fields(&["name"]);
+    for (_, groups) in fetch_regions(backend, regions, errors).await? {
+        for group in groups {
+            printer.print(&group_record(&group))?;
+        }
+    }
+    printer.finish()
+}
+
+/// Counts the events containing `term` in every group of `regions` over the last `since_ms`
+/// and prints the groups with any, most matches first. Regions and queries that fail are
+/// reported on `errors`, the counts of the rest are still printed
+pub async fn search(
+    backend: &dyn LogBackend,
+    regions: Vec<Option<String>>,
+    term: String,
+    since_ms: i64,
+    printer: Printer<impl Write>,
+    mut errors: impl Write,
+) -> Result<(), String> {
+    let mut printer = printer.with_text_fields(&["matches", "group"]);
+    let end = chrono::Utc::now().timestamp_millis();
+    let query = format!(
+        "filter @message like \"{}\" | stats count(*) as matches by @log",
+        term.replace('\\', "\\\\").replace('"', "\\\"")
+    );
+    let mut batches = vec![];
+    for (region, groups) in fetch_regions(backend, regions, &mut errors).await? {
+        let names = groups
+            .into_iter()
+            .map(|group| group.name)
+            .collect::<Vec<_>>();
+        for names in names.chunks(GROUPS_PER_QUERY) {
+            batches.push((region.clone(), names.to_vec()));
+        }
+    }
+    let results = stream::iter(batches)
+        .map(|(region, names)| async {
+            let result = backend
+                .run_query(
+                    region.clone(),
+                    names.clone(),
+                    query.clone(),
+                    end - since_ms,
+                    end,
+                )
+                .await;
+            (region, names, result)
+        })
+        .buffer_unordered(QUERY_CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await;
+    let mut rows = vec![];
+    let mut searched = 0;
+    let mut error = None;
+    for (region, names, result) in results {
+        match result {
+            Ok(more) => {
+                rows.extend(more);
+                searched += 1;
+            }
+            Err(e) => {
+                let groups = match names.as_slice() {
+                    [name] => name.clone(),
+                    _ => format!("{} groups from {}", names.len(), names[0]),
+                };
+                report(
+                    &mut errors,
+                    region.as_deref(),
+                    &format!("{}: {}", groups, e),
+                )?;
+                error = Some(e);
+            }
+        }
+    }
+    // only give up when nothing could be searched
+    if let Some(e) = error.filter(|_| searched == 0) {
+        return Err(e);
+    }
+    let mut counts = rows
+        .into_iter()
+        .filter_map(|row| {
+            let field = |name: &str| {
+                row.iter()
+                    .find(|(field, _)| field == name)
+                    .map(|(_, value)| value.clone())
+            };
+            // @log is "<account id>:<log group name>"
+            let log = field("@log")?;
+            let group = log.split_once(':').map_or(log.as_str(), |(_, name)| name);
+            Some((group.to_string(), field("matches")?.parse::<u64>().ok()?))
+        })
+        .collect::<Vec<_>>();
+    counts.sort_by(|(a_group, a), (b_group, b)| b.cmp(a).then_with(|| a_group.cmp(b_group)));
+    for (group, matches) in counts {
+        printer.print(&vec![
+            ("group".to_string(), group),
+            ("matches".to_string(), matches.to_string()),
+        ])?;
+    }
+    printer.finish()
+}
+
+/// Names of the `$name` placeholders in `query`, each once in order of first use
+pub fn placeholders(query: &str) -> Vec<String> {
+    let mut names = vec![];
+    substitute(query, |name| {
+        if !names.iter().any(|known| known == name) {
+            names.push(name.to_string());
+        }
+        String::new()
+    });
+    names
+}
+
+/// Replaces every `$name` in `query` with its value, `$$` stands for a plain `$`
+pub fn fill_placeholders(query: &str, values: &[(String, String)]) -> Result<String, String> {
+    let mut missing = None;
+    let filled = substitute(query, |name| {
+        match values.iter().find(|(known, _)| known == name) {
+            Some((_, value)) => value.clone(),
+            None => {
+                missing.get_or_insert_with(|| name.to_string());
+                String::new()
+            }
+        }
+    });
+    match missing {
+        Some(name) => Err(format!("no value for ${}, pass --var {}=...", name, name)),
+        None => Ok(filled),
+    }
+}
+
+/// `query` with each placeholder swapped for what `replace` returns for its name
+fn substitute(query: &str, mut replace: impl FnMut(&str) -> String) -> String {
+    let mut filled = String::with_capacity(query.len());
+    let mut rest = query;
+    while let Some(dollar) = rest.find('$') {
+        filled.push_str(&rest[..dollar]);
+        let after = &rest[dollar + 1..];
+        if let Some(after) = after.strip_prefix('$') {
+            filled.push('$');
+            rest = after;
+            continue;
+        }
+        let len = after
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(after.len());
+        if len == 0 || after.starts_with(|c: char| c.is_ascii_digit()) {
+            filled.push('$');
+        } else {
+            filled.push_str(&replace(&after[..len]));
+        }
+        rest = &after[len..];
+    }
+    filled.push_str(rest);
+    filled
+}
+
+/// Queries `loglog query --split` and `loglog search` run at once
+const QUERY_CONCURRENCY: usize = 4;
+
+/// Insights takes at most this many groups per query
+const GROUPS_PER_QUERY: usize = 50;
+
+/// Runs `query` over `log_group` for the last `since_ms` and prints every row.
+/// With `split_ms` the range is queried in pieces that long, oldest first, and each
+/// piece is printed as soon as it and every piece before it are done
+pub async fn query(
+    backend: &dyn LogBackend,
+    log_group: String,
+    query: String,
+    since_ms: i64,
+    split_ms: Option<i64>,
+    mut printer: Printer<impl Write>,
+) -> Result<(), String> {
+    let end = chrono::Utc::now().timestamp_millis();
+    let ranges = match split_ms {
+        Some(split_ms) => split_range(end - since_ms, end, split_ms),
+        None => vec![(end - since_ms, end)],
+    };
+    let mut pieces = stream::iter(ranges)
+        .map(|(start, end)| {
+            backend.run_query(None, vec![log_group.clone()], query.clone(), start, end)
+        })
+        .buffered(QUERY_CONCURRENCY);
+    while let Some(rows) = pieces.next().await {
+        for row in rows? {
+            printer.print(&row)?;
+        }
+        printer.flush()?;
+    }
+    printer.finish()
+}
+
+/// `start..end` cut into consecutive pieces of `every_ms`, the last one may be shorter
+pub fn split_range(start: i64, end: i64, every_ms: i64) -> Vec<(i64, i64)> {
+    if every_ms <= 0 {
+        return vec![(start, end)];
+    }
+    (0..)
+        .map(|piece| start + piece * every_ms)
+        .take_while(|piece_start| *piece_start < end)
+        .map(|piece_start| (piece_start, (piece_start + every_ms).min(end)))
+        .collect()
+}
+
+/// Follows `log_group` and prints events as they arrive until the session ends
+pub async fn tail(
+    backend: &dyn LogBackend,
+    log_group: String,
+    stream_prefix: String,
+    filter_pattern: String,
+    printer: Printer<impl Write>,
+) -> Result<(), String> {
+    let mut printer = printer.with_text_fields(&["timestamp", "log_stream", "message"]);
+    let group = fetch_all_groups(backend, None)
+        .await?
+        .into_iter()
+        .find(|group| group.name == log_group)
+        .ok_or_else(|| format!("no log group named {}", log_group))?;
+    let mut live_tail = backend
+        .start_live_tail(None, vec![group.arn], stream_prefix, filter_pattern)
+        .await?;
+    while let Some(events) = live_tail.next_events().await? {
+        for event in events {
+            printer.print(&event_record(&event))?;
+        }
+        printer.flush()?;
+    }
+    printer.finish()
+}
+
+/// Prints every binding as `<context> <action> <keys>`, the names overrides are written with
+pub fn keys(keymap: &Keymap, printer: Printer<impl Write>) -> Result<(), String> {
+    let mut printer = printer.with_text_fields(&["context", "action", "keys"]);
+    for context in Context::ALL {
+        for (action, keys) in keymap.bindings(context) {
+            printer.print(&vec![
+                ("context".to_string(), context.name().to_string()),
+                ("action".to_string(), action.name().to_string()),
+                ("keys".to_string(), format_keys(keys)),
+                ("description".to_string(), action.description().to_string()),
+            ])?;
+        }
+    }
+    printer.finish()
+}
+
+/// Parses durations like `90s`, `15m`, `2h` or `7d` into milliseconds
+pub fn parse_since(since: &str) -> Result<i64, String> {
+    let invalid = || format!("invalid duration {}, expected e.g. 15m, 2h or 7d", since);
+    let unit_at = since
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (amount, unit) = since.split_at(unit_at);
+    let amount = amount.parse::<i64>().map_err(|_| invalid())?;
+    let unit_ms = match unit {
+        "s" => 1000,
+        "m" => 60 * 1000,
+        "h" => 3600 * 1000,
+        "d" => 24 * 3600 * 1000,
+        _ => return Err(invalid()),
+    };
+    Ok(amount * unit_ms)
+}
+
+/// Every group of each region, regions that fail are reported on `errors` and left out.
+/// Fails only when every region did
+async fn fetch_regions(
+    backend: &dyn LogBackend,
+    regions: Vec<Option<String>>,
+    mut errors: impl Write,
+) -> Result<Vec<(Option<String>, Vec<LogGroup>)>, String> {
+    let mut listed = vec![];
+    let mut error = None;
+    for region in regions {
+        match fetch_all_groups(backend, region.clone()).await {
+            Ok(groups) => listed.push((region, groups)),
+            Err(e) => {
+                report(&mut errors, region.as_deref(), &e)?;
+                error = Some(e);
+            }
+        }
+    }
+    match error {
+        Some(e) if listed.is_empty() => Err(e),
+        _ => Ok(listed),
+    }
+}
+
+/// Writes a line about what failed in `region` to `errors`
+fn report(errors: &mut impl Write, region: Option<&str>, error: &str) -> Result<(), String> {
+    let region = region.unwrap_or("the configured region");
+    writeln!(errors, "{}: {}", region, error).map_err(|e| e.to_string())
+}
+
+async fn fetch_all_groups(
+    backend: &dyn LogBackend,
+    region: Option<String>,
+) -> Result<Vec<LogGroup>, String> {
+    let mut log_groups = vec![];
+    let mut next_token = None;
+    loop {
+        let page = backend.fetch_log_groups(region.clone(), next_token).await?;
+        log_groups.extend(page.log_groups);
+        match page.next_token {
+            Some(token) => next_token = Some(token),
+            None => return Ok(log_groups),
+        }
+    }
+}
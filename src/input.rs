@@ -0,0 +1,150 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A line of text being typed, with a cursor and the usual readline editing keys.
+/// The cursor moves by grapheme, so an accented letter or an emoji with modifiers is
+/// stepped over and deleted as the one character it looks like
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Input {
+    value: String,
+    /// byte offset into `value`, always on a grapheme boundary
+    cursor: usize,
+}
+
+impl Input {
+    /// Starts out with `value`, the cursor at its end
+    pub fn new(value: impl Into<String>) -> Input {
+        let value = value.into();
+        Input {
+            cursor: value.len(),
+            value,
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.value.is_empty()
+    }
+
+    /// Replaces the text, the cursor moves to its end
+    pub fn set(&mut self, value: impl Into<String>) {
+        *self = Input::new(value);
+    }
+
+    pub fn clear(&mut self) {
+        self.set("");
+    }
+
+    /// Types `text` at the cursor
+    pub fn insert(&mut self, text: &str) {
+        self.value.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    /// Types pasted text, line breaks become spaces since the input is a single line
+    pub fn paste(&mut self, text: &str) {
+        let text = text
+            .trim_end_matches(['\r', '\n'])
+            .replace("\r\n", " ")
+            .replace(['\r', '\n', '\t'], " ");
+        self.insert(&text);
+    }
+
+    /// Applies an editing key, false when it isn't one, e.g. Enter or Esc
+    pub fn handle_key(&mut self, key: &KeyEvent) -> bool {
+        // ctrl+alt is how AltGr arrives on some platforms, it types characters like @
+        let alt_gr = key
+            .modifiers
+            .contains(KeyModifiers::CONTROL | KeyModifiers::ALT);
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL) && !alt_gr;
+        let alt = key.modifiers.contains(KeyModifiers::ALT) && !alt_gr;
+        match key.code {
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::Char('e') if ctrl => self.cursor = self.value.len(),
+            KeyCode::Char('w') if ctrl => self.delete_to(self.word_start()),
+            KeyCode::Char('u') if ctrl => self.delete_to(0),
+            KeyCode::Char('k') if ctrl => self.value.truncate(self.cursor),
+            KeyCode::Char(_) if ctrl || alt => return false,
+            KeyCode::Char(c) => self.insert(c.encode_utf8(&mut [0; 4])),
+            KeyCode::Backspace => self.delete_to(self.previous()),
+            KeyCode::Delete => {
+                let next = self.next();
+                self.value.drain(self.cursor..next);
+            }
+            KeyCode::Left if ctrl => self.cursor = self.word_start(),
+            KeyCode::Right if ctrl => self.cursor = self.word_end(),
+            KeyCode::Left => self.cursor = self.previous(),
+            KeyCode::Right => self.cursor = self.next(),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.value.len(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// `prefix`, the text with the cursor drawn as a reversed cell, then `suffix`
+    pub fn line(&self, prefix: impl Into<String>, suffix: impl Into<String>) -> Line<'static> {
+        let (before, after) = self.value.split_at(self.cursor);
+        let under_cursor = after.graphemes(true).next().unwrap_or(" ");
+        let after = after.get(under_cursor.len()..).unwrap_or_default();
+        Line::from(vec![
+            Span::raw(prefix.into()),
+            Span::raw(before.to_string()),
+            Span::styled(
+                under_cursor.to_string(),
+                Style::new().add_modifier(Modifier::REVERSED),
+            ),
+            Span::raw(after.to_string()),
+            Span::raw(suffix.into()),
+        ])
+    }
+
+    /// Removes everything between `start` and the cursor
+    fn delete_to(&mut self, start: usize) {
+        self.value.drain(start..self.cursor);
+        self.cursor = start;
+    }
+
+    /// Where the grapheme before the cursor starts
+    fn previous(&self) -> usize {
+        self.value[..self.cursor]
+            .grapheme_indices(true)
+            .next_back()
+            .map_or(0, |(index, _)| index)
+    }
+
+    /// Where the grapheme after the cursor ends
+    fn next(&self) -> usize {
+        self.value[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map_or(self.cursor, |grapheme| self.cursor + grapheme.len())
+    }
+
+    /// Start of the word before the cursor, skipping the whitespace right before it
+    fn word_start(&self) -> usize {
+        let before = self.value[..self.cursor].trim_end();
+        before
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(0, |(index, c)| index + c.len_utf8())
+    }
+
+    /// End of the word after the cursor, skipping the whitespace right after it
+    fn word_end(&self) -> usize {
+        let after = &self.value[self.cursor..];
+        let whitespace = after.len() - after.trim_start().len();
+        let word = after[whitespace..]
+            .find(char::is_whitespace)
+            .unwrap_or(after.len() - whitespace);
+        self.cursor + whitespace + word
+    }
+}
@@ -0,0 +1,195 @@
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Serialize;
+
+use crate::shared::{format_count, format_elapsed, format_range, format_window};
+
+/// A query the viewer ran and what came back
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QueryRecord {
+    /// when the results arrived, ms since the epoch
+    pub finished_at: i64,
+    pub log_groups: Vec<String>,
+    /// the region of each of `log_groups`, groups in different regions can share a name
+    pub regions: Vec<Option<String>>,
+    /// the Insights queries run instead of the built in one
+    pub queries: Vec<String>,
+    /// the fields picked for the query, they're what makes up `queries` when set
+    pub query_fields: Option<Vec<String>>,
+    pub start: i64,
+    pub end: i64,
+    pub stream_prefix: Option<String>,
+    /// the viewer's filter when the results arrived
+    pub filter: Option<String>,
+    pub events: usize,
+    /// how long the results took to come back
+    pub took_ms: u64,
+    /// set when it queried the last so long rather than a fixed range, running it again
+    /// queries up to now
+    pub window_ms: Option<i64>,
+}
+
+impl QueryRecord {
+    /// A line for the history: when, which groups, over what, what came back and the query
+    pub fn summary(&self) -> String {
+        let range = match self.window_ms {
+            Some(window_ms) => format!("last {}", format_window(window_ms)),
+            None => format_range(self.start, self.end),
+        };
+        let finished_at = chrono::DateTime::from_timestamp_millis(self.finished_at)
+            .map(|time| time.format("%H:%M:%S").to_string())
+            .unwrap_or_default();
+        let mut parts = vec![
+            finished_at,
+            self.log_groups.join(", "),
+            range,
+            format!(
+                "{} events in {}",
+                format_count(self.events),
+                format_elapsed(Duration::from_millis(self.took_ms))
+            ),
+        ];
+        if let Some(prefix) = &self.stream_prefix {
+            parts.push(format!("streams {}*", prefix));
+        }
+        parts.extend(self.queries.iter().cloned());
+        parts.join("  ")
+    }
+}
+
+/// An event bookmarked in the viewer, its message is kept as an excerpt
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Bookmark {
+    pub timestamp: i64,
+    pub log_group: String,
+    pub log_stream: String,
+    pub message: String,
+    /// what it showed, typed when it was bookmarked or later in the bookmarks pane
+    pub note: String,
+}
+
+impl Bookmark {
+    /// Whether both bookmark the same event, whatever their notes say
+    fn is_same_event(&self, other: &Bookmark) -> bool {
+        self.timestamp == other.timestamp
+            && self.log_group == other.log_group
+            && self.log_stream == other.log_stream
+            && self.message == other.message
+    }
+
+    /// A line for the bookmarks pane: when, where and the note, or the message without one
+    pub fn summary(&self) -> String {
+        let text = if self.note.is_empty() {
+            self.message.lines().next().unwrap_or_default()
+        } else {
+            &self.note
+        };
+        format!(
+            "{}  {}  {}",
+            format_timestamp(self.timestamp),
+            self.log_group,
+            text
+        )
+    }
+}
+
+/// What was looked at this session, exported for an incident's postmortem
+#[derive(Debug, Default, Serialize)]
+pub struct Investigation {
+    pub queries: Vec<QueryRecord>,
+    pub bookmarks: Vec<Bookmark>,
+}
+
+fn format_timestamp(ms: i64) -> String {
+    chrono::DateTime::from_timestamp_millis(ms)
+        .map(|time| time.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| ms.to_string())
+}
+
+impl Investigation {
+    pub fn is_empty(&self) -> bool {
+        self.queries.is_empty() && self.bookmarks.is_empty()
+    }
+
+    /// Adds the bookmark, or takes it out when its event is already bookmarked. The index
+    /// of the new bookmark when it was added
+    pub fn toggle_bookmark(&mut self, bookmark: Bookmark) -> Option<usize> {
+        match self
+            .bookmarks
+            .iter()
+            .position(|b| b.is_same_event(&bookmark))
+        {
+            Some(index) => {
+                self.bookmarks.remove(index);
+                None
+            }
+            None => {
+                self.bookmarks.push(bookmark);
+                Some(self.bookmarks.len() - 1)
+            }
+        }
+    }
+
+    /// The queries in the order they ran, then the bookmarked events oldest first
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from("# Investigation\n");
+        if !self.queries.is_empty() {
+            out.push_str("\n## Queries\n\n");
+        }
+        for (index, record) in self.queries.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "{}. `{}` from {} to {}, {} events",
+                index + 1,
+                record.log_groups.join("`, `"),
+                format_timestamp(record.start),
+                format_timestamp(record.end),
+                record.events
+            );
+            for query in &record.queries {
+                let _ = writeln!(out, "   - query `{}`", query);
+            }
+            if let Some(prefix) = &record.stream_prefix {
+                let _ = writeln!(out, "   - streams starting with `{}`", prefix);
+            }
+            if let Some(filter) = &record.filter {
+                let _ = writeln!(out, "   - filtered by `{}`", filter);
+            }
+        }
+        let mut bookmarks = self.bookmarks.iter().collect::<Vec<_>>();
+        bookmarks.sort_by_key(|bookmark| bookmark.timestamp);
+        if !bookmarks.is_empty() {
+            out.push_str("\n## Bookmarked events\n");
+        }
+        for bookmark in bookmarks {
+            let _ = writeln!(
+                out,
+                "\n{} `{}` `{}`\n",
+                format_timestamp(bookmark.timestamp),
+                bookmark.log_group,
+                bookmark.log_stream,
+            );
+            if !bookmark.note.is_empty() {
+                let _ = writeln!(out, "> {}\n", bookmark.note);
+            }
+            let _ = writeln!(out, "```\n{}\n```", bookmark.message.trim_end());
+        }
+        out
+    }
+
+    /// Writes `<name>.md` and `<name>.json` to `dir`, returns the path of the markdown
+    pub fn write(&self, dir: &Path, name: &str) -> Result<PathBuf, String> {
+        let markdown = dir.join(format!("{}.md", name));
+        let json = dir.join(format!("{}.json", name));
+        let contents = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(&json, contents).map_err(|e| format!("can't write {}: {}", json.display(), e))?;
+        fs::write(&markdown, self.to_markdown())
+            .map_err(|e| format!("can't write {}: {}", markdown.display(), e))?;
+        Ok(markdown)
+    }
+}
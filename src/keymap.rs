@@ -0,0 +1,568 @@
+use std::collections::BTreeMap;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Where a binding applies, global bindings work on every screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Context {
+    Global,
+    Groups,
+    Viewer,
+    Detail,
+    Alarms,
+    /// the list of bookmarks
+    BookmarkList,
+    /// the menu of changes to the marked groups
+    BulkMenu,
+}
+
+impl Context {
+    pub const ALL: [Context; 7] = [
+        Context::Global,
+        Context::Groups,
+        Context::Viewer,
+        Context::Detail,
+        Context::Alarms,
+        Context::BookmarkList,
+        Context::BulkMenu,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Context::Global => "global",
+            Context::Groups => "groups",
+            Context::Viewer => "viewer",
+            Context::Detail => "detail",
+            Context::Alarms => "alarms",
+            Context::BookmarkList => "bookmarks",
+            Context::BulkMenu => "bulk",
+        }
+    }
+}
+
+/// Everything a key can be bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    Back,
+    Help,
+    Debug,
+    ScrollUp,
+    ScrollDown,
+    PageUp,
+    PageDown,
+    Open,
+    Search,
+    Mark,
+    ShowHidden,
+    Hide,
+    Bulk,
+    Reload,
+    Rerun,
+    Info,
+    Tail,
+    NextError,
+    PreviousError,
+    SearchToken,
+    SearchOlder,
+    SearchNewer,
+    FilterToken,
+    Filter,
+    ClearFilter,
+    MetricFilter,
+    Unmask,
+    Inspect,
+    FieldStats,
+    PickFields,
+    Compare,
+    Fold,
+    CursorLeft,
+    CursorRight,
+    NextWord,
+    PreviousWord,
+    StreamPrefix,
+    OpenSplit,
+    SwitchPane,
+    Background,
+    Inbox,
+    Bookmark,
+    Around,
+    SortByTime,
+    PauseTail,
+    TailFilter,
+    Expiry,
+    Metadata,
+    TimeRange,
+    Bookmarks,
+    History,
+    Export,
+    ShowAlarms,
+    Accounts,
+    Remove,
+    Confirm,
+    SetRetention,
+    AddTag,
+    Delete,
+}
+
+impl Action {
+    /// The name used for overrides in the config, e.g. `"viewer.search_token"`
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Back => "back",
+            Action::Help => "help",
+            Action::Debug => "debug",
+            Action::ScrollUp => "scroll_up",
+            Action::ScrollDown => "scroll_down",
+            Action::PageUp => "page_up",
+            Action::PageDown => "page_down",
+            Action::Open => "open",
+            Action::Search => "search",
+            Action::Mark => "mark",
+            Action::ShowHidden => "show_hidden",
+            Action::Hide => "hide",
+            Action::Bulk => "bulk",
+            Action::Reload => "reload",
+            Action::Rerun => "rerun",
+            Action::Info => "info",
+            Action::Tail => "tail",
+            Action::NextError => "next_error",
+            Action::PreviousError => "previous_error",
+            Action::SearchToken => "search_token",
+            Action::SearchOlder => "search_older",
+            Action::SearchNewer => "search_newer",
+            Action::FilterToken => "filter_token",
+            Action::Filter => "filter",
+            Action::ClearFilter => "clear_filter",
+            Action::MetricFilter => "metric_filter",
+            Action::Unmask => "unmask",
+            Action::Inspect => "inspect",
+            Action::FieldStats => "field_stats",
+            Action::PickFields => "pick_fields",
+            Action::Compare => "compare",
+            Action::Fold => "fold",
+            Action::CursorLeft => "cursor_left",
+            Action::CursorRight => "cursor_right",
+            Action::NextWord => "next_word",
+            Action::PreviousWord => "previous_word",
+            Action::StreamPrefix => "stream_prefix",
+            Action::OpenSplit => "open_split",
+            Action::SwitchPane => "switch_pane",
+            Action::Background => "background",
+            Action::Inbox => "inbox",
+            Action::Bookmark => "bookmark",
+            Action::Around => "around",
+            Action::SortByTime => "sort_by_time",
+            Action::PauseTail => "pause_tail",
+            Action::TailFilter => "tail_filter",
+            Action::Expiry => "expiry",
+            Action::Metadata => "metadata",
+            Action::TimeRange => "time_range",
+            Action::Bookmarks => "bookmarks",
+            Action::History => "history",
+            Action::Export => "export",
+            Action::ShowAlarms => "alarms",
+            Action::Accounts => "accounts",
+            Action::Remove => "remove",
+            Action::Confirm => "confirm",
+            Action::SetRetention => "retention",
+            Action::AddTag => "tag",
+            Action::Delete => "delete",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Back => "go back, quit on the first screen",
+            Action::Help => "show these keys",
+            Action::Debug => "show timings, tasks and the newest log lines",
+            Action::ScrollUp => "move up",
+            Action::ScrollDown => "move down",
+            Action::PageUp => "move up a page",
+            Action::PageDown => "move down a page",
+            Action::Open => "open the marked groups or the highlighted one",
+            Action::Search => "search groups",
+            Action::Mark => "mark a group, or pick a field in the query fields",
+            Action::ShowHidden => "show or hide hidden groups",
+            Action::Hide => "hide a group",
+            Action::Bulk => "change every marked group",
+            Action::Reload => "run the query again",
+            Action::Rerun => "run the query again and say whether anything new turned up",
+            Action::Info => "show the settings, field indexes and metric filters of the group",
+            Action::Tail => "start or stop the live tail",
+            Action::NextError => "next error",
+            Action::PreviousError => "previous error",
+            Action::SearchToken => "search for the token under the cursor",
+            Action::SearchOlder => "older match",
+            Action::SearchNewer => "newer match",
+            Action::FilterToken => "only show events with the token under the cursor",
+            Action::Filter => "filter events, /regex/ for a regex",
+            Action::ClearFilter => "clear the filter",
+            Action::MetricFilter => "test a metric filter pattern on the loaded events",
+            Action::Unmask => "fetch the event again with masked values revealed",
+            Action::Inspect => "show every field of the event, or its message again",
+            Action::FieldStats => "numeric field stats",
+            Action::PickFields => "choose the fields the query returns",
+            Action::Compare => "compare with the window just before",
+            Action::Fold => "expand or fold the event",
+            Action::CursorLeft => "cursor left",
+            Action::CursorRight => "cursor right",
+            Action::NextWord => "cursor to the next word",
+            Action::PreviousWord => "cursor to the previous word",
+            Action::StreamPrefix => "only fetch streams with a prefix",
+            Action::OpenSplit => "open a second viewer",
+            Action::SwitchPane => "switch between the viewers",
+            Action::Background => "leave the query running and go back, it lands in the inbox",
+            Action::Inbox => "show queries left running and their results",
+            Action::Bookmark => "bookmark the event with a note, or take the bookmark out",
+            Action::Around => "query the minutes around a pasted time, e.g. when an alert fired",
+            Action::SortByTime => {
+                "put events shown out of order back in the order they were logged"
+            }
+            Action::PauseTail => "hold the live tail's new events back while reading, or show them",
+            Action::TailFilter => {
+                "tail only events matching a CloudWatch filter pattern, applied by CloudWatch"
+            }
+            Action::Expiry => "show when the oldest events of each group are deleted",
+            Action::Metadata => "show the size, newest event and tags of each group",
+            Action::TimeRange => "pick how far back opening a group queries",
+            Action::Bookmarks => "list the bookmarks and their notes",
+            Action::History => "list the queries run across every group, and run one again",
+            Action::Export => "write the queries run and bookmarked events to a report",
+            Action::ShowAlarms => "show the alarms going off and open the logs behind them",
+            Action::Accounts => "switch to another profile or an account role of an SSO session",
+            Action::Remove => "remove the highlighted bookmark",
+            Action::Confirm => "answer yes when asked to confirm",
+            Action::SetRetention => "set how many days the marked groups keep their logs",
+            Action::AddTag => "tag the marked groups",
+            Action::Delete => "delete the marked groups and their logs",
+        }
+    }
+}
+
+/// A key and the ctrl/alt modifiers it needs, shift is part of the character
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl Key {
+    const fn new(code: KeyCode) -> Key {
+        Key {
+            code,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+
+    const fn ctrl(c: char) -> Key {
+        Key {
+            code: KeyCode::Char(c),
+            modifiers: KeyModifiers::CONTROL,
+        }
+    }
+
+    const fn char(c: char) -> Key {
+        Key::new(KeyCode::Char(c))
+    }
+
+    fn matches(&self, event: &KeyEvent) -> bool {
+        self.code == event.code
+            && event.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT) == self.modifiers
+    }
+
+    /// Parses `j`, `ctrl-u`, `alt-enter`, `space`, `down` and the like
+    pub fn parse(key: &str) -> Result<Key, String> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = key;
+        loop {
+            if let Some(after) = rest.strip_prefix("ctrl-") {
+                modifiers |= KeyModifiers::CONTROL;
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix("alt-") {
+                modifiers |= KeyModifiers::ALT;
+                rest = after;
+            } else {
+                break;
+            }
+        }
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest {
+                "enter" => KeyCode::Enter,
+                "esc" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "space" => KeyCode::Char(' '),
+                "backspace" => KeyCode::Backspace,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                _ => match rest.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => return Err(format!("unknown key {}", key)),
+                },
+            },
+        };
+        Ok(Key { code, modifiers })
+    }
+}
+
+impl std::fmt::Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::Enter => write!(f, "enter"),
+            KeyCode::Esc => write!(f, "esc"),
+            KeyCode::Tab => write!(f, "tab"),
+            KeyCode::Backspace => write!(f, "backspace"),
+            KeyCode::Up => write!(f, "up"),
+            KeyCode::Down => write!(f, "down"),
+            KeyCode::Left => write!(f, "left"),
+            KeyCode::Right => write!(f, "right"),
+            KeyCode::PageUp => write!(f, "pageup"),
+            KeyCode::PageDown => write!(f, "pagedown"),
+            KeyCode::Home => write!(f, "home"),
+            KeyCode::End => write!(f, "end"),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+/// Which keys trigger which action in each context, the help overlay and `loglog keys` are
+/// drawn from this so they always match what the keys do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    bindings: Vec<(Context, Action, Vec<Key>)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        use Action::*;
+        use Context::*;
+        let bindings = vec![
+            (Global, Quit, vec![Key::char('q')]),
+            (Global, Back, vec![Key::new(KeyCode::Esc)]),
+            (Global, Help, vec![Key::char('?')]),
+            (Global, Debug, vec![Key::new(KeyCode::F(12))]),
+            (Global, Inbox, vec![Key::char('I')]),
+            (Global, Bookmarks, vec![Key::char('K')]),
+            (Global, History, vec![Key::char('L')]),
+            (Global, Export, vec![Key::char('X')]),
+            (Global, ShowAlarms, vec![Key::char('A')]),
+            (Global, Accounts, vec![Key::char('P')]),
+            (Global, Confirm, vec![Key::char('y')]),
+            (
+                Groups,
+                ScrollDown,
+                vec![Key::char('j'), Key::new(KeyCode::Down)],
+            ),
+            (
+                Groups,
+                ScrollUp,
+                vec![Key::char('k'), Key::new(KeyCode::Up)],
+            ),
+            (Groups, Open, vec![Key::new(KeyCode::Enter)]),
+            (Groups, Search, vec![Key::char('/')]),
+            (Groups, Mark, vec![Key::char(' ')]),
+            (Groups, ShowHidden, vec![Key::char('H')]),
+            (Groups, Hide, vec![Key::char('x')]),
+            (Groups, Bulk, vec![Key::char('b')]),
+            (Groups, Reload, vec![Key::char('r')]),
+            (Groups, Info, vec![Key::char('i')]),
+            (Groups, Expiry, vec![Key::char('e')]),
+            (Groups, Metadata, vec![Key::char('m')]),
+            (Groups, TimeRange, vec![Key::char('t')]),
+            (
+                Viewer,
+                ScrollDown,
+                vec![Key::char('j'), Key::new(KeyCode::Down)],
+            ),
+            (
+                Viewer,
+                ScrollUp,
+                vec![Key::char('k'), Key::new(KeyCode::Up)],
+            ),
+            (Viewer, PageDown, vec![Key::ctrl('d')]),
+            (Viewer, PageUp, vec![Key::ctrl('u')]),
+            (Viewer, Open, vec![Key::new(KeyCode::Enter)]),
+            (Viewer, Mark, vec![Key::char(' ')]),
+            (Viewer, Reload, vec![Key::char('r')]),
+            (Viewer, Rerun, vec![Key::char('R')]),
+            (Viewer, Tail, vec![Key::char('t')]),
+            (Viewer, NextError, vec![Key::char('e')]),
+            (Viewer, PreviousError, vec![Key::char('E')]),
+            (Viewer, SearchToken, vec![Key::char('*')]),
+            (Viewer, SearchOlder, vec![Key::char('n')]),
+            (Viewer, SearchNewer, vec![Key::char('N')]),
+            (Viewer, FilterToken, vec![Key::char('f')]),
+            (Viewer, Filter, vec![Key::char('/')]),
+            (Viewer, ClearFilter, vec![Key::char('F')]),
+            (Viewer, MetricFilter, vec![Key::char('m')]),
+            (Viewer, Unmask, vec![Key::char('U')]),
+            (Viewer, FieldStats, vec![Key::char('#')]),
+            (Viewer, PickFields, vec![Key::char('Q')]),
+            (Viewer, Compare, vec![Key::char('c')]),
+            (Viewer, Fold, vec![Key::char('z')]),
+            (
+                Viewer,
+                CursorLeft,
+                vec![Key::char('h'), Key::new(KeyCode::Left)],
+            ),
+            (
+                Viewer,
+                CursorRight,
+                vec![Key::char('l'), Key::new(KeyCode::Right)],
+            ),
+            (Viewer, NextWord, vec![Key::char('w')]),
+            (Viewer, PreviousWord, vec![Key::char('b')]),
+            (Viewer, StreamPrefix, vec![Key::char('s')]),
+            (Viewer, OpenSplit, vec![Key::char('v')]),
+            (Viewer, SwitchPane, vec![Key::new(KeyCode::Tab)]),
+            (Viewer, Background, vec![Key::char('B')]),
+            (Viewer, Bookmark, vec![Key::char('M')]),
+            (Viewer, Around, vec![Key::char('@')]),
+            (Viewer, SortByTime, vec![Key::char('O')]),
+            (Viewer, PauseTail, vec![Key::char('p')]),
+            (Viewer, TailFilter, vec![Key::char('T')]),
+            (
+                Detail,
+                ScrollDown,
+                vec![Key::char('j'), Key::new(KeyCode::Down)],
+            ),
+            (
+                Detail,
+                ScrollUp,
+                vec![Key::char('k'), Key::new(KeyCode::Up)],
+            ),
+            (Detail, PageDown, vec![Key::ctrl('d')]),
+            (Detail, PageUp, vec![Key::ctrl('u')]),
+            (Detail, Inspect, vec![Key::char('f')]),
+            (
+                Alarms,
+                ScrollDown,
+                vec![Key::char('j'), Key::new(KeyCode::Down)],
+            ),
+            (
+                Alarms,
+                ScrollUp,
+                vec![Key::char('k'), Key::new(KeyCode::Up)],
+            ),
+            (Alarms, Open, vec![Key::new(KeyCode::Enter)]),
+            (Alarms, Reload, vec![Key::char('r')]),
+            (
+                BookmarkList,
+                ScrollDown,
+                vec![Key::char('j'), Key::new(KeyCode::Down)],
+            ),
+            (
+                BookmarkList,
+                ScrollUp,
+                vec![Key::char('k'), Key::new(KeyCode::Up)],
+            ),
+            (BookmarkList, Open, vec![Key::new(KeyCode::Enter)]),
+            (BookmarkList, Remove, vec![Key::char('x')]),
+            (BulkMenu, SetRetention, vec![Key::char('r')]),
+            (BulkMenu, AddTag, vec![Key::char('t')]),
+            (BulkMenu, Delete, vec![Key::char('d')]),
+        ];
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /// The default keymap with `overrides` applied, keyed by `<context>.<action>` like
+    /// `"viewer.search_token"`. An override replaces every default key of that action, and may
+    /// not take a key another action of the same context is still bound to
+    pub fn with_overrides(overrides: &BTreeMap<String, Vec<String>>) -> Result<Keymap, String> {
+        let mut keymap = Keymap::default();
+        for (name, keys) in overrides {
+            let binding = keymap
+                .bindings
+                .iter_mut()
+                .find(|(context, action, _)| {
+                    name.split_once('.') == Some((context.name(), action.name()))
+                })
+                .ok_or_else(|| format!("unknown key binding {}", name))?;
+            binding.2 = keys
+                .iter()
+                .map(|key| Key::parse(key))
+                .collect::<Result<_, _>>()?;
+        }
+        for (index, (context, action, keys)) in keymap.bindings.iter().enumerate() {
+            let clash = keymap.bindings[index + 1..]
+                .iter()
+                .filter(|(c, _, _)| c == context)
+                .find_map(|(_, other, other_keys)| {
+                    let key = keys.iter().find(|key| other_keys.contains(key))?;
+                    Some((key, other))
+                });
+            if let Some((key, other)) = clash {
+                return Err(format!(
+                    "{} is bound to both {}.{} and {}.{}",
+                    key,
+                    context.name(),
+                    action.name(),
+                    context.name(),
+                    other.name()
+                ));
+            }
+        }
+        Ok(keymap)
+    }
+
+    /// The action `key` triggers in `context`
+    pub fn action(&self, context: Context, key: &KeyEvent) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(c, _, keys)| *c == context && keys.iter().any(|k| k.matches(key)))
+            .map(|(_, action, _)| *action)
+    }
+
+    /// Every binding in `context`, in the order they're listed in the help
+    pub fn bindings(&self, context: Context) -> impl Iterator<Item = (Action, &[Key])> {
+        self.bindings
+            .iter()
+            .filter(move |(c, _, _)| *c == context)
+            .map(|(_, action, keys)| (*action, keys.as_slice()))
+    }
+
+    /// The keys of an action joined for display, e.g. `j, down`
+    pub fn keys(&self, context: Context, action: Action) -> String {
+        self.bindings(context)
+            .find(|(a, _)| *a == action)
+            .map(|(_, keys)| format_keys(keys))
+            .unwrap_or_default()
+    }
+
+    /// The footer every screen shows, e.g. `esc to go back, ? for keys`
+    pub fn hint(&self) -> String {
+        format!(
+            "{} to go back, {} for keys",
+            self.keys(Context::Global, Action::Back),
+            self.keys(Context::Global, Action::Help)
+        )
+    }
+}
+
+pub fn format_keys(keys: &[Key]) -> String {
+    keys.iter()
+        .map(|key| key.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
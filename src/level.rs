@@ -0,0 +1,58 @@
+/// Severity of a log line, guessed from the message since CloudWatch has no level field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "TRACE" => Some(LogLevel::Trace),
+            "DEBUG" => Some(LogLevel::Debug),
+            "INFO" => Some(LogLevel::Info),
+            "WARN" | "WARNING" => Some(LogLevel::Warn),
+            "ERROR" | "ERR" | "FATAL" | "CRITICAL" => Some(LogLevel::Error),
+            _ => None,
+        }
+    }
+}
+
+/// Only the start of a message is searched, levels are always written up front
+const LEVEL_SEARCH_LENGTH: usize = 120;
+
+/// Detects the level from a json `"level"` field or the first upper case level word,
+/// e.g. `[ERROR]` from lambda or `2024-01-01 WARN something` from most loggers
+pub fn detect_level(message: &str) -> Option<LogLevel> {
+    if let Some(level) = json_level(message) {
+        return Some(level);
+    }
+    let end = message
+        .char_indices()
+        .nth(LEVEL_SEARCH_LENGTH)
+        .map(|(index, _)| index)
+        .unwrap_or(message.len());
+    message[..end]
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .filter(|word| word.chars().all(|c| c.is_ascii_uppercase()))
+        .find_map(LogLevel::from_name)
+}
+
+fn json_level(message: &str) -> Option<LogLevel> {
+    let trimmed = message.trim_start();
+    if !trimmed.starts_with('{') {
+        return None;
+    }
+    let (_, after_key) = ["\"level\"", "\"severity\"", "\"lvl\""]
+        .iter()
+        .find_map(|key| trimmed.split_once(key))?;
+    let value = after_key
+        .trim_start()
+        .strip_prefix(':')?
+        .trim_start()
+        .strip_prefix('"')?;
+    LogLevel::from_name(value.split('"').next()?)
+}
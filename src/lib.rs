@@ -0,0 +1,35 @@
+mod accounts;
+mod alarms;
+pub mod app;
+mod aws;
+pub mod backend;
+pub mod config;
+pub mod demo;
+mod emf;
+mod envelope;
+pub mod fields;
+pub mod headless;
+mod input;
+pub mod investigation;
+pub mod keymap;
+mod level;
+mod log_detail;
+mod log_groups;
+mod log_viewer;
+pub mod logging;
+pub mod output;
+pub mod paths;
+pub mod pattern;
+mod popup;
+mod query;
+pub mod recording;
+pub mod search;
+pub mod settings;
+pub mod shared;
+mod spill;
+mod sso;
+pub mod state;
+pub mod table;
+mod tasks;
+pub mod theme;
+mod timestamp;
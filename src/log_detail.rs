@@ -0,0 +1,238 @@
+use std::sync::Arc;
+
+use crossterm::event::Event;
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Paragraph, Widget, Wrap},
+};
+use tokio::sync::mpsc;
+
+use crate::{
+    backend::LogBackend,
+    config::MessageTimestamps,
+    emf::{self, MetricDirective},
+    keymap::{Action, Context, Keymap},
+    shared::{format_elapsed, LogEvent, QueryRow},
+    tasks::{TaskKey, TaskSupervisor},
+};
+
+pub enum LogDetailOutboundMessage {
+    /// every field of the event behind the `@ptr`
+    Fields(Arc<str>, Result<QueryRow, String>),
+}
+
+/// Full view of a single event, the message is wrapped instead of cut off at the terminal width
+#[derive(Debug)]
+pub struct LogDetailComponent {
+    pub event: Option<LogEvent>,
+    /// region of the event's group, its fields are fetched from there
+    region: Option<String>,
+    /// shows the fields instead of the message, they're loading while `fields` is `None`
+    show_fields: bool,
+    fields: Option<Result<QueryRow, String>>,
+    scroll: u16,
+    /// reads the application's own time out of the message, shown next to `@timestamp`
+    pub message_timestamps: MessageTimestamps,
+    pub keymap: Arc<Keymap>,
+    log_detail_tx: mpsc::UnboundedSender<LogDetailOutboundMessage>,
+    backend: Arc<dyn LogBackend>,
+    tasks: TaskSupervisor,
+}
+
+impl LogDetailComponent {
+    pub fn new(
+        log_detail_tx: mpsc::UnboundedSender<LogDetailOutboundMessage>,
+        backend: Arc<dyn LogBackend>,
+        tasks: TaskSupervisor,
+    ) -> Self {
+        Self {
+            event: None,
+            region: None,
+            show_fields: false,
+            fields: None,
+            scroll: 0,
+            message_timestamps: MessageTimestamps::default(),
+            keymap: Arc::default(),
+            log_detail_tx,
+            backend,
+            tasks,
+        }
+    }
+
+    pub fn show(&mut self, event: LogEvent, region: Option<String>) {
+        self.tasks.cancel(&TaskKey::LogRecord);
+        self.event = Some(event);
+        self.region = region;
+        self.show_fields = false;
+        self.fields = None;
+        self.scroll = 0;
+    }
+
+    /// Switches between the message and every field, the fields are fetched the first time
+    fn toggle_fields(&mut self) {
+        let Some(event) = &self.event else {
+            return;
+        };
+        self.show_fields = !self.show_fields;
+        self.scroll = 0;
+        if !self.show_fields || self.fields.is_some() {
+            return;
+        }
+        let Some(pointer) = event.pointer.clone() else {
+            self.fields = Some(Err("only events from a query have fields".to_string()));
+            return;
+        };
+        let backend = self.backend.clone();
+        let region = self.region.clone();
+        let log_detail_tx = self.log_detail_tx.clone();
+        self.tasks.spawn(TaskKey::LogRecord, async move {
+            let result = backend.fetch_log_record(region, pointer.to_string()).await;
+            let _ = log_detail_tx.send(LogDetailOutboundMessage::Fields(pointer, result));
+        });
+    }
+
+    pub fn set_fields(&mut self, pointer: Arc<str>, result: Result<QueryRow, String>) {
+        // a different event was opened since
+        if self.event.as_ref().and_then(|event| event.pointer.as_ref()) == Some(&pointer) {
+            self.fields = Some(result);
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        let key = match event {
+            Event::Key(key) => key,
+            _ => return false,
+        };
+        match self.keymap.action(Context::Detail, key) {
+            Some(Action::ScrollUp) => self.scroll = self.scroll.saturating_sub(1),
+            Some(Action::ScrollDown) => self.scroll = self.scroll.saturating_add(1),
+            Some(Action::PageUp) => self.scroll = self.scroll.saturating_sub(20),
+            Some(Action::PageDown) => self.scroll = self.scroll.saturating_add(20),
+            Some(Action::Inspect) => self.toggle_fields(),
+            _ => return false,
+        };
+        true
+    }
+
+    /// The time in the message and how far `@timestamp` is from it, when there's one
+    fn message_time_line(&self, event: &LogEvent) -> Option<Line<'static>> {
+        let logged = self
+            .message_timestamps
+            .extract(&event.log_group, &event.message)?;
+        let time = chrono::DateTime::from_timestamp_millis(logged)?.to_rfc3339();
+        let skew = event.timestamp - logged;
+        let skew = match skew.signum() {
+            0 => "same as @timestamp".to_string(),
+            sign => format!(
+                "@timestamp {} {}",
+                format_elapsed(std::time::Duration::from_millis(skew.unsigned_abs())),
+                if sign > 0 { "later" } else { "earlier" }
+            ),
+        };
+        Some(Line::from(format!("logged {} ({})", time, skew)).right_aligned())
+    }
+
+    /// A line per field, names padded so the values line up
+    fn field_lines(fields: &QueryRow) -> Vec<Line<'_>> {
+        let width = fields
+            .iter()
+            .map(|(field, _)| field.chars().count())
+            .max()
+            .unwrap_or_default();
+        fields
+            .iter()
+            .map(|(field, value)| {
+                Line::from(vec![
+                    Span::styled(
+                        format!("{:width$}  ", field),
+                        Style::new().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw(value.as_str()),
+                ])
+            })
+            .collect()
+    }
+
+    /// Each namespace's metrics with their values and units, then the dimensions they're under
+    fn metric_lines(directives: &[MetricDirective]) -> Vec<Line<'static>> {
+        let bold = Style::new().add_modifier(Modifier::BOLD);
+        let mut lines = vec![];
+        for directive in directives {
+            lines.push(Line::styled(
+                format!("metrics in {}", directive.namespace),
+                bold,
+            ));
+            let width = directive
+                .metrics
+                .iter()
+                .map(|(name, _, _)| name.chars().count())
+                .max()
+                .unwrap_or_default();
+            for (name, value, unit) in &directive.metrics {
+                let unit = unit.as_deref().unwrap_or_default();
+                lines.push(Line::from(vec![
+                    Span::styled(format!("  {:width$}  ", name), bold),
+                    Span::raw(format!("{} {}", value, unit).trim_end().to_string()),
+                ]));
+            }
+            for set in &directive.dimension_sets {
+                let dimensions = set
+                    .iter()
+                    .map(|(name, value)| format!("{}={}", name, value))
+                    .collect::<Vec<_>>();
+                lines.push(Line::from(format!("  by {}", dimensions.join(", "))));
+            }
+        }
+        lines
+    }
+}
+
+impl Widget for &LogDetailComponent {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let Some(event) = &self.event else {
+            return;
+        };
+        let timestamp = chrono::DateTime::from_timestamp_millis(event.timestamp)
+            .map(|timestamp| timestamp.to_rfc3339())
+            .unwrap_or_default();
+
+        let mut block = Block::bordered()
+            .title(format!("{} / {}", event.log_group, event.log_stream))
+            .title(Line::from(timestamp).right_aligned())
+            .title_bottom(Line::from(self.keymap.hint()).right_aligned());
+        if let Some(line) = self.message_time_line(event) {
+            block = block.title_bottom(line);
+        }
+
+        let text: Text = match (self.show_fields, &self.fields) {
+            (false, _) => {
+                let mut text = Text::from(&*event.message);
+                let logged = event.envelope.as_deref().unwrap_or(&event.message);
+                if let Some(directives) = emf::parse(logged) {
+                    // the raw metric definitions are unreadable, lay them out instead
+                    text.push_line("");
+                    text.extend(LogDetailComponent::metric_lines(&directives));
+                } else if let Some(envelope) = &event.envelope {
+                    text.push_line("");
+                    text.push_line(Span::styled(
+                        "logged as",
+                        Style::new().add_modifier(Modifier::BOLD),
+                    ));
+                    text.extend(Text::from(&**envelope));
+                }
+                text
+            }
+            (true, None) => "loading fields…".into(),
+            (true, Some(Ok(fields))) => LogDetailComponent::field_lines(fields).into(),
+            (true, Some(Err(e))) => format!("couldn't load the fields: {}", e).into(),
+        };
+        Paragraph::new(text)
+            .block(block)
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0))
+            .render(area, buf);
+    }
+}
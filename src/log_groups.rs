@@ -1,258 +1,1227 @@
-use std::sync::{Arc, RwLock};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
-use crossterm::event::{Event, KeyCode, KeyEventKind};
-use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use futures::{stream, FutureExt, StreamExt};
 use ratatui::{
     buffer::Buffer,
     layout::{Constraint, Rect},
     style::{Color, Style},
     text::{Line, Span},
-    widgets::{Block, HighlightSpacing, Row, StatefulWidget, Table, TableState, Widget},
+    widgets::{Block, HighlightSpacing, Paragraph, Row, StatefulWidget, Table, TableState, Widget},
 };
+use regex_lite::Regex;
 use tokio::sync::mpsc;
 
-use crate::shared::LoadingState;
+use crate::{
+    backend::{GroupChange, LogBackend},
+    input::Input,
+    keymap::{Action, Context, Keymap},
+    log_viewer::WINDOW_MS,
+    popup::{ConfirmPopup, GroupInfoPopup, ListPopup, PromptPopup},
+    search::{fuzzy_rank, SearchWorker},
+    shared::{
+        format_bytes, format_count, format_elapsed, format_window, is_offline, Alarm, FilterAlarms,
+        GroupMetadata, InputMode, LoadingState, LogGroup, Notification,
+    },
+    tasks::{TaskKey, TaskSupervisor},
+    theme::Theme,
+};
 
-#[derive(Debug, Clone)]
-pub struct LogGroupListComponent {
-    pub(crate) state: Arc<RwLock<LogGroupListState>>,
-    sorted_log_groups: Vec<(String, Vec<usize>)>,
-    search_term: String,
-    is_searching: bool,
-}
+/// How many groups have their metadata fetched at once, CloudWatch throttles bursts of calls
+const METADATA_CONCURRENCY: usize = 4;
+
+/// What the `t` popup offers to query when a group is opened, in minutes
+const TIME_RANGES: [i64; 8] = [
+    15,
+    60,
+    3 * 60,
+    12 * 60,
+    24 * 60,
+    3 * 24 * 60,
+    7 * 24 * 60,
+    30 * 24 * 60,
+];
 
 #[derive(Debug)]
-pub struct LogGroupListState {
-    log_groups: Vec<String>,
+pub struct LogGroupListComponent {
+    /// shared with the search worker so a keystroke doesn't copy every group
+    log_groups: Arc<Vec<LogGroup>>,
     loading_state: LoadingState,
     table_state: TableState,
+    /// indices into `log_groups` of the rows shown, with the characters the search matched
+    sorted_log_groups: Vec<(usize, Vec<usize>)>,
+    selected_log_groups: Vec<LogGroup>,
+    search_term: Input,
+    is_searching: bool,
+    /// matches the search term off the ui thread, results come back as `SearchResults`
+    searcher: SearchWorker,
+    /// a search is running and `sorted_log_groups` still shows the previous results
+    search_pending: bool,
+    /// list groups from every region instead of just the configured one
+    pub all_regions: bool,
+    /// names of the groups to highlight once they've loaded, the ones used last time
+    pub preselect: Vec<String>,
+    /// open the preselected groups straight away instead of only highlighting them
+    pub open_preselected: bool,
+    /// groups matching any of these are left out of the list unless `show_hidden` is on
+    pub exclude: Vec<Regex>,
+    /// names of groups hidden one by one, these are remembered between runs
+    pub hidden_log_groups: Vec<String>,
+    show_hidden: bool,
+    /// adds a column with when each group's oldest events are deleted, for retention audits
+    show_expiry: bool,
+    /// adds size, newest event and tag columns, the last two fetched for the rows on screen
+    show_metadata: bool,
+    /// by group arn, once fetched
+    metadata: HashMap<String, Result<GroupMetadata, String>>,
+    /// arns of the groups the running metadata task fetches
+    metadata_requested: Vec<String>,
+    /// how many loaded groups are excluded, kept up to date by `apply_search`
+    hidden_count: usize,
+    /// how many groups the search runs over, the loaded ones less any hidden
+    candidate_count: usize,
+    /// the `b` menu for changing every marked group at once
+    bulk_prompt: Option<BulkPrompt>,
+    /// the `i` popup
+    info: Option<GroupInfo>,
+    /// a bulk change cut short by the network going, finished once it's back
+    offline_changes: Option<(Vec<LogGroup>, GroupChange)>,
+    /// how far back opening a group queries
+    window_ms: i64,
+    /// the highlighted entry of the `t` popup while it's open
+    time_range_picker: Option<usize>,
+    pub keymap: Arc<Keymap>,
+    pub theme: Theme,
     group_selection_tx: mpsc::UnboundedSender<LogGroupSelectionOutboundMessage>,
+    backend: Arc<dyn LogBackend>,
+    tasks: TaskSupervisor,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum BulkPrompt {
+    Menu,
+    Retention(Input),
+    Tag(Input),
+    ConfirmDelete,
+}
+
+#[derive(Debug)]
+struct GroupInfo {
+    group: LogGroup,
+    /// `None` until they've loaded
+    field_indexes: Option<Result<Vec<String>, String>>,
+    /// the group's metric filters with the alarms watching their metrics, `None` until loaded
+    metric_filters: Option<Result<FilterAlarms, String>>,
+    /// index of the highlighted alarm across every filter
+    selected_alarm: usize,
+}
+
+impl GroupInfo {
+    fn alarms(&self) -> impl Iterator<Item = &Alarm> {
+        self.metric_filters
+            .iter()
+            .flatten()
+            .flatten()
+            .flat_map(|(_, alarms)| alarms)
+    }
 }
 
 pub enum LogGroupSelectionOutboundMessage {
-    SelectedGroup(String),
-    ApplySearch,
+    SelectedGroups(Vec<LogGroup>),
+    AppendLogGroups(Vec<LogGroup>),
+    SetLoadingState(LoadingState),
+    /// the individually hidden groups changed and should be saved
+    SetHiddenGroups(Vec<String>),
+    Notify(Notification),
+    LogGroupDeleted(LogGroup),
+    /// tags and newest event of the group with this arn
+    GroupMetadata(String, Result<GroupMetadata, String>),
+    /// ranked matches for the search started as `generation`
+    SearchResults(u64, Vec<(usize, Vec<usize>)>),
+    /// indexed fields of the named group, and its metric filters with the alarms on their
+    /// metrics
+    GroupInfo(
+        String,
+        Result<Vec<String>, String>,
+        Result<FilterAlarms, String>,
+    ),
+    /// an alarm picked in the group info, to be shown with the others
+    ShowAlarm(Alarm),
+    /// the groups a bulk change hadn't got to when the network went, from the one that failed
+    ChangesOffline(Vec<LogGroup>, GroupChange),
 }
 
 impl LogGroupListComponent {
     pub fn new(
         group_selection_tx: mpsc::UnboundedSender<LogGroupSelectionOutboundMessage>,
+        backend: Arc<dyn LogBackend>,
+        tasks: TaskSupervisor,
     ) -> Self {
         Self {
-            state: Arc::new(RwLock::new(LogGroupListState {
-                log_groups: vec![],
-                loading_state: LoadingState::Idle,
-                table_state: TableState::default(),
-                group_selection_tx,
-            })),
-            search_term: String::new(),
+            log_groups: Arc::default(),
+            loading_state: LoadingState::Idle,
+            table_state: TableState::default(),
+            search_term: Input::default(),
             is_searching: false,
+            searcher: SearchWorker::new(),
+            search_pending: false,
+            all_regions: false,
+            preselect: vec![],
+            open_preselected: false,
+            exclude: vec![],
+            hidden_log_groups: vec![],
+            show_hidden: false,
+            show_expiry: false,
+            show_metadata: false,
+            metadata: HashMap::new(),
+            metadata_requested: vec![],
+            hidden_count: 0,
+            candidate_count: 0,
+            bulk_prompt: None,
+            info: None,
+            offline_changes: None,
+            window_ms: WINDOW_MS,
+            time_range_picker: None,
+            keymap: Arc::default(),
+            theme: Theme::default(),
             sorted_log_groups: vec![],
+            selected_log_groups: vec![],
+            group_selection_tx,
+            backend,
+            tasks,
+        }
+    }
+    pub fn run(&mut self) {
+        self.log_groups = Arc::default();
+        self.metadata.clear();
+        self.metadata_requested.clear();
+        self.apply_search();
+        self.loading_state = LoadingState::Loading;
+        let regions = if self.all_regions {
+            self.backend.regions().into_iter().map(Some).collect()
+        } else {
+            vec![None]
+        };
+        // the task only reports back through messages, all state stays on the ui side
+        self.tasks.spawn(
+            TaskKey::LogGroups,
+            load_log_groups(
+                self.backend.clone(),
+                self.group_selection_tx.clone(),
+                regions,
+            ),
+        );
+    }
+
+    pub fn append_log_groups(&mut self, log_groups: Vec<LogGroup>) {
+        // only copies when a search still holds on to the old list
+        Arc::make_mut(&mut self.log_groups).extend(log_groups);
+        if self.table_state.selected().is_none() && !self.log_groups.is_empty() {
+            self.table_state.select_first();
         }
+        self.apply_search();
+        self.apply_preselect(false);
     }
-    pub fn run(&self) {
-        let this = self.clone(); // clone the widget to pass to the background task
-        tokio::spawn(this.fetch_log_groups());
+
+    pub fn loading_state(&self) -> &LoadingState {
+        &self.loading_state
     }
 
-    async fn fetch_log_groups(self) {
-        self.state.write().unwrap().loading_state = LoadingState::Loading;
+    pub fn set_loading_state(&mut self, loading_state: LoadingState) {
+        self.loading_state = loading_state;
+        if self.loading_state != LoadingState::Loading {
+            self.apply_preselect(true);
+        }
+    }
+
+    /// Waits until every preselected group arrived, or whatever did once loading is `done`
+    fn apply_preselect(&mut self, done: bool) {
+        if self.preselect.is_empty() {
+            return;
+        }
+        let groups = self
+            .preselect
+            .iter()
+            .filter_map(|name| self.log_groups.iter().find(|group| &group.name == name))
+            .cloned()
+            .collect::<Vec<_>>();
+        if groups.len() < self.preselect.len() && !done {
+            return;
+        }
+        self.preselect.clear();
+        let Some(row) = groups.first().and_then(|first| {
+            self.sorted_log_groups
+                .iter()
+                .position(|(index, _)| &self.log_groups[*index] == first)
+        }) else {
+            return;
+        };
+        self.table_state.select(Some(row));
+        if self.open_preselected {
+            self.open(groups);
+        }
+    }
 
-        let config = aws_config::load_from_env().await;
-        let client = aws_sdk_cloudwatchlogs::Client::new(&config);
+    /// A listed group by name, in `region` when it's known
+    pub fn find_log_group(&self, name: &str, region: Option<&str>) -> Option<&LogGroup> {
+        self.log_groups
+            .iter()
+            .find(|group| group.name == name && (region.is_none() || group.region() == region))
+    }
 
-        let mut next_token = None;
-        loop {
-            let response = match client
-                .describe_log_groups()
-                .set_next_token(next_token)
-                .send()
-                .await
-            {
-                Ok(response) => response,
-                Err(err) => {
-                    let mut state = self.state.write().unwrap();
-                    state.loading_state = LoadingState::Error(err.to_string());
-                    state.log_groups.clear();
-                    return;
+    /// The account the listed groups are in, `None` until one has loaded
+    pub fn account_id(&self) -> Option<&str> {
+        self.log_groups.iter().find_map(LogGroup::account_id)
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.loading_state == LoadingState::Loading
+    }
+
+    /// Loading, or still applying a bulk change
+    pub fn is_busy(&self) -> bool {
+        self.is_loading() || self.tasks.status(&TaskKey::GroupChanges).is_some()
+    }
+
+    pub fn remove_log_group(&mut self, log_group: &LogGroup) {
+        Arc::make_mut(&mut self.log_groups).retain(|group| group != log_group);
+        self.selected_log_groups.retain(|group| group != log_group);
+        self.apply_search();
+    }
+
+    /// Applies `change` to every marked group one after the other
+    fn change_selected(&mut self, change: GroupChange) {
+        self.bulk_prompt = None;
+        self.change(self.selected_log_groups.clone(), change);
+    }
+
+    fn change(&self, log_groups: Vec<LogGroup>, change: GroupChange) {
+        self.tasks.spawn(
+            TaskKey::GroupChanges,
+            change_log_groups(
+                self.backend.clone(),
+                self.group_selection_tx.clone(),
+                log_groups,
+                change,
+            ),
+        );
+    }
+
+    /// Keeps what's left of a bulk change for `retry_changes`
+    pub fn set_offline_changes(&mut self, log_groups: Vec<LogGroup>, change: GroupChange) {
+        self.offline_changes = Some((log_groups, change));
+    }
+
+    /// Carries on with the bulk change the network cut short
+    pub fn retry_changes(&mut self) {
+        if let Some((log_groups, change)) = self.offline_changes.take() {
+            self.change(log_groups, change);
+        }
+    }
+
+    fn handle_bulk_prompt_event(&mut self, key: &KeyEvent) {
+        let Some(prompt) = &mut self.bulk_prompt else {
+            return;
+        };
+        match prompt {
+            BulkPrompt::Menu => {
+                match (
+                    self.keymap.action(Context::BulkMenu, key),
+                    self.keymap.action(Context::Global, key),
+                ) {
+                    (Some(Action::SetRetention), _) => {
+                        self.bulk_prompt = Some(BulkPrompt::Retention(Input::default()))
+                    }
+                    (Some(Action::AddTag), _) => {
+                        self.bulk_prompt = Some(BulkPrompt::Tag(Input::default()))
+                    }
+                    (Some(Action::Delete), _) => self.bulk_prompt = Some(BulkPrompt::ConfirmDelete),
+                    (_, Some(Action::Back)) => self.bulk_prompt = None,
+                    _ => (),
                 }
-            };
-            let partial_log_groups = response
-                .log_groups
-                .unwrap_or_default()
-                .into_iter()
-                .filter_map(|group| group.log_group_name)
-                .collect::<Vec<String>>();
+            }
+            BulkPrompt::ConfirmDelete => {
+                if self.keymap.action(Context::Global, key) == Some(Action::Confirm) {
+                    self.change_selected(GroupChange::Delete);
+                } else {
+                    self.bulk_prompt = None;
+                }
+            }
+            BulkPrompt::Retention(_) | BulkPrompt::Tag(_) if key.code == KeyCode::Esc => {
+                self.bulk_prompt = None
+            }
+            BulkPrompt::Retention(input) if key.code == KeyCode::Enter => {
+                if let Ok(days) = input.value().trim().parse() {
+                    self.change_selected(GroupChange::SetRetention(days));
+                }
+            }
+            BulkPrompt::Tag(input) if key.code == KeyCode::Enter => {
+                if let Some((key, value)) = input.value().split_once('=') {
+                    let change = GroupChange::Tag(key.trim().into(), value.trim().into());
+                    self.change_selected(change);
+                }
+            }
+            BulkPrompt::Retention(input) | BulkPrompt::Tag(input) => {
+                input.handle_key(key);
+            }
+        }
+    }
 
-            let mut state = self.state.write().unwrap();
-            state.log_groups.extend(partial_log_groups);
-            if !state.log_groups.is_empty() {
-                state.table_state.select_first();
+    fn scroll_down(&mut self) {
+        self.table_state.select_next();
+    }
+
+    fn scroll_up(&mut self) {
+        self.table_state.select_previous();
+    }
+
+    /// The group shown in `row` of the (possibly searched) list
+    fn row_group(&self, row: usize) -> Option<&LogGroup> {
+        let (index, _) = self.sorted_log_groups.get(row)?;
+        self.log_groups.get(*index)
+    }
+
+    /// Shows the highlighted group's settings and loads its field indexes and metric filters
+    fn open_info(&mut self) {
+        let Some(group) = self
+            .table_state
+            .selected()
+            .and_then(|row| self.row_group(row))
+            .cloned()
+        else {
+            return;
+        };
+        let backend = self.backend.clone();
+        let group_selection_tx = self.group_selection_tx.clone();
+        let (region, name) = (group.region().map(str::to_string), group.name.clone());
+        self.tasks.spawn(TaskKey::GroupInfo, async move {
+            let (field_indexes, metric_filters) = futures::future::join(
+                backend.fetch_field_indexes(region.clone(), name.clone()),
+                load_filter_alarms(backend.as_ref(), region, name.clone()),
+            )
+            .await;
+            let _ = group_selection_tx.send(LogGroupSelectionOutboundMessage::GroupInfo(
+                name,
+                field_indexes,
+                metric_filters,
+            ));
+        });
+        self.info = Some(GroupInfo {
+            group,
+            field_indexes: None,
+            metric_filters: None,
+            selected_alarm: 0,
+        });
+    }
+
+    pub fn set_group_info(
+        &mut self,
+        name: String,
+        field_indexes: Result<Vec<String>, String>,
+        metric_filters: Result<FilterAlarms, String>,
+    ) {
+        if let Some(info) = &mut self.info {
+            if info.group.name == name {
+                info.field_indexes = Some(field_indexes);
+                info.metric_filters = Some(metric_filters);
             }
-            state
-                .group_selection_tx
-                .send(LogGroupSelectionOutboundMessage::ApplySearch)
-                .unwrap();
-            if response.next_token.is_some() {
-                next_token = response.next_token;
-            } else {
-                return state.loading_state = LoadingState::Loaded;
+        }
+    }
+
+    /// Up and down pick one of the alarms and enter jumps to it, any other key closes the info
+    fn handle_info_event(&mut self, key: &KeyEvent) {
+        let Some(info) = &mut self.info else {
+            return;
+        };
+        let last = info.alarms().count().checked_sub(1);
+        match (self.keymap.action(Context::Groups, key), last) {
+            (Some(Action::ScrollUp), Some(_)) => {
+                info.selected_alarm = info.selected_alarm.saturating_sub(1)
             }
+            (Some(Action::ScrollDown), Some(last)) => {
+                info.selected_alarm = (info.selected_alarm + 1).min(last)
+            }
+            (Some(Action::Open), Some(_)) => {
+                if let Some(alarm) = info.alarms().nth(info.selected_alarm).cloned() {
+                    let _ = self
+                        .group_selection_tx
+                        .send(LogGroupSelectionOutboundMessage::ShowAlarm(alarm));
+                }
+                self.close_info();
+            }
+            _ => self.close_info(),
+        }
+    }
+
+    fn close_info(&mut self) {
+        self.info = None;
+        self.tasks.cancel(&TaskKey::GroupInfo);
+    }
+
+    fn toggle_selected(&mut self) {
+        let selected = self.table_state.selected();
+        let Some(group) = selected.and_then(|row| self.row_group(row)).cloned() else {
+            return;
+        };
+        if let Some(position) = self.selected_log_groups.iter().position(|g| g == &group) {
+            self.selected_log_groups.remove(position);
+        } else {
+            self.selected_log_groups.push(group);
+        }
+    }
+
+    fn open(&self, groups: Vec<LogGroup>) {
+        if !groups.is_empty() {
+            let _ = self
+                .group_selection_tx
+                .send(LogGroupSelectionOutboundMessage::SelectedGroups(groups));
+        }
+    }
+
+    /// Opens the group at `index` of the (possibly searched) list
+    fn open_row(&self, index: usize) {
+        if let Some(group) = self.row_group(index) {
+            self.open(vec![group.clone()]);
         }
     }
 
-    fn scroll_down(&self) {
-        self.state.write().unwrap().table_state.select_next();
+    fn is_hidden(&self, group: &LogGroup) -> bool {
+        self.hidden_log_groups.contains(&group.name)
+            || self
+                .exclude
+                .iter()
+                .any(|pattern| pattern.is_match(&group.name))
     }
 
-    fn scroll_up(&self) {
-        self.state.write().unwrap().table_state.select_previous();
+    /// Hides the highlighted group, or brings it back while hidden groups are shown
+    fn toggle_hidden(&mut self) {
+        let selected = self.table_state.selected();
+        let Some(name) = selected
+            .and_then(|row| self.row_group(row))
+            .map(|group| group.name.clone())
+        else {
+            return;
+        };
+        if let Some(position) = self.hidden_log_groups.iter().position(|n| n == &name) {
+            self.hidden_log_groups.remove(position);
+        } else {
+            self.hidden_log_groups.push(name);
+        }
+        let _ = self
+            .group_selection_tx
+            .send(LogGroupSelectionOutboundMessage::SetHiddenGroups(
+                self.hidden_log_groups.clone(),
+            ));
+        self.apply_search();
+    }
+
+    fn toggle_show_hidden(&mut self) {
+        self.show_hidden = !self.show_hidden;
+        self.apply_search();
     }
 
     pub fn apply_search(&mut self) {
+        let mut hidden_count = 0;
+        let mut candidates = Vec::with_capacity(self.log_groups.len());
+        for (index, group) in self.log_groups.iter().enumerate() {
+            let hidden = self.is_hidden(group);
+            if hidden {
+                hidden_count += 1;
+            }
+            if !hidden || self.show_hidden {
+                candidates.push(index);
+            }
+        }
+        self.hidden_count = hidden_count;
+        self.candidate_count = candidates.len();
         if self.search_term.is_empty() {
-            self.sorted_log_groups = self
-                .state
-                .read()
-                .unwrap()
-                .log_groups
-                .clone()
+            self.searcher.cancel();
+            self.search_pending = false;
+            let rows = candidates
                 .into_iter()
-                .map(|group| (group, vec![]))
+                .map(|index| (index, vec![]))
                 .collect();
+            self.set_rows(rows);
             return;
         }
-        let groups = self.state.read().unwrap().log_groups.clone();
-        let matcher = SkimMatcherV2::default().ignore_case();
-        self.sorted_log_groups = groups
-            .into_iter()
-            .map(|group| {
-                (
-                    group.clone(),
-                    matcher.fuzzy_indices(&group, &self.search_term),
-                )
-            })
-            .filter(|(_, score)| match score {
-                Some((s, _)) => s > &5,
-                None => false,
+        let term = self.search_term.value().to_string();
+        let groups = self.log_groups.clone();
+        let tx = self.group_selection_tx.clone();
+        self.search_pending = true;
+        self.searcher.spawn(move |cancellation| {
+            let names = candidates.iter().map(|&index| groups[index].name.as_str());
+            let Some(matches) = fuzzy_rank(&term, names, cancellation) else {
+                return;
+            };
+            let results = matches
+                .into_iter()
+                .map(|(candidate, indices)| (candidates[candidate], indices))
+                .collect();
+            let _ = tx.send(LogGroupSelectionOutboundMessage::SearchResults(
+                cancellation.generation(),
+                results,
+            ));
+        });
+    }
+
+    /// Shows the results of the latest search, anything older has been superseded
+    pub fn set_search_results(&mut self, generation: u64, results: Vec<(usize, Vec<usize>)>) {
+        if generation != self.searcher.generation() {
+            return;
+        }
+        self.search_pending = false;
+        self.set_rows(results);
+    }
+
+    /// Shows `rows`, keeping the highlighted group selected when it's still among them
+    fn set_rows(&mut self, rows: Vec<(usize, Vec<usize>)>) {
+        let Some(selected) = self.table_state.selected() else {
+            self.sorted_log_groups = rows;
+            return;
+        };
+        let group = self
+            .sorted_log_groups
+            .get(selected)
+            .map(|(index, _)| *index);
+        self.sorted_log_groups = rows;
+        let row = group
+            .and_then(|group| {
+                self.sorted_log_groups
+                    .iter()
+                    .position(|(index, _)| *index == group)
             })
-            .map(|(group, score)| (group, score.unwrap_or_default()))
-            .map(|(group, (_, indices))| (group, indices))
-            .collect();
+            .unwrap_or(selected.min(self.sorted_log_groups.len().saturating_sub(1)));
+        self.table_state.select(Some(row));
     }
 
-    pub fn handle_event(&mut self, event: &Event) -> bool {
-        if let Event::Key(key) = event {
-            if key.kind == KeyEventKind::Press {
-                match key.code {
-                    KeyCode::Down => self.scroll_down(),
-                    KeyCode::Up => self.scroll_up(),
-                    KeyCode::Enter => {
-                        let state = self.state.write().unwrap();
-                        if let Some((selected, _)) = self
-                            .sorted_log_groups
-                            .get(state.table_state.selected().unwrap_or(0))
-                        {
-                            state
-                                .group_selection_tx
-                                .send(LogGroupSelectionOutboundMessage::SelectedGroup(
-                                    selected.clone(),
-                                ))
-                                .unwrap();
-                        }
-                    }
-                    _ => (),
-                };
+    pub fn is_search_pending(&self) -> bool {
+        self.search_pending
+    }
+
+    /// Opens every marked group merged together, or just the highlighted one
+    fn open_marked(&mut self) {
+        if self.selected_log_groups.is_empty() {
+            self.open_row(self.table_state.selected().unwrap_or(0));
+        } else {
+            self.open(self.selected_log_groups.clone());
+        }
+    }
+
+    /// Only rows inside the viewport are built, accounts can have tens of thousands of groups
+    fn render_rows(&mut self, block: Block, area: Rect, buf: &mut Buffer) {
+        let height = block.inner(area).height as usize;
+        let len = self.sorted_log_groups.len();
+        let selected = self
+            .table_state
+            .selected()
+            .map(|selected| selected.min(len.saturating_sub(1)));
+        self.table_state.select(selected);
+        let mut offset = self.table_state.offset().min(len.saturating_sub(height));
+        if let Some(selected) = selected {
+            if selected < offset {
+                offset = selected;
+            } else if height > 0 && selected >= offset + height {
+                offset = selected + 1 - height;
             }
-            if self.is_searching {
-                match key.code {
-                    KeyCode::Esc => {
-                        self.is_searching = false;
-                        self.search_term.clear();
-                        self.sorted_log_groups = self
-                            .state
-                            .read()
-                            .unwrap()
-                            .log_groups
-                            .clone()
-                            .iter()
-                            .map(|group| (group.clone(), vec![]))
-                            .collect();
-                    }
-                    KeyCode::Backspace => {
-                        self.search_term.pop();
-                    }
-                    KeyCode::Char(c) => self.search_term.push(c),
-                    _ => (),
+        }
+        *self.table_state.offset_mut() = offset;
+        if self.show_metadata {
+            self.fetch_metadata(offset, height);
+        }
+
+        let now = chrono::Utc::now().timestamp_millis();
+        let rows = self
+            .sorted_log_groups
+            .iter()
+            .skip(offset)
+            .take(height)
+            .enumerate()
+            .map(|(row, (index, indecies))| {
+                let log_group = &self.log_groups[*index];
+                // 1-9 open the first nine visible rows directly
+                let number = if row < 9 {
+                    Span::styled(format!("{} ", row + 1), Style::new().fg(self.theme.muted))
+                } else {
+                    Span::raw("  ")
+                };
+                let marker = if self.selected_log_groups.contains(log_group) {
+                    Span::styled("● ", Style::new().fg(self.theme.ok))
+                } else {
+                    Span::raw("  ")
+                };
+                Row::new(
+                    vec![
+                        Line::from(
+                            [number, marker]
+                                .into_iter()
+                                .chain(highlighted_name(&log_group.name, indecies, &self.theme))
+                                .collect::<Vec<_>>(),
+                        ),
+                        Line::styled(
+                            log_group.region().unwrap_or_default(),
+                            Style::new().fg(self.theme.muted),
+                        ),
+                        self.expiry_line(log_group, now),
+                    ]
+                    .into_iter()
+                    .chain(self.metadata_lines(log_group, now))
+                    .collect::<Vec<_>>(),
+                )
+            });
+        // the region column only matters when groups come from several regions
+        let region_width = if self.all_regions { 15 } else { 0 };
+        let expiry_width = if self.show_expiry { 14 } else { 0 };
+        let metadata_widths = if self.show_metadata {
+            [10, 10, 24]
+        } else {
+            [0, 0, 0]
+        };
+        let widths = [
+            Constraint::Fill(1),
+            Constraint::Length(region_width),
+            Constraint::Length(expiry_width),
+        ]
+        .into_iter()
+        .chain(metadata_widths.map(Constraint::Length));
+        let table = Table::new(rows, widths)
+            .block(block)
+            .highlight_spacing(HighlightSpacing::Always)
+            .highlight_symbol("🪵")
+            .highlight_style(Style::new().fg(self.theme.accent));
+
+        let mut viewport_state =
+            TableState::default().with_selected(selected.map(|selected| selected - offset));
+        StatefulWidget::render(table, area, buf, &mut viewport_state);
+    }
+
+    fn toggle_metadata(&mut self) {
+        self.show_metadata = !self.show_metadata;
+        if !self.show_metadata {
+            self.tasks.cancel(&TaskKey::GroupMetadata);
+            self.metadata_requested.clear();
+        }
+    }
+
+    /// Fetches the metadata of the rows on screen that don't have it yet, a few groups at a
+    /// time. Scrolling past rows drops them, fetching every group up front would run into
+    /// CloudWatch's rate limits in accounts with thousands
+    fn fetch_metadata(&mut self, offset: usize, height: usize) {
+        let missing = self
+            .sorted_log_groups
+            .iter()
+            .skip(offset)
+            .take(height)
+            .map(|(index, _)| &self.log_groups[*index])
+            .filter(|group| !group.arn.is_empty() && !self.metadata.contains_key(&group.arn))
+            .cloned()
+            .collect::<Vec<_>>();
+        if missing
+            .iter()
+            .all(|group| self.metadata_requested.contains(&group.arn))
+        {
+            return;
+        }
+        self.metadata_requested = missing.iter().map(|group| group.arn.clone()).collect();
+        let backend = self.backend.clone();
+        let group_selection_tx = self.group_selection_tx.clone();
+        self.tasks.spawn(TaskKey::GroupMetadata, async move {
+            let mut results = stream::iter(missing)
+                .map(|group| {
+                    let region = group.region().map(str::to_string);
+                    let result =
+                        backend.fetch_group_metadata(region, group.name, group.arn.clone());
+                    result.map(|result| (group.arn, result))
+                })
+                .buffer_unordered(METADATA_CONCURRENCY);
+            while let Some((arn, result)) = results.next().await {
+                let message = LogGroupSelectionOutboundMessage::GroupMetadata(arn, result);
+                if group_selection_tx.send(message).is_err() {
+                    return;
                 }
-                self.apply_search();
-                return key.code == KeyCode::Esc || KeyCode::Char('q') == key.code;
             }
-            if key.kind == KeyEventKind::Press {
-                match key.code {
-                    KeyCode::Char('/') => self.is_searching = !self.is_searching,
-                    KeyCode::Char('j') => self.scroll_down(),
-                    KeyCode::Char('k') => self.scroll_up(),
-                    KeyCode::Char('r') => {
-                        if self.state.read().unwrap().loading_state != LoadingState::Loading {
-                            let this = self.clone();
-                            tokio::spawn(this.fetch_log_groups());
-                        }
+        });
+    }
+
+    pub fn set_metadata(&mut self, arn: String, result: Result<GroupMetadata, String>) {
+        self.metadata.insert(arn, result);
+    }
+
+    /// Size, how long ago the newest event was logged and tags, e.g. `1.5 GB  3m ago  team=api`
+    fn metadata_lines(&self, log_group: &LogGroup, now: i64) -> Vec<Line<'static>> {
+        if !self.show_metadata {
+            return vec![];
+        }
+        let muted = Style::new().fg(self.theme.muted);
+        let size = Line::styled(
+            log_group.stored_bytes.map(format_bytes).unwrap_or_default(),
+            muted,
+        );
+        let (last_event, tags) = match self.metadata.get(&log_group.arn) {
+            None => (Line::styled("…", muted), Line::default()),
+            Some(Err(_)) => {
+                let failed = Line::styled("?", Style::new().fg(self.theme.error));
+                (failed.clone(), failed)
+            }
+            Some(Ok(metadata)) => {
+                let last_event = match metadata.last_event {
+                    Some(last_event) => {
+                        let since = Duration::from_millis((now - last_event).max(0) as u64);
+                        format!("{} ago", format_elapsed(since))
                     }
-                    _ => (),
+                    None => "never".to_string(),
                 };
+                let tags = metadata
+                    .tags
+                    .iter()
+                    .map(|(key, value)| format!("{}={}", key, value))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                (Line::styled(last_event, muted), Line::styled(tags, muted))
+            }
+        };
+        vec![size, last_event, tags]
+    }
+
+    /// When the group's oldest events go, with its retention, e.g. `in 12d (30d)`. Groups
+    /// keeping everything stand out
+    fn expiry_line(&self, log_group: &LogGroup, now: i64) -> Line<'static> {
+        let Some(days) = log_group.retention_in_days else {
+            return Line::styled("never", Style::new().fg(self.theme.warning));
+        };
+        let expiry = log_group.oldest_expiry(now).unwrap_or(now);
+        let when = match (expiry - now) / (24 * 3600 * 1000) {
+            0 if expiry == now => "now".to_string(),
+            0 => "today".to_string(),
+            days => format!("in {}d", days),
+        };
+        Line::styled(
+            format!("{} ({}d)", when, days),
+            Style::new().fg(self.theme.muted),
+        )
+    }
+
+    /// `Command` while the bulk prompt or group info is open, `Search` while typing a search
+    pub fn mode(&self) -> InputMode {
+        if self.bulk_prompt.is_some() || self.info.is_some() || self.time_range_picker.is_some() {
+            InputMode::Command
+        } else if self.is_searching {
+            InputMode::Search
+        } else {
+            InputMode::Normal
+        }
+    }
+
+    /// True when the key was meant for the group list, anything but `Normal` keeps every key
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        let key = match event {
+            Event::Key(key) => key,
+            Event::Paste(text) if self.mode() == InputMode::Search => {
+                self.search_term.paste(text);
+                self.apply_search();
+                return true;
+            }
+            Event::Paste(text) => match &mut self.bulk_prompt {
+                Some(BulkPrompt::Retention(input) | BulkPrompt::Tag(input)) => {
+                    input.paste(text);
+                    return true;
+                }
+                _ => return false,
+            },
+            _ => return false,
+        };
+        let mode = self.mode();
+        if key.kind == KeyEventKind::Press {
+            match mode {
+                InputMode::Command => self.handle_command_event(key),
+                InputMode::Search => self.handle_search_event(key),
+                InputMode::Normal => self.handle_normal_event(key),
+            }
+        }
+        mode != InputMode::Normal
+    }
+
+    fn handle_command_event(&mut self, key: &KeyEvent) {
+        if self.bulk_prompt.is_some() {
+            self.handle_bulk_prompt_event(key);
+        } else if self.time_range_picker.is_some() {
+            self.handle_time_range_event(key);
+        } else {
+            self.handle_info_event(key);
+        }
+    }
+
+    /// Keys edit the search term, Esc leaves the search and clears it
+    fn handle_search_event(&mut self, key: &KeyEvent) {
+        let term = self.search_term.value().to_string();
+        match key.code {
+            // only alt+digit opens a row, digits are valid in group names
+            KeyCode::Char(c @ '1'..='9') if key.modifiers.contains(KeyModifiers::ALT) => {
+                return self.open_numbered_row(c);
+            }
+            KeyCode::Down => self.scroll_down(),
+            KeyCode::Up => self.scroll_up(),
+            KeyCode::Enter => self.open_marked(),
+            KeyCode::Esc => {
+                self.is_searching = false;
+                self.search_term.clear();
+            }
+            _ => {
+                self.search_term.handle_key(key);
+            }
+        }
+        // moving the cursor leaves the results as they are
+        if self.search_term.value() != term {
+            self.apply_search();
+        }
+    }
+
+    fn handle_normal_event(&mut self, key: &KeyEvent) {
+        if let KeyCode::Char(c @ '1'..='9') = key.code {
+            return self.open_numbered_row(c);
+        }
+        match self.keymap.action(Context::Groups, key) {
+            Some(Action::Search) => self.is_searching = true,
+            Some(Action::ScrollDown) => self.scroll_down(),
+            Some(Action::ScrollUp) => self.scroll_up(),
+            Some(Action::Open) => self.open_marked(),
+            Some(Action::Mark) => self.toggle_selected(),
+            Some(Action::ShowHidden) => self.toggle_show_hidden(),
+            Some(Action::Expiry) => self.show_expiry = !self.show_expiry,
+            Some(Action::Metadata) => self.toggle_metadata(),
+            Some(Action::Hide) => self.toggle_hidden(),
+            Some(Action::Info) => self.open_info(),
+            Some(Action::TimeRange) => self.open_time_range_picker(),
+            Some(Action::Bulk) if !self.selected_log_groups.is_empty() => {
+                self.bulk_prompt = Some(BulkPrompt::Menu)
+            }
+            Some(Action::Reload) if self.loading_state != LoadingState::Loading => self.run(),
+            _ => (),
+        };
+    }
+
+    /// How far back the viewer queries the groups opened from here
+    pub fn window_ms(&self) -> i64 {
+        self.window_ms
+    }
+
+    fn open_time_range_picker(&mut self) {
+        let current = TIME_RANGES
+            .iter()
+            .position(|minutes| minutes * 60_000 == self.window_ms);
+        self.time_range_picker = Some(current.unwrap_or_default());
+    }
+
+    fn handle_time_range_event(&mut self, key: &KeyEvent) {
+        let Some(selected) = self.time_range_picker else {
+            return;
+        };
+        match self.keymap.action(Context::Groups, key) {
+            Some(Action::ScrollUp) => self.time_range_picker = Some(selected.saturating_sub(1)),
+            Some(Action::ScrollDown) => {
+                self.time_range_picker = Some((selected + 1).min(TIME_RANGES.len() - 1))
             }
+            Some(Action::Open) => {
+                self.window_ms = TIME_RANGES[selected] * 60_000;
+                self.time_range_picker = None;
+            }
+            _ => self.time_range_picker = None,
         }
-        false
+    }
+
+    /// Opens the row numbered `c` on screen
+    fn open_numbered_row(&mut self, c: char) {
+        let row = c.to_digit(10).unwrap_or(1) as usize - 1;
+        self.open_row(self.table_state.offset() + row);
     }
 }
 
-impl Widget for &LogGroupListComponent {
+impl Widget for &mut LogGroupListComponent {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let mut state = self.state.write().unwrap();
-
-        // a block with a right aligned title with the loading state on the right
-        let loading_state = Line::from(format!("{:?}", state.loading_state)).right_aligned();
+        // a block with a right aligned title with the loading state on the right, groups
+        // listed so far can be opened while later pages are still on their way
+        let loading_state = match (&self.loading_state, self.log_groups.len()) {
+            (LoadingState::Loading, count) if count > 0 => {
+                Line::from(format!("Loading, {} so far", format_count(count)))
+            }
+            (loading_state, _) => Line::from(format!("{:?}", loading_state)),
+        }
+        .right_aligned();
         let title = if self.is_searching {
-            Line::styled(
-                format!("/{}", self.search_term),
-                Style::new().fg(Color::Red),
-            )
+            self.search_term
+                .line("/", if self.search_pending { " …" } else { "" })
+                .style(Style::new().fg(self.theme.accent))
         } else {
             Line::from("")
         };
 
-        let block = Block::bordered()
+        let mut block = Block::bordered()
             .title("Log Groups".to_string())
             .title_bottom(title)
             .title(loading_state)
-            .title_bottom(Line::from("q to quit").right_aligned());
-
-        // a table with the list of pull requests
-        let rows = self.sorted_log_groups.iter().map(|(log_group, indecies)| {
-            Row::new(vec![Line::from(
-                log_group
-                    .char_indices()
-                    .map(|(index, c)| {
-                        Span::styled(
-                            c.to_string(),
-                            Style::new().fg(if indecies.contains(&index) {
-                                Color::Red
-                            } else {
-                                Color::Reset
-                            }),
-                        )
-                    })
-                    .collect::<Vec<_>>(),
-            )])
-        });
-        let widths = [Constraint::Fill(1)];
-        let table = Table::new(rows, widths)
+            .title_bottom(
+                Line::from(format!(
+                    "{} to select, {}",
+                    self.keymap.keys(Context::Groups, Action::Mark),
+                    self.keymap.hint()
+                ))
+                .right_aligned(),
+            );
+        if !self.search_term.is_empty() {
+            block = block.title(Line::styled(
+                format!(
+                    "{} / {} matches",
+                    format_count(self.sorted_log_groups.len()),
+                    format_count(self.candidate_count)
+                ),
+                Style::new().fg(self.theme.muted),
+            ));
+        }
+        let show_hidden = self.keymap.keys(Context::Groups, Action::ShowHidden);
+        let block = match (self.hidden_count, self.show_hidden) {
+            (0, _) => block,
+            (count, false) => {
+                block.title_bottom(format!("{} hidden, {} to show", count, show_hidden))
+            }
+            (count, true) => {
+                block.title_bottom(format!("showing {} hidden, {} to hide", count, show_hidden))
+            }
+        };
+
+        let block = if self.window_ms == WINDOW_MS {
+            block
+        } else {
+            block.title_bottom(Line::styled(
+                format!(
+                    "opens the last {}, {} to change",
+                    format_window(self.window_ms),
+                    self.keymap.keys(Context::Groups, Action::TimeRange)
+                ),
+                Style::new().fg(self.theme.accent),
+            ))
+        };
+
+        let no_matches = !self.search_term.is_empty()
+            && !self.search_pending
+            && self.sorted_log_groups.is_empty();
+        if no_matches {
+            Paragraph::new(Line::styled(
+                format!("no matches for '{}'", self.search_term.value()),
+                Style::new().fg(self.theme.muted),
+            ))
+            .centered()
             .block(block)
-            .highlight_spacing(HighlightSpacing::Always)
-            .highlight_symbol("🪵")
-            .highlight_style(Style::new().fg(Color::Red));
+            .render(area, buf);
+        } else {
+            self.render_rows(block, area, buf);
+        }
+
+        if let Some(info) = &self.info {
+            GroupInfoPopup {
+                group: &info.group,
+                field_indexes: info.field_indexes.as_ref(),
+                metric_filters: info.metric_filters.as_ref(),
+                selected_alarm: info.selected_alarm,
+                theme: &self.theme,
+            }
+            .render(area, buf);
+            return;
+        }
+
+        if let Some(selected) = self.time_range_picker {
+            let entries = TIME_RANGES
+                .iter()
+                .map(|minutes| format!("last {}", format_window(minutes * 60_000)))
+                .collect::<Vec<_>>();
+            ListPopup {
+                title: "Time range",
+                entries: &entries,
+                selected,
+                empty: "",
+                hint: "enter to query this far back, esc to close",
+                theme: &self.theme,
+            }
+            .render(area, buf);
+            return;
+        }
+
+        let count = self.selected_log_groups.len();
+        let (message, hint) = match &self.bulk_prompt {
+            None => return,
+            Some(BulkPrompt::Menu) => (
+                Line::from(format!(
+                    "Change {} groups: {} retention, {} tag, {} delete",
+                    count,
+                    self.keymap.keys(Context::BulkMenu, Action::SetRetention),
+                    self.keymap.keys(Context::BulkMenu, Action::AddTag),
+                    self.keymap.keys(Context::BulkMenu, Action::Delete)
+                )),
+                format!(
+                    "{} to cancel",
+                    self.keymap.keys(Context::Global, Action::Back)
+                ),
+            ),
+            Some(BulkPrompt::Retention(input)) => (
+                input.line("Retention in days: ", ""),
+                "enter to apply, esc to cancel".to_string(),
+            ),
+            Some(BulkPrompt::Tag(input)) => (
+                input.line("Tag key=value: ", ""),
+                "enter to apply, esc to cancel".to_string(),
+            ),
+            Some(BulkPrompt::ConfirmDelete) => {
+                let message = format!("Delete {} groups and all of their logs?", count);
+                ConfirmPopup {
+                    title: "Delete",
+                    message: &message,
+                    keymap: &self.keymap,
+                    theme: &self.theme,
+                }
+                .render(area, buf);
+                return;
+            }
+        };
+        PromptPopup {
+            title: "Bulk change",
+            message,
+            hint: &hint,
+        }
+        .render(area, buf);
+    }
+}
 
-        StatefulWidget::render(table, area, buf, &mut state.table_state);
+/// Unmatched names are a single span, only search matches pay for a span per character
+fn highlighted_name<'a>(name: &'a str, indices: &[usize], theme: &Theme) -> Vec<Span<'a>> {
+    if indices.is_empty() {
+        return vec![Span::raw(name)];
+    }
+    name.char_indices()
+        .map(|(index, c)| {
+            Span::styled(
+                c.to_string(),
+                Style::new().fg(if indices.contains(&index) {
+                    theme.accent
+                } else {
+                    Color::Reset
+                }),
+            )
+        })
+        .collect()
+}
+
+/// Changes each group in turn so every outcome can be reported on its own
+async fn change_log_groups(
+    backend: Arc<dyn LogBackend>,
+    group_selection_tx: mpsc::UnboundedSender<LogGroupSelectionOutboundMessage>,
+    log_groups: Vec<LogGroup>,
+    change: GroupChange,
+) {
+    for (index, log_group) in log_groups.iter().enumerate() {
+        let result = backend
+            .change_log_group(log_group.clone(), change.clone())
+            .await;
+        if result.as_ref().is_err_and(|e| is_offline(e)) {
+            let _ = group_selection_tx.send(LogGroupSelectionOutboundMessage::ChangesOffline(
+                log_groups[index..].to_vec(),
+                change,
+            ));
+            return;
+        }
+        let notification = match &result {
+            Ok(()) => Notification {
+                message: format!("{}: done", log_group.name),
+                is_error: false,
+            },
+            Err(e) => Notification {
+                message: format!("{}: {}", log_group.name, e),
+                is_error: true,
+            },
+        };
+        if group_selection_tx
+            .send(LogGroupSelectionOutboundMessage::Notify(notification))
+            .is_err()
+        {
+            return;
+        }
+        if result.is_ok() && change == GroupChange::Delete {
+            let _ = group_selection_tx.send(LogGroupSelectionOutboundMessage::LogGroupDeleted(
+                log_group.clone(),
+            ));
+        }
+    }
+}
+
+/// The group's metric filters, each with the alarms watching the metric it publishes
+async fn load_filter_alarms(
+    backend: &dyn LogBackend,
+    region: Option<String>,
+    log_group_name: String,
+) -> Result<FilterAlarms, String> {
+    let filters = backend
+        .fetch_metric_filters(region.clone(), log_group_name)
+        .await?;
+    let mut filter_alarms = vec![];
+    for filter in filters {
+        let alarms = match (&filter.metric_name, &filter.namespace) {
+            (Some(metric_name), Some(namespace)) => {
+                backend
+                    .fetch_metric_alarms(region.clone(), metric_name.clone(), namespace.clone())
+                    .await?
+            }
+            _ => vec![],
+        };
+        filter_alarms.push((filter, alarms));
+    }
+    Ok(filter_alarms)
+}
+
+/// Pages through every region at once, pages are appended as they arrive
+async fn load_log_groups(
+    backend: Arc<dyn LogBackend>,
+    group_selection_tx: mpsc::UnboundedSender<LogGroupSelectionOutboundMessage>,
+    regions: Vec<Option<String>>,
+) {
+    let fetches = regions
+        .into_iter()
+        .map(|region| fetch_log_groups(backend.clone(), group_selection_tx.clone(), region));
+    let results = tokio::select! {
+        // the app is gone, nobody is left to page for
+        _ = group_selection_tx.closed() => return,
+        results = futures::future::join_all(fetches) => results,
+    };
+    // regions that aren't enabled for the account fail, that's fine as long as one answered
+    let loading_state = if results.iter().any(Result::is_ok) {
+        LoadingState::Loaded
+    } else {
+        results
+            .into_iter()
+            .find_map(Result::err)
+            .map(LoadingState::Error)
+            .unwrap_or(LoadingState::Loaded)
+    };
+    let _ = group_selection_tx.send(LogGroupSelectionOutboundMessage::SetLoadingState(
+        loading_state,
+    ));
+}
+
+async fn fetch_log_groups(
+    backend: Arc<dyn LogBackend>,
+    group_selection_tx: mpsc::UnboundedSender<LogGroupSelectionOutboundMessage>,
+    region: Option<String>,
+) -> Result<(), String> {
+    let mut next_token = None;
+    loop {
+        let page = backend.fetch_log_groups(region.clone(), next_token).await?;
+        if group_selection_tx
+            .send(LogGroupSelectionOutboundMessage::AppendLogGroups(
+                page.log_groups,
+            ))
+            .is_err()
+        {
+            return Ok(());
+        }
+        match page.next_token {
+            Some(token) => next_token = Some(token),
+            None => return Ok(()),
+        }
     }
 }
@@ -1,135 +1,2208 @@
 use std::{
-    fs,
-    sync::{Arc, RwLock},
+    collections::{BTreeMap, VecDeque},
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
-use aws_sdk_cloudwatchlogs::types::QueryStatus;
-use crossterm::event::{Event, KeyCode, KeyModifiers};
+use crossterm::event::{Event, KeyCode, KeyEvent};
 use ratatui::{
     buffer::Buffer,
-    layout::{Constraint, Rect},
-    style::{Color, Style},
+    layout::{Constraint, Layout, Rect},
+    style::Style,
     text::Line,
-    widgets::{Block, HighlightSpacing, Row, StatefulWidget, TableState, Widget},
+    widgets::{Block, Borders, Paragraph, Widget, Wrap},
 };
 use tokio::sync::mpsc;
+use tracing::debug;
 
-use crate::table::Table;
-use crate::{aws, shared::LoadingState};
+use crate::table::{Highlight, Table};
+use crate::{
+    aws::{parse_query_timestamp, QUERY_TIME_LIMIT_MINUTES},
+    backend::LogBackend,
+    config::{
+        MessageTimestamps, ViewerQueries, DEFAULT_CONFIRM_QUERY_BYTES, DEFAULT_MEMORY_EVENTS,
+        DEFAULT_VIEWER_QUERY,
+    },
+    envelope::{self, unwrap_events},
+    fields::{field_stats, json_fields, numeric_fields, FieldStats},
+    input::Input,
+    investigation::{Bookmark, QueryRecord},
+    keymap::{Action, Context, Keymap},
+    level::{detect_level, LogLevel},
+    pattern::{Pattern, PatternCache},
+    popup::{ConfirmPopup, FieldStatsPopup, FieldsPopup},
+    query::{self, fields_of, with_fields},
+    search::{find_all, SearchWorker},
+    shared::{
+        by_region, dedupe, format_bytes, format_count, format_elapsed, format_range, format_window,
+        InputMode, LoadingState, LogEvent, LogGroup, Notification, MAX_FILTERED_EVENTS,
+    },
+    tasks::{TaskKey, TaskSupervisor},
+    theme::Theme,
+    timestamp::parse_around,
+};
+
+/// Which of the two viewers a component is, the split one sits on the right
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pane {
+    Main,
+    Split,
+}
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct LogVieweromponent {
-    pub state: Arc<RwLock<LogViewerState>>,
-    pub log_group_name: String,
-    displayed_messages: Vec<String>,
+    pane: Pane,
+    /// draws the border dimmed when the other pane has focus
+    pub focused: bool,
+    pub log_groups: Vec<LogGroup>,
+    /// queried instead of the last 24 hours, e.g. the time around an alarm going off
+    pub time_range: Option<(i64, i64)>,
+    /// how far back the queries reach when there's no `time_range`
+    pub window_ms: i64,
+    /// only streams starting with this are fetched, empty fetches everything
+    pub stream_prefix: String,
+    stream_prefix_input: Option<Input>,
+    /// a CloudWatch filter pattern the live tail is started with, empty tails every event
+    tail_filter: String,
+    tail_filter_input: Option<Input>,
+    /// a time typed or pasted after `@`, the query covers the minutes around it
+    around_input: Option<Input>,
+    around_error: Option<String>,
+    /// typed after `/`, a regex when wrapped in slashes
+    filter_input: Option<Input>,
+    filter_error: Option<String>,
+    /// indexed fields of the groups, loaded when `/` is pressed to hint at cheaper queries
+    indexed_fields: Vec<String>,
+    patterns: PatternCache,
+    /// set with `*` from the token under the cursor, `n`/`N` jump between matches
+    search: Option<String>,
+    /// indices into the table's events of every match, `None` until the worker is done
+    search_matches: Option<Vec<usize>>,
+    searcher: SearchWorker,
+    field_stats: Option<FieldStatsPicker>,
+    field_picker: Option<FieldPicker>,
+    /// fields picked with the `Q` popup, they replace the `fields` command of every group's query
+    query_fields: Option<Vec<String>>,
+    /// typed after `m`, up and down go through the patterns already set up on the groups
+    metric_filter_input: Option<Input>,
+    /// patterns of the groups' metric filters, loaded when `m` is pressed
+    metric_filters: Vec<String>,
+    /// the pattern tried last and how it went
+    metric_filter: Option<MetricFilterTest>,
+    /// the same query over the window just before, shown next to the current results
+    comparison: Option<Comparison>,
+    /// queries estimated to scan more than this wait for `y`, 0 never asks
+    pub confirm_query_bytes: i64,
+    /// count errors per hour next to every query
+    pub error_summary: bool,
+    /// configured Insights queries to run instead of the built in one
+    pub queries: ViewerQueries,
+    /// errors in each hour of the window, oldest first
+    error_counts: Option<Vec<u64>>,
+    /// events kept in memory per table, the rest are spilled to disk
+    memory_events: usize,
+    /// windows held back until the estimated scan size is confirmed
+    pending_query: Option<PendingQuery>,
+    loading_state: LoadingState,
+    /// bumped by every run and when the viewer is closed, fetches tagged with an older
+    /// one belong to groups or a query that's no longer on screen and are dropped
+    generation: u64,
+    /// when the running query started, its elapsed time is shown while loading
+    query_started: Option<Instant>,
+    /// the window of the running query, it's recorded for the investigation export
+    query_range: Option<(i64, i64)>,
+    /// the last query that finished, until the app takes it for the investigation
+    finished_query: Option<QueryRecord>,
+    /// set by `R` until its results arrive, they're compared with the ones on screen
+    rerunning: bool,
+    /// events newer than anything the run before `R` returned
+    rerun_new_events: Option<usize>,
+    /// when the live tail started, and when each batch arrived with how many events it had
+    /// over the last `TAIL_RATE_WINDOW`
+    tail_started: Option<Instant>,
+    tail_batches: VecDeque<(Instant, usize)>,
+    /// CloudWatch is sending a sample of the tailed events rather than all of them
+    tail_sampled: bool,
+    /// bumped whenever a live tail is started or stopped, what an older one sent is dropped.
+    /// Apart from `generation` as a tail carries on across runs
+    tail_generation: u64,
+    /// events the live tail brought in while paused, shown once it's resumed
+    tail_paused: Option<Vec<LogEvent>>,
     table: Table,
+    log_viewer_tx: mpsc::UnboundedSender<LogViewerOutboundMessage>,
+    backend: Arc<dyn LogBackend>,
+    tasks: TaskSupervisor,
+    pub keymap: Arc<Keymap>,
+    theme: Theme,
 }
 
+/// State of the `#` popup, `stats` is set once a field is picked
+#[derive(Debug)]
+struct FieldStatsPicker {
+    fields: Vec<String>,
+    selected: usize,
+    stats: Option<FieldStats>,
+}
+
+/// State of the `Q` popup, every field the query could ask for and whether it does
+#[derive(Debug)]
+struct FieldPicker {
+    fields: Vec<(String, bool)>,
+    selected: usize,
+}
+
+/// A query limit this low likely cuts the results short, it's pointed out next to the title
+const SMALL_LIMIT: usize = 100;
+
+/// Offered in the `Q` popup even before any event has them
+const COMMON_FIELDS: [&str; 5] = ["@timestamp", "@message", "@logStream", "@log", "@requestId"];
+
 #[derive(Debug)]
-pub struct LogViewerState {
-    log_messsages: Vec<String>,
+struct MetricFilterTest {
+    pattern: String,
+    /// index of the oldest event it was tried on
+    from: usize,
+    /// how many of the newest events it was tried on
+    tested: usize,
+    /// the number of matches, `None` while the test runs
+    result: Option<Result<usize, String>>,
+}
+
+/// TestMetricFilter is called in batches of 50, this keeps a test to a handful of calls
+const MAX_METRIC_FILTER_EVENTS: usize = 500;
+
+#[derive(Debug)]
+struct PendingQuery {
+    windows: Vec<Window>,
+    estimated_bytes: i64,
+}
+
+#[derive(Debug)]
+struct Comparison {
+    table: Table,
     loading_state: LoadingState,
-    group_selection_tx: mpsc::UnboundedSender<LogViewerOutboundMessage>,
+}
+
+/// Which day a query covers, compare mode runs both
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Window {
+    Current,
+    Previous,
+}
+
+pub const WINDOW_MS: i64 = 24 * 3600 * 1000;
+
+const HOUR_MS: i64 = 3600 * 1000;
+
+/// Counts error events per hour, the same words `detect_level` takes for an error
+const ERROR_SUMMARY_QUERY: &str =
+    "filter @message like /ERROR|ERR|FATAL|CRITICAL/ | stats count(*) as errors by bin(1h)";
+
+/// The live tail's events per second are averaged over this long
+const TAIL_RATE_WINDOW: Duration = Duration::from_secs(10);
+
+/// Running queries turn to a warning this long before Insights stops them
+const QUERY_TIME_WARNING: Duration = Duration::from_secs(10 * 60);
+
+/// One frame a second, the app redraws every second while a query runs
+const SPINNER: [char; 4] = ['◐', '◓', '◑', '◒'];
+
+impl Window {
+    /// The last `window_ms` or `fixed`, the previous window is as long and ends where the
+    /// current one starts
+    fn range(self, fixed: Option<(i64, i64)>, window_ms: i64) -> (i64, i64) {
+        self.range_at(fixed, window_ms, chrono::Utc::now().timestamp_millis())
+    }
+
+    fn range_at(self, fixed: Option<(i64, i64)>, window_ms: i64, now: i64) -> (i64, i64) {
+        let (start, end) = fixed.unwrap_or((now - window_ms, now));
+        let shift = match self {
+            Window::Current => 0,
+            Window::Previous => end - start,
+        };
+        (start - shift, end - shift)
+    }
 }
 
 pub enum LogViewerOutboundMessage {
-    ReRender,
-    UnselectLogGroup,
-    SetLogs(Vec<String>),
+    /// the event with the region of its group
+    ShowDetail(LogEvent, Option<String>),
+    /// the fetches are tagged with the generation of the viewer that started them
+    SetLogs(u64, Vec<LogEvent>),
+    /// tagged with the tail generation of the live tail they came from
+    AppendLogs(u64, Vec<LogEvent>),
+    SetComparisonLogs(u64, Result<Vec<LogEvent>, String>),
+    /// errors per hour of the current window, oldest first
+    ErrorCounts(u64, Vec<u64>),
+    SetLoadingState(u64, LoadingState),
+    /// the live tail ended on its own, with the error if it failed
+    TailEnded(u64, Option<String>),
+    /// the live tail started or stopped sampling the events
+    TailSampled(u64, bool),
+    /// the message behind the `@ptr` with its masked values revealed
+    Unmasked(Arc<str>, Result<String, String>),
+    Notify(Notification),
+    IndexedFields(u64, Vec<String>),
+    MetricFilters(u64, Vec<String>),
+    /// indices into the table's events matched by the pattern
+    MetricFilterMatches(u64, String, Result<Vec<usize>, String>),
+    /// every event matching the search started as `generation`
+    SearchResults(u64, Vec<usize>),
 }
 
 impl LogVieweromponent {
-    pub fn new(log_viewer_tx: mpsc::UnboundedSender<LogViewerOutboundMessage>) -> Self {
+    pub fn new(
+        pane: Pane,
+        log_viewer_tx: mpsc::UnboundedSender<LogViewerOutboundMessage>,
+        backend: Arc<dyn LogBackend>,
+        tasks: TaskSupervisor,
+    ) -> Self {
         Self {
-            state: Arc::new(RwLock::new(LogViewerState {
-                log_messsages: vec![],
-                loading_state: LoadingState::Loading,
-                group_selection_tx: log_viewer_tx,
-            })),
-            log_group_name: String::new(),
-            displayed_messages: vec![],
-            table: Table::new(vec![]),
+            pane,
+            focused: true,
+            log_groups: vec![],
+            time_range: None,
+            window_ms: WINDOW_MS,
+            stream_prefix: String::new(),
+            stream_prefix_input: None,
+            tail_filter: String::new(),
+            tail_filter_input: None,
+            around_input: None,
+            around_error: None,
+            filter_input: None,
+            filter_error: None,
+            patterns: PatternCache::default(),
+            search: None,
+            search_matches: None,
+            searcher: SearchWorker::new(),
+            field_stats: None,
+            field_picker: None,
+            query_fields: None,
+            metric_filter_input: None,
+            metric_filters: vec![],
+            indexed_fields: vec![],
+            metric_filter: None,
+            comparison: None,
+            confirm_query_bytes: DEFAULT_CONFIRM_QUERY_BYTES,
+            error_summary: false,
+            queries: ViewerQueries::default(),
+            error_counts: None,
+            memory_events: DEFAULT_MEMORY_EVENTS,
+            pending_query: None,
+            loading_state: LoadingState::Idle,
+            generation: 0,
+            query_started: None,
+            query_range: None,
+            finished_query: None,
+            rerunning: false,
+            rerun_new_events: None,
+            tail_started: None,
+            tail_batches: VecDeque::new(),
+            tail_sampled: false,
+            tail_generation: 0,
+            tail_paused: None,
+            table: Table::with_memory_cap(DEFAULT_MEMORY_EVENTS),
+            log_viewer_tx,
+            backend,
+            tasks,
+            keymap: Arc::default(),
+            theme: Theme::default(),
+        }
+    }
+
+    /// Keeps at most `events` of each table in memory, older ones are spilled to disk
+    pub fn set_memory_events(&mut self, events: usize) {
+        self.memory_events = events;
+        self.table.set_memory_cap(events);
+        if let Some(comparison) = &mut self.comparison {
+            comparison.table.set_memory_cap(events);
         }
     }
-    pub fn run(&self) {
-        let this = self.clone(); // clone the widget to pass to the background task
-        tokio::spawn(this.fetch_logs());
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.table.theme = theme;
+        if let Some(comparison) = &mut self.comparison {
+            comparison.table.theme = theme;
+        }
     }
 
-    async fn fetch_logs(self) {
-        self.state.write().unwrap().loading_state = LoadingState::Loading;
+    pub fn run(&mut self) {
+        self.table.show_tags = self.log_groups.len() > 1;
+        self.table.protected_groups = self
+            .log_groups
+            .iter()
+            .filter(|group| group.data_protected)
+            .map(|group| group.name.as_str().into())
+            .collect();
+        self.rerunning = false;
+        self.rerun_new_events = None;
+        self.generation += 1;
+        let mut windows = vec![Window::Current];
+        if self.comparison.is_some() {
+            windows.push(Window::Previous);
+        }
+        self.query(windows);
+    }
 
-        let (outbound_message, loading_state) = match aws::fetch_logs(
-            self.log_group_name.clone(),
-            chrono::Utc::now().timestamp_millis() - (24 * (3600 * 1000)),
-            chrono::Utc::now().timestamp_millis(),
-        )
-        .await
-        {
-            Ok(log_messages) => (
-                LogViewerOutboundMessage::SetLogs(log_messages),
-                LoadingState::Loaded,
+    /// Runs the query again, the results say how many events are new since the last run
+    fn rerun(&mut self) {
+        self.run();
+        self.rerunning = true;
+    }
+
+    /// Starts the queries for `windows`, unless they'd scan enough to be worth asking first
+    fn query(&mut self, windows: Vec<Window>) {
+        let now = chrono::Utc::now().timestamp_millis();
+        let estimated_bytes = windows
+            .iter()
+            .flat_map(|window| {
+                let (start, end) = window.range(self.time_range, self.window_ms);
+                self.log_groups
+                    .iter()
+                    .map(move |group| group.estimated_scan_bytes(start, end, now))
+            })
+            .sum::<i64>();
+        if self.confirm_query_bytes > 0 && estimated_bytes > self.confirm_query_bytes {
+            self.pending_query = Some(PendingQuery {
+                windows,
+                estimated_bytes,
+            });
+            return;
+        }
+        self.start_query(windows);
+    }
+
+    fn start_query(&mut self, windows: Vec<Window>) {
+        for window in windows {
+            match window {
+                Window::Current => {
+                    self.loading_state = LoadingState::Loading;
+                    self.query_started = Some(Instant::now());
+                    self.query_range = Some(window.range(self.time_range, self.window_ms));
+                    self.count_errors();
+                }
+                Window::Previous => match &mut self.comparison {
+                    Some(comparison) => comparison.loading_state = LoadingState::Loading,
+                    None => continue,
+                },
+            }
+            self.fetch(window);
+        }
+    }
+
+    fn fetch(&self, window: Window) {
+        let key = match window {
+            Window::Current => TaskKey::Logs(self.pane),
+            Window::Previous => TaskKey::ComparisonLogs(self.pane),
+        };
+        self.tasks.spawn(
+            key,
+            fetch_logs(
+                self.backend.clone(),
+                self.log_viewer_tx.clone(),
+                self.fetches(),
+                self.stream_prefix.clone(),
+                window,
+                window.range(self.time_range, self.window_ms),
+                self.generation,
+            ),
+        );
+    }
+
+    /// The groups' names split by region and by the query configured for them, each is a query
+    fn fetches(&self) -> Vec<Fetch> {
+        let mut fetches: Vec<Fetch> = vec![];
+        for (region, groups) in by_region(&self.log_groups) {
+            for group in groups {
+                let query = self.queries.for_group(&group.name);
+                let query = match &self.query_fields {
+                    Some(fields) => {
+                        Some(with_fields(query.unwrap_or(DEFAULT_VIEWER_QUERY), fields))
+                    }
+                    None => query.map(str::to_string),
+                };
+                match fetches
+                    .iter_mut()
+                    .find(|fetch| fetch.region == region && fetch.query == query)
+                {
+                    Some(fetch) => fetch.log_group_names.push(group.name.clone()),
+                    None => fetches.push(Fetch {
+                        region: region.clone(),
+                        query,
+                        log_group_names: vec![group.name.clone()],
+                    }),
+                }
+            }
+        }
+        fetches
+    }
+
+    /// Starts the per hour error count for the current window, when it's turned on
+    fn count_errors(&mut self) {
+        self.error_counts = None;
+        if !self.error_summary {
+            return;
+        }
+        let (start, end) = Window::Current.range(self.time_range, self.window_ms);
+        let backend = self.backend.clone();
+        let log_viewer_tx = self.log_viewer_tx.clone();
+        let generation = self.generation;
+        let regions = by_region(&self.log_groups)
+            .into_iter()
+            .map(|(region, groups)| {
+                let names = groups.iter().map(|group| group.name.clone()).collect();
+                (region, names)
+            })
+            .collect::<Vec<(Option<String>, Vec<String>)>>();
+        self.tasks
+            .spawn(TaskKey::ErrorSummary(self.pane), async move {
+                let hours = (end - start + HOUR_MS - 1) / HOUR_MS;
+                let mut counts = vec![0; hours.max(1) as usize];
+                for (region, names) in regions {
+                    let query = ERROR_SUMMARY_QUERY.to_string();
+                    let Ok(rows) = backend.run_query(region, names, query, start, end).await else {
+                        continue;
+                    };
+                    for row in rows {
+                        let field = |name: &str| {
+                            row.iter()
+                                .find(|(field, _)| field == name)
+                                .map(|(_, value)| value.as_str())
+                        };
+                        let (Some(hour), Some(errors)) = (
+                            field("bin(1h)").and_then(parse_query_timestamp),
+                            field("errors").and_then(|errors| errors.parse::<u64>().ok()),
+                        ) else {
+                            continue;
+                        };
+                        // bins are aligned to the hour while the window isn't, so there's one more
+                        // bin than buckets and the partial ones at either end share a bucket
+                        let bucket = ((hour - start).max(0) / HOUR_MS) as usize;
+                        let last = counts.len() - 1;
+                        counts[bucket.min(last)] += errors;
+                    }
+                }
+                let _ =
+                    log_viewer_tx.send(LogViewerOutboundMessage::ErrorCounts(generation, counts));
+            });
+    }
+
+    pub fn set_error_counts(&mut self, generation: u64, counts: Vec<u64>) {
+        if generation == self.generation {
+            self.error_counts = Some(counts);
+        }
+    }
+
+    /// What the groups store and how long they keep it, warning when the queried range
+    /// reaches back past the shortest retention
+    fn retention_line(&self) -> Line<'static> {
+        let mut parts = vec![];
+        let stored = self
+            .log_groups
+            .iter()
+            .filter_map(|group| group.stored_bytes);
+        if let Some(stored) = stored.reduce(|total, bytes| total + bytes) {
+            parts.push(format!("{} stored", format_bytes(stored)));
+        }
+        let retention = self
+            .log_groups
+            .iter()
+            .filter_map(|group| group.retention_in_days)
+            .min();
+        let mut color = self.theme.muted;
+        if let Some(days) = retention {
+            parts.push(format!("{}d retention", days));
+            // the comparison reaches back a window further than the current one
+            let oldest = match self.comparison {
+                Some(_) => Window::Previous,
+                None => Window::Current,
+            };
+            let now = chrono::Utc::now().timestamp_millis();
+            let (start, _) = oldest.range_at(self.time_range, self.window_ms, now);
+            if now - start > days as i64 * 24 * HOUR_MS {
+                parts.push("older events are gone".to_string());
+                color = self.theme.warning;
+            }
+        }
+        Line::styled(parts.join(", "), Style::new().fg(color))
+    }
+
+    fn sorted_by_query(&self) -> bool {
+        self.fetches()
+            .into_iter()
+            .any(|fetch| fetch.query.as_deref().is_some_and(query::sorts))
+    }
+
+    pub fn set_message_timestamps(&mut self, message_timestamps: MessageTimestamps) {
+        self.table.message_timestamps = message_timestamps;
+    }
+
+    /// How the queries shape the results: sorted their own way or cut off at a small limit
+    fn query_line(&self) -> Option<Line<'static>> {
+        let queries = self
+            .fetches()
+            .into_iter()
+            .filter_map(|fetch| fetch.query)
+            .collect::<Vec<_>>();
+        let mut parts = vec![];
+        let mut color = self.theme.muted;
+        if self.sorted_by_query() {
+            parts.push("sorted by the query".to_string());
+        }
+        if let Some(limit) = queries.iter().filter_map(|query| query::limit(query)).min() {
+            parts.push(format!("limit {}", limit));
+            if limit <= SMALL_LIMIT {
+                color = self.theme.warning;
+            }
+        }
+        if parts.is_empty() {
+            return None;
+        }
+        Some(Line::styled(parts.join(", "), Style::new().fg(color)))
+    }
+
+    /// What's being queried while nothing has come back yet, instead of an empty table
+    fn pending_lines(&self) -> Option<Vec<Line<'static>>> {
+        let started = self.query_started?;
+        if self.loading_state != LoadingState::Loading || !self.table.is_empty() {
+            return None;
+        }
+        let elapsed = started.elapsed();
+        let spinner = SPINNER[elapsed.as_secs() as usize % SPINNER.len()];
+        let group_names = self
+            .log_groups
+            .iter()
+            .map(|group| group.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let range = match self.time_range {
+            Some((start, end)) => format_range(start, end),
+            None => format!("the last {}", format_window(self.window_ms)),
+        };
+        let mut lines = vec![
+            Line::styled(
+                format!("{} querying {}", spinner, group_names),
+                Style::new().fg(self.theme.accent),
             ),
-            Err(e) => (LogViewerOutboundMessage::ReRender, LoadingState::Error(e)),
+            Line::from(format!(
+                "over {}, {} so far",
+                range,
+                format_elapsed(elapsed)
+            )),
+            Line::from(""),
+        ];
+        if self.stream_prefix.is_empty() {
+            let mut queries = vec![];
+            for fetch in self.fetches() {
+                let query = fetch.query.unwrap_or(DEFAULT_VIEWER_QUERY.to_string());
+                if !queries.contains(&query) {
+                    queries.push(query);
+                }
+            }
+            lines.extend(
+                queries
+                    .into_iter()
+                    .map(|query| Line::styled(query, Style::new().fg(self.theme.muted))),
+            );
+        } else {
+            lines.push(Line::styled(
+                format!("events from streams {}*", self.stream_prefix),
+                Style::new().fg(self.theme.muted),
+            ));
+        }
+        Some(lines)
+    }
+
+    /// Where the selected event came from: its stream, when it was ingested and its id
+    fn source_line(&self) -> Option<Line<'static>> {
+        let event = self.table.selected()?;
+        let mut parts = vec![format!("stream {}", event.log_stream)];
+        if let Some(ingestion_time) = event.ingestion_time {
+            let ingested = chrono::DateTime::from_timestamp_millis(ingestion_time)
+                .map(|ingested| ingested.to_rfc3339())
+                .unwrap_or_default();
+            let delay = Duration::from_millis((ingestion_time - event.timestamp).max(0) as u64);
+            parts.push(format!(
+                "ingested {}, {} after",
+                ingested,
+                format_elapsed(delay)
+            ));
+        }
+        if let Some(id) = event.event_id.as_ref().or(event.pointer.as_ref()) {
+            parts.push(format!("id {}", id));
+        }
+        Some(Line::styled(
+            parts.join(" · "),
+            Style::new().fg(self.theme.muted),
+        ))
+    }
+
+    /// Shows the window just before next to the current results, or hides it again
+    fn toggle_comparison(&mut self) {
+        if self.comparison.take().is_some() {
+            self.tasks.cancel(&TaskKey::ComparisonLogs(self.pane));
+            return;
+        }
+        let mut table = Table::with_memory_cap(self.memory_events);
+        table.show_tags = self.table.show_tags;
+        table.protected_groups = self.table.protected_groups.clone();
+        table.theme = self.theme;
+        self.comparison = Some(Comparison {
+            table,
+            loading_state: LoadingState::Idle,
+        });
+        self.query(vec![Window::Previous]);
+    }
+
+    pub fn set_comparison_logs(&mut self, generation: u64, result: Result<Vec<LogEvent>, String>) {
+        if generation != self.generation {
+            return;
+        }
+        let Some(comparison) = &mut self.comparison else {
+            return;
+        };
+        match result {
+            Ok(log_messages) => {
+                comparison.loading_state = LoadingState::Loaded;
+                comparison.table.set_data(log_messages);
+            }
+            Err(e) => comparison.loading_state = LoadingState::Error(e),
+        }
+    }
+
+    fn is_tailing(&self) -> bool {
+        self.tasks.status(&TaskKey::LiveTail(self.pane)).is_some()
+    }
+
+    /// The groups and what's shown of them, e.g. `/aws/lambda/checkout [24h]`
+    pub fn window_title(&self) -> String {
+        let group_names = self
+            .log_groups
+            .iter()
+            .map(|group| group.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let shown = match self.time_range {
+            _ if self.is_tailing() => "live".to_string(),
+            Some((start, end)) => format_range(start, end),
+            None => format_window(self.window_ms),
         };
+        format!("{} [{}]", group_names, shown)
+    }
 
-        let mut state = self.state.write().unwrap();
-        state.loading_state = loading_state;
+    fn toggle_tail(&mut self) {
+        if self.is_tailing() {
+            return self.stop_tail();
+        }
+        self.tail_started = Some(Instant::now());
+        self.tail_batches.clear();
+        self.tail_paused = None;
+        self.start_tail();
+    }
+
+    /// Starts a live tail session, replacing the one running with its events left on screen
+    fn start_tail(&mut self) {
+        self.tail_sampled = false;
+        self.tail_generation += 1;
+        self.tasks.spawn(
+            TaskKey::LiveTail(self.pane),
+            tail_logs(
+                self.backend.clone(),
+                self.log_viewer_tx.clone(),
+                self.tail_generation,
+                by_region(&self.log_groups)
+                    .into_iter()
+                    .map(|(region, groups)| {
+                        (
+                            region,
+                            groups.iter().map(|group| group.arn.clone()).collect(),
+                        )
+                    })
+                    .collect(),
+                self.stream_prefix.clone(),
+                self.tail_filter.clone(),
+            ),
+        );
+    }
 
-        // let lines: Vec<String> = fs::read_to_string("logs")
-        //     .unwrap()
-        //     .lines()
-        //     .map(|line| line.to_string())
-        //     .collect();
-        state.group_selection_tx.send(outbound_message).unwrap();
+    /// Tails with the new pattern, the session is started again as it can't be changed
+    fn set_tail_filter(&mut self, pattern: String) {
+        self.tail_filter = pattern;
+        if self.is_tailing() {
+            self.start_tail();
+        } else {
+            self.toggle_tail();
+        }
     }
 
-    pub fn set_logs(&mut self, log_messages: Vec<String>) {
-        self.table.data = log_messages;
+    pub fn is_querying(&self) -> bool {
+        self.loading_state == LoadingState::Loading
+    }
+
+    pub fn loading_state(&self) -> &LoadingState {
+        &self.loading_state
+    }
+
+    /// Leaves the running query to finish on its own, its results still arrive tagged with the
+    /// returned generation. `None` when no query is running
+    pub fn detach_query(&mut self) -> Option<u64> {
+        (self.is_querying()
+            && self.tasks.rekey(
+                &TaskKey::Logs(self.pane),
+                TaskKey::Background(self.generation),
+            ))
+        .then_some(self.generation)
+    }
+
+    /// Shows the results of a query that ran in the background instead of running one
+    pub fn show_results(
+        &mut self,
+        log_groups: Vec<LogGroup>,
+        result: Result<Vec<LogEvent>, String>,
+    ) {
+        self.log_groups = log_groups;
+        self.generation += 1;
+        self.query_range = None;
+        match result {
+            Ok(log_messages) => self.set_logs(self.generation, log_messages),
+            Err(e) => self.loading_state = LoadingState::Error(e),
+        }
+    }
+
+    /// How long the running query has taken so far, `None` when none is running
+    pub fn query_elapsed(&self) -> Option<Duration> {
+        self.query_started
+            .filter(|_| self.is_querying())
+            .map(|started| started.elapsed())
+    }
+
+    /// Events in its tables, roughly the bytes of the ones in memory and how many are on disk
+    pub fn stored_events(&self) -> (usize, usize, usize) {
+        let tables = std::iter::once(&self.table)
+            .chain(self.comparison.as_ref().map(|comparison| &comparison.table));
+        tables.fold((0, 0, 0), |(events, bytes, spilled), table| {
+            (
+                events + table.len(),
+                bytes + table.memory_bytes(),
+                spilled + table.spilled(),
+            )
+        })
+    }
+
+    /// Whether rows that just arrived are still highlighted, they fade with every redraw
+    pub fn is_fading(&self) -> bool {
+        self.table.is_fading()
+    }
+
+    /// Whether a query or live tail is still in flight
+    pub fn is_busy(&self) -> bool {
+        self.loading_state == LoadingState::Loading
+            || self.is_tailing()
+            || self
+                .tasks
+                .status(&TaskKey::ComparisonLogs(self.pane))
+                .is_some()
+    }
+
+    pub fn stop_tail(&mut self) {
+        self.tasks.cancel(&TaskKey::LiveTail(self.pane));
+        self.tail_generation += 1;
+        self.resume_tail();
+    }
+
+    /// Freezes the table while the live tail keeps going, or shows what arrived meanwhile
+    fn toggle_tail_pause(&mut self) {
+        if self.tail_paused.is_some() {
+            self.resume_tail();
+        } else if self.is_tailing() {
+            self.tail_paused = Some(vec![]);
+        }
+    }
+
+    fn resume_tail(&mut self) {
+        if let Some(held_back) = self.tail_paused.take() {
+            self.show_tailed(held_back);
+        }
+    }
+
+    pub fn set_tail_sampled(&mut self, tail_generation: u64, sampled: bool) {
+        if tail_generation == self.tail_generation {
+            self.tail_sampled = sampled;
+        }
+    }
+
+    /// Events per second the live tail brought in over the last `TAIL_RATE_WINDOW`, or since
+    /// it started when that's more recent
+    fn tail_rate(&self) -> f64 {
+        let window = self
+            .tail_started
+            .map_or(TAIL_RATE_WINDOW, |started| started.elapsed())
+            .clamp(Duration::from_secs(1), TAIL_RATE_WINDOW);
+        let events = self
+            .tail_batches
+            .iter()
+            .filter(|(arrived, _)| arrived.elapsed() <= TAIL_RATE_WINDOW)
+            .map(|(_, count)| count)
+            .sum::<usize>();
+        events as f64 / window.as_secs_f64()
+    }
+
+    pub fn tail_ended(&mut self, tail_generation: u64, error: Option<String>) {
+        if tail_generation != self.tail_generation {
+            return;
+        }
+        self.resume_tail();
+        if let Some(error) = error {
+            self.loading_state = LoadingState::Error(error);
+        }
+    }
+
+    pub fn set_loading_state(&mut self, generation: u64, loading_state: LoadingState) {
+        if generation == self.generation {
+            debug!(pane = ?self.pane, ?loading_state, "loading state changed");
+            self.loading_state = loading_state;
+        }
+    }
+
+    pub fn set_logs(&mut self, generation: u64, log_messages: Vec<LogEvent>) {
+        if generation != self.generation {
+            return;
+        }
+        let mut new_events = 0;
+        if std::mem::take(&mut self.rerunning) {
+            let newest = self.table.last_timestamp();
+            new_events = log_messages
+                .iter()
+                .filter(|event| newest.is_none_or(|newest| event.timestamp > newest))
+                .count();
+            self.rerun_new_events = Some(new_events);
+        }
+        debug!(pane = ?self.pane, events = log_messages.len(), "loaded events");
+        self.loading_state = LoadingState::Loaded;
+        self.metric_filter = None;
+        // a query's own order isn't a clock problem
+        self.table.check_order = !self.sorted_by_query();
+        self.table.set_data(log_messages);
+        // the new events are the newest, the table keeps them last whatever order it shows
+        if new_events > 0 {
+            self.table.mark_fresh(self.table.len() - new_events);
+        }
+        self.start_search();
+        if let Some((start, end)) = self.query_range.take() {
+            self.finished_query = Some(self.query_record(start, end));
+        }
+    }
+
+    /// What the query that just finished covered, taken once for the investigation export
+    pub fn take_finished_query(&mut self) -> Option<QueryRecord> {
+        self.finished_query.take()
+    }
+
+    fn query_record(&self, start: i64, end: i64) -> QueryRecord {
+        let mut queries = self
+            .fetches()
+            .into_iter()
+            .filter_map(|fetch| fetch.query)
+            .collect::<Vec<_>>();
+        queries.dedup();
+        QueryRecord {
+            finished_at: chrono::Utc::now().timestamp_millis(),
+            log_groups: self
+                .log_groups
+                .iter()
+                .map(|group| group.name.clone())
+                .collect(),
+            regions: self
+                .log_groups
+                .iter()
+                .map(|group| group.region().map(str::to_string))
+                .collect(),
+            queries,
+            query_fields: self.query_fields.clone(),
+            start,
+            end,
+            stream_prefix: Some(self.stream_prefix.clone()).filter(|prefix| !prefix.is_empty()),
+            filter: self.table.filter().map(str::to_string),
+            events: self.table.len(),
+            took_ms: self
+                .query_started
+                .map(|started| started.elapsed().as_millis() as u64)
+                .unwrap_or_default(),
+            window_ms: self.time_range.is_none().then_some(self.window_ms),
+        }
+    }
+
+    /// Queries `log_groups` the way `record` did: over its window, streams and picked fields,
+    /// with its filter applied to the results
+    pub fn run_again(&mut self, log_groups: Vec<LogGroup>, record: &QueryRecord) {
+        self.log_groups = log_groups;
+        match record.window_ms {
+            Some(window_ms) => {
+                self.time_range = None;
+                self.window_ms = window_ms;
+            }
+            None => {
+                self.time_range = Some((record.start, record.end));
+                self.window_ms = WINDOW_MS;
+            }
+        }
+        self.stream_prefix = record.stream_prefix.clone().unwrap_or_default();
+        self.query_fields = record.query_fields.clone();
+        self.apply_filter(record.filter.clone().unwrap_or_default());
+        self.run();
+    }
+
+    /// The selected event as a bookmark for the investigation export
+    pub fn selected_bookmark(&self) -> Option<Bookmark> {
+        self.table.selected().map(|event| Bookmark {
+            timestamp: event.timestamp,
+            log_group: event.log_group.to_string(),
+            log_stream: event.log_stream.to_string(),
+            message: event.message.to_string(),
+            note: String::new(),
+        })
+    }
+
+    /// Marks the bookmarked events in the gutter
+    pub fn set_bookmarks(&mut self, bookmarks: &[Bookmark]) {
+        self.table.set_bookmarked(bookmarks.iter().map(|bookmark| {
+            (
+                bookmark.timestamp,
+                bookmark.log_stream.as_str(),
+                bookmark.message.as_str(),
+            )
+        }));
+    }
+
+    pub fn append_logs(&mut self, tail_generation: u64, log_messages: Vec<LogEvent>) {
+        if tail_generation != self.tail_generation {
+            return;
+        }
+        while self
+            .tail_batches
+            .front()
+            .is_some_and(|(arrived, _)| arrived.elapsed() > TAIL_RATE_WINDOW)
+        {
+            self.tail_batches.pop_front();
+        }
+        self.tail_batches
+            .push_back((Instant::now(), log_messages.len()));
+        match &mut self.tail_paused {
+            Some(held_back) => held_back.extend(log_messages),
+            None => self.show_tailed(log_messages),
+        }
+    }
+
+    fn show_tailed(&mut self, log_messages: Vec<LogEvent>) {
+        if log_messages.is_empty() {
+            return;
+        }
+        // batches from different groups can overlap, keep the merged view chronological
+        self.table.append(log_messages);
+        self.start_search();
     }
 
     pub fn clear_logs(&mut self) {
-        let mut state = self.state.write().unwrap();
-        state.log_messsages = vec![];
-        self.displayed_messages = vec![];
+        self.table.clear();
+        if let Some(held_back) = &mut self.tail_paused {
+            held_back.clear();
+        }
+        self.metric_filter = None;
+        self.tasks.cancel(&TaskKey::MetricFilterTest(self.pane));
+        self.clear_search();
+    }
+
+    fn clear_search(&mut self) {
+        self.search = None;
+        self.table.search = None;
+        self.search_matches = None;
+        self.searcher.cancel();
+    }
+
+    /// Searches for the token under the cursor and jumps to its previous occurrence
+    fn search_token(&mut self) {
+        if let Some(token) = self.table.token_under_cursor() {
+            self.table.search = Some(token.clone());
+            self.search = Some(token);
+            self.start_search();
+            self.search_previous();
+        }
+    }
+
+    /// Finds every match on the worker, jumps scan the events directly until it's done
+    fn start_search(&mut self) {
+        let Some(term) = self.search.clone() else {
+            return;
+        };
+        let messages = self
+            .table
+            .events()
+            .map(|event| event.message)
+            .collect::<Vec<_>>();
+        let tx = self.log_viewer_tx.clone();
+        self.search_matches = None;
+        self.searcher.spawn(move |cancellation| {
+            let messages = messages.iter().map(|message| &**message);
+            if let Some(matches) = find_all(&term, messages, cancellation) {
+                let _ = tx.send(LogViewerOutboundMessage::SearchResults(
+                    cancellation.generation(),
+                    matches,
+                ));
+            }
+        });
+    }
+
+    pub fn set_search_results(&mut self, generation: u64, matches: Vec<usize>) {
+        if generation == self.searcher.generation() {
+            self.search_matches = Some(matches);
+        }
+    }
+
+    pub fn is_search_pending(&self) -> bool {
+        self.search.is_some() && self.search_matches.is_none()
+    }
+
+    /// Jumps to the previous (older) match, wrapping around to the newest
+    fn search_previous(&mut self) {
+        self.jump_to_match(true);
     }
 
+    /// Jumps to the next (newer) match, wrapping around to the oldest
+    fn search_next(&mut self) {
+        self.jump_to_match(false);
+    }
+
+    fn jump_to_match(&mut self, older: bool) {
+        let Some(search) = self.search.take() else {
+            return;
+        };
+        let matches = self.search_matches.take();
+        let is_match = |index: usize, event: &LogEvent| match &matches {
+            Some(matches) => matches.binary_search(&index).is_ok(),
+            None => event.message.contains(search.as_str()),
+        };
+        let jump = |table: &mut Table| {
+            if older {
+                table.select_previous_indexed(is_match)
+            } else {
+                table.select_next_indexed(is_match)
+            }
+        };
+        if !jump(&mut self.table) {
+            if older {
+                self.table.select_newest();
+            } else {
+                self.table.select_oldest();
+            }
+            let on_match = self
+                .table
+                .selected_position()
+                .and_then(|index| Some((index, self.table.event(index)?)))
+                .is_some_and(|(index, event)| is_match(index, &event));
+            if !on_match {
+                jump(&mut self.table);
+            }
+        }
+        self.search = Some(search);
+        self.search_matches = matches;
+    }
+
+    fn open_field_stats(&mut self) {
+        self.field_stats = Some(FieldStatsPicker {
+            fields: numeric_fields(self.table.events()),
+            selected: 0,
+            stats: None,
+        });
+    }
+
+    fn handle_field_stats_event(&mut self, key: &KeyEvent) {
+        let Some(picker) = &mut self.field_stats else {
+            return;
+        };
+        match (
+            self.keymap.action(Context::Viewer, key),
+            self.keymap.action(Context::Global, key),
+        ) {
+            (_, Some(Action::Back)) if picker.stats.is_some() => picker.stats = None,
+            (Some(Action::FieldStats), _) | (_, Some(Action::Back)) => self.field_stats = None,
+            (Some(Action::ScrollUp), _) => picker.selected = picker.selected.saturating_sub(1),
+            (Some(Action::ScrollDown), _) => {
+                picker.selected = (picker.selected + 1).min(picker.fields.len().saturating_sub(1));
+            }
+            (Some(Action::Open), _) => {
+                if let Some(field) = picker.fields.get(picker.selected) {
+                    picker.stats = field_stats(self.table.events(), field);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Lists the common fields, the ones in the query and the ones found in json messages
+    fn open_field_picker(&mut self) {
+        let picked = self.query_fields.clone().unwrap_or_else(|| {
+            let query = self
+                .log_groups
+                .first()
+                .and_then(|group| self.queries.for_group(&group.name));
+            fields_of(query.unwrap_or(DEFAULT_VIEWER_QUERY))
+        });
+        let mut fields: Vec<String> = COMMON_FIELDS
+            .iter()
+            .map(|field| field.to_string())
+            .collect();
+        for field in picked
+            .iter()
+            .cloned()
+            .chain(json_fields(self.table.events()))
+        {
+            if !fields.contains(&field) {
+                fields.push(field);
+            }
+        }
+        self.field_picker = Some(FieldPicker {
+            fields: fields
+                .into_iter()
+                .map(|field| {
+                    let is_picked = picked.contains(&field);
+                    (field, is_picked)
+                })
+                .collect(),
+            selected: 0,
+        });
+    }
+
+    fn handle_field_picker_event(&mut self, key: &KeyEvent) {
+        let Some(picker) = &mut self.field_picker else {
+            return;
+        };
+        match (
+            self.keymap.action(Context::Viewer, key),
+            self.keymap.action(Context::Global, key),
+        ) {
+            (Some(Action::ScrollUp), _) => picker.selected = picker.selected.saturating_sub(1),
+            (Some(Action::ScrollDown), _) => {
+                picker.selected = (picker.selected + 1).min(picker.fields.len().saturating_sub(1));
+            }
+            (Some(Action::Mark), _) => {
+                if let Some((_, picked)) = picker.fields.get_mut(picker.selected) {
+                    *picked = !*picked;
+                }
+            }
+            (Some(Action::Open), _) => {
+                let fields = picker
+                    .fields
+                    .iter()
+                    .filter(|(_, picked)| *picked)
+                    .map(|(field, _)| field.clone())
+                    .collect::<Vec<_>>();
+                // nothing picked goes back to the configured queries
+                self.query_fields = Some(fields).filter(|fields| !fields.is_empty());
+                self.field_picker = None;
+                self.run();
+            }
+            (_, Some(Action::Back)) => self.field_picker = None,
+            _ => (),
+        }
+    }
+
+    fn filter_token(&mut self) {
+        if let Some(token) = self.table.token_under_cursor() {
+            let pattern = Arc::new(Pattern::Text(token.clone()));
+            self.table.set_filter(token, pattern);
+        }
+    }
+
+    /// Queries the window around the time typed after `@`, or the last 24 hours when it's
+    /// cleared
+    fn query_around(&mut self, text: &str) {
+        let time_range = if text.trim().is_empty() {
+            None
+        } else {
+            let now = chrono::Utc::now().timestamp_millis();
+            match parse_around(text, now) {
+                Ok(time_range) => Some(time_range),
+                Err(e) => {
+                    self.around_error = Some(e);
+                    return;
+                }
+            }
+        };
+        self.around_input = None;
+        self.time_range = time_range;
+        self.run();
+    }
+
+    /// Applies a typed filter, compiled patterns are reused when switching between filters
+    fn apply_filter(&mut self, source: String) {
+        if source.is_empty() {
+            self.filter_input = None;
+            return self.table.clear_filter();
+        }
+        match self.patterns.get(&source) {
+            Ok(pattern) => {
+                self.filter_input = None;
+                self.filter_error = None;
+                self.table.set_filter(source, pattern);
+            }
+            Err(e) => self.filter_error = Some(e),
+        }
+    }
+
+    /// Fetches the highlighted event again with its masked values revealed
+    fn unmask_selected(&mut self) {
+        let Some(event) = self.table.selected() else {
+            return;
+        };
+        let Some(pointer) = event.pointer.clone() else {
+            let _ = self
+                .log_viewer_tx
+                .send(LogViewerOutboundMessage::Notify(Notification {
+                    message: "only events from a query can be unmasked".to_string(),
+                    is_error: true,
+                }));
+            return;
+        };
+        let region = self.region_of(&event);
+        let backend = self.backend.clone();
+        let log_viewer_tx = self.log_viewer_tx.clone();
+        self.tasks.spawn(TaskKey::Unmask(self.pane), async move {
+            let result = backend.fetch_unmasked(region, pointer.to_string()).await;
+            let _ = log_viewer_tx.send(LogViewerOutboundMessage::Unmasked(pointer, result));
+        });
+    }
+
+    pub fn set_unmasked(&mut self, pointer: Arc<str>, result: Result<String, String>) {
+        match result {
+            Ok(message) => {
+                let message = envelope::unwrap(&message).unwrap_or(message);
+                self.table.set_unmasked(pointer, message.into());
+                self.start_search();
+            }
+            Err(e) => {
+                let _ = self
+                    .log_viewer_tx
+                    .send(LogViewerOutboundMessage::Notify(Notification {
+                        message: format!("unmask failed: {}", e),
+                        is_error: true,
+                    }));
+            }
+        }
+    }
+
+    /// Opens the filter input and loads the groups' indexed fields to hint at
+    fn open_filter_input(&mut self) {
+        self.filter_input = Some(Input::new(self.table.filter().unwrap_or_default()));
+        self.tasks.spawn(
+            TaskKey::IndexedFields(self.pane),
+            load_indexed_fields(
+                self.backend.clone(),
+                self.log_viewer_tx.clone(),
+                self.generation,
+                self.log_groups.clone(),
+            ),
+        );
+    }
+
+    pub fn set_indexed_fields(&mut self, generation: u64, fields: Vec<String>) {
+        if generation == self.generation {
+            self.indexed_fields = fields;
+        }
+    }
+
+    /// Opens the pattern input and loads the groups' metric filters to pick from
+    fn open_metric_filter_input(&mut self) {
+        let pattern = self.metric_filter.as_ref().map(|test| test.pattern.clone());
+        self.metric_filter_input = Some(Input::new(pattern.unwrap_or_default()));
+        self.tasks.spawn(
+            TaskKey::MetricFilters(self.pane),
+            load_metric_filters(
+                self.backend.clone(),
+                self.log_viewer_tx.clone(),
+                self.generation,
+                self.log_groups.clone(),
+            ),
+        );
+    }
+
+    pub fn set_metric_filters(&mut self, generation: u64, patterns: Vec<String>) {
+        if generation == self.generation {
+            self.metric_filters = patterns;
+        }
+    }
+
+    /// Replaces the input with the next or previous existing pattern
+    fn cycle_metric_filters(&mut self, forward: bool) {
+        let (Some(input), len) = (&mut self.metric_filter_input, self.metric_filters.len()) else {
+            return;
+        };
+        if len == 0 {
+            return;
+        }
+        let next = match self
+            .metric_filters
+            .iter()
+            .position(|pattern| pattern == input.value())
+        {
+            Some(index) if forward => (index + 1) % len,
+            Some(index) => (index + len - 1) % len,
+            None if forward => 0,
+            None => len - 1,
+        };
+        input.set(self.metric_filters[next].clone());
+    }
+
+    /// Tries `pattern` on the newest loaded events, an empty pattern clears the last test
+    fn test_metric_filter(&mut self, pattern: String) {
+        self.metric_filter_input = None;
+        self.table.set_highlight(None);
+        if pattern.is_empty() {
+            self.metric_filter = None;
+            self.tasks.cancel(&TaskKey::MetricFilterTest(self.pane));
+            return;
+        }
+        let len = self.table.len();
+        let from = len.saturating_sub(MAX_METRIC_FILTER_EVENTS);
+        let messages = (from..len)
+            .map(|index| {
+                self.table
+                    .event(index)
+                    .map(|event| event.message.to_string())
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<_>>();
+        self.metric_filter = Some(MetricFilterTest {
+            pattern: pattern.clone(),
+            from,
+            tested: messages.len(),
+            result: None,
+        });
+        // the pattern is checked the same way everywhere, any of the groups' regions will do
+        let region = self
+            .log_groups
+            .first()
+            .and_then(|group| group.region())
+            .map(str::to_string);
+        let backend = self.backend.clone();
+        let log_viewer_tx = self.log_viewer_tx.clone();
+        let generation = self.generation;
+        self.tasks
+            .spawn(TaskKey::MetricFilterTest(self.pane), async move {
+                let result = backend
+                    .test_metric_filter(region, pattern.clone(), messages)
+                    .await
+                    .map(|matches| matches.into_iter().map(|index| from + index).collect());
+                let _ = log_viewer_tx.send(LogViewerOutboundMessage::MetricFilterMatches(
+                    generation, pattern, result,
+                ));
+            });
+    }
+
+    pub fn set_metric_filter_matches(
+        &mut self,
+        generation: u64,
+        pattern: String,
+        result: Result<Vec<usize>, String>,
+    ) {
+        let Some(test) = self
+            .metric_filter
+            .as_mut()
+            .filter(|test| generation == self.generation && test.pattern == pattern)
+        else {
+            return;
+        };
+        match result {
+            Ok(matches) => {
+                test.result = Some(Ok(matches.len()));
+                self.table.set_highlight(Some(Highlight {
+                    from: test.from,
+                    matches: matches.into_iter().collect(),
+                }));
+            }
+            Err(e) => test.result = Some(Err(e)),
+        }
+    }
+
+    /// Resets everything tied to the current groups once the viewer is left
+    pub fn close(&mut self) {
+        self.stop_tail();
+        for key in [
+            TaskKey::Logs(self.pane),
+            TaskKey::ComparisonLogs(self.pane),
+            TaskKey::ErrorSummary(self.pane),
+            TaskKey::IndexedFields(self.pane),
+            TaskKey::MetricFilters(self.pane),
+            TaskKey::Unmask(self.pane),
+        ] {
+            self.tasks.cancel(&key);
+        }
+        // anything those tasks already sent is still queued
+        self.generation += 1;
+        self.loading_state = LoadingState::Idle;
+        self.query_started = None;
+        self.query_range = None;
+        self.time_range = None;
+        self.window_ms = WINDOW_MS;
+        self.rerunning = false;
+        self.comparison = None;
+        self.pending_query = None;
+        self.filter_input = None;
+        self.metric_filter_input = None;
+        self.clear_logs();
+        self.log_groups.clear();
+        self.stream_prefix.clear();
+        self.tail_filter.clear();
+    }
+
+    fn show_detail(&self) {
+        if let Some(event) = self.table.selected() {
+            let region = self.region_of(&event);
+            let _ = self
+                .log_viewer_tx
+                .send(LogViewerOutboundMessage::ShowDetail(event, region));
+        }
+    }
+
+    /// The region of the group the event came from
+    fn region_of(&self, event: &LogEvent) -> Option<String> {
+        self.log_groups
+            .iter()
+            .find(|group| *group.name == *event.log_group)
+            .and_then(|group| group.region())
+            .map(str::to_string)
+    }
+
+    /// `Command` while typing into an input or a popup is open, `Search` while `*` matches
+    /// are highlighted
+    pub fn mode(&self) -> InputMode {
+        let typing = self.stream_prefix_input.is_some()
+            || self.tail_filter_input.is_some()
+            || self.around_input.is_some()
+            || self.filter_input.is_some()
+            || self.metric_filter_input.is_some();
+        let popup = self.field_stats.is_some() || self.field_picker.is_some();
+        if typing || popup || self.pending_query.is_some() {
+            InputMode::Command
+        } else if self.search.is_some() {
+            InputMode::Search
+        } else {
+            InputMode::Normal
+        }
+    }
+
+    /// True when the key was meant for the viewer. A search only keeps Esc, which ends it,
+    /// every other key still works while one is active
     pub fn handle_event(&mut self, event: &Event) -> bool {
         let key = match event {
             Event::Key(key) => key,
+            Event::Paste(text) => return self.paste(text),
             _ => return false,
         };
-        match (key.code, key.modifiers) {
-            (KeyCode::Esc, _) => {
-                let _ = self
-                    .state
-                    .write()
-                    .unwrap()
-                    .group_selection_tx
-                    .send(LogViewerOutboundMessage::UnselectLogGroup);
-                return true;
-            }
-            (KeyCode::Char('r'), _) => self.run(),
-            (KeyCode::Char('k') | KeyCode::Up, _) => self.table.scroll_up(None),
-            (KeyCode::Char('j') | KeyCode::Down, _) => self.table.scroll_down(None),
-            (KeyCode::Char('u'), KeyModifiers::CONTROL) => self.table.scroll_up(Some(20)),
-            (KeyCode::Char('d'), KeyModifiers::CONTROL) => self.table.scroll_down(Some(20)),
+        match self.mode() {
+            InputMode::Command => {
+                self.handle_command_event(key);
+                true
+            }
+            InputMode::Search if key.code == KeyCode::Esc => {
+                self.clear_search();
+                true
+            }
+            InputMode::Search | InputMode::Normal => {
+                self.handle_normal_event(key);
+                false
+            }
+        }
+    }
+
+    /// Pastes into whichever input is open, false when none is
+    fn paste(&mut self, text: &str) -> bool {
+        let input = self
+            .stream_prefix_input
+            .as_mut()
+            .or(self.tail_filter_input.as_mut())
+            .or(self.around_input.as_mut())
+            .or(self.filter_input.as_mut())
+            .or(self.metric_filter_input.as_mut());
+        let Some(input) = input else {
+            return false;
+        };
+        input.paste(text);
+        self.filter_error = None;
+        self.around_error = None;
+        true
+    }
+
+    /// Keys go into whichever input or popup is open, Esc closes it
+    fn handle_command_event(&mut self, key: &KeyEvent) {
+        let code = key.code;
+        if let Some(input) = &mut self.stream_prefix_input {
+            match code {
+                KeyCode::Esc => self.stream_prefix_input = None,
+                KeyCode::Enter => {
+                    self.stream_prefix = input.value().to_string();
+                    self.stream_prefix_input = None;
+                    self.run();
+                }
+                _ => {
+                    input.handle_key(key);
+                }
+            }
+            return;
+        }
+        if let Some(input) = &mut self.tail_filter_input {
+            match code {
+                KeyCode::Esc => self.tail_filter_input = None,
+                KeyCode::Enter => {
+                    let pattern = input.value().trim().to_string();
+                    self.tail_filter_input = None;
+                    self.set_tail_filter(pattern);
+                }
+                _ => {
+                    input.handle_key(key);
+                }
+            }
+            return;
+        }
+        if let Some(input) = &mut self.around_input {
+            self.around_error = None;
+            match code {
+                KeyCode::Esc => self.around_input = None,
+                KeyCode::Enter => {
+                    let text = input.value().to_string();
+                    self.query_around(&text);
+                }
+                _ => {
+                    input.handle_key(key);
+                }
+            }
+            return;
+        }
+        if let Some(input) = &mut self.filter_input {
+            self.filter_error = None;
+            match code {
+                KeyCode::Esc => self.filter_input = None,
+                KeyCode::Enter => {
+                    let source = input.value().to_string();
+                    self.apply_filter(source);
+                }
+                _ => {
+                    input.handle_key(key);
+                }
+            }
+            return;
+        }
+        if let Some(input) = &mut self.metric_filter_input {
+            match code {
+                KeyCode::Esc => self.metric_filter_input = None,
+                KeyCode::Enter => {
+                    let pattern = input.value().to_string();
+                    self.test_metric_filter(pattern);
+                }
+                KeyCode::Up => self.cycle_metric_filters(false),
+                KeyCode::Down => self.cycle_metric_filters(true),
+                _ => {
+                    input.handle_key(key);
+                }
+            }
+            return;
+        }
+        if self.field_stats.is_some() {
+            return self.handle_field_stats_event(key);
+        }
+        if self.field_picker.is_some() {
+            return self.handle_field_picker_event(key);
+        }
+        if let Some(pending_query) = self.pending_query.take() {
+            if self.keymap.action(Context::Global, key) == Some(Action::Confirm) {
+                self.start_query(pending_query.windows);
+            }
+        }
+    }
+
+    fn handle_normal_event(&mut self, key: &KeyEvent) {
+        let Some(action) = self.keymap.action(Context::Viewer, key) else {
+            return;
+        };
+        match action {
+            Action::Open => self.show_detail(),
+            Action::Reload => self.run(),
+            Action::Rerun => self.rerun(),
+            Action::Tail => self.toggle_tail(),
+            Action::PauseTail => self.toggle_tail_pause(),
+            Action::NextError => {
+                self.table.select_next_where(is_error);
+            }
+            Action::PreviousError => {
+                self.table.select_previous_where(is_error);
+            }
+            Action::SearchToken => self.search_token(),
+            Action::SearchOlder => self.search_previous(),
+            Action::SearchNewer => self.search_next(),
+            Action::FilterToken => self.filter_token(),
+            Action::FieldStats => self.open_field_stats(),
+            Action::PickFields => self.open_field_picker(),
+            Action::Compare => self.toggle_comparison(),
+            Action::Fold => self.table.toggle_fold(),
+            Action::ClearFilter => self.table.clear_filter(),
+            Action::Filter => self.open_filter_input(),
+            Action::CursorLeft => self.table.cursor_left(),
+            Action::CursorRight => self.table.cursor_right(),
+            Action::NextWord => self.table.cursor_next_word(),
+            Action::PreviousWord => self.table.cursor_previous_word(),
+            Action::MetricFilter => self.open_metric_filter_input(),
+            Action::Unmask => self.unmask_selected(),
+            Action::StreamPrefix => {
+                self.stream_prefix_input = Some(Input::new(&self.stream_prefix))
+            }
+            Action::TailFilter => self.tail_filter_input = Some(Input::new(&self.tail_filter)),
+            Action::Around => self.around_input = Some(Input::default()),
+            Action::SortByTime => self.table.sort_by_time(),
+            Action::ScrollUp => self.table.scroll_up(None),
+            Action::ScrollDown => self.table.scroll_down(None),
+            Action::PageUp => self.table.scroll_up(Some(20)),
+            Action::PageDown => self.table.scroll_down(Some(20)),
             _ => (),
         };
-        false
     }
 }
 
 impl Widget for &LogVieweromponent {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let mut state = self.state.write().unwrap();
-        let loading_state = Line::from(format!("{:?}", state.loading_state)).right_aligned();
+        let loading_state = match (&self.loading_state, self.query_started) {
+            (LoadingState::Loading, Some(started)) => {
+                let elapsed = started.elapsed();
+                let limit = Duration::from_secs(QUERY_TIME_LIMIT_MINUTES * 60);
+                if elapsed + QUERY_TIME_WARNING >= limit {
+                    Line::styled(
+                        format!(
+                            "Loading {}, stopped at {}m",
+                            format_elapsed(elapsed),
+                            QUERY_TIME_LIMIT_MINUTES
+                        ),
+                        Style::new().fg(self.theme.warning),
+                    )
+                } else {
+                    Line::from(format!("Loading {}", format_elapsed(elapsed)))
+                }
+            }
+            (loading_state, _) => Line::from(format!("{:?}", loading_state)),
+        }
+        .right_aligned();
+        let group_names = self
+            .log_groups
+            .iter()
+            .map(|group| group.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let mut block = Block::bordered()
+            .title(group_names)
+            .title(self.retention_line())
+            .title(loading_state);
+        if let Some(query_line) = self.query_line() {
+            block = block.title(query_line);
+        }
+        if let Some(counts) = &self.error_counts {
+            let total = counts.iter().sum::<u64>();
+            block = block.title(Line::styled(
+                format!(
+                    "errors/h {} {}",
+                    sparkline(counts),
+                    format_count(total as usize)
+                ),
+                Style::new().fg(if total > 0 {
+                    self.theme.error
+                } else {
+                    self.theme.muted
+                }),
+            ));
+        }
+        let out_of_order = self.table.out_of_order();
+        if out_of_order > 0 {
+            block = block.title(Line::styled(
+                format!(
+                    "{} out of order, {} to sort by time",
+                    format_count(out_of_order),
+                    self.keymap.keys(Context::Viewer, Action::SortByTime)
+                ),
+                Style::new().fg(self.theme.warning),
+            ));
+        }
+        if let Some(position) = self.table.position_label() {
+            block = block.title_bottom(
+                Line::styled(position, Style::new().fg(self.theme.muted)).right_aligned(),
+            );
+        }
+        block = block.title_bottom(Line::from(self.keymap.hint()).right_aligned());
+        if !self.focused {
+            block = block.border_style(Style::new().fg(self.theme.muted));
+        }
+        if let Some(input) = &self.stream_prefix_input {
+            block = block.title_bottom(
+                input
+                    .line("stream prefix: ", "")
+                    .style(Style::new().fg(self.theme.accent)),
+            );
+        } else if !self.stream_prefix.is_empty() {
+            block = block.title_bottom(format!("streams: {}*", self.stream_prefix));
+        }
+        if let Some(input) = &self.tail_filter_input {
+            block = block.title_bottom(
+                input
+                    .line("tail filter: ", "")
+                    .style(Style::new().fg(self.theme.accent)),
+            );
+        } else if !self.tail_filter.is_empty() {
+            block = block.title_bottom(format!("tail filter: {}", self.tail_filter));
+        }
+        if let Some(input) = &self.around_input {
+            let suffix = match &self.around_error {
+                Some(e) => format!(" ({})", e),
+                None if input.is_empty() => format!(
+                    " (a time ±minutes, empty for the last {})",
+                    format_window(self.window_ms)
+                ),
+                None => String::new(),
+            };
+            block = block.title_bottom(
+                input
+                    .line("around: ", suffix)
+                    .style(Style::new().fg(self.theme.accent)),
+            );
+        }
+        if let Some(search) = &self.search {
+            let matches = match &self.search_matches {
+                Some(matches) => format!("{} matches", matches.len()),
+                None => "…".to_string(),
+            };
+            block = block.title_bottom(format!("search: {} ({})", search, matches));
+        }
+        if let Some(input) = &self.filter_input {
+            let error = self
+                .filter_error
+                .as_ref()
+                .map(|e| format!(" ({})", e))
+                .unwrap_or_default();
+            block = block.title_bottom(
+                input
+                    .line("filter: ", error)
+                    .style(Style::new().fg(self.theme.accent)),
+            );
+            // the filter itself runs locally, but the same field in a query can skip events
+            if let Some(field) = self
+                .indexed_fields
+                .iter()
+                .find(|field| input.value().contains(field.as_str()))
+            {
+                block = block.title_bottom(Line::styled(
+                    format!(
+                        "{} is indexed, filtering on it in a query scans less",
+                        field
+                    ),
+                    Style::new().fg(self.theme.muted),
+                ));
+            }
+        } else if let Some(filter) = self.table.filter() {
+            block = block.title_bottom(Line::styled(
+                format!(
+                    "filter: {} ({} to clear)",
+                    filter,
+                    self.keymap.keys(Context::Viewer, Action::ClearFilter)
+                ),
+                Style::new().fg(self.theme.warning),
+            ));
+        }
+        if let Some(input) = &self.metric_filter_input {
+            let existing = if self.metric_filters.is_empty() {
+                String::new()
+            } else {
+                format!(" (↑↓ {} existing)", self.metric_filters.len())
+            };
+            block = block.title_bottom(
+                input
+                    .line("metric filter: ", existing)
+                    .style(Style::new().fg(self.theme.accent)),
+            );
+        } else if let Some(test) = &self.metric_filter {
+            let (result, color) = match &test.result {
+                None => ("testing…".to_string(), self.theme.muted),
+                Some(Ok(matches)) => (
+                    format!("{} of the last {} match", matches, test.tested),
+                    self.theme.ok,
+                ),
+                Some(Err(e)) => (e.clone(), self.theme.error),
+            };
+            block = block.title_bottom(Line::styled(
+                format!("metric filter: {} ({})", test.pattern, result),
+                Style::new().fg(color),
+            ));
+        }
+        if let Some(new_events) = self.rerun_new_events {
+            block = block.title_bottom(match new_events {
+                0 => Line::styled("rerun: nothing new", Style::new().fg(self.theme.ok)),
+                count => Line::styled(
+                    format!("rerun: {} new since the last run", count),
+                    Style::new().fg(self.theme.warning),
+                ),
+            });
+        }
+        if let Some(held_back) = &self.tail_paused {
+            block = block.title_bottom(Line::styled(
+                format!(
+                    "paused, {} new, {} to resume",
+                    format_count(held_back.len()),
+                    self.keymap.keys(Context::Viewer, Action::PauseTail)
+                ),
+                Style::new().fg(self.theme.warning),
+            ));
+        }
+        if self.is_tailing() {
+            let rate = self.tail_rate();
+            block = block.title_bottom(if self.tail_sampled {
+                Line::styled(
+                    format!("tailing {:.1}/s, sampled", rate),
+                    Style::new().fg(self.theme.warning),
+                )
+            } else {
+                Line::styled(
+                    format!("tailing {:.1}/s", rate),
+                    Style::new().fg(self.theme.ok),
+                )
+            });
+        }
+        if self.table.spilled() > 0 {
+            block = block.title_bottom(Line::styled(
+                format!("{} on disk", self.table.spilled()),
+                Style::new().fg(self.theme.muted),
+            ));
+        }
+
+        let mut inner = block.inner(area);
+        block.render(area, buf);
+        if let Some(source) = self.source_line() {
+            let [rows, footer] =
+                Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(inner);
+            source.render(footer, buf);
+            inner = rows;
+        }
+        match &self.comparison {
+            Some(comparison) => {
+                let [previous_area, current_area] =
+                    Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).areas(inner);
+                let previous = Block::new()
+                    .borders(Borders::RIGHT)
+                    .title(window_summary(
+                        match (self.time_range, self.window_ms) {
+                            (None, WINDOW_MS) => "previous day",
+                            _ => "previous window",
+                        },
+                        &comparison.table,
+                    ))
+                    .title(Line::from(format!("{:?}", comparison.loading_state)).right_aligned());
+                let label = match (self.time_range, self.window_ms) {
+                    (None, WINDOW_MS) => "last day",
+                    _ => "this window",
+                };
+                let current = Block::new().title(window_summary(label, &self.table));
+                comparison.table.render(previous.inner(previous_area), buf);
+                previous.render(previous_area, buf);
+                self.table.render(current.inner(current_area), buf);
+                current.render(current_area, buf);
+            }
+            None => match self.pending_lines() {
+                Some(lines) => Paragraph::new(lines)
+                    .centered()
+                    .wrap(Wrap { trim: true })
+                    .render(inner, buf),
+                None => self.table.render(inner, buf),
+            },
+        }
+        if let Some(picker) = &self.field_stats {
+            let hint = format!(
+                "{} to pick, {} to close",
+                self.keymap.keys(Context::Viewer, Action::Open),
+                self.keymap.keys(Context::Global, Action::Back)
+            );
+            FieldStatsPopup {
+                fields: &picker.fields,
+                selected: picker.selected,
+                stats: picker.stats.as_ref(),
+                hint: &hint,
+                theme: &self.theme,
+            }
+            .render(area, buf);
+        }
+        if let Some(picker) = &self.field_picker {
+            let hint = format!(
+                "{} to pick, {} to run, {} to close",
+                self.keymap.keys(Context::Viewer, Action::Mark),
+                self.keymap.keys(Context::Viewer, Action::Open),
+                self.keymap.keys(Context::Global, Action::Back)
+            );
+            FieldsPopup {
+                fields: &picker.fields,
+                selected: picker.selected,
+                hint: &hint,
+                theme: &self.theme,
+            }
+            .render(area, buf);
+        }
+        if let Some(pending_query) = &self.pending_query {
+            let message = format!(
+                "This query scans about {} of logs, run it anyway?",
+                format_bytes(pending_query.estimated_bytes)
+            );
+            ConfirmPopup {
+                title: "Expensive query",
+                message: &message,
+                keymap: &self.keymap,
+                theme: &self.theme,
+            }
+            .render(area, buf);
+        }
+    }
+}
+
+fn is_error(event: &LogEvent) -> bool {
+    detect_level(&event.message) == Some(LogLevel::Error)
+}
+
+/// One block character per count, scaled to the largest
+fn sparkline(counts: &[u64]) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = counts.iter().copied().max().unwrap_or(0).max(1);
+    counts
+        .iter()
+        .map(|&count| BLOCKS[(count * (BLOCKS.len() as u64 - 1)).div_ceil(max) as usize])
+        .collect()
+}
+
+fn window_summary(label: &str, table: &Table) -> String {
+    let errors = table.events().filter(is_error).count();
+    format!("{}: {} events, {} errors", label, table.len(), errors)
+}
+
+/// Groups queried together, they're in the same region and have the same query configured
+struct Fetch {
+    region: Option<String>,
+    query: Option<String>,
+    log_group_names: Vec<String>,
+}
+
+/// Queries each region separately, groups can be picked from several with `--all-regions`.
+/// A stream prefix filters events directly, which can't run a configured query
+async fn fetch_logs(
+    backend: Arc<dyn LogBackend>,
+    log_viewer_tx: mpsc::UnboundedSender<LogViewerOutboundMessage>,
+    fetches: Vec<Fetch>,
+    stream_prefix: String,
+    window: Window,
+    (start, end): (i64, i64),
+    generation: u64,
+) {
+    // a query that sorts picked the order itself, events aren't put back in time order
+    let keep_order = stream_prefix.is_empty()
+        && fetches
+            .iter()
+            .any(|fetch| fetch.query.as_deref().is_some_and(query::sorts));
+    let fetches = fetches.into_iter().map(|fetch| {
+        if stream_prefix.is_empty() {
+            backend.fetch_logs(fetch.region, fetch.log_group_names, fetch.query, start, end)
+        } else {
+            backend.filter_logs(
+                fetch.region,
+                fetch.log_group_names,
+                stream_prefix.clone(),
+                start,
+                end,
+            )
+        }
+    });
+    let results = tokio::select! {
+        // the app is gone, stop polling the query
+        _ = log_viewer_tx.closed() => return,
+        results = futures::future::join_all(fetches) => results,
+    };
+    let truncated = match stream_prefix.is_empty() {
+        true => vec![],
+        false => truncated_groups(&results),
+    };
+    let result = results
+        .into_iter()
+        .collect::<Result<Vec<_>, String>>()
+        .map(|events| {
+            let mut events = events.into_iter().flatten().collect::<Vec<_>>();
+            if !keep_order {
+                events.sort_by_key(|event| event.timestamp);
+                dedupe(&mut events);
+            }
+            unwrap_events(&mut events);
+            events
+        });
+
+    let outbound_message = match (window, result) {
+        (Window::Current, Ok(log_messages)) => {
+            LogViewerOutboundMessage::SetLogs(generation, log_messages)
+        }
+        (Window::Current, Err(e)) => {
+            LogViewerOutboundMessage::SetLoadingState(generation, LoadingState::Error(e))
+        }
+        (Window::Previous, result) => {
+            LogViewerOutboundMessage::SetComparisonLogs(generation, result)
+        }
+    };
+
+    // let lines: Vec<String> = fs::read_to_string("logs")
+    //     .unwrap()
+    //     .lines()
+    //     .map(|line| line.to_string())
+    //     .collect();
+    let _ = log_viewer_tx.send(outbound_message);
+    if let Some(first) = truncated.first() {
+        let groups = match truncated.len() {
+            1 => first.to_string(),
+            n => format!("{} and {} more groups", first, n - 1),
+        };
+        let _ = log_viewer_tx.send(LogViewerOutboundMessage::Notify(Notification {
+            message: format!(
+                "only the first {} events of {} were fetched, narrow the time range for later ones",
+                format_count(MAX_FILTERED_EVENTS),
+                groups
+            ),
+            is_error: true,
+        }));
+    }
+}
+
+/// The groups a stream prefix fetch stopped paging early for
+fn truncated_groups(results: &[Result<Vec<LogEvent>, String>]) -> Vec<Arc<str>> {
+    let mut counts: BTreeMap<&Arc<str>, usize> = BTreeMap::new();
+    for event in results.iter().flatten().flatten() {
+        *counts.entry(&event.log_group).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .filter(|(_, count)| *count >= MAX_FILTERED_EVENTS)
+        .map(|(group, _)| group.clone())
+        .collect()
+}
+
+/// Collects the patterns of every group's metric filters, groups that fail are left out
+async fn load_metric_filters(
+    backend: Arc<dyn LogBackend>,
+    log_viewer_tx: mpsc::UnboundedSender<LogViewerOutboundMessage>,
+    generation: u64,
+    log_groups: Vec<LogGroup>,
+) {
+    let mut patterns = vec![];
+    for group in log_groups {
+        let region = group.region().map(str::to_string);
+        let Ok(filters) = backend.fetch_metric_filters(region, group.name).await else {
+            continue;
+        };
+        for filter in filters {
+            if !patterns.contains(&filter.pattern) {
+                patterns.push(filter.pattern);
+            }
+        }
+    }
+    let _ = log_viewer_tx.send(LogViewerOutboundMessage::MetricFilters(
+        generation, patterns,
+    ));
+}
+
+/// Indexed fields across the groups, without duplicates
+async fn load_indexed_fields(
+    backend: Arc<dyn LogBackend>,
+    log_viewer_tx: mpsc::UnboundedSender<LogViewerOutboundMessage>,
+    generation: u64,
+    log_groups: Vec<LogGroup>,
+) {
+    let mut fields = vec![];
+    for group in log_groups {
+        let region = group.region().map(str::to_string);
+        let Ok(indexes) = backend.fetch_field_indexes(region, group.name).await else {
+            continue;
+        };
+        for field in indexes {
+            if !fields.contains(&field) {
+                fields.push(field);
+            }
+        }
+    }
+    let _ = log_viewer_tx.send(LogViewerOutboundMessage::IndexedFields(generation, fields));
+}
 
-        let block = Block::bordered()
-            .title(self.log_group_name.to_string())
-            .title(loading_state)
-            .title_bottom(Line::from("q to quit").right_aligned());
+async fn tail_logs(
+    backend: Arc<dyn LogBackend>,
+    log_viewer_tx: mpsc::UnboundedSender<LogViewerOutboundMessage>,
+    tail_generation: u64,
+    mut log_group_arns: Vec<(Option<String>, Vec<String>)>,
+    stream_prefix: String,
+    filter_pattern: String,
+) {
+    if log_group_arns.len() > 1 {
+        let error = "live tail can only follow groups from a single region".to_string();
+        let _ = log_viewer_tx.send(LogViewerOutboundMessage::TailEnded(
+            tail_generation,
+            Some(error),
+        ));
+        return;
+    }
+    let (region, log_group_arns) = log_group_arns.pop().unwrap_or_default();
+    let mut sampled = false;
+    let mut live_tail = match backend
+        .start_live_tail(region, log_group_arns, stream_prefix, filter_pattern)
+        .await
+    {
+        Ok(live_tail) => live_tail,
+        Err(e) => {
+            let _ = log_viewer_tx.send(LogViewerOutboundMessage::TailEnded(
+                tail_generation,
+                Some(e),
+            ));
+            return;
+        }
+    };
 
-        self.table.render(area, buf);
+    loop {
+        let events = tokio::select! {
+            // the app is gone, stop tailing
+            _ = log_viewer_tx.closed() => return,
+            events = live_tail.next_events() => events,
+        };
+        if live_tail.is_sampled() != sampled {
+            sampled = live_tail.is_sampled();
+            if log_viewer_tx
+                .send(LogViewerOutboundMessage::TailSampled(
+                    tail_generation,
+                    sampled,
+                ))
+                .is_err()
+            {
+                return;
+            }
+        }
+        let error = match events {
+            Ok(Some(events)) if events.is_empty() => continue,
+            Ok(Some(mut events)) => {
+                unwrap_events(&mut events);
+                if log_viewer_tx
+                    .send(LogViewerOutboundMessage::AppendLogs(
+                        tail_generation,
+                        events,
+                    ))
+                    .is_err()
+                {
+                    return;
+                }
+                continue;
+            }
+            Ok(None) => None,
+            Err(e) => Some(e),
+        };
+        let _ = log_viewer_tx.send(LogViewerOutboundMessage::TailEnded(tail_generation, error));
+        return;
     }
 }
@@ -0,0 +1,97 @@
+use std::{
+    collections::VecDeque,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use tracing::Level;
+use tracing_subscriber::fmt::{format::FmtSpan, MakeWriter};
+
+use crate::paths::{self, Dir};
+
+/// A log past this size is started over instead of appended to
+const MAX_LOG_BYTES: u64 = 10 * 1024 * 1024;
+
+/// How many of the newest lines are kept around for the debug overlay
+const RECENT_LINES: usize = 8;
+
+static RECENT: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// The newest lines written to the log, oldest first
+pub fn recent_lines() -> Vec<String> {
+    RECENT.lock().unwrap().iter().cloned().collect()
+}
+
+struct LogFile(Mutex<File>);
+
+/// One event's line, written to the file and kept for the overlay once it's complete
+struct LogLine<'a> {
+    file: &'a Mutex<File>,
+    line: Vec<u8>,
+}
+
+impl<'a> MakeWriter<'a> for LogFile {
+    type Writer = LogLine<'a>;
+
+    fn make_writer(&'a self) -> LogLine<'a> {
+        LogLine {
+            file: &self.0,
+            line: vec![],
+        }
+    }
+}
+
+impl Write for LogLine<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.line.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for LogLine<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.write_all(&self.line);
+        }
+        let mut recent = RECENT.lock().unwrap();
+        recent.push_back(String::from_utf8_lossy(&self.line).trim_end().to_string());
+        if recent.len() > RECENT_LINES {
+            recent.pop_front();
+        }
+    }
+}
+
+/// `loglog.log` in the platform's state directory
+pub fn default_path() -> Option<PathBuf> {
+    Some(paths::dir(Dir::State)?.join("loglog.log"))
+}
+
+/// Writes tracing events to `path`. Errors and warnings are always kept, `debug` adds every
+/// AWS call with how long it took and the ui's state changes
+pub fn init(path: &Path, debug: bool) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("can't create {}: {}", dir.display(), e))?;
+    }
+    let too_big = fs::metadata(path).is_ok_and(|metadata| metadata.len() > MAX_LOG_BYTES);
+    let file = if too_big {
+        File::create(path)
+    } else {
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+    .map_err(|e| format!("can't open {}: {}", path.display(), e))?;
+    let level = if debug { Level::DEBUG } else { Level::WARN };
+    tracing_subscriber::fmt()
+        .with_writer(LogFile(Mutex::new(file)))
+        .with_ansi(false)
+        .with_max_level(level)
+        // a span closing records how long the call it covers took
+        .with_span_events(FmtSpan::CLOSE)
+        .try_init()
+        .map_err(|e| e.to_string())
+}
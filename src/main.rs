@@ -1,125 +1,302 @@
-use color_eyre::Result;
-use futures::StreamExt;
-
-use log_groups::{LogGroupListComponent, LogGroupSelectionOutboundMessage};
-use log_viewer::{LogViewerOutboundMessage, LogVieweromponent};
-use ratatui::{
-    crossterm::event::{Event, EventStream, KeyCode, KeyEventKind},
-    DefaultTerminal, Frame,
+use std::{
+    io::{self, IsTerminal},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
 };
-use tokio::sync::mpsc;
 
-mod aws;
-mod log_groups;
-mod log_viewer;
-mod shared;
-mod table;
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use color_eyre::{eyre::eyre, Result};
+use crossterm::{
+    event::{DisableBracketedPaste, DisableFocusChange, EnableBracketedPaste, EnableFocusChange},
+    style::Print,
+};
+use loglog::{
+    app::App,
+    backend::{AwsBackend, LogBackend},
+    config::{compile_pattern, Config, MessageTimestamps, ViewerQueries},
+    demo::DemoBackend,
+    headless,
+    keymap::Keymap,
+    logging,
+    output::{OutputFormat, Printer},
+    recording::{RecordingBackend, ReplayBackend},
+    settings::{Flags, Settings},
+    state::StateStore,
+    theme::{self, Background, Theme},
+};
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    color_eyre::install()?;
-    let terminal = ratatui::init();
+/// Saves and restores the window title on terminals that keep a stack of them
+const PUSH_TITLE: &str = "\x1b[22;0t";
+const POP_TITLE: &str = "\x1b[23;0t";
 
-    let app = App::new();
-    let app_result = app.run(terminal).await;
-    ratatui::restore();
-    app_result
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Quit straight away even when queries are still running
+    #[arg(long)]
+    no_confirm_quit: bool,
+
+    /// List log groups from every region instead of just the configured one
+    #[arg(long, global = true)]
+    all_regions: bool,
+
+    /// Open the log groups used last time straight away
+    #[arg(long)]
+    open_last: bool,
+
+    /// AWS profile to use, defaults to LOGLOG_PROFILE
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    /// Region to use, defaults to LOGLOG_REGION
+    #[arg(long, global = true)]
+    region: Option<String>,
+
+    /// How groups, events and query rows are printed, defaults to LOGLOG_OUTPUT or text
+    #[arg(long, global = true, value_enum)]
+    output: Option<OutputFormat>,
+
+    /// Only print these comma separated fields, in this order
+    #[arg(long, global = true, value_delimiter = ',')]
+    fields: Option<Vec<String>>,
+
+    /// Record every AWS call, how long it took and what the ui did to loglog.log in the state
+    /// directory, which otherwise only gets errors
+    #[arg(long, global = true)]
+    debug: bool,
+
+    /// Show made up log groups and events instead of connecting to AWS
+    #[arg(long, global = true)]
+    demo: bool,
+
+    /// Write every AWS response to this file, to be played back with --replay
+    #[arg(long, global = true, value_name = "PATH")]
+    record: Option<PathBuf>,
+
+    /// Answer with the responses written by --record instead of calling AWS
+    #[arg(long, global = true, value_name = "PATH", conflicts_with = "demo")]
+    replay: Option<PathBuf>,
 }
 
-#[derive(Debug)]
-struct App {
-    should_quit: bool,
-    selected_group: Option<String>,
-    log_groups_component: LogGroupListComponent,
-    log_group_selection_rx: mpsc::UnboundedReceiver<LogGroupSelectionOutboundMessage>,
-    log_viewer_component: LogVieweromponent,
-    log_viewer_rx: mpsc::UnboundedReceiver<LogViewerOutboundMessage>,
+/// Without a subcommand loglog starts the interactive UI
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Print the name of every log group
+    Groups,
+    /// Print new events from a log group as they arrive
+    Tail {
+        group: String,
+        /// Only follow streams starting with this
+        #[arg(long, default_value = "")]
+        stream_prefix: String,
+        /// Only print events matching this CloudWatch filter pattern, e.g. '"ERROR" -timeout'
+        #[arg(long, default_value = "")]
+        filter: String,
+    },
+    /// Run a Logs Insights query and print the results
+    Query {
+        group: String,
+        /// e.g. 'fields @timestamp, @message | filter @message like /error/', defaults to
+        /// LOGLOG_QUERY
+        #[arg(short, long)]
+        query: Option<String>,
+        /// How far back to query, e.g. 15m, 2h or 7d, defaults to LOGLOG_SINCE or 1h
+        #[arg(long)]
+        since: Option<String>,
+        /// Query the range in pieces this long, e.g. 1d, to stay under the result limit.
+        /// Rows are only merged, so aggregations are per piece
+        #[arg(long)]
+        split: Option<String>,
+        /// Value for a `$name` placeholder in the query, e.g. requestId=abc. Placeholders
+        /// without one are asked for, `$since` is the --since duration
+        #[arg(long = "var", value_name = "NAME=VALUE")]
+        vars: Vec<String>,
+    },
+    /// Count the events containing `term` in every log group, to find where it's logged
+    Search {
+        term: String,
+        /// How far back to search, e.g. 15m, 2h or 7d, defaults to LOGLOG_SINCE or 1h
+        #[arg(long)]
+        since: Option<String>,
+    },
+    /// Print every key binding, including overrides from the config
+    Keys,
+    /// Print a completion script for `shell`
+    Completions { shell: Shell },
 }
 
-impl App {
-    pub async fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
-        self.log_groups_component.run();
-
-        let mut events = EventStream::new();
-
-        while !self.should_quit {
-            terminal.draw(|frame| self.draw(frame))?;
-            tokio::select! {
-                event = self.log_group_selection_rx.recv() => {
-                    match event {
-                        Some(LogGroupSelectionOutboundMessage::SelectedGroup(group)) => {
-                            self.selected_group = Some(group.clone());
-                            self.log_viewer_component.log_group_name = group;
-                            // TODO handle reselecvtion and stuff
-                            self.log_viewer_component.run()
-                        },
-                        None => (),
-                        Some(LogGroupSelectionOutboundMessage::ApplySearch) => {
-                            self.log_groups_component.apply_search();
-                        }
-
-                    }
-                },
-                 event = self.log_viewer_rx.recv() => {
-                    match event {
-                        None => (),
-                        Some(LogViewerOutboundMessage::ReRender) => {},
-                        Some(LogViewerOutboundMessage::SetLogs(log_messages)) => {
-                            self.log_viewer_component.set_logs(log_messages);
-                        }
-                        Some(LogViewerOutboundMessage::UnselectLogGroup) => {
-                            self.selected_group = None;
-                            self.log_viewer_component.clear_logs();
-                            self.log_viewer_component.log_group_name.clear();
-                        }
-                    }
-                },
-                Some(Ok(event)) = events.next() => self.handle_event(&event),
-            }
-        }
-        Ok(())
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    color_eyre::install()?;
+    if let Some(path) = logging::default_path() {
+        // loglog works the same without its log, a read-only state directory shouldn't stop it
+        let _ = logging::init(&path, args.debug);
     }
-
-    fn draw(&self, frame: &mut Frame) {
-        if self.selected_group.is_some() {
-            frame.render_widget(&self.log_viewer_component, frame.area());
-        } else {
-            frame.render_widget(&self.log_groups_component, frame.area());
+    let config = Config::load(Config::default_path()).map_err(|e| eyre!(e))?;
+    let mut flags = Flags {
+        region: args.region.clone(),
+        profile: args.profile.clone(),
+        output: args.output,
+        ..Default::default()
+    };
+    match &args.command {
+        Some(Command::Query { query, since, .. }) => {
+            flags.query = query.clone();
+            flags.since = since.clone();
         }
+        Some(Command::Search { since, .. }) => flags.since = since.clone(),
+        _ => {}
+    }
+    let settings =
+        Settings::resolve(flags, &config, |name| std::env::var(name).ok()).map_err(|e| eyre!(e))?;
+    let keymap = Keymap::with_overrides(&config.keys).map_err(|e| eyre!(e))?;
+    let mut backend: Arc<dyn LogBackend> = if let Some(path) = &args.replay {
+        Arc::new(ReplayBackend::load(path).map_err(|e| eyre!(e))?)
+    } else if args.demo {
+        Arc::new(DemoBackend::default())
+    } else {
+        Arc::new(AwsBackend::new(
+            settings.profile.clone(),
+            settings.region.clone(),
+        ))
+    };
+    if let Some(path) = &args.record {
+        backend = Arc::new(RecordingBackend::create(backend, path).map_err(|e| eyre!(e))?);
     }
+    let mut stdout = io::stdout();
+    let printer = Printer::new(io::stdout(), settings.output, args.fields.clone());
+    let regions = if args.all_regions {
+        backend.regions().into_iter().map(Some).collect()
+    } else {
+        vec![None]
+    };
+    let result = match args.command {
+        None => return interactive(args, config, keymap, backend).await,
+        Some(Command::Groups) => {
+            headless::list_groups(backend.as_ref(), regions, printer, io::stderr()).await
+        }
+        Some(Command::Tail {
+            group,
+            stream_prefix,
+            filter,
+        }) => headless::tail(backend.as_ref(), group, stream_prefix, filter, printer).await,
+        Some(Command::Query {
+            group, split, vars, ..
+        }) => {
+            let query = settings
+                .query
+                .ok_or_else(|| eyre!("no query, pass -q or set LOGLOG_QUERY"))?;
+            let query = fill_query(&query, vars, &settings.since).map_err(|e| eyre!(e))?;
+            let since_ms = headless::parse_since(&settings.since).map_err(|e| eyre!(e))?;
+            let split_ms = split
+                .map(|split| headless::parse_since(&split))
+                .transpose()
+                .map_err(|e| eyre!(e))?;
+            headless::query(backend.as_ref(), group, query, since_ms, split_ms, printer).await
+        }
+        Some(Command::Search { term, .. }) => {
+            let since_ms = headless::parse_since(&settings.since).map_err(|e| eyre!(e))?;
+            headless::search(
+                backend.as_ref(),
+                regions,
+                term,
+                since_ms,
+                printer,
+                io::stderr(),
+            )
+            .await
+        }
+        Some(Command::Keys) => headless::keys(&keymap, printer),
+        Some(Command::Completions { shell }) => {
+            clap_complete::generate(shell, &mut Args::command(), "loglog", &mut stdout);
+            Ok(())
+        }
+    };
+    result.map_err(|e| eyre!(e))
+}
 
-    fn handle_event(&mut self, event: &Event) {
-        let prevent_exit = if self.selected_group.is_some() {
-            self.log_viewer_component.handle_event(event)
-        } else {
-            self.log_groups_component.handle_event(event)
-        };
-        if let Event::Key(key) = event {
-            if key.kind == KeyEventKind::Press {
-                match key.code {
-                    KeyCode::Char('q') | KeyCode::Esc => {
-                        if !prevent_exit {
-                            self.should_quit = true
-                        }
-                    }
-                    _ => {}
-                }
-            }
+/// Fills the query's placeholders from `--var`, asking on stdin for any left over
+fn fill_query(query: &str, vars: Vec<String>, since: &str) -> Result<String, String> {
+    let mut values = vars
+        .iter()
+        .map(|var| {
+            var.split_once('=')
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .ok_or_else(|| format!("invalid --var {}, expected NAME=VALUE", var))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    values.push(("since".to_string(), since.to_string()));
+    let stdin = io::stdin();
+    for name in headless::placeholders(query) {
+        if values.iter().any(|(known, _)| *known == name) || !stdin.is_terminal() {
+            continue;
         }
+        eprint!("{}: ", name);
+        let mut value = String::new();
+        stdin.read_line(&mut value).map_err(|e| e.to_string())?;
+        values.push((name, value.trim_end_matches(['\r', '\n']).to_string()));
     }
+    headless::fill_placeholders(query, &values)
 }
 
-impl App {
-    fn new() -> Self {
-        let (tx, rx) = mpsc::unbounded_channel::<LogGroupSelectionOutboundMessage>();
-        let (log_viewer_tx, log_viewer_rx) = mpsc::unbounded_channel::<LogViewerOutboundMessage>();
-        Self {
-            should_quit: false,
-            selected_group: None,
-            log_groups_component: LogGroupListComponent::new(tx),
-            log_viewer_component: LogVieweromponent::new(log_viewer_tx),
-            log_viewer_rx,
-            log_group_selection_rx: rx,
-        }
+async fn interactive(
+    args: Args,
+    config: Config,
+    keymap: Keymap,
+    backend: Arc<dyn LogBackend>,
+) -> Result<()> {
+    let background = config
+        .theme
+        .as_deref()
+        .map(Background::parse)
+        .transpose()
+        .map_err(|e| eyre!(e))?;
+    let exclude = config
+        .exclude_groups
+        .iter()
+        .map(|pattern| compile_pattern(pattern))
+        .collect::<Result<Vec<_>, String>>()
+        .map_err(|e| eyre!(e))?;
+    let queries = ViewerQueries::from_config(&config).map_err(|e| eyre!(e))?;
+    let message_timestamps = MessageTimestamps::from_config(&config).map_err(|e| eyre!(e))?;
+    let terminal = ratatui::init();
+    // pastes arrive as a single event instead of a key per character
+    crossterm::execute!(io::stdout(), EnableBracketedPaste)?;
+    // focus changes decide whether finished queries raise a desktop notification
+    crossterm::execute!(io::stdout(), EnableFocusChange)?;
+    // xterm's title stack, loglog's titles are popped off it again on exit
+    crossterm::execute!(io::stdout(), Print(PUSH_TITLE))?;
+    let background = background
+        .or_else(theme::detect_background)
+        .unwrap_or_default();
+
+    let mut app = App::new(backend);
+    app.confirm_quit = !args.no_confirm_quit;
+    app.set_keymap(keymap);
+    app.set_theme(Theme::for_background(background));
+    app.set_all_regions(args.all_regions);
+    app.set_exclude_patterns(exclude);
+    app.set_confirm_query_bytes(config.confirm_query_bytes);
+    app.set_memory_events(config.memory_events);
+    app.set_error_summary(config.error_summary);
+    app.set_viewer_queries(queries);
+    app.set_message_timestamps(message_timestamps);
+    let notify_after =
+        Some(Duration::from_secs(config.notify_after_seconds)).filter(|after| !after.is_zero());
+    app.set_query_notifications(notify_after, config.notify_when_focused);
+    if let Some(dir) = &config.export_dir {
+        app.set_export_dir(dir.clone());
     }
+    app.set_state_store(StateStore::new(StateStore::default_path()), args.open_last);
+    let app_result = app.run(terminal).await;
+    let _ = crossterm::execute!(io::stdout(), DisableBracketedPaste, DisableFocusChange);
+    let _ = crossterm::execute!(io::stdout(), Print(POP_TITLE));
+    ratatui::restore();
+    app_result
 }
@@ -0,0 +1,197 @@
+use std::io::Write;
+
+use crate::shared::{LogEvent, LogGroup, QueryRow};
+
+/// How the headless subcommands print what they find
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// space separated values, meant for people
+    #[default]
+    Text,
+    /// a single array of objects
+    Json,
+    /// one object per line
+    Ndjson,
+    /// a header row, then one row per record
+    Csv,
+}
+
+/// Writes records with their columns always in the same order, `fields` picks and orders them.
+/// Csv columns are fixed by the first record unless `fields` is set
+pub struct Printer<W: Write> {
+    out: W,
+    format: OutputFormat,
+    fields: Option<Vec<String>>,
+    /// text shows only these unless `fields` is set, empty shows `field=value` for everything
+    text_fields: Vec<String>,
+    header: Option<Vec<String>>,
+    records: usize,
+}
+
+impl<W: Write> Printer<W> {
+    pub fn new(out: W, format: OutputFormat, fields: Option<Vec<String>>) -> Self {
+        Self {
+            out,
+            format,
+            fields,
+            text_fields: vec![],
+            header: None,
+            records: 0,
+        }
+    }
+
+    pub fn with_text_fields(mut self, text_fields: &[&str]) -> Self {
+        self.text_fields = text_fields.iter().map(|field| field.to_string()).collect();
+        self
+    }
+
+    pub fn print(&mut self, record: &QueryRow) -> Result<(), String> {
+        let line = match self.format {
+            OutputFormat::Text => self.text(record),
+            OutputFormat::Ndjson => json_object(&self.columns(record)),
+            OutputFormat::Json => {
+                let separator = if self.records == 0 { "[\n" } else { ",\n" };
+                format!("{}  {}", separator, json_object(&self.columns(record)))
+            }
+            OutputFormat::Csv => {
+                let header = match &self.header {
+                    Some(header) => header.clone(),
+                    None => {
+                        let header = self.fields.clone().unwrap_or_else(|| {
+                            record.iter().map(|(field, _)| field.clone()).collect()
+                        });
+                        writeln!(self.out, "{}", csv_row(&header)).map_err(|e| e.to_string())?;
+                        self.header = Some(header.clone());
+                        header
+                    }
+                };
+                let values = header
+                    .iter()
+                    .map(|field| field_value(record, field).unwrap_or_default().to_string())
+                    .collect::<Vec<_>>();
+                csv_row(&values)
+            }
+        };
+        self.records += 1;
+        match self.format {
+            OutputFormat::Json => write!(self.out, "{}", line),
+            _ => writeln!(self.out, "{}", line),
+        }
+        .map_err(|e| e.to_string())
+    }
+
+    pub fn flush(&mut self) -> Result<(), String> {
+        self.out.flush().map_err(|e| e.to_string())
+    }
+
+    /// Closes the json array, the other formats are complete after every record
+    pub fn finish(&mut self) -> Result<(), String> {
+        if self.format == OutputFormat::Json {
+            let end = if self.records == 0 { "[]\n" } else { "\n]\n" };
+            write!(self.out, "{}", end).map_err(|e| e.to_string())?;
+        }
+        self.flush()
+    }
+
+    fn columns<'a>(&self, record: &'a QueryRow) -> Vec<(String, Option<&'a str>)> {
+        match &self.fields {
+            Some(fields) => fields
+                .iter()
+                .map(|field| (field.clone(), field_value(record, field)))
+                .collect(),
+            None => record
+                .iter()
+                .map(|(field, value)| (field.clone(), Some(value.as_str())))
+                .collect(),
+        }
+    }
+
+    fn text(&self, record: &QueryRow) -> String {
+        let fields = self.fields.as_ref().unwrap_or(&self.text_fields);
+        if fields.is_empty() {
+            return record
+                .iter()
+                .map(|(field, value)| format!("{}={}", field, value))
+                .collect::<Vec<_>>()
+                .join(" ");
+        }
+        fields
+            .iter()
+            .map(|field| field_value(record, field).unwrap_or_default().trim_end())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+pub fn group_record(group: &LogGroup) -> QueryRow {
+    vec![
+        ("name".to_string(), group.name.clone()),
+        ("arn".to_string(), group.arn.clone()),
+        (
+            "region".to_string(),
+            group.region().unwrap_or_default().to_string(),
+        ),
+        (
+            "stored_bytes".to_string(),
+            group
+                .stored_bytes
+                .map(|bytes| bytes.to_string())
+                .unwrap_or_default(),
+        ),
+        (
+            "retention_in_days".to_string(),
+            group
+                .retention_in_days
+                .map(|days| days.to_string())
+                .unwrap_or_default(),
+        ),
+    ]
+}
+
+pub fn event_record(event: &LogEvent) -> QueryRow {
+    let timestamp = chrono::DateTime::from_timestamp_millis(event.timestamp)
+        .map(|timestamp| timestamp.to_rfc3339())
+        .unwrap_or_default();
+    vec![
+        ("timestamp".to_string(), timestamp),
+        ("log_group".to_string(), event.log_group.to_string()),
+        ("log_stream".to_string(), event.log_stream.to_string()),
+        ("message".to_string(), event.message.to_string()),
+    ]
+}
+
+fn field_value<'a>(record: &'a QueryRow, field: &str) -> Option<&'a str> {
+    record
+        .iter()
+        .find(|(name, _)| name == field)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Built by hand since a json map would sort the keys
+fn json_object(columns: &[(String, Option<&str>)]) -> String {
+    let fields = columns
+        .iter()
+        .map(|(field, value)| {
+            let value = match value {
+                Some(value) => serde_json::Value::from(*value),
+                None => serde_json::Value::Null,
+            };
+            format!("{}:{}", serde_json::Value::from(field.as_str()), value)
+        })
+        .collect::<Vec<_>>();
+    format!("{{{}}}", fields.join(","))
+}
+
+fn csv_row(values: &[String]) -> String {
+    values
+        .iter()
+        .map(|value| {
+            if value.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", value.replace('"', "\"\""))
+            } else {
+                value.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
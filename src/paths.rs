@@ -0,0 +1,73 @@
+use std::{ffi::OsString, path::PathBuf};
+
+/// The kinds of files loglog keeps, each in its own directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dir {
+    /// settings the user writes
+    Config,
+    /// anything that can be thrown away and fetched again
+    Cache,
+    /// what loglog remembers between runs
+    State,
+}
+
+/// Platforms lay their directories out differently
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    /// XDG base directories, also used for other unixes
+    Linux,
+    MacOs,
+    Windows,
+}
+
+impl Platform {
+    pub fn current() -> Platform {
+        if cfg!(target_os = "macos") {
+            Platform::MacOs
+        } else if cfg!(windows) {
+            Platform::Windows
+        } else {
+            Platform::Linux
+        }
+    }
+}
+
+/// loglog's directory for `dir` on this machine, `None` without a home directory
+pub fn dir(dir: Dir) -> Option<PathBuf> {
+    resolve(dir, Platform::current(), |name| std::env::var_os(name))
+}
+
+/// loglog's directory for `dir` on `platform`, with `env` looking up variables
+pub fn resolve(
+    dir: Dir,
+    platform: Platform,
+    env: impl Fn(&str) -> Option<OsString>,
+) -> Option<PathBuf> {
+    let var = |name: &str| {
+        env(name)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    };
+    let base = match platform {
+        Platform::Linux => {
+            let (name, fallback) = match dir {
+                Dir::Config => ("XDG_CONFIG_HOME", ".config"),
+                Dir::Cache => ("XDG_CACHE_HOME", ".cache"),
+                Dir::State => ("XDG_STATE_HOME", ".local/state"),
+            };
+            var(name).or_else(|| var("HOME").map(|home| home.join(fallback)))?
+        }
+        Platform::MacOs => {
+            let library = var("HOME")?.join("Library");
+            match dir {
+                Dir::Config | Dir::State => library.join("Application Support"),
+                Dir::Cache => library.join("Caches"),
+            }
+        }
+        Platform::Windows => match dir {
+            Dir::Config => var("APPDATA")?,
+            Dir::Cache | Dir::State => var("LOCALAPPDATA")?,
+        },
+    };
+    Some(base.join("loglog"))
+}
@@ -0,0 +1,61 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use regex_lite::Regex;
+
+/// What a filter matches, `/.../` is a regex and anything else plain text
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Text(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    pub fn parse(source: &str) -> Result<Pattern, String> {
+        match source
+            .strip_prefix('/')
+            .and_then(|source| source.strip_suffix('/'))
+        {
+            Some(regex) if !regex.is_empty() => Regex::new(regex)
+                .map(Pattern::Regex)
+                .map_err(|e| format!("invalid regex {}: {}", source, e)),
+            _ => Ok(Pattern::Text(source.to_string())),
+        }
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        match self {
+            Pattern::Text(pattern) => text.contains(pattern.as_str()),
+            Pattern::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+/// How many compiled patterns are kept around for flipping between filters
+const CACHED_PATTERNS: usize = 16;
+
+/// Recently used patterns by source, the least recently used one is dropped first
+#[derive(Debug, Default)]
+pub struct PatternCache {
+    /// most recently used last
+    patterns: VecDeque<(String, Arc<Pattern>)>,
+}
+
+impl PatternCache {
+    pub fn get(&mut self, source: &str) -> Result<Arc<Pattern>, String> {
+        let cached = self
+            .patterns
+            .iter()
+            .position(|(cached, _)| cached == source)
+            .and_then(|index| self.patterns.remove(index));
+        let pattern = match cached {
+            Some((_, pattern)) => pattern,
+            None => Arc::new(Pattern::parse(source)?),
+        };
+        if self.patterns.len() == CACHED_PATTERNS {
+            self.patterns.pop_front();
+        }
+        self.patterns
+            .push_back((source.to_string(), pattern.clone()));
+        Ok(pattern)
+    }
+}
@@ -0,0 +1,474 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::{Alignment, Rect},
+    style::Style,
+    text::{Line, Span},
+    widgets::{Block, Clear, Paragraph, Widget, Wrap},
+};
+
+use crate::{
+    fields::FieldStats,
+    keymap::{format_keys, Action, Context, Keymap},
+    shared::{format_bytes, FilterAlarms, LogGroup},
+    theme::Theme,
+};
+
+/// A `width` x `height` area in the middle of `area`, shrunk to fit if needed
+pub fn centered_area(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+/// A yes/no question drawn over whatever screen is active
+#[derive(Debug, Clone)]
+pub struct ConfirmPopup<'a> {
+    pub title: &'a str,
+    pub message: &'a str,
+    pub keymap: &'a Keymap,
+    pub theme: &'a Theme,
+}
+
+impl Widget for ConfirmPopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let area = centered_area(area, 50, 5);
+        Clear.render(area, buf);
+        let hint = format!(
+            "{} to confirm, any other key to cancel",
+            self.keymap.keys(Context::Global, Action::Confirm)
+        );
+        let block = Block::bordered()
+            .title(self.title)
+            .title_bottom(Line::from(hint).right_aligned())
+            .border_style(Style::new().fg(self.theme.error));
+        Paragraph::new(self.message)
+            .block(block)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true })
+            .render(area, buf);
+    }
+}
+
+/// A titled message with a hint on how to answer it, used for menus and text prompts
+#[derive(Debug, Clone)]
+pub struct PromptPopup<'a> {
+    pub title: &'a str,
+    pub message: Line<'a>,
+    pub hint: &'a str,
+}
+
+impl Widget for PromptPopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let area = centered_area(area, 50, 5);
+        Clear.render(area, buf);
+        let block = Block::bordered()
+            .title(self.title)
+            .title_bottom(Line::from(self.hint).right_aligned());
+        Paragraph::new(self.message)
+            .block(block)
+            .wrap(Wrap { trim: false })
+            .render(area, buf);
+    }
+}
+
+/// Lists numeric fields to pick from, then the spread of the picked one
+#[derive(Debug, Clone)]
+pub struct FieldStatsPopup<'a> {
+    pub fields: &'a [String],
+    pub selected: usize,
+    pub stats: Option<&'a FieldStats>,
+    pub hint: &'a str,
+    pub theme: &'a Theme,
+}
+
+impl Widget for FieldStatsPopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let height = match self.stats {
+            Some(_) => 6,
+            None => self.fields.len().max(1) as u16 + 2,
+        };
+        let area = centered_area(area, 50, height);
+        Clear.render(area, buf);
+        let block = Block::bordered()
+            .title("Field stats")
+            .title_bottom(Line::from(self.hint).right_aligned());
+
+        let lines = match self.stats {
+            Some(stats) => vec![
+                Line::from(self.fields[self.selected].as_str()),
+                Line::from(format!("count {}", stats.count)),
+                Line::from(format!("min {}  max {}", stats.min, stats.max)),
+                Line::from(format!("p50 {}  p95 {}", stats.p50, stats.p95)),
+            ],
+            None if self.fields.is_empty() => vec![Line::from("no numeric fields in these logs")],
+            None => self
+                .fields
+                .iter()
+                .enumerate()
+                .map(|(index, field)| {
+                    let style = if index == self.selected {
+                        Style::new().bg(self.theme.selection)
+                    } else {
+                        Style::new()
+                    };
+                    Line::styled(field.as_str(), style)
+                })
+                .collect(),
+        };
+        // keep the selected field in view when the list is taller than the terminal
+        let scroll = match self.stats {
+            Some(_) => 0,
+            None => (self.selected + 3).saturating_sub(area.height as usize) as u16,
+        };
+        Paragraph::new(lines)
+            .block(block)
+            .scroll((scroll, 0))
+            .render(area, buf);
+    }
+}
+
+/// Fields the viewer's query can return, each with whether it's picked
+#[derive(Debug, Clone)]
+pub struct FieldsPopup<'a> {
+    pub fields: &'a [(String, bool)],
+    pub selected: usize,
+    pub hint: &'a str,
+    pub theme: &'a Theme,
+}
+
+impl Widget for FieldsPopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let area = centered_area(area, 50, self.fields.len() as u16 + 2);
+        Clear.render(area, buf);
+        let block = Block::bordered()
+            .title("Query fields")
+            .title_bottom(Line::from(self.hint).right_aligned());
+        let lines = self
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(index, (field, picked))| {
+                let style = if index == self.selected {
+                    Style::new().bg(self.theme.selection)
+                } else {
+                    Style::new()
+                };
+                let check = if *picked { "[x]" } else { "[ ]" };
+                Line::styled(format!("{} {}", check, field), style)
+            })
+            .collect::<Vec<_>>();
+        // keep the selected field in view when the list is taller than the terminal
+        let scroll = (self.selected + 3).saturating_sub(area.height as usize) as u16;
+        Paragraph::new(lines)
+            .block(block)
+            .scroll((scroll, 0))
+            .render(area, buf);
+    }
+}
+
+/// Lines to pick from with the highlighted one shown, like the inbox and the bookmarks
+#[derive(Debug, Clone)]
+pub struct ListPopup<'a> {
+    pub title: &'a str,
+    pub entries: &'a [String],
+    pub selected: usize,
+    /// shown instead of the list when it's empty
+    pub empty: &'a str,
+    pub hint: &'a str,
+    pub theme: &'a Theme,
+}
+
+impl Widget for ListPopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let area = centered_area(area, 70, self.entries.len().max(1) as u16 + 2);
+        Clear.render(area, buf);
+        let block = Block::bordered()
+            .title(self.title)
+            .title_bottom(Line::from(self.hint).right_aligned());
+        let lines = match self.entries {
+            [] => vec![Line::styled(self.empty, Style::new().fg(self.theme.muted))],
+            entries => entries
+                .iter()
+                .enumerate()
+                .map(|(index, entry)| {
+                    let style = if index == self.selected {
+                        Style::new().bg(self.theme.selection)
+                    } else {
+                        Style::new()
+                    };
+                    Line::styled(entry.as_str(), style)
+                })
+                .collect(),
+        };
+        let scroll = (self.selected + 3).saturating_sub(area.height as usize) as u16;
+        Paragraph::new(lines)
+            .block(block)
+            .scroll((scroll, 0))
+            .render(area, buf);
+    }
+}
+
+/// Settings of a single group, field indexes arrive after it opens
+#[derive(Debug, Clone)]
+pub struct GroupInfoPopup<'a> {
+    pub group: &'a LogGroup,
+    /// `None` while still loading
+    pub field_indexes: Option<&'a Result<Vec<String>, String>>,
+    /// `None` while still loading
+    pub metric_filters: Option<&'a Result<FilterAlarms, String>>,
+    /// index of the highlighted alarm across every filter
+    pub selected_alarm: usize,
+    pub theme: &'a Theme,
+}
+
+impl Widget for GroupInfoPopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let label = |label: &'static str| Span::styled(label, Style::new().fg(self.theme.muted));
+        let mut lines = vec![
+            Line::from(self.group.name.as_str()),
+            Line::from(vec![
+                label("region      "),
+                Span::raw(self.group.region().unwrap_or("default")),
+            ]),
+            Line::from(vec![
+                label("stored      "),
+                Span::raw(format_bytes(self.group.stored_bytes.unwrap_or_default())),
+            ]),
+            Line::from(vec![
+                label("retention   "),
+                Span::raw(match self.group.retention_in_days {
+                    Some(days) => format!("{} days", days),
+                    None => "never expires".to_string(),
+                }),
+            ]),
+            Line::from(vec![
+                label("masking     "),
+                Span::raw(if self.group.data_protected {
+                    "on"
+                } else {
+                    "off"
+                }),
+            ]),
+        ];
+        match self.field_indexes {
+            None => lines.push(Line::from(vec![
+                label("indexes     "),
+                Span::raw("loading…"),
+            ])),
+            Some(Err(e)) => lines.push(Line::from(vec![
+                label("indexes     "),
+                Span::styled(e.as_str(), Style::new().fg(self.theme.error)),
+            ])),
+            Some(Ok(fields)) if fields.is_empty() => {
+                lines.push(Line::from(vec![label("indexes     "), Span::raw("none")]))
+            }
+            Some(Ok(fields)) => {
+                for (index, field) in fields.iter().enumerate() {
+                    let label = if index == 0 {
+                        "indexes     "
+                    } else {
+                        "            "
+                    };
+                    lines.push(Line::from(vec![
+                        Span::styled(label, Style::new().fg(self.theme.muted)),
+                        Span::styled(field.as_str(), Style::new().fg(self.theme.accent)),
+                    ]));
+                }
+            }
+        }
+        let mut alarm_count = 0;
+        match self.metric_filters {
+            None => lines.push(Line::from(vec![
+                label("filters     "),
+                Span::raw("loading…"),
+            ])),
+            Some(Err(e)) => lines.push(Line::from(vec![
+                label("filters     "),
+                Span::styled(e.as_str(), Style::new().fg(self.theme.error)),
+            ])),
+            Some(Ok(filters)) if filters.is_empty() => {
+                lines.push(Line::from(vec![label("filters     "), Span::raw("none")]))
+            }
+            Some(Ok(filters)) => {
+                for (index, (filter, alarms)) in filters.iter().enumerate() {
+                    let metric = match (&filter.namespace, &filter.metric_name) {
+                        (Some(namespace), Some(metric_name)) => {
+                            format!(" → {}/{}", namespace, metric_name)
+                        }
+                        _ => String::new(),
+                    };
+                    lines.push(Line::from(vec![
+                        label(if index == 0 {
+                            "filters     "
+                        } else {
+                            "            "
+                        }),
+                        Span::raw(filter.name.as_str()),
+                        Span::styled(
+                            format!("  {}", filter.pattern),
+                            Style::new().fg(self.theme.accent),
+                        ),
+                        Span::styled(metric, Style::new().fg(self.theme.muted)),
+                    ]));
+                    if alarms.is_empty() {
+                        lines.push(Line::from(vec![
+                            label("              "),
+                            label("no alarms on its metric"),
+                        ]));
+                    }
+                    for alarm in alarms {
+                        let style = if alarm.is_going_off() {
+                            Style::new().fg(self.theme.error)
+                        } else {
+                            Style::new()
+                        };
+                        let mut line = Line::from(vec![
+                            label("              "),
+                            Span::styled(format!("alarm {}", alarm.name), style),
+                            label(" "),
+                            Span::styled(
+                                alarm.state.to_lowercase(),
+                                Style::new().fg(self.theme.muted),
+                            ),
+                        ]);
+                        if alarm_count == self.selected_alarm {
+                            line = line.style(Style::new().bg(self.theme.selection));
+                        }
+                        lines.push(line);
+                        alarm_count += 1;
+                    }
+                }
+            }
+        }
+        let hint = if alarm_count > 0 {
+            "enter to jump to the alarm, any other key to close"
+        } else {
+            "any key to close"
+        };
+        let area = centered_area(area, 64, lines.len() as u16 + 2);
+        Clear.render(area, buf);
+        let block = Block::bordered()
+            .title("Group")
+            .title_bottom(Line::from(hint).right_aligned());
+        Paragraph::new(lines).block(block).render(area, buf);
+    }
+}
+
+/// Numbers for diagnosing a slow ui, drawn in the top right corner over whatever is open
+#[derive(Debug, Clone)]
+pub struct DebugPopup<'a> {
+    /// a label and its value per line
+    pub stats: &'a [(&'static str, String)],
+    pub log: &'a [String],
+    pub theme: &'a Theme,
+}
+
+impl Widget for DebugPopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let height = self.stats.len() + self.log.len() + 3;
+        let width = 72.min(area.width);
+        let area = Rect {
+            x: area.right() - width,
+            width,
+            height: (height as u16).min(area.height),
+            ..area
+        };
+        Clear.render(area, buf);
+        let block = Block::bordered().title("Debug");
+        let mut lines = self
+            .stats
+            .iter()
+            .map(|(label, value)| {
+                Line::from(vec![
+                    Span::styled(format!("{:9} ", label), Style::new().fg(self.theme.accent)),
+                    Span::raw(value.as_str()),
+                ])
+            })
+            .collect::<Vec<_>>();
+        lines.push(Line::from(""));
+        lines.extend(
+            self.log
+                .iter()
+                .map(|line| Line::styled(line.as_str(), Style::new().fg(self.theme.muted))),
+        );
+        Paragraph::new(lines).block(block).render(area, buf);
+    }
+}
+
+/// The keys of the global and current screen bindings, drawn from the keymap
+#[derive(Debug, Clone)]
+pub struct HelpPopup<'a> {
+    pub keymap: &'a Keymap,
+    pub context: Context,
+    /// how many lines are scrolled past, see `max_scroll`
+    pub scroll: u16,
+    pub theme: &'a Theme,
+}
+
+impl HelpPopup<'_> {
+    fn lines(&self) -> Vec<Line<'static>> {
+        let rows = [self.context, Context::Global]
+            .into_iter()
+            .flat_map(|context| self.keymap.bindings(context))
+            .map(|(action, keys)| (format_keys(keys), action.description()))
+            .collect::<Vec<_>>();
+        let key_width = rows.iter().map(|(keys, _)| keys.len()).max().unwrap_or(0);
+        rows.into_iter()
+            .map(|(keys, description)| {
+                Line::from(vec![
+                    Span::styled(
+                        format!("{:width$}  ", keys, width = key_width),
+                        Style::new().fg(self.theme.accent),
+                    ),
+                    Span::raw(description),
+                ])
+            })
+            .collect()
+    }
+
+    fn hint(&self) -> String {
+        format!(
+            "{}, {} to scroll, any other key to close",
+            self.keymap.keys(self.context, Action::ScrollDown),
+            self.keymap.keys(self.context, Action::ScrollUp)
+        )
+    }
+
+    /// The popup sized to fit its longest line, as far as `area` allows
+    fn area(&self, area: Rect, lines: &[Line]) -> Rect {
+        let width = lines
+            .iter()
+            .map(Line::width)
+            .chain([self.hint().len()])
+            .max()
+            .unwrap_or(0);
+        centered_area(area, width as u16 + 2, lines.len() as u16 + 2)
+    }
+
+    /// How far the bindings can scroll in `area` before the last one is at the bottom
+    pub fn max_scroll(&self, area: Rect) -> u16 {
+        let lines = self.lines();
+        let height = self.area(area, &lines).height.saturating_sub(2);
+        (lines.len() as u16).saturating_sub(height)
+    }
+}
+
+impl Widget for HelpPopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let lines = self.lines();
+        let area = self.area(area, &lines);
+        Clear.render(area, buf);
+        let block = Block::bordered()
+            .title("Keys")
+            .title_bottom(Line::from(self.hint()).right_aligned());
+        Paragraph::new(lines)
+            .block(block)
+            .scroll((self.scroll, 0))
+            .render(area, buf);
+    }
+}
@@ -0,0 +1,49 @@
+/// The commands of `query`, split on the pipes between them
+pub fn commands(query: &str) -> impl Iterator<Item = &str> {
+    query.split('|').map(str::trim)
+}
+
+/// Whether `command` is the Insights command `name`, e.g. `fields`
+fn is_command(command: &str, name: &str) -> bool {
+    command
+        .split_whitespace()
+        .next()
+        .is_some_and(|first| first.eq_ignore_ascii_case(name))
+}
+
+/// The fields listed by the `fields` command `query` starts with
+pub fn fields_of(query: &str) -> Vec<String> {
+    let first = commands(query).next().unwrap_or_default();
+    if !is_command(first, "fields") {
+        return vec![];
+    }
+    first["fields".len()..]
+        .split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// `query` starting with a `fields` command listing `fields`, in place of the one it had
+pub fn with_fields(query: &str, fields: &[String]) -> String {
+    let command = format!("fields {}", fields.join(", "));
+    match query.split_once('|') {
+        Some((first, rest)) if is_command(first, "fields") => format!("{} |{}", command, rest),
+        None if is_command(query, "fields") => command,
+        _ => format!("{} | {}", command, query),
+    }
+}
+
+/// Whether the query orders its results itself, they're then shown in that order
+pub fn sorts(query: &str) -> bool {
+    commands(query).any(|command| is_command(command, "sort"))
+}
+
+/// The smallest `limit` of the query, Insights returns at most that many rows
+pub fn limit(query: &str) -> Option<usize> {
+    commands(query)
+        .filter(|command| is_command(command, "limit"))
+        .filter_map(|command| command["limit".len()..].trim().parse().ok())
+        .min()
+}
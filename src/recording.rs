@@ -0,0 +1,690 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::{self, File},
+    io::Write,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures::{future::BoxFuture, FutureExt};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    backend::{GroupChange, LiveTailSession, LogBackend},
+    shared::{
+        Account, Alarm, GroupMetadata, LogEvent, LogGroup, LogGroupPage, MetricFilter, QueryCounts,
+        QueryRow,
+    },
+};
+
+/// Arguments left out when matching a replayed call to a recorded one, they depend on when
+/// the call was made
+const TIME_ARGS: [&str; 2] = ["start", "end"];
+
+/// One response as it's written to the recording, a json object per line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    call: String,
+    args: Value,
+    /// how long the call took, replays take as long so responses arrive in the same order
+    took_ms: u64,
+    result: Result<Value, String>,
+}
+
+/// Passes every call on to `backend` and appends its response to a file, for `--record`
+#[derive(Debug)]
+pub struct RecordingBackend {
+    backend: Arc<dyn LogBackend>,
+    file: Arc<Mutex<File>>,
+}
+
+impl RecordingBackend {
+    /// Starts a new recording at `path`, replacing an earlier one
+    pub fn create(backend: Arc<dyn LogBackend>, path: &Path) -> Result<RecordingBackend, String> {
+        let file =
+            File::create(path).map_err(|e| format!("can't create {}: {}", path.display(), e))?;
+        Ok(RecordingBackend {
+            backend,
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+
+    fn record<'a, T: Serialize + Send + 'a>(
+        &self,
+        call: &str,
+        args: Value,
+        response: BoxFuture<'a, Result<T, String>>,
+    ) -> BoxFuture<'a, Result<T, String>> {
+        let file = self.file.clone();
+        let call = call.to_string();
+        async move {
+            let started = Instant::now();
+            let result = response.await;
+            write_entry(&file, call, args, started, &result);
+            result
+        }
+        .boxed()
+    }
+}
+
+fn write_entry<T: Serialize>(
+    file: &Mutex<File>,
+    call: String,
+    args: Value,
+    started: Instant,
+    result: &Result<T, String>,
+) {
+    let entry = Entry {
+        call,
+        args,
+        took_ms: started.elapsed().as_millis() as u64,
+        result: result
+            .as_ref()
+            .map(|value| serde_json::to_value(value).unwrap_or_default())
+            .map_err(String::clone),
+    };
+    // a recording is a debugging aid, failing to write it mustn't break the session
+    if let (Ok(mut file), Ok(line)) = (file.lock(), serde_json::to_string(&entry)) {
+        let _ = writeln!(file, "{}", line);
+    }
+}
+
+impl LogBackend for RecordingBackend {
+    fn regions(&self) -> Vec<String> {
+        self.backend.regions()
+    }
+
+    fn fetch_log_groups(
+        &self,
+        region: Option<String>,
+        next_token: Option<String>,
+    ) -> BoxFuture<'_, Result<LogGroupPage, String>> {
+        let args = json!({ "region": region, "next_token": next_token });
+        self.record(
+            "fetch_log_groups",
+            args,
+            self.backend.fetch_log_groups(region, next_token),
+        )
+    }
+
+    fn fetch_logs(
+        &self,
+        region: Option<String>,
+        log_group_names: Vec<String>,
+        query: Option<String>,
+        start: i64,
+        end: i64,
+    ) -> BoxFuture<'_, Result<Vec<LogEvent>, String>> {
+        let args = json!({
+            "region": region,
+            "log_group_names": log_group_names,
+            "query": query,
+            "start": start,
+            "end": end,
+        });
+        let response = self
+            .backend
+            .fetch_logs(region, log_group_names, query, start, end);
+        self.record("fetch_logs", args, response)
+    }
+
+    fn filter_logs(
+        &self,
+        region: Option<String>,
+        log_group_names: Vec<String>,
+        log_stream_name_prefix: String,
+        start: i64,
+        end: i64,
+    ) -> BoxFuture<'_, Result<Vec<LogEvent>, String>> {
+        let args = json!({
+            "region": region,
+            "log_group_names": log_group_names,
+            "log_stream_name_prefix": log_stream_name_prefix,
+            "start": start,
+            "end": end,
+        });
+        let response =
+            self.backend
+                .filter_logs(region, log_group_names, log_stream_name_prefix, start, end);
+        self.record("filter_logs", args, response)
+    }
+
+    fn run_query(
+        &self,
+        region: Option<String>,
+        log_group_names: Vec<String>,
+        query: String,
+        start: i64,
+        end: i64,
+    ) -> BoxFuture<'_, Result<Vec<QueryRow>, String>> {
+        let args = json!({
+            "region": region,
+            "log_group_names": log_group_names,
+            "query": query,
+            "start": start,
+            "end": end,
+        });
+        let response = self
+            .backend
+            .run_query(region, log_group_names, query, start, end);
+        self.record("run_query", args, response)
+    }
+
+    fn start_live_tail(
+        &self,
+        region: Option<String>,
+        log_group_arns: Vec<String>,
+        log_stream_name_prefix: String,
+        filter_pattern: String,
+    ) -> BoxFuture<'_, Result<Box<dyn LiveTailSession>, String>> {
+        let args = json!({
+            "region": region,
+            "log_group_arns": log_group_arns,
+            "log_stream_name_prefix": log_stream_name_prefix,
+            "filter_pattern": filter_pattern,
+        });
+        let file = self.file.clone();
+        let response = self.backend.start_live_tail(
+            region,
+            log_group_arns,
+            log_stream_name_prefix,
+            filter_pattern,
+        );
+        async move {
+            let started = Instant::now();
+            let result = response.await;
+            let recorded = result.as_ref().map(|_| ()).map_err(String::clone);
+            write_entry(
+                &file,
+                "start_live_tail".to_string(),
+                args.clone(),
+                started,
+                &recorded,
+            );
+            result.map(|session| {
+                Box::new(RecordingTail {
+                    session,
+                    file,
+                    args,
+                }) as Box<dyn LiveTailSession>
+            })
+        }
+        .boxed()
+    }
+
+    fn change_log_group(
+        &self,
+        log_group: LogGroup,
+        change: GroupChange,
+    ) -> BoxFuture<'_, Result<(), String>> {
+        let args = json!({ "log_group": log_group.arn, "change": change });
+        self.record(
+            "change_log_group",
+            args,
+            self.backend.change_log_group(log_group, change),
+        )
+    }
+
+    fn fetch_metric_filters(
+        &self,
+        region: Option<String>,
+        log_group_name: String,
+    ) -> BoxFuture<'_, Result<Vec<MetricFilter>, String>> {
+        let args = json!({ "region": region, "log_group_name": log_group_name });
+        self.record(
+            "fetch_metric_filters",
+            args,
+            self.backend.fetch_metric_filters(region, log_group_name),
+        )
+    }
+
+    fn fetch_field_indexes(
+        &self,
+        region: Option<String>,
+        log_group_name: String,
+    ) -> BoxFuture<'_, Result<Vec<String>, String>> {
+        let args = json!({ "region": region, "log_group_name": log_group_name });
+        self.record(
+            "fetch_field_indexes",
+            args,
+            self.backend.fetch_field_indexes(region, log_group_name),
+        )
+    }
+
+    fn fetch_group_metadata(
+        &self,
+        region: Option<String>,
+        log_group_name: String,
+        log_group_arn: String,
+    ) -> BoxFuture<'_, Result<GroupMetadata, String>> {
+        let args = json!({
+            "region": region,
+            "log_group_name": log_group_name,
+            "log_group_arn": log_group_arn,
+        });
+        self.record(
+            "fetch_group_metadata",
+            args,
+            self.backend
+                .fetch_group_metadata(region, log_group_name, log_group_arn),
+        )
+    }
+
+    fn fetch_alarms(&self, region: Option<String>) -> BoxFuture<'_, Result<Vec<Alarm>, String>> {
+        let args = json!({ "region": region });
+        self.record("fetch_alarms", args, self.backend.fetch_alarms(region))
+    }
+
+    fn fetch_metric_filter_groups(
+        &self,
+        region: Option<String>,
+        metric_name: String,
+        namespace: String,
+    ) -> BoxFuture<'_, Result<Vec<String>, String>> {
+        let args = json!({ "region": region, "metric_name": metric_name, "namespace": namespace });
+        self.record(
+            "fetch_metric_filter_groups",
+            args,
+            self.backend
+                .fetch_metric_filter_groups(region, metric_name, namespace),
+        )
+    }
+
+    fn fetch_metric_alarms(
+        &self,
+        region: Option<String>,
+        metric_name: String,
+        namespace: String,
+    ) -> BoxFuture<'_, Result<Vec<Alarm>, String>> {
+        let args = json!({ "region": region, "metric_name": metric_name, "namespace": namespace });
+        self.record(
+            "fetch_metric_alarms",
+            args,
+            self.backend
+                .fetch_metric_alarms(region, metric_name, namespace),
+        )
+    }
+
+    fn fetch_unmasked(
+        &self,
+        region: Option<String>,
+        pointer: String,
+    ) -> BoxFuture<'_, Result<String, String>> {
+        let args = json!({ "region": region, "pointer": pointer });
+        self.record(
+            "fetch_unmasked",
+            args,
+            self.backend.fetch_unmasked(region, pointer),
+        )
+    }
+
+    fn test_metric_filter(
+        &self,
+        region: Option<String>,
+        pattern: String,
+        messages: Vec<String>,
+    ) -> BoxFuture<'_, Result<Vec<usize>, String>> {
+        let args = json!({ "region": region, "pattern": pattern, "messages": messages });
+        self.record(
+            "test_metric_filter",
+            args,
+            self.backend.test_metric_filter(region, pattern, messages),
+        )
+    }
+
+    fn fetch_log_record(
+        &self,
+        region: Option<String>,
+        pointer: String,
+    ) -> BoxFuture<'_, Result<QueryRow, String>> {
+        let args = json!({ "region": region, "pointer": pointer });
+        self.record(
+            "fetch_log_record",
+            args,
+            self.backend.fetch_log_record(region, pointer),
+        )
+    }
+
+    fn fetch_accounts(&self) -> BoxFuture<'_, Result<Vec<Account>, String>> {
+        self.record("fetch_accounts", json!({}), self.backend.fetch_accounts())
+    }
+
+    fn switch_account(&self, account: Account) -> BoxFuture<'_, Result<(), String>> {
+        let args = json!({ "account": account });
+        self.record("switch_account", args, self.backend.switch_account(account))
+    }
+
+    fn query_counts(&self) -> QueryCounts {
+        self.backend.query_counts()
+    }
+}
+
+/// Records every batch of a tail under the arguments it was started with
+struct RecordingTail {
+    session: Box<dyn LiveTailSession>,
+    file: Arc<Mutex<File>>,
+    args: Value,
+}
+
+impl LiveTailSession for RecordingTail {
+    fn next_events(&mut self) -> BoxFuture<'_, Result<Option<Vec<LogEvent>>, String>> {
+        async move {
+            let started = Instant::now();
+            let result = self.session.next_events().await;
+            let call = "live_tail_events".to_string();
+            write_entry(&self.file, call, self.args.clone(), started, &result);
+            result
+        }
+        .boxed()
+    }
+
+    fn is_sampled(&self) -> bool {
+        self.session.is_sampled()
+    }
+}
+
+/// Answers calls with the responses of a recording, for `--replay`. Each call gets the
+/// responses recorded for the same arguments in the order they were recorded, taking as long
+/// as they did, so a session plays out the same way again
+#[derive(Debug, Default)]
+pub struct ReplayBackend {
+    responses: Mutex<HashMap<String, VecDeque<Entry>>>,
+    regions: Vec<String>,
+}
+
+impl ReplayBackend {
+    pub fn load(path: &Path) -> Result<ReplayBackend, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("can't read {}: {}", path.display(), e))?;
+        let mut replay = ReplayBackend::default();
+        for (index, line) in contents.lines().enumerate() {
+            let entry: Entry = serde_json::from_str(line)
+                .map_err(|e| format!("{} line {}: {}", path.display(), index + 1, e))?;
+            if entry.call == "fetch_log_groups" {
+                if let Some(region) = entry.args["region"].as_str() {
+                    if !replay.regions.iter().any(|known| known == region) {
+                        replay.regions.push(region.to_string());
+                    }
+                }
+            }
+            replay
+                .responses
+                .get_mut()
+                .unwrap()
+                .entry(key(&entry.call, &entry.args))
+                .or_default()
+                .push_back(entry);
+        }
+        Ok(replay)
+    }
+
+    fn next_entry(&self, call: &str, args: &Value) -> Option<Entry> {
+        self.responses
+            .lock()
+            .unwrap()
+            .get_mut(&key(call, args))?
+            .pop_front()
+    }
+
+    fn replay<T: DeserializeOwned + Send + 'static>(
+        &self,
+        call: &str,
+        args: Value,
+    ) -> BoxFuture<'_, Result<T, String>> {
+        let entry = self.next_entry(call, &args);
+        let call = call.to_string();
+        async move {
+            match entry {
+                Some(entry) => play(entry).await,
+                None => Err(format!("nothing recorded for {} {}", call, args)),
+            }
+        }
+        .boxed()
+    }
+}
+
+/// The recorded response, once as long as the recorded call took has passed
+async fn play<T: DeserializeOwned>(entry: Entry) -> Result<T, String> {
+    tokio::time::sleep(Duration::from_millis(entry.took_ms)).await;
+    entry
+        .result
+        .and_then(|value| serde_json::from_value(value).map_err(|e| e.to_string()))
+}
+
+/// What a replayed call is matched to recorded ones by, the call and its arguments apart from
+/// the time range
+fn key(call: &str, args: &Value) -> String {
+    let mut args = args.clone();
+    if let Value::Object(args) = &mut args {
+        for name in TIME_ARGS {
+            args.remove(name);
+        }
+    }
+    format!("{} {}", call, args)
+}
+
+impl LogBackend for ReplayBackend {
+    fn regions(&self) -> Vec<String> {
+        self.regions.clone()
+    }
+
+    fn fetch_log_groups(
+        &self,
+        region: Option<String>,
+        next_token: Option<String>,
+    ) -> BoxFuture<'_, Result<LogGroupPage, String>> {
+        self.replay(
+            "fetch_log_groups",
+            json!({ "region": region, "next_token": next_token }),
+        )
+    }
+
+    fn fetch_logs(
+        &self,
+        region: Option<String>,
+        log_group_names: Vec<String>,
+        query: Option<String>,
+        _start: i64,
+        _end: i64,
+    ) -> BoxFuture<'_, Result<Vec<LogEvent>, String>> {
+        let args = json!({ "region": region, "log_group_names": log_group_names, "query": query });
+        self.replay("fetch_logs", args)
+    }
+
+    fn filter_logs(
+        &self,
+        region: Option<String>,
+        log_group_names: Vec<String>,
+        log_stream_name_prefix: String,
+        _start: i64,
+        _end: i64,
+    ) -> BoxFuture<'_, Result<Vec<LogEvent>, String>> {
+        let args = json!({
+            "region": region,
+            "log_group_names": log_group_names,
+            "log_stream_name_prefix": log_stream_name_prefix,
+        });
+        self.replay("filter_logs", args)
+    }
+
+    fn run_query(
+        &self,
+        region: Option<String>,
+        log_group_names: Vec<String>,
+        query: String,
+        _start: i64,
+        _end: i64,
+    ) -> BoxFuture<'_, Result<Vec<QueryRow>, String>> {
+        let args = json!({ "region": region, "log_group_names": log_group_names, "query": query });
+        self.replay("run_query", args)
+    }
+
+    fn start_live_tail(
+        &self,
+        region: Option<String>,
+        log_group_arns: Vec<String>,
+        log_stream_name_prefix: String,
+        filter_pattern: String,
+    ) -> BoxFuture<'_, Result<Box<dyn LiveTailSession>, String>> {
+        let args = json!({
+            "region": region,
+            "log_group_arns": log_group_arns,
+            "log_stream_name_prefix": log_stream_name_prefix,
+            "filter_pattern": filter_pattern,
+        });
+        let batches: VecDeque<Entry> = self
+            .responses
+            .lock()
+            .unwrap()
+            .remove(&key("live_tail_events", &args))
+            .unwrap_or_default();
+        self.replay::<()>("start_live_tail", args)
+            .map(|result| {
+                result.map(|()| Box::new(ReplayTail { batches }) as Box<dyn LiveTailSession>)
+            })
+            .boxed()
+    }
+
+    fn change_log_group(
+        &self,
+        log_group: LogGroup,
+        change: GroupChange,
+    ) -> BoxFuture<'_, Result<(), String>> {
+        self.replay(
+            "change_log_group",
+            json!({ "log_group": log_group.arn, "change": change }),
+        )
+    }
+
+    fn fetch_metric_filters(
+        &self,
+        region: Option<String>,
+        log_group_name: String,
+    ) -> BoxFuture<'_, Result<Vec<MetricFilter>, String>> {
+        self.replay(
+            "fetch_metric_filters",
+            json!({ "region": region, "log_group_name": log_group_name }),
+        )
+    }
+
+    fn fetch_field_indexes(
+        &self,
+        region: Option<String>,
+        log_group_name: String,
+    ) -> BoxFuture<'_, Result<Vec<String>, String>> {
+        self.replay(
+            "fetch_field_indexes",
+            json!({ "region": region, "log_group_name": log_group_name }),
+        )
+    }
+
+    fn fetch_group_metadata(
+        &self,
+        region: Option<String>,
+        log_group_name: String,
+        log_group_arn: String,
+    ) -> BoxFuture<'_, Result<GroupMetadata, String>> {
+        self.replay(
+            "fetch_group_metadata",
+            json!({
+                "region": region,
+                "log_group_name": log_group_name,
+                "log_group_arn": log_group_arn,
+            }),
+        )
+    }
+
+    fn fetch_alarms(&self, region: Option<String>) -> BoxFuture<'_, Result<Vec<Alarm>, String>> {
+        self.replay("fetch_alarms", json!({ "region": region }))
+    }
+
+    fn fetch_metric_filter_groups(
+        &self,
+        region: Option<String>,
+        metric_name: String,
+        namespace: String,
+    ) -> BoxFuture<'_, Result<Vec<String>, String>> {
+        self.replay(
+            "fetch_metric_filter_groups",
+            json!({ "region": region, "metric_name": metric_name, "namespace": namespace }),
+        )
+    }
+
+    fn fetch_metric_alarms(
+        &self,
+        region: Option<String>,
+        metric_name: String,
+        namespace: String,
+    ) -> BoxFuture<'_, Result<Vec<Alarm>, String>> {
+        self.replay(
+            "fetch_metric_alarms",
+            json!({ "region": region, "metric_name": metric_name, "namespace": namespace }),
+        )
+    }
+
+    fn fetch_unmasked(
+        &self,
+        region: Option<String>,
+        pointer: String,
+    ) -> BoxFuture<'_, Result<String, String>> {
+        self.replay(
+            "fetch_unmasked",
+            json!({ "region": region, "pointer": pointer }),
+        )
+    }
+
+    fn test_metric_filter(
+        &self,
+        region: Option<String>,
+        pattern: String,
+        messages: Vec<String>,
+    ) -> BoxFuture<'_, Result<Vec<usize>, String>> {
+        self.replay(
+            "test_metric_filter",
+            json!({ "region": region, "pattern": pattern, "messages": messages }),
+        )
+    }
+
+    fn fetch_log_record(
+        &self,
+        region: Option<String>,
+        pointer: String,
+    ) -> BoxFuture<'_, Result<QueryRow, String>> {
+        self.replay(
+            "fetch_log_record",
+            json!({ "region": region, "pointer": pointer }),
+        )
+    }
+
+    fn fetch_accounts(&self) -> BoxFuture<'_, Result<Vec<Account>, String>> {
+        self.replay("fetch_accounts", json!({}))
+    }
+
+    fn switch_account(&self, account: Account) -> BoxFuture<'_, Result<(), String>> {
+        self.replay("switch_account", json!({ "account": account }))
+    }
+
+    fn query_counts(&self) -> QueryCounts {
+        QueryCounts::default()
+    }
+}
+
+/// The recorded batches of a tail, it ends once they've all been played
+struct ReplayTail {
+    batches: VecDeque<Entry>,
+}
+
+impl LiveTailSession for ReplayTail {
+    fn next_events(&mut self) -> BoxFuture<'_, Result<Option<Vec<LogEvent>>, String>> {
+        let entry = self.batches.pop_front();
+        async move {
+            match entry {
+                Some(entry) => play(entry).await,
+                None => Ok(None),
+            }
+        }
+        .boxed()
+    }
+}
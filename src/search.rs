@@ -0,0 +1,155 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+};
+
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+
+/// Fuzzy matches scoring this or less are too scattered to be useful
+const MIN_SCORE: i64 = 5;
+
+/// How many candidates are matched between checks for a newer search
+const CHECK_EVERY: usize = 256;
+
+type Job = (u64, Box<dyn FnOnce(&Cancellation) + Send>);
+
+/// Runs searches on their own thread so typing never waits on matching,
+/// starting a search cancels whichever one is queued or still running
+#[derive(Clone)]
+pub struct SearchWorker {
+    jobs: mpsc::Sender<Job>,
+    generation: Arc<AtomicU64>,
+}
+
+impl fmt::Debug for SearchWorker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SearchWorker")
+            .field("generation", &self.generation())
+            .finish()
+    }
+}
+
+impl Default for SearchWorker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Handed to a running search, set once a newer search has been started
+pub struct Cancellation {
+    generation: u64,
+    current: Arc<AtomicU64>,
+}
+
+impl Cancellation {
+    /// One that's never cancelled, for running a search directly
+    pub fn never() -> Cancellation {
+        Cancellation {
+            generation: 0,
+            current: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.current.load(Ordering::Relaxed) != self.generation
+    }
+
+    /// The generation to tag results with
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+}
+
+impl SearchWorker {
+    /// The thread exits once every clone of the worker is dropped
+    pub fn new() -> Self {
+        let (jobs, rx) = mpsc::channel::<Job>();
+        let generation = Arc::new(AtomicU64::new(0));
+        let current = generation.clone();
+        thread::spawn(move || {
+            while let Ok(mut job) = rx.recv() {
+                // only the newest of a burst of keystrokes is worth matching
+                while let Ok(newer) = rx.try_recv() {
+                    job = newer;
+                }
+                let (generation, search) = job;
+                let cancellation = Cancellation {
+                    generation,
+                    current: current.clone(),
+                };
+                if !cancellation.is_cancelled() {
+                    search(&cancellation);
+                }
+            }
+        });
+        Self { jobs, generation }
+    }
+
+    /// Queues `search` and returns the generation its results belong to
+    pub fn spawn(&self, search: impl FnOnce(&Cancellation) + Send + 'static) -> u64 {
+        let generation = self.generation.fetch_add(1, Ordering::Relaxed) + 1;
+        let _ = self.jobs.send((generation, Box::new(search)));
+        generation
+    }
+
+    /// Stops the current search, its results will no longer match `generation`
+    pub fn cancel(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Results tagged with anything else are stale and should be dropped
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+}
+
+/// Fuzzy matches `term` against every candidate, best first, with the matched character
+/// positions. `None` when cancelled part way through
+pub fn fuzzy_rank<'a>(
+    term: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+    cancellation: &Cancellation,
+) -> Option<Vec<(usize, Vec<usize>)>> {
+    let matcher = SkimMatcherV2::default().ignore_case();
+    let mut matches = vec![];
+    for (index, candidate) in candidates.into_iter().enumerate() {
+        if index % CHECK_EVERY == 0 && cancellation.is_cancelled() {
+            return None;
+        }
+        if let Some((score, indices)) = matcher.fuzzy_indices(candidate, term) {
+            if score > MIN_SCORE {
+                matches.push((score, index, indices));
+            }
+        }
+    }
+    // stable, so equally good matches keep their original order
+    matches.sort_by_key(|(score, _, _)| std::cmp::Reverse(*score));
+    Some(
+        matches
+            .into_iter()
+            .map(|(_, index, indices)| (index, indices))
+            .collect(),
+    )
+}
+
+/// Indices of every candidate containing `term`. `None` when cancelled part way through
+pub fn find_all<'a>(
+    term: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+    cancellation: &Cancellation,
+) -> Option<Vec<usize>> {
+    let mut matches = vec![];
+    for (index, candidate) in candidates.into_iter().enumerate() {
+        if index % CHECK_EVERY == 0 && cancellation.is_cancelled() {
+            return None;
+        }
+        if candidate.contains(term) {
+            matches.push(index);
+        }
+    }
+    Some(matches)
+}
@@ -0,0 +1,66 @@
+use clap::ValueEnum;
+
+use crate::{config::Config, output::OutputFormat};
+
+/// How far back `loglog query` looks when nothing says otherwise
+pub const DEFAULT_SINCE: &str = "1h";
+
+/// Settings that can come from a flag, the config file or a `LOGLOG_*` variable.
+/// The first of those that is set wins
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+    pub region: Option<String>,
+    pub profile: Option<String>,
+    pub since: String,
+    pub query: Option<String>,
+    pub output: OutputFormat,
+}
+
+/// The settings given as flags, anything unset falls through to the config and environment
+#[derive(Debug, Clone, Default)]
+pub struct Flags {
+    pub region: Option<String>,
+    pub profile: Option<String>,
+    pub since: Option<String>,
+    pub query: Option<String>,
+    pub output: Option<OutputFormat>,
+}
+
+impl Settings {
+    /// `env` looks up a variable, `std::env::var` outside of tests
+    pub fn resolve(
+        flags: Flags,
+        config: &Config,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Settings, String> {
+        let env = |name: &str| env(name).filter(|value| !value.is_empty());
+        let output = match flags.output {
+            Some(output) => output,
+            None => match config.output.clone().or_else(|| env("LOGLOG_OUTPUT")) {
+                Some(output) => OutputFormat::from_str(&output, true)
+                    .map_err(|_| format!("invalid output format {}", output))?,
+                None => OutputFormat::default(),
+            },
+        };
+        Ok(Settings {
+            region: flags
+                .region
+                .or_else(|| config.region.clone())
+                .or_else(|| env("LOGLOG_REGION")),
+            profile: flags
+                .profile
+                .or_else(|| config.profile.clone())
+                .or_else(|| env("LOGLOG_PROFILE")),
+            since: flags
+                .since
+                .or_else(|| config.since.clone())
+                .or_else(|| env("LOGLOG_SINCE"))
+                .unwrap_or_else(|| DEFAULT_SINCE.to_string()),
+            query: flags
+                .query
+                .or_else(|| config.query.clone())
+                .or_else(|| env("LOGLOG_QUERY")),
+            output,
+        })
+    }
+}
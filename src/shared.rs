@@ -1,3 +1,7 @@
+use std::{sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum LoadingState {
     #[default]
@@ -6,3 +10,327 @@ pub enum LoadingState {
     Loaded,
     Error(String),
 }
+
+impl LoadingState {
+    /// Failed without reaching AWS, worth trying again once the network is back
+    pub fn is_offline(&self) -> bool {
+        matches!(self, LoadingState::Error(e) if is_offline(e))
+    }
+}
+
+/// Errors of requests that never reached AWS start with this, e.g. over a VPN that's gone
+pub const OFFLINE: &str = "you appear to be offline";
+
+/// Events a stream prefix fetch returns per group at most, paging stops once a group has this
+/// many
+pub const MAX_FILTERED_EVENTS: usize = 10_000;
+
+pub fn is_offline(error: &str) -> bool {
+    error.starts_with(OFFLINE)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogGroup {
+    pub name: String,
+    pub arn: String,
+    pub stored_bytes: Option<i64>,
+    pub retention_in_days: Option<i32>,
+    /// milliseconds since the epoch
+    pub creation_time: Option<i64>,
+    /// a data protection policy masks sensitive values in its events
+    pub data_protected: bool,
+}
+
+impl LogGroup {
+    /// The region from the arn, `arn:aws:logs:<region>:<account>:log-group:<name>`
+    pub fn region(&self) -> Option<&str> {
+        self.arn
+            .split(':')
+            .nth(3)
+            .filter(|region| !region.is_empty())
+    }
+
+    /// The account from the arn
+    pub fn account_id(&self) -> Option<&str> {
+        self.arn
+            .split(':')
+            .nth(4)
+            .filter(|account_id| !account_id.is_empty())
+    }
+
+    /// Roughly how many bytes a query over `start..end` scans, assuming the stored bytes are
+    /// spread evenly over the retention period, or over the group's lifetime if it never expires
+    pub fn estimated_scan_bytes(&self, start: i64, end: i64, now: i64) -> i64 {
+        let stored_bytes = self.stored_bytes.unwrap_or_default();
+        let retained_ms = match (self.retention_in_days, self.creation_time) {
+            (Some(days), _) => days as i64 * 24 * 3600 * 1000,
+            (None, Some(creation_time)) => now - creation_time,
+            (None, None) => return stored_bytes,
+        };
+        if retained_ms <= 0 {
+            return stored_bytes;
+        }
+        let fraction = ((end - start) as f64 / retained_ms as f64).clamp(0.0, 1.0);
+        (stored_bytes as f64 * fraction) as i64
+    }
+
+    /// When its oldest events are deleted, `now` once the group is older than its retention
+    /// as something expires every day from then on. `None` when it keeps everything
+    pub fn oldest_expiry(&self, now: i64) -> Option<i64> {
+        let retained_ms = self.retention_in_days? as i64 * 24 * 3600 * 1000;
+        let created = self.creation_time.unwrap_or(i64::MIN / 2);
+        Some((created + retained_ms).max(now))
+    }
+}
+
+/// A CloudWatch metric alarm
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Alarm {
+    pub name: String,
+    /// `None` for the region configured in the environment
+    pub region: Option<String>,
+    pub metric_name: Option<String>,
+    pub namespace: Option<String>,
+    /// `ALARM`, `OK` or `INSUFFICIENT_DATA`
+    pub state: String,
+    /// why it went into its state, as CloudWatch put it
+    pub reason: String,
+    /// when it went into its state, milliseconds since the epoch
+    pub state_updated: i64,
+}
+
+impl Alarm {
+    pub fn is_going_off(&self) -> bool {
+        self.state == "ALARM"
+    }
+}
+
+/// Credentials the AWS calls can be switched to without restarting
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Account {
+    /// a profile from the AWS config, whether it uses SSO, `credential_process` or keys
+    Profile(String),
+    /// a role in an account reached through an SSO session's login
+    Sso {
+        session: String,
+        account_id: String,
+        account_name: String,
+        role_name: String,
+    },
+}
+
+impl Account {
+    /// A line for the accounts popup
+    pub fn summary(&self) -> String {
+        match self {
+            Account::Profile(name) => format!("profile  {}", name),
+            Account::Sso {
+                session,
+                account_id,
+                account_name,
+                role_name,
+            } => format!(
+                "{}  {} ({})  {}",
+                session, account_name, account_id, role_name
+            ),
+        }
+    }
+}
+
+/// What listing the groups leaves out, fetched group by group for the rows on screen
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupMetadata {
+    /// when its newest event was logged, ms since the epoch. CloudWatch updates it lazily
+    pub last_event: Option<i64>,
+    /// sorted by key
+    pub tags: Vec<(String, String)>,
+}
+
+/// A metric filter on a group and the metric it publishes
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetricFilter {
+    pub name: String,
+    pub pattern: String,
+    pub metric_name: Option<String>,
+    pub namespace: Option<String>,
+}
+
+/// A group's metric filters, each with the alarms watching the metric it publishes
+pub type FilterAlarms = Vec<(MetricFilter, Vec<Alarm>)>;
+
+/// `bytes` in the largest unit that keeps it above one, e.g. `1.5 TB`
+pub fn format_bytes(bytes: i64) -> String {
+    let mut size = bytes as f64;
+    for unit in ["B", "KB", "MB", "GB"] {
+        if size < 1024.0 {
+            return format!("{:.1} {}", size, unit);
+        }
+        size /= 1024.0;
+    }
+    format!("{:.1} TB", size)
+}
+
+/// `count` with commas between the thousands, e.g. `56,789`
+pub fn format_count(count: usize) -> String {
+    let digits = count.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+/// `elapsed` to the second in its two largest units, e.g. `3m 05s`
+pub fn format_elapsed(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    match (seconds / 3600, seconds / 60 % 60, seconds % 60) {
+        (0, 0, seconds) => format!("{}s", seconds),
+        (0, minutes, seconds) => format!("{}m {:02}s", minutes, seconds),
+        (hours, minutes, _) => format!("{}h {:02}m", hours, minutes),
+    }
+}
+
+/// A query window of `ms` in the largest whole unit, e.g. `15m`, `24h` or `7d`
+pub fn format_window(ms: i64) -> String {
+    let minutes = ms / 60_000;
+    match (minutes % 60, minutes / 60) {
+        (0, hours) if hours > 24 && hours % 24 == 0 => format!("{}d", hours / 24),
+        (0, hours) => format!("{}h", hours),
+        _ => format!("{}m", minutes),
+    }
+}
+
+/// `start` to `end` in UTC, the date is left out of the end when it's the same day
+pub fn format_range(start: i64, end: i64) -> String {
+    let (Some(start), Some(end)) = (
+        chrono::DateTime::from_timestamp_millis(start),
+        chrono::DateTime::from_timestamp_millis(end),
+    ) else {
+        return String::new();
+    };
+    let end_format = if start.date_naive() == end.date_naive() {
+        "%H:%M"
+    } else {
+        "%Y-%m-%d %H:%M"
+    };
+    format!(
+        "{}–{} UTC",
+        start.format("%Y-%m-%d %H:%M"),
+        end.format(end_format)
+    )
+}
+
+/// Groups `log_groups` by region so each region can be queried with its own client
+pub fn by_region<'a>(
+    log_groups: impl IntoIterator<Item = &'a LogGroup>,
+) -> Vec<(Option<String>, Vec<&'a LogGroup>)> {
+    let mut regions: Vec<(Option<String>, Vec<&LogGroup>)> = vec![];
+    for group in log_groups {
+        let region = group.region().map(str::to_string);
+        match regions.iter_mut().find(|(r, _)| *r == region) {
+            Some((_, groups)) => groups.push(group),
+            None => regions.push((region, vec![group])),
+        }
+    }
+    regions
+}
+
+/// Logs Insights queries started and not finished yet, CloudWatch queues them when the account
+/// already runs as many as its quota allows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryCounts {
+    pub queued: usize,
+    pub running: usize,
+}
+
+/// A short message for the status bar, e.g. the outcome of a bulk change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub message: String,
+    pub is_error: bool,
+}
+
+/// One page of `describe_log_groups` results
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogGroupPage {
+    pub log_groups: Vec<LogGroup>,
+    pub next_token: Option<String>,
+}
+
+/// One Logs Insights result, field names and values in the order the query produced them
+pub type QueryRow = Vec<(String, String)>;
+
+/// The strings are shared, so the table, the filtered rows, searches and the detail view
+/// hand out the same copy instead of cloning every message
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogEvent {
+    pub timestamp: i64,
+    pub message: Arc<str>,
+    pub log_group: Arc<str>,
+    pub log_stream: Arc<str>,
+    /// the Logs Insights `@ptr`, lets the event be fetched again, e.g. unmasked
+    pub pointer: Option<Arc<str>>,
+    /// when CloudWatch received the event, `timestamp` is when it says it happened
+    pub ingestion_time: Option<i64>,
+    /// only set for events found by filtering, queries identify them by `pointer`
+    pub event_id: Option<Arc<str>>,
+    /// the message as logged when it wrapped `message`, e.g. in a Fluent Bit record
+    pub envelope: Option<Arc<str>>,
+    /// fields picked for the query that have no column, shown before the message
+    #[serde(default)]
+    pub fields: Vec<(Arc<str>, Arc<str>)>,
+}
+
+impl LogEvent {
+    /// Compares `@ptr`s when both events have one, otherwise when, where and what they logged
+    pub fn is_same(&self, other: &LogEvent) -> bool {
+        match (&self.pointer, &other.pointer) {
+            (Some(pointer), Some(other)) => pointer == other,
+            _ => {
+                self.timestamp == other.timestamp
+                    && self.log_stream == other.log_stream
+                    && self.message == other.message
+            }
+        }
+    }
+}
+
+/// Drops repeats of the same event, e.g. returned by two overlapping queries.
+/// `events` must be sorted by timestamp
+pub fn dedupe(events: &mut Vec<LogEvent>) {
+    let mut kept: Vec<LogEvent> = Vec::with_capacity(events.len());
+    // repeats share a timestamp, only the events since it changed need comparing
+    let mut same_time = 0;
+    for event in std::mem::take(events) {
+        if kept
+            .last()
+            .is_some_and(|last| last.timestamp != event.timestamp)
+        {
+            same_time = kept.len();
+        }
+        if !kept[same_time..].iter().any(|kept| kept.is_same(&event)) {
+            kept.push(event);
+        }
+    }
+    *events = kept;
+}
+
+/// What a component does with keys. Esc always steps back to `Normal` first, only an Esc
+/// in `Normal` reaches the app, which leaves the screen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InputMode {
+    #[default]
+    Normal,
+    /// a search is active, Esc ends it
+    Search,
+    /// typing into an input or answering a prompt or popup, every key goes to it
+    Command,
+}
+
+/// Data protection replaces every masked value with a run of asterisks
+pub fn has_masked_values(message: &str) -> bool {
+    message.contains("****")
+}
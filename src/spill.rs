@@ -0,0 +1,265 @@
+use std::{
+    cell::RefCell,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::shared::LogEvent;
+
+/// Most events written to disk at once, and read back together when scrolled to
+const CHUNK_EVENTS: usize = 10_000;
+
+type SpilledEvent = (
+    i64,
+    String,
+    String,
+    String,
+    Option<String>,
+    Option<i64>,
+    Option<String>,
+    Option<String>,
+    Vec<(String, String)>,
+);
+
+/// Tells the spill files of several viewers apart
+static NEXT_FILE: AtomicU64 = AtomicU64::new(0);
+
+/// Events in chronological order, once there are more than `cap` the oldest are moved out
+/// to a temp file and read back a chunk at a time when something asks for them
+#[derive(Debug, Default)]
+pub struct EventStore {
+    /// 0 keeps everything in memory
+    cap: usize,
+    /// the newest events, everything older is in `spill`
+    memory: Vec<LogEvent>,
+    spill: Option<SpillFile>,
+    /// the spilled chunk read back last, scrolling mostly stays within one
+    paged: RefCell<Option<(usize, Vec<LogEvent>)>>,
+}
+
+#[derive(Debug)]
+struct SpillFile {
+    path: PathBuf,
+    file: File,
+    chunks: Vec<Chunk>,
+    /// events across every chunk
+    len: usize,
+}
+
+#[derive(Debug)]
+struct Chunk {
+    /// index of the chunk's first event
+    start: usize,
+    offset: u64,
+    bytes: usize,
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl EventStore {
+    pub fn set_cap(&mut self, cap: usize) {
+        self.cap = cap;
+        self.spill_excess();
+    }
+
+    pub fn len(&self) -> usize {
+        self.spilled() + self.memory.len()
+    }
+
+    /// Roughly how much memory the events kept in memory take, shared strings are counted
+    /// for every event holding them
+    pub fn memory_bytes(&self) -> usize {
+        self.memory
+            .iter()
+            .map(|event| {
+                let optional = [&event.pointer, &event.event_id, &event.envelope]
+                    .into_iter()
+                    .flatten()
+                    .map(|value| value.len())
+                    .sum::<usize>();
+                std::mem::size_of::<LogEvent>()
+                    + event.message.len()
+                    + event.log_group.len()
+                    + event.log_stream.len()
+                    + optional
+            })
+            .sum()
+    }
+
+    /// How many of the oldest events live on disk
+    pub fn spilled(&self) -> usize {
+        self.spill.as_ref().map_or(0, |spill| spill.len)
+    }
+
+    /// The event at `index`, `None` when out of range or its chunk couldn't be read back
+    pub fn get(&self, index: usize) -> Option<LogEvent> {
+        let spilled = self.spilled();
+        if index >= spilled {
+            return self.memory.get(index - spilled).cloned();
+        }
+        let spill = self.spill.as_ref()?;
+        let chunk = spill.chunks.partition_point(|chunk| chunk.start <= index) - 1;
+        let mut paged = self.paged.borrow_mut();
+        if paged.as_ref().is_none_or(|(paged, _)| *paged != chunk) {
+            *paged = Some((chunk, spill.read(&spill.chunks[chunk]).ok()?));
+        }
+        let (_, events) = paged.as_ref()?;
+        events.get(index - spill.chunks[chunk].start).cloned()
+    }
+
+    /// The newest event, always in memory
+    pub fn last(&self) -> Option<&LogEvent> {
+        self.memory.last()
+    }
+
+    /// Every event oldest first, spilled chunks are read back one after another
+    pub fn iter(&self) -> impl Iterator<Item = LogEvent> + '_ {
+        (0..self.len()).filter_map(|index| self.get(index))
+    }
+
+    /// Adds events that are all newer than the ones already stored
+    pub fn extend(&mut self, events: Vec<LogEvent>) {
+        self.memory.extend(events);
+        self.spill_excess();
+    }
+
+    /// Adds events in any order, only the ones still in memory are sorted so anything
+    /// older than what was spilled ends up right after it
+    pub fn extend_unordered(&mut self, events: Vec<LogEvent>) {
+        self.memory.extend(events);
+        self.memory.sort_by_key(|event| event.timestamp);
+        self.spill_excess();
+    }
+
+    pub fn clear(&mut self) {
+        self.memory.clear();
+        self.spill = None;
+        *self.paged.get_mut() = None;
+    }
+
+    /// Moves the oldest events to disk until at most `cap` are left in memory.
+    /// When the temp file can't be written they simply stay in memory
+    fn spill_excess(&mut self) {
+        if self.cap == 0 {
+            return;
+        }
+        let chunk_events = (self.cap / 2).clamp(1, CHUNK_EVENTS);
+        while self.memory.len() > self.cap {
+            if self.spill.is_none() {
+                match SpillFile::create() {
+                    Ok(spill) => self.spill = Some(spill),
+                    Err(_) => return,
+                }
+            }
+            let Some(spill) = &mut self.spill else {
+                return;
+            };
+            let count = chunk_events.min(self.memory.len());
+            if spill.write(&self.memory[..count]).is_err() {
+                return;
+            }
+            self.memory.drain(..count);
+        }
+    }
+}
+
+impl SpillFile {
+    fn create() -> io::Result<SpillFile> {
+        let path = std::env::temp_dir().join(format!(
+            "loglog-{}-{}.events",
+            std::process::id(),
+            NEXT_FILE.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        Ok(SpillFile {
+            path,
+            file,
+            chunks: vec![],
+            len: 0,
+        })
+    }
+
+    /// Appends `events` as a json array of
+    /// `[timestamp, group, stream, message, pointer, ingestion time, event id, envelope, fields]`
+    fn write(&mut self, events: &[LogEvent]) -> io::Result<()> {
+        let rows = events
+            .iter()
+            .map(|event| {
+                (
+                    event.timestamp,
+                    &*event.log_group,
+                    &*event.log_stream,
+                    &*event.message,
+                    event.pointer.as_deref(),
+                    event.ingestion_time,
+                    event.event_id.as_deref(),
+                    event.envelope.as_deref(),
+                    event
+                        .fields
+                        .iter()
+                        .map(|(name, value)| (&**name, &**value))
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let bytes = serde_json::to_vec(&rows)?;
+        let offset = self.file.seek(SeekFrom::End(0))?;
+        self.file.write_all(&bytes)?;
+        self.chunks.push(Chunk {
+            start: self.len,
+            offset,
+            bytes: bytes.len(),
+        });
+        self.len += events.len();
+        Ok(())
+    }
+
+    fn read(&self, chunk: &Chunk) -> io::Result<Vec<LogEvent>> {
+        let mut file = &self.file;
+        let mut bytes = vec![0; chunk.bytes];
+        file.seek(SeekFrom::Start(chunk.offset))?;
+        file.read_exact(&mut bytes)?;
+        let rows: Vec<SpilledEvent> = serde_json::from_slice(&bytes)?;
+        Ok(rows
+            .into_iter()
+            .map(
+                |(
+                    timestamp,
+                    log_group,
+                    log_stream,
+                    message,
+                    pointer,
+                    ingestion_time,
+                    event_id,
+                    envelope,
+                    fields,
+                )| {
+                    LogEvent {
+                        timestamp,
+                        message: message.into(),
+                        log_group: log_group.into(),
+                        log_stream: log_stream.into(),
+                        pointer: pointer.map(Into::into),
+                        ingestion_time,
+                        event_id: event_id.map(Into::into),
+                        envelope: envelope.map(Into::into),
+                        fields: fields
+                            .into_iter()
+                            .map(|(name, value)| (name.into(), value.into()))
+                            .collect(),
+                    }
+                },
+            )
+            .collect())
+    }
+}
@@ -0,0 +1,213 @@
+use std::{io::ErrorKind, path::PathBuf};
+
+use aws_config::{
+    sso::{SsoCredentialsProvider, SsoTokenProvider},
+    Region,
+};
+use aws_credential_types::provider::{token::ProvideToken, SharedCredentialsProvider};
+use futures::{stream, StreamExt, TryStreamExt};
+use tracing::instrument;
+
+use crate::{aws::error_message, shared::Account};
+
+/// How many accounts have their roles listed at once, large organisations have hundreds
+const ROLE_LOOKUPS: usize = 8;
+
+/// A `[sso-session name]` section of the AWS config
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SsoSession {
+    pub name: String,
+    pub start_url: String,
+    pub region: String,
+}
+
+/// The profiles and SSO sessions the AWS config sets up
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct AwsConfigFile {
+    pub profiles: Vec<String>,
+    pub sso_sessions: Vec<SsoSession>,
+}
+
+impl AwsConfigFile {
+    /// Reads `AWS_CONFIG_FILE` or `~/.aws/config`, nothing is set up when there's neither
+    pub fn load() -> Result<AwsConfigFile, String> {
+        let Some(path) = config_path() else {
+            return Ok(AwsConfigFile::default());
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Ok(AwsConfigFile::parse(&text)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(AwsConfigFile::default()),
+            Err(e) => Err(format!("can't read {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn parse(text: &str) -> AwsConfigFile {
+        let mut file = AwsConfigFile::default();
+        for (header, keys) in sections(text) {
+            let value = |key: &str| {
+                keys.iter()
+                    .find(|(name, _)| name == key)
+                    .map(|(_, value)| value.clone())
+            };
+            if header == "default" {
+                file.profiles.push(header);
+            } else if let Some(name) = header.strip_prefix("profile ") {
+                file.profiles.push(name.trim().to_string());
+            } else if let Some(name) = header.strip_prefix("sso-session ") {
+                // a session without both can't be logged into
+                if let (Some(start_url), Some(region)) =
+                    (value("sso_start_url"), value("sso_region"))
+                {
+                    file.sso_sessions.push(SsoSession {
+                        name: name.trim().to_string(),
+                        start_url,
+                        region,
+                    });
+                }
+            }
+        }
+        file
+    }
+
+    pub fn sso_session(&self, name: &str) -> Option<&SsoSession> {
+        self.sso_sessions
+            .iter()
+            .find(|session| session.name == name)
+    }
+}
+
+fn config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("AWS_CONFIG_FILE") {
+        return Some(PathBuf::from(path));
+    }
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(".aws").join("config"))
+}
+
+/// Each `[header]` with its `key = value` lines, comments and nested values left out
+fn sections(text: &str) -> Vec<(String, Vec<(String, String)>)> {
+    let mut sections: Vec<(String, Vec<(String, String)>)> = vec![];
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            continue;
+        }
+        if let Some(header) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            sections.push((header.trim().to_string(), vec![]));
+        } else if let (Some((key, value)), Some((_, keys))) =
+            (line.split_once('='), sections.last_mut())
+        {
+            keys.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    sections
+}
+
+/// Every role in every account the session's login reaches, fails when it isn't logged in
+#[instrument(level = "debug", skip_all, fields(session = %session.name), err)]
+pub async fn list_accounts(session: &SsoSession) -> Result<Vec<Account>, String> {
+    let region = Region::new(session.region.clone());
+    let sdk_config = aws_config::from_env().region(region.clone()).load().await;
+    let token = SsoTokenProvider::builder()
+        .configure(&sdk_config)
+        .session_name(&session.name)
+        .start_url(&session.start_url)
+        .region(region)
+        .build()
+        .await
+        .provide_token()
+        .await
+        .map_err(|e| {
+            format!(
+                "{} isn't logged in, run aws sso login --sso-session {}: {}",
+                session.name, session.name, e
+            )
+        })?;
+    let client = aws_sdk_sso::Client::new(&sdk_config);
+    let mut accounts = vec![];
+    let mut next_token = None;
+    loop {
+        let response = client
+            .list_accounts()
+            .access_token(token.token())
+            .set_next_token(next_token)
+            .send()
+            .await
+            .map_err(error_message)?;
+        accounts.extend(response.account_list().iter().filter_map(|account| {
+            Some((
+                account.account_id()?.to_string(),
+                account.account_name().unwrap_or_default().to_string(),
+            ))
+        }));
+        next_token = response.next_token().map(str::to_string);
+        if next_token.is_none() {
+            break;
+        }
+    }
+    let roles = stream::iter(accounts)
+        .map(|(account_id, account_name)| {
+            list_account_roles(&client, token.token(), session, account_id, account_name)
+        })
+        .buffered(ROLE_LOOKUPS)
+        .try_collect::<Vec<_>>()
+        .await?;
+    Ok(roles.into_iter().flatten().collect())
+}
+
+async fn list_account_roles(
+    client: &aws_sdk_sso::Client,
+    token: &str,
+    session: &SsoSession,
+    account_id: String,
+    account_name: String,
+) -> Result<Vec<Account>, String> {
+    let mut roles = vec![];
+    let mut next_token = None;
+    loop {
+        let response = client
+            .list_account_roles()
+            .access_token(token)
+            .account_id(&account_id)
+            .set_next_token(next_token)
+            .send()
+            .await
+            .map_err(error_message)?;
+        roles.extend(
+            response
+                .role_list()
+                .iter()
+                .filter_map(|role| role.role_name())
+                .map(|role_name| Account::Sso {
+                    session: session.name.clone(),
+                    account_id: account_id.clone(),
+                    account_name: account_name.clone(),
+                    role_name: role_name.to_string(),
+                }),
+        );
+        next_token = response.next_token().map(str::to_string);
+        if next_token.is_none() {
+            return Ok(roles);
+        }
+    }
+}
+
+/// Credentials for `role_name` in `account_id`, fetched with the session's login when first used
+pub fn credentials(
+    session: &SsoSession,
+    account_id: &str,
+    role_name: &str,
+) -> SharedCredentialsProvider {
+    SharedCredentialsProvider::new(
+        SsoCredentialsProvider::builder()
+            .session_name(&session.name)
+            .start_url(&session.start_url)
+            .region(Region::new(session.region.clone()))
+            .account_id(account_id)
+            .role_name(role_name)
+            .build(),
+    )
+}
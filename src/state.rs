@@ -0,0 +1,51 @@
+use std::{fs, io, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::paths::{self, Dir};
+
+/// What is remembered between runs
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct State {
+    /// names of the groups opened most recently
+    pub last_log_groups: Vec<String>,
+    /// names of groups hidden from the list with `x`
+    pub hidden_log_groups: Vec<String>,
+}
+
+/// Loads and saves `State` as json, a store without a path remembers nothing
+#[derive(Debug, Clone, Default)]
+pub struct StateStore {
+    path: Option<PathBuf>,
+}
+
+impl StateStore {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self { path }
+    }
+
+    /// `state.json` in the platform's state directory
+    pub fn default_path() -> Option<PathBuf> {
+        Some(paths::dir(Dir::State)?.join("state.json"))
+    }
+
+    /// A missing or unreadable file is treated as a fresh start
+    pub fn load(&self) -> State {
+        self.path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, state: &State) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(state)?)
+    }
+}
@@ -1,21 +1,524 @@
-use std::cmp::{max, min};
+use std::{
+    cmp::{max, min},
+    collections::{HashMap, HashSet, VecDeque},
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
-    style::{Color, Style},
-    widgets::{Block, Borders, Widget},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::Widget,
+};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::{
+    config::MessageTimestamps,
+    level::{detect_level, LogLevel},
+    pattern::Pattern,
+    shared::{dedupe, format_count, has_masked_values, LogEvent},
+    spill::EventStore,
+    theme::Theme,
 };
 
-#[derive(Debug, Clone)]
+/// Messages over this are giant payloads, rows only show the start of them
+const LARGE_MESSAGE_BYTES: usize = 16 * 1024;
+
+/// How much of a large message the row shows, more than any terminal is wide
+const LARGE_MESSAGE_PREVIEW_BYTES: usize = 512;
+
+/// Cut lines end at the last space when it's at most this many cells from the edge, further
+/// away more of the line is kept by cutting mid-word
+const WORD_BREAK_CELLS: usize = 16;
+
+/// How long rows of events that just arrived stay highlighted
+const FRESH_FOR: Duration = Duration::from_secs(3);
+
+#[derive(Debug)]
 pub struct Table {
     y: usize,
-    pub data: Vec<String>,
+    /// always by `@timestamp`, however the rows are ordered
+    data: EventStore,
+    /// indices into `data` of the events matching `filter`, in `order`
+    visible: Vec<usize>,
+    order: RowOrder,
+    filter: Option<(String, Arc<Pattern>)>,
+    /// prefix every row with a colored group/stream tag, used when several groups are merged
+    pub show_tags: bool,
+    /// events a metric filter was tried on, drawn by whether it matched them
+    highlight: Option<Highlight>,
+    /// groups with a data protection policy, their masked events are marked
+    pub protected_groups: HashSet<Arc<str>>,
+    /// the `*` search term, colored wherever it appears in a row
+    pub search: Option<String>,
+    /// messages fetched again with masked values revealed, by `@ptr`
+    unmasked: HashMap<Arc<str>, Arc<str>>,
+    /// character column of the cursor within the selected message
+    cursor: usize,
+    /// multi-line events shown in full, keyed by `event_key`
+    expanded: HashSet<u64>,
+    /// events bookmarked for the investigation, keyed by `event_key` and marked in the gutter
+    bookmarked: HashSet<u64>,
+    /// mark events logged before the one above them, off when a query picked the order
+    pub check_order: bool,
+    /// events are ordered by the time in their message where there's one
+    pub message_timestamps: MessageTimestamps,
+    /// how many events are older than the one before them
+    out_of_order: usize,
+    /// when recent batches arrived with the index of their first event, oldest first.
+    /// Their rows are highlighted until the highlight has faded
+    fresh: VecDeque<(Instant, usize)>,
+    pub theme: Theme,
+}
+
+/// How the rows are shown, `data` stays by `@timestamp` so lookups and appends can search it
+#[derive(Debug, Default)]
+enum RowOrder {
+    #[default]
+    Timestamp,
+    /// by when each event says it happened, see `Table::event_time`
+    MessageTime,
+    /// as the query sorted its results, by `event_key`. Events it didn't return, e.g. tailed
+    /// ones, come after them
+    Query(HashMap<u64, usize>),
+}
+
+/// Every event from `from` on was tested, `matches` holds the indices of the ones that matched
+#[derive(Debug, Clone, Default)]
+pub struct Highlight {
+    pub from: usize,
+    pub matches: HashSet<usize>,
 }
 
 impl Table {
-    pub fn new(data: Vec<String>) -> Self {
-        Self { y: 0, data }
+    pub fn new(data: Vec<LogEvent>) -> Self {
+        let mut table = Self {
+            y: 0,
+            data: EventStore::default(),
+            visible: vec![],
+            order: RowOrder::Timestamp,
+            filter: None,
+            highlight: None,
+            protected_groups: HashSet::new(),
+            search: None,
+            unmasked: HashMap::new(),
+            show_tags: false,
+            cursor: 0,
+            expanded: HashSet::new(),
+            bookmarked: HashSet::new(),
+            check_order: true,
+            message_timestamps: MessageTimestamps::default(),
+            out_of_order: 0,
+            fresh: VecDeque::new(),
+            theme: Theme::default(),
+        };
+        table.set_data(data);
+        table
+    }
+
+    /// Replaces every event. A selection scrolled away from the newest event stays on the
+    /// same event, or the closest one after it when it's gone. Events a query sorted some other
+    /// way than by time keep that order, the selection is left where it is
+    pub fn set_data(&mut self, mut data: Vec<LogEvent>) {
+        let chronological = data.is_sorted_by_key(|event| event.timestamp);
+        let anchor = match self.y {
+            0 => None,
+            _ if !chronological => None,
+            _ => self
+                .selected_position()
+                .and_then(|index| self.data.get(index)),
+        };
+        self.order = if chronological {
+            RowOrder::Timestamp
+        } else {
+            let ranks = data
+                .iter()
+                .enumerate()
+                .map(|(rank, event)| {
+                    (
+                        event_key(event.timestamp, &event.log_stream, &event.message),
+                        rank,
+                    )
+                })
+                .collect();
+            data.sort_by_key(|event| event.timestamp);
+            RowOrder::Query(ranks)
+        };
+        self.highlight = None;
+        self.fresh.clear();
+        self.out_of_order = self.count_out_of_order(None, &data);
+        self.data.clear();
+        self.data.extend(data);
+        self.refresh_visible();
+        if let Some(anchor) = anchor {
+            self.anchor_at(&anchor);
+        }
+    }
+
+    /// When the event says it happened, the time in its message or else `@timestamp`
+    fn event_time(&self, event: &LogEvent) -> i64 {
+        self.message_timestamps
+            .extract(&event.log_group, &event.message)
+            .unwrap_or(event.timestamp)
+    }
+
+    /// Events in `data` older than the one before them, `previous` is the event time of the
+    /// one before the first
+    fn count_out_of_order(&self, previous: Option<i64>, data: &[LogEvent]) -> usize {
+        let mut previous = previous.unwrap_or(i64::MIN);
+        let mut count = 0;
+        for event in data {
+            let time = self.event_time(event);
+            if time < previous {
+                count += 1;
+            }
+            previous = time;
+        }
+        count
+    }
+
+    /// Where the event's row goes when the rows aren't by `@timestamp`
+    fn row_key(&self, event: &LogEvent) -> i64 {
+        match &self.order {
+            RowOrder::Timestamp => event.timestamp,
+            RowOrder::MessageTime => self.event_time(event),
+            RowOrder::Query(ranks) => ranks
+                .get(&event_key(
+                    event.timestamp,
+                    &event.log_stream,
+                    &event.message,
+                ))
+                .map_or(i64::MAX, |&rank| rank as i64),
+        }
+    }
+
+    /// Whether the rows are by `@timestamp`, the order clock problems show up in
+    fn by_timestamp(&self) -> bool {
+        self.check_order && matches!(self.order, RowOrder::Timestamp)
+    }
+
+    /// Events logged before the one shown above them, e.g. from streams whose clocks
+    /// disagree. Always 0 while `check_order` is off or the rows are sorted some other way
+    pub fn out_of_order(&self) -> usize {
+        if self.by_timestamp() {
+            self.out_of_order
+        } else {
+            0
+        }
+    }
+
+    fn is_out_of_order(&self, index: usize) -> bool {
+        if !self.by_timestamp() || self.out_of_order == 0 || index == 0 {
+            return false;
+        }
+        match (self.data.get(index - 1), self.data.get(index)) {
+            (Some(previous), Some(event)) => self.event_time(&event) < self.event_time(&previous),
+            _ => false,
+        }
+    }
+
+    /// Shows the events in the order they were logged, the selection stays on its event.
+    /// Events appended later are put in that order too
+    pub fn sort_by_time(&mut self) {
+        let selected = self.selected_position();
+        self.order = RowOrder::MessageTime;
+        self.refresh_visible();
+        self.reselect(selected);
+    }
+
+    fn anchor_at(&mut self, anchor: &LogEvent) {
+        let mut index = self.partition_point(|event| event.timestamp < anchor.timestamp);
+        while let Some(event) = self.data.get(index) {
+            if event.timestamp != anchor.timestamp || event.is_same(anchor) {
+                break;
+            }
+            index += 1;
+        }
+        if self
+            .data
+            .get(index)
+            .is_none_or(|event| !event.is_same(anchor))
+        {
+            // gone, the first event after it keeps the same spot in time
+            index = self.partition_point(|event| event.timestamp <= anchor.timestamp);
+        }
+        let position = self.visible.partition_point(|&visible| visible < index);
+        self.y = self.visible.len().saturating_sub(position + 1);
+    }
+
+    fn contains(&self, event: &LogEvent) -> bool {
+        let start = self.partition_point(|stored| stored.timestamp < event.timestamp);
+        (start..self.data.len())
+            .map_while(|index| self.data.get(index))
+            .take_while(|stored| stored.timestamp == event.timestamp)
+            .any(|stored| stored.is_same(event))
+    }
+
+    /// Index of the first event for which `before` is false, events are in chronological order
+    fn partition_point(&self, before: impl Fn(&LogEvent) -> bool) -> usize {
+        let (mut low, mut high) = (0, self.data.len());
+        while low < high {
+            let middle = low + (high - low) / 2;
+            match self.data.get(middle) {
+                Some(event) if before(&event) => low = middle + 1,
+                _ => high = middle,
+            }
+        }
+        low
+    }
+
+    /// An empty table keeping at most `events` in memory and older ones in a temp file,
+    /// 0 keeps them all
+    pub fn with_memory_cap(events: usize) -> Self {
+        let mut table = Table::new(vec![]);
+        table.set_memory_cap(events);
+        table
+    }
+
+    pub fn set_memory_cap(&mut self, events: usize) {
+        self.data.set_cap(events);
+    }
+
+    /// Adds events and keeps everything in chronological order, events already shown are
+    /// left out
+    pub fn append(&mut self, mut data: Vec<LogEvent>) {
+        let newest = self.data.last().map(|event| event.timestamp);
+        data.sort_by_key(|event| event.timestamp);
+        dedupe(&mut data);
+        // only events no newer than what's stored can already be there
+        data.retain(|event| {
+            newest.is_none_or(|newest| event.timestamp > newest) || !self.contains(event)
+        });
+        let in_order = data
+            .iter()
+            .try_fold(newest.unwrap_or(i64::MIN), |previous, event| {
+                (event.timestamp >= previous).then_some(event.timestamp)
+            })
+            .is_some();
+        if !in_order {
+            // indices move around, whatever was highlighted no longer lines up
+            self.highlight = None;
+            self.fresh.clear();
+            self.data.extend_unordered(data);
+            // anything older than what was spilled lands after it, this is rare enough to recount
+            let all = self.data.iter().collect::<Vec<_>>();
+            self.out_of_order = self.count_out_of_order(None, &all);
+            return self.refresh_visible();
+        }
+        let previous = self.data.last().map(|event| self.event_time(event));
+        self.out_of_order += self.count_out_of_order(previous, &data);
+        // the usual tail batch lands after everything else, only the new events need matching
+        let start = self.data.len();
+        if !data.is_empty() {
+            self.mark_fresh(start);
+        }
+        let filter = self.filter.as_ref().map(|(_, pattern)| pattern);
+        let last_row = self.visible.len().saturating_sub(1);
+        self.visible.extend(
+            data.iter()
+                .enumerate()
+                .filter(|(_, event)| filter.is_none_or(|pattern| pattern.matches(&event.message)))
+                .map(|(index, _)| start + index),
+        );
+        self.data.extend(data);
+        // rows sorted some other way only need sorting again when the new ones don't go last
+        if !matches!(self.order, RowOrder::Timestamp)
+            && !self.visible[last_row..]
+                .is_sorted_by_key(|&index| self.data.get(index).map(|event| self.row_key(&event)))
+        {
+            self.refresh_visible();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.order = RowOrder::Timestamp;
+        self.out_of_order = 0;
+        self.highlight = None;
+        self.fresh.clear();
+        self.unmasked.clear();
+        self.expanded.clear();
+        self.filter = None;
+        self.y = 0;
+        self.refresh_visible();
+    }
+
+    /// Highlights the events from index `from` on as just arrived
+    pub fn mark_fresh(&mut self, from: usize) {
+        while self
+            .fresh
+            .front()
+            .is_some_and(|(arrived, _)| arrived.elapsed() >= FRESH_FOR)
+        {
+            self.fresh.pop_front();
+        }
+        self.fresh.push_back((Instant::now(), from));
+    }
+
+    /// Whether any row is still highlighted as just arrived, it needs redrawing as it fades
+    pub fn is_fading(&self) -> bool {
+        self.fresh
+            .back()
+            .is_some_and(|(arrived, _)| arrived.elapsed() < FRESH_FOR)
+    }
+
+    /// The background of the event at `index` while its highlight fades
+    fn fresh_color(&self, index: usize) -> Option<Color> {
+        let (arrived, _) = self.fresh.iter().rev().find(|(_, from)| *from <= index)?;
+        let step = (arrived.elapsed().as_secs_f64() / FRESH_FOR.as_secs_f64()
+            * self.theme.fresh.len() as f64) as usize;
+        self.theme.fresh.get(step).copied()
+    }
+
+    /// Everything loaded, including events hidden by the filter, spilled ones are read back
+    pub fn events(&self) -> impl Iterator<Item = LogEvent> + '_ {
+        self.data.iter().map(|event| self.with_unmasked(event))
+    }
+
+    /// The event at `index` into `events()`
+    pub fn event(&self, index: usize) -> Option<LogEvent> {
+        self.data.get(index).map(|event| self.with_unmasked(event))
+    }
+
+    /// Shows `message` for the event behind `pointer` from now on
+    pub fn set_unmasked(&mut self, pointer: Arc<str>, message: Arc<str>) {
+        self.unmasked.insert(pointer, message);
+    }
+
+    fn with_unmasked(&self, mut event: LogEvent) -> LogEvent {
+        if let Some(message) = event.pointer.as_ref().and_then(|p| self.unmasked.get(p)) {
+            event.message = message.clone();
+        }
+        event
+    }
+
+    /// Shown in full rather than folded to its first line
+    fn is_expanded(&self, event: &LogEvent) -> bool {
+        self.expanded.contains(&event_key(
+            event.timestamp,
+            &event.log_stream,
+            &event.message,
+        ))
+    }
+
+    /// Marks these events, by timestamp, stream and message, as bookmarked
+    pub fn set_bookmarked<'a>(
+        &mut self,
+        events: impl IntoIterator<Item = (i64, &'a str, &'a str)>,
+    ) {
+        self.bookmarked = events
+            .into_iter()
+            .map(|(timestamp, log_stream, message)| event_key(timestamp, log_stream, message))
+            .collect();
+    }
+
+    /// The glyph in the gutter before the row, the first of bookmarked, error, just arrived
+    /// and folded that applies
+    fn marker(&self, index: usize, event: &LogEvent, style: Style) -> Span<'static> {
+        let key = event_key(event.timestamp, &event.log_stream, &event.message);
+        if self.bookmarked.contains(&key) {
+            Span::styled("★", style.fg(self.theme.accent))
+        } else if detect_level(&event.message) == Some(LogLevel::Error) {
+            Span::styled("!", style.fg(self.theme.error))
+        } else if self.fresh_color(index).is_some() {
+            Span::styled("•", style.fg(self.theme.ok))
+        } else if event.message.len() <= LARGE_MESSAGE_BYTES && event.message.contains('\n') {
+            let glyph = if self.expanded.contains(&key) {
+                "▾"
+            } else {
+                "▸"
+            };
+            Span::styled(glyph, style.fg(self.theme.muted))
+        } else {
+            Span::styled(" ", style)
+        }
+    }
+
+    /// Masked by a data protection policy and not unmasked yet
+    fn is_masked(&self, event: &LogEvent) -> bool {
+        self.protected_groups.contains(&event.log_group) && has_masked_values(&event.message)
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.len() == 0
+    }
+
+    pub fn last_timestamp(&self) -> Option<i64> {
+        self.data.last().map(|event| event.timestamp)
+    }
+
+    /// Roughly the bytes taken by the events still in memory
+    pub fn memory_bytes(&self) -> usize {
+        self.data.memory_bytes()
+    }
+
+    /// How many of the oldest events were moved to disk
+    pub fn spilled(&self) -> usize {
+        self.data.spilled()
+    }
+
+    /// The filter as the user wrote it
+    pub fn filter(&self) -> Option<&str> {
+        self.filter.as_ref().map(|(source, _)| source.as_str())
+    }
+
+    /// Only shows events matching `pattern`, compiled once from `source`
+    pub fn set_filter(&mut self, source: String, pattern: Arc<Pattern>) {
+        let selected = self.selected_position();
+        self.filter = Some((source, pattern));
+        self.refresh_visible();
+        self.reselect(selected);
+    }
+
+    pub fn set_highlight(&mut self, highlight: Option<Highlight>) {
+        self.highlight = highlight;
+    }
+
+    pub fn clear_filter(&mut self) {
+        let selected = self.selected_position();
+        self.filter = None;
+        self.refresh_visible();
+        self.reselect(selected);
+    }
+
+    /// Selects the event at `index` again if it's still shown, the newest one otherwise
+    fn reselect(&mut self, index: Option<usize>) {
+        let position = index.and_then(|index| match self.order {
+            RowOrder::Timestamp => self.visible.binary_search(&index).ok(),
+            _ => self.visible.iter().position(|&visible| visible == index),
+        });
+        self.y = match position {
+            Some(position) => self.visible.len() - 1 - position,
+            None => 0,
+        };
+    }
+
+    fn refresh_visible(&mut self) {
+        let mut visible = match &self.filter {
+            Some((_, pattern)) => self
+                .data
+                .iter()
+                .enumerate()
+                .filter(|(_, event)| pattern.matches(&event.message))
+                .map(|(index, _)| index)
+                .collect(),
+            None => (0..self.data.len()).collect::<Vec<_>>(),
+        };
+        if !matches!(self.order, RowOrder::Timestamp) {
+            // stable, events sorting the same stay by `@timestamp`
+            visible.sort_by_cached_key(|&index| {
+                self.data.get(index).map(|event| self.row_key(&event))
+            });
+        }
+        self.visible = visible;
+        self.y = min(self.y, self.visible.len().saturating_sub(1));
     }
 
     pub fn scroll_down(&mut self, by: Option<usize>) {
@@ -23,34 +526,463 @@ impl Table {
     }
 
     pub fn scroll_up(&mut self, by: Option<usize>) {
-        self.y = min(self.y.saturating_add(by.unwrap_or(1)), self.data.len() - 1);
+        self.y = min(
+            self.y.saturating_add(by.unwrap_or(1)),
+            self.visible.len().saturating_sub(1),
+        );
+    }
+
+    /// Position of the selection within `visible`
+    fn selected_index(&self) -> usize {
+        self.visible.len().saturating_sub(self.y + 1)
+    }
+
+    /// `line 1,234 / 56,789 (2%)`, counting the shown events from the oldest
+    pub fn position_label(&self) -> Option<String> {
+        if self.visible.is_empty() {
+            return None;
+        }
+        let line = self.selected_index() + 1;
+        Some(format!(
+            "line {} / {} ({}%)",
+            format_count(line),
+            format_count(self.visible.len()),
+            line * 100 / self.visible.len()
+        ))
+    }
+
+    /// The highlighted event, `y` counts up from the newest event at the bottom
+    pub fn selected(&self) -> Option<LogEvent> {
+        self.event(self.selected_position()?)
+    }
+
+    /// Moves the selection down to the next newer event matching `predicate`,
+    /// returns false when there is none
+    pub fn select_next_where(&mut self, predicate: impl Fn(&LogEvent) -> bool) -> bool {
+        let found = self.find_next(|_, event| predicate(event));
+        self.select_found(found)
+    }
+
+    /// Like `select_next_where`, the predicate also gets the event's index into `events()`
+    pub fn select_next_indexed(&mut self, predicate: impl Fn(usize, &LogEvent) -> bool) -> bool {
+        let found = self.find_next(predicate);
+        self.select_found(found)
+    }
+
+    /// Moves the selection up to the previous older event matching `predicate`,
+    /// returns false when there is none
+    pub fn select_previous_where(&mut self, predicate: impl Fn(&LogEvent) -> bool) -> bool {
+        let found = self.find_previous(|_, event| predicate(event));
+        self.select_found(found)
+    }
+
+    /// Like `select_previous_where`, the predicate also gets the event's index into `events()`
+    pub fn select_previous_indexed(
+        &mut self,
+        predicate: impl Fn(usize, &LogEvent) -> bool,
+    ) -> bool {
+        let found = self.find_previous(predicate);
+        self.select_found(found)
+    }
+
+    /// Position within `visible` of the next newer event matching `predicate`
+    fn find_next(&self, predicate: impl Fn(usize, &LogEvent) -> bool) -> Option<usize> {
+        (self.selected_index() + 1..self.visible.len()).find(|&i| {
+            let index = self.visible[i];
+            self.event(index)
+                .is_some_and(|event| predicate(index, &event))
+        })
+    }
+
+    fn find_previous(&self, predicate: impl Fn(usize, &LogEvent) -> bool) -> Option<usize> {
+        (0..self.selected_index()).rev().find(|&i| {
+            let index = self.visible[i];
+            self.event(index)
+                .is_some_and(|event| predicate(index, &event))
+        })
+    }
+
+    fn select_found(&mut self, found: Option<usize>) -> bool {
+        if let Some(index) = found {
+            self.y = self.visible.len() - 1 - index;
+        }
+        found.is_some()
+    }
+
+    pub fn select_oldest(&mut self) {
+        self.y = self.visible.len().saturating_sub(1);
+    }
+
+    pub fn select_newest(&mut self) {
+        self.y = 0;
+    }
+
+    /// Index into `events()` of the highlighted event
+    pub fn selected_position(&self) -> Option<usize> {
+        self.visible.iter().rev().nth(self.y).copied()
+    }
+
+    pub fn cursor_left(&mut self) {
+        self.cursor = self.clamped_cursor().saturating_sub(1);
+    }
+
+    pub fn cursor_right(&mut self) {
+        self.cursor = min(
+            self.clamped_cursor() + 1,
+            self.selected_chars().len().saturating_sub(1),
+        );
+    }
+
+    /// Moves the cursor to the start of the next token of the selected message
+    pub fn cursor_next_word(&mut self) {
+        let chars = self.selected_chars();
+        let mut cursor = self.clamped_cursor();
+        while cursor < chars.len() && !is_delimiter(chars[cursor]) {
+            cursor += 1;
+        }
+        while cursor < chars.len() && is_delimiter(chars[cursor]) {
+            cursor += 1;
+        }
+        if cursor < chars.len() {
+            self.cursor = cursor;
+        }
+    }
+
+    /// Moves the cursor to the start of the current or previous token
+    pub fn cursor_previous_word(&mut self) {
+        let chars = self.selected_chars();
+        let mut cursor = self.clamped_cursor();
+        while cursor > 0 && is_delimiter(chars[cursor - 1]) {
+            cursor -= 1;
+        }
+        while cursor > 0 && !is_delimiter(chars[cursor - 1]) {
+            cursor -= 1;
+        }
+        self.cursor = cursor;
+    }
+
+    /// The token of the selected message the cursor sits on, e.g. a request id or ip
+    pub fn token_under_cursor(&self) -> Option<String> {
+        token_at(&self.selected_chars(), self.clamped_cursor())
+    }
+
+    fn selected_chars(&self) -> Vec<char> {
+        self.selected()
+            .map(|event| event.message.chars().collect())
+            .unwrap_or_default()
+    }
+
+    /// Expands or collapses the selected event when it spans several lines, e.g. a stack trace
+    pub fn toggle_fold(&mut self) {
+        let Some(key) = self
+            .selected()
+            .as_ref()
+            .map(|event| event_key(event.timestamp, &event.log_stream, &event.message))
+        else {
+            return;
+        };
+        if !self.expanded.remove(&key) {
+            self.expanded.insert(key);
+        }
     }
+
+    /// The lines a row takes up, each with the fold marker to draw after it
+    fn row_lines<'a>(&self, event: &'a LogEvent) -> Vec<(&'a str, Option<String>)> {
+        let mut lines = self.message_lines(event);
+        if self.is_masked(event) {
+            if let Some((_, marker)) = lines.first_mut() {
+                *marker = Some(format!("{} [masked]", marker.take().unwrap_or_default()));
+            }
+        }
+        lines
+    }
+
+    fn message_lines<'a>(&self, event: &'a LogEvent) -> Vec<(&'a str, Option<String>)> {
+        if event.message.len() > LARGE_MESSAGE_BYTES {
+            let preview = large_message_preview(&event.message);
+            let size = format!(" … {} KB, enter to expand", event.message.len() / 1024);
+            return vec![(preview, Some(size))];
+        }
+        let mut lines = event.message.lines();
+        let first = lines.next().unwrap_or_default();
+        let rest = lines.count();
+        if rest == 0 {
+            return vec![(first, None)];
+        }
+        if !self.is_expanded(event) {
+            return vec![(first, Some(format!(" ▸ {} more lines", rest)))];
+        }
+        event
+            .message
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (line, (index == 0).then(|| " ▾".to_string())))
+            .collect()
+    }
+
+    /// `line` with a span per stretch between search matches, a single span without any
+    fn search_line<'a>(&self, line: &'a str, style: Style) -> Line<'a> {
+        let Some(term) = self.search.as_deref().filter(|term| !term.is_empty()) else {
+            return Line::styled(line, style);
+        };
+        let match_style = style.fg(self.theme.accent).add_modifier(Modifier::BOLD);
+        let mut spans = vec![];
+        let mut end = 0;
+        for (start, matched) in line.match_indices(term) {
+            if start > end {
+                spans.push(Span::styled(&line[end..start], style));
+            }
+            spans.push(Span::styled(matched, match_style));
+            end = start + matched.len();
+        }
+        if end < line.len() {
+            spans.push(Span::styled(&line[end..], style));
+        }
+        Line::from(spans)
+    }
+
+    /// The column is kept while moving between rows, shorter rows clamp it
+    fn clamped_cursor(&self) -> usize {
+        // only count as far as the cursor, this runs on every render
+        let len = self
+            .selected()
+            .map(|event| event.message.chars().take(self.cursor + 1).count())
+            .unwrap_or(0);
+        min(self.cursor, len.saturating_sub(1))
+    }
+}
+
+/// The line and column of the `cursor`th character of a possibly multi-line message
+fn line_and_column(message: &str, cursor: usize) -> Option<(usize, usize)> {
+    let mut seen = 0;
+    for (line_index, line) in message.split('\n').enumerate() {
+        let len = line.chars().count();
+        if cursor < seen + len {
+            return Some((line_index, cursor - seen));
+        }
+        // the newline itself
+        seen += len + 1;
+    }
+    None
+}
+
+/// Separates tokens, `:` `/` `.` `-` and `_` are left out so ips, arns and uuids stay whole
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || "\"'`,;=()[]{}<>|".contains(c)
+}
+
+fn token_at(chars: &[char], column: usize) -> Option<String> {
+    if chars.get(column).is_none_or(|&c| is_delimiter(c)) {
+        return None;
+    }
+    let start = chars[..column]
+        .iter()
+        .rposition(|&c| is_delimiter(c))
+        .map_or(0, |index| index + 1);
+    let end = chars[column..]
+        .iter()
+        .position(|&c| is_delimiter(c))
+        .map_or(chars.len(), |index| column + index);
+    Some(chars[start..end].iter().collect())
+}
+
+/// The first line of `message`, cut on a char boundary so rendering never walks the whole payload
+fn large_message_preview(message: &str) -> &str {
+    let mut end = LARGE_MESSAGE_PREVIEW_BYTES.min(message.len());
+    while !message.is_char_boundary(end) {
+        end -= 1;
+    }
+    message[..end].lines().next().unwrap_or_default()
+}
+
+/// `line` cut to leave room for an ellipsis in `width` cells, `true` when it didn't fit.
+/// Cuts at a space when there's one close to the edge, so a row doesn't seem to end mid-word
+fn truncate_at_word(line: &str, width: u16) -> (&str, bool) {
+    let width = width as usize;
+    if line.width() <= width {
+        return (line, false);
+    }
+    let mut end = 0;
+    let mut used = 0;
+    for (index, c) in line.char_indices() {
+        used += c.width().unwrap_or_default();
+        if used >= width {
+            break;
+        }
+        end = index + c.len_utf8();
+    }
+    let cut = &line[..end];
+    if line[end..].starts_with(char::is_whitespace) {
+        return (cut.trim_end(), true);
+    }
+    match cut.rfind(char::is_whitespace) {
+        Some(space) if space > 0 && cut[space..].width() <= WORD_BREAK_CELLS => {
+            (cut[..space].trim_end(), true)
+        }
+        _ => (cut, true),
+    }
+}
+
+/// Identifies an event across appends, which reorder `data`
+fn event_key(timestamp: i64, log_stream: &str, message: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (timestamp, log_stream, message).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A stable color per group or stream name so interleaved rows can be told apart at a glance
+fn tag_color(tags: &[Color], name: &str) -> Color {
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    tags[hasher.finish() as usize % tags.len()]
+}
+
+fn tag(event: &LogEvent) -> String {
+    let group = event.log_group.rsplit('/').next().unwrap_or_default();
+    // lambda stream names share a long date/version prefix, the tail is what differs
+    let stream_start = event
+        .log_stream
+        .char_indices()
+        .rev()
+        .nth(7)
+        .map(|(index, _)| index)
+        .unwrap_or(0);
+    format!("[{}/{}] ", group, &event.log_stream[stream_start..])
+}
+
+/// The picked fields as `name=value`, ahead of the message they came with
+fn fields_prefix(event: &LogEvent) -> String {
+    event
+        .fields
+        .iter()
+        .map(|(name, value)| format!("{}={} ", name, value))
+        .collect()
 }
 
 impl Widget for &Table {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let height = area.height;
+        let height = area.height as usize;
         if height < 1 {
             return;
         }
 
-        let innner_height = (height - 2) as usize;
-        let starting = min(self.y, self.data.len().saturating_sub(innner_height));
-        let messages_to_render = self.data.iter().rev().skip(starting).take(innner_height);
-        for (index, message) in messages_to_render.rev().enumerate() {
-            buf.set_stringn(
-                area.x + 1,
-                area.y + index as u16 + 1,
-                message,
-                (area.width - 2) as usize,
-                Style::new().bg(if self.y == starting + (innner_height - index) - 1 {
-                    Color::LightRed
-                } else {
-                    Color::Reset
-                }),
-            );
+        // rows can span several lines, so walk up from the selection until the area is full
+        // then top up with newer rows when the oldest event was reached first
+        let mut rows = VecDeque::new();
+        let mut used = 0;
+        for (offset, &index) in self.visible.iter().enumerate().rev().skip(self.y) {
+            if used >= height {
+                break;
+            }
+            let Some(event) = self.event(index) else {
+                continue;
+            };
+            let lines = self.row_lines(&event).len();
+            used += lines;
+            rows.push_front((self.visible.len() - 1 - offset, index, event, lines));
+        }
+        let mut newer = self.y;
+        while used < height && newer > 0 {
+            newer -= 1;
+            let index = self.visible[self.visible.len() - 1 - newer];
+            let Some(event) = self.event(index) else {
+                continue;
+            };
+            let lines = self.row_lines(&event).len();
+            used += lines;
+            rows.push_back((newer, index, event, lines));
+        }
+        // clip older rows at the top, but never the start of the selected row
+        let above_selected = rows
+            .iter()
+            .take_while(|(offset, _, _, _)| *offset != self.y)
+            .map(|(_, _, _, lines)| lines)
+            .sum::<usize>();
+        let mut skip = min(used.saturating_sub(height), above_selected);
+        // unless it's taller than the area, then it's clipped to keep the cursor's line in view
+        if let Some((_, _, event, lines)) = rows.iter().find(|(offset, ..)| *offset == self.y) {
+            let cursor_line = line_and_column(&event.message, self.clamped_cursor())
+                .map_or(0, |(line, _)| line)
+                .min(lines - 1);
+            skip += (cursor_line + 1).saturating_sub(height);
         }
 
-        Block::new().borders(Borders::ALL).render(area, buf);
+        let mut y = area.y;
+        for (offset, index, event, _) in &rows {
+            let lines = self.row_lines(event);
+            let is_selected = *offset == self.y;
+            let mut row_style = Style::new().bg(if is_selected {
+                self.theme.selection
+            } else {
+                self.fresh_color(*index).unwrap_or(Color::Reset)
+            });
+            if let Some(highlight) = self.highlight.as_ref().filter(|h| *index >= h.from) {
+                row_style = row_style.fg(if highlight.matches.contains(index) {
+                    self.theme.ok
+                } else {
+                    self.theme.muted
+                });
+            }
+            let cursor = if is_selected {
+                line_and_column(&event.message, self.clamped_cursor())
+            } else {
+                None
+            };
+            for (line_index, (line, fold)) in lines.into_iter().enumerate() {
+                if skip > 0 {
+                    skip -= 1;
+                    continue;
+                }
+                if y >= area.bottom() {
+                    return;
+                }
+                let marker = if line_index == 0 {
+                    self.marker(*index, event, row_style)
+                } else {
+                    Span::styled(" ", row_style)
+                };
+                let (x, _) = buf.set_span(area.x, y, &marker, area.width);
+                // a thin gutter per stream, separates events from concurrent lambda containers
+                let gutter = if line_index == 0 && self.is_out_of_order(*index) {
+                    Span::styled("↑", row_style.fg(self.theme.warning))
+                } else {
+                    Span::styled(
+                        "▌",
+                        row_style.fg(tag_color(&self.theme.tags, &event.log_stream)),
+                    )
+                };
+                let (mut x, _) = buf.set_span(x, y, &gutter, area.right().saturating_sub(x));
+                if self.show_tags && line_index == 0 {
+                    let tag = Span::styled(
+                        tag(event),
+                        row_style.fg(tag_color(&self.theme.tags, &event.log_group)),
+                    );
+                    (x, _) = buf.set_span(x, y, &tag, area.right().saturating_sub(x));
+                }
+                if line_index == 0 && !event.fields.is_empty() {
+                    let fields = Span::styled(fields_prefix(event), row_style.fg(self.theme.muted));
+                    (x, _) = buf.set_span(x, y, &fields, area.right().saturating_sub(x));
+                }
+                let (line, truncated) = truncate_at_word(line, area.right().saturating_sub(x));
+                let mut line = self.search_line(line, row_style);
+                if truncated {
+                    line.push_span(Span::styled("…", row_style.fg(self.theme.muted)));
+                }
+                let (text_end, _) = buf.set_line(x, y, &line, area.right().saturating_sub(x));
+                if let Some(fold) = fold {
+                    let fold = Span::styled(fold, row_style.fg(self.theme.muted));
+                    buf.set_span(text_end, y, &fold, area.right().saturating_sub(text_end));
+                }
+                if let Some((_, column)) = cursor.filter(|(line, _)| *line == line_index) {
+                    let cursor_x = x as usize + column;
+                    if cursor_x < area.right() as usize {
+                        if let Some(cell) = buf.cell_mut((cursor_x as u16, y)) {
+                            cell.set_style(Style::new().add_modifier(Modifier::REVERSED));
+                        }
+                    }
+                }
+                y += 1;
+            }
+        }
     }
 }
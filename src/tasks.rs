@@ -0,0 +1,243 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use tokio::{
+    sync::{mpsc, OwnedSemaphorePermit, Semaphore},
+    task::AbortHandle,
+};
+use tracing::debug;
+
+use crate::log_viewer::Pane;
+
+/// How many background AWS tasks may run at once, the rest wait for a free slot. Live tails
+/// and queries left running for the inbox don't count, they can run for as long as they like
+const MAX_CONCURRENT_TASKS: usize = 4;
+
+/// Identifies a background task, spawning a task under a key that is already taken replaces it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TaskKey {
+    LogGroups,
+    GroupChanges,
+    GroupInfo,
+    GroupMetadata,
+    Logs(Pane),
+    ComparisonLogs(Pane),
+    LiveTail(Pane),
+    IndexedFields(Pane),
+    ErrorSummary(Pane),
+    MetricFilters(Pane),
+    MetricFilterTest(Pane),
+    Unmask(Pane),
+    LogRecord,
+    Alarms,
+    AlarmGroups,
+    Accounts,
+    SwitchAccount,
+    Reconnect,
+    /// a viewer query left running for the inbox, by the generation it was started with
+    Background(u64),
+}
+
+impl TaskKey {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TaskKey::LogGroups => "loading groups",
+            TaskKey::GroupChanges => "changing groups",
+            TaskKey::GroupInfo => "loading group info",
+            TaskKey::GroupMetadata => "loading group metadata",
+            TaskKey::Logs(Pane::Main) => "querying logs",
+            TaskKey::Logs(Pane::Split) => "querying split logs",
+            TaskKey::ComparisonLogs(Pane::Main) => "querying previous window",
+            TaskKey::ComparisonLogs(Pane::Split) => "querying split previous window",
+            TaskKey::LiveTail(Pane::Main) => "live tail",
+            TaskKey::LiveTail(Pane::Split) => "split live tail",
+            TaskKey::IndexedFields(Pane::Main) => "loading indexed fields",
+            TaskKey::IndexedFields(Pane::Split) => "loading split indexed fields",
+            TaskKey::ErrorSummary(Pane::Main) => "counting errors",
+            TaskKey::ErrorSummary(Pane::Split) => "counting split errors",
+            TaskKey::MetricFilters(Pane::Main) => "loading metric filters",
+            TaskKey::MetricFilters(Pane::Split) => "loading split metric filters",
+            TaskKey::MetricFilterTest(Pane::Main) => "testing metric filter",
+            TaskKey::MetricFilterTest(Pane::Split) => "testing split metric filter",
+            TaskKey::Unmask(Pane::Main) => "unmasking",
+            TaskKey::Unmask(Pane::Split) => "unmasking split",
+            TaskKey::LogRecord => "loading event fields",
+            TaskKey::Alarms => "loading alarms",
+            TaskKey::AlarmGroups => "finding the groups of an alarm",
+            TaskKey::Accounts => "listing accounts",
+            TaskKey::SwitchAccount => "switching accounts",
+            TaskKey::Reconnect => "offline, retrying",
+            TaskKey::Background(_) => "background query",
+        }
+    }
+
+    /// Whether the task takes one of the `MAX_CONCURRENT_TASKS` slots
+    fn is_limited(&self) -> bool {
+        !matches!(self, TaskKey::LiveTail(_) | TaskKey::Background(_))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskStatus {
+    Queued,
+    Running,
+}
+
+#[derive(Debug)]
+struct Task {
+    id: u64,
+    status: TaskStatus,
+    abort_handle: AbortHandle,
+    /// the slot it runs in, given back when it's moved to a key that isn't limited
+    permit: Arc<Mutex<Option<OwnedSemaphorePermit>>>,
+}
+
+/// Owns every background task so they can be limited, cancelled and shown in the status bar
+#[derive(Debug, Clone)]
+pub struct TaskSupervisor {
+    tasks: Arc<Mutex<HashMap<TaskKey, Task>>>,
+    permits: Arc<Semaphore>,
+    next_id: Arc<AtomicU64>,
+    /// pinged whenever a status changes so the ui redraws
+    status_tx: mpsc::UnboundedSender<()>,
+}
+
+impl TaskSupervisor {
+    pub fn new(status_tx: mpsc::UnboundedSender<()>) -> Self {
+        Self {
+            tasks: Arc::new(Mutex::new(HashMap::new())),
+            permits: Arc::new(Semaphore::new(MAX_CONCURRENT_TASKS)),
+            next_id: Arc::new(AtomicU64::new(0)),
+            status_tx,
+        }
+    }
+
+    /// Queues `task` under `key`, cancelling whatever was running under that key before
+    pub fn spawn<F>(&self, key: TaskKey, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let this = self.clone();
+        let key_label = key.label();
+        let permit = Arc::new(Mutex::new(None));
+        let slot = permit.clone();
+
+        // hold the lock until the task is registered so it can't report on itself too early
+        let mut tasks = self.tasks.lock().unwrap();
+        let handle = tokio::spawn(async move {
+            if this.is_limited(id) {
+                let acquired = this.permits.clone().acquire_owned().await.ok();
+                // it may have been moved to a key that isn't limited while it waited
+                if this.is_limited(id) {
+                    *slot.lock().unwrap() = acquired;
+                }
+            }
+            this.set_status(id, Some(TaskStatus::Running));
+            task.await;
+            this.set_status(id, None);
+        });
+        let previous = tasks.insert(
+            key,
+            Task {
+                id,
+                status: TaskStatus::Queued,
+                abort_handle: handle.abort_handle(),
+                permit,
+            },
+        );
+        drop(tasks);
+
+        debug!(
+            task = key_label,
+            replaced = previous.is_some(),
+            "queued task"
+        );
+        if let Some(previous) = previous {
+            previous.abort_handle.abort();
+        }
+        let _ = self.status_tx.send(());
+    }
+
+    pub fn cancel(&self, key: &TaskKey) {
+        let task = self.tasks.lock().unwrap().remove(key);
+        if let Some(task) = task {
+            debug!(task = key.label(), "cancelled task");
+            task.abort_handle.abort();
+            let _ = self.status_tx.send(());
+        }
+    }
+
+    /// Moves the task under `from` to `to` without interrupting it, false when there's none.
+    /// Moved to a key that isn't limited it gives its slot to the next task
+    pub fn rekey(&self, from: &TaskKey, to: TaskKey) -> bool {
+        let mut tasks = self.tasks.lock().unwrap();
+        let Some(task) = tasks.remove(from) else {
+            return false;
+        };
+        debug!(from = from.label(), to = to.label(), "moved task");
+        if !to.is_limited() {
+            task.permit.lock().unwrap().take();
+        }
+        if let Some(previous) = tasks.insert(to, task) {
+            previous.abort_handle.abort();
+        }
+        drop(tasks);
+        let _ = self.status_tx.send(());
+        true
+    }
+
+    /// Whether the task with `id` is still around under a key that takes a slot
+    fn is_limited(&self, id: u64) -> bool {
+        self.tasks
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(key, task)| task.id == id && key.is_limited())
+    }
+
+    pub fn status(&self, key: &TaskKey) -> Option<TaskStatus> {
+        self.tasks.lock().unwrap().get(key).map(|task| task.status)
+    }
+
+    pub fn statuses(&self) -> Vec<(TaskKey, TaskStatus)> {
+        self.tasks
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(key, task)| (key.clone(), task.status))
+            .collect()
+    }
+
+    /// `None` marks the task as done, updates from a task that has since been replaced are
+    /// ignored. Tasks are found by id, they may have been moved to another key
+    fn set_status(&self, id: u64, status: Option<TaskStatus>) {
+        let mut tasks = self.tasks.lock().unwrap();
+        let Some(key) = tasks
+            .iter()
+            .find(|(_, task)| task.id == id)
+            .map(|(key, _)| key.clone())
+        else {
+            return;
+        };
+        match status {
+            Some(status) => {
+                if let Some(task) = tasks.get_mut(&key) {
+                    task.status = status;
+                }
+            }
+            None => {
+                debug!(task = key.label(), "finished task");
+                tasks.remove(&key);
+            }
+        }
+        drop(tasks);
+        let _ = self.status_tx.send(());
+    }
+}
@@ -0,0 +1,175 @@
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode},
+};
+use ratatui::style::Color;
+
+/// Whether the terminal draws on a dark or a light background
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Background {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl Background {
+    pub fn parse(name: &str) -> Result<Background, String> {
+        match name {
+            "dark" => Ok(Background::Dark),
+            "light" => Ok(Background::Light),
+            _ => Err(format!("unknown theme {}, expected dark or light", name)),
+        }
+    }
+}
+
+/// Colors by purpose, so every screen stays readable on either background
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// background of the selected row or list entry
+    pub selection: Color,
+    /// search matches, the highlighted group and inputs being typed
+    pub accent: Color,
+    /// unfocused borders, row numbers and other secondary text
+    pub muted: Color,
+    pub ok: Color,
+    pub warning: Color,
+    pub error: Color,
+    /// tell streams and groups apart in the viewer
+    pub tags: [Color; 6],
+    /// background of events that just arrived, a step further along every second
+    pub fresh: [Color; 3],
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::for_background(Background::Dark)
+    }
+}
+
+impl Theme {
+    pub fn for_background(background: Background) -> Theme {
+        match background {
+            Background::Dark => Theme {
+                selection: Color::LightRed,
+                accent: Color::Red,
+                muted: Color::DarkGray,
+                ok: Color::Green,
+                warning: Color::Yellow,
+                error: Color::Red,
+                tags: [
+                    Color::Cyan,
+                    Color::Green,
+                    Color::Yellow,
+                    Color::Magenta,
+                    Color::Blue,
+                    Color::LightRed,
+                ],
+                fresh: [Color::Indexed(28), Color::Indexed(22), Color::Indexed(235)],
+            },
+            // the light variants of the ansi colors wash out on white, stick to the dark ones
+            Background::Light => Theme {
+                selection: Color::LightBlue,
+                accent: Color::Blue,
+                muted: Color::Gray,
+                ok: Color::Green,
+                warning: Color::Magenta,
+                error: Color::Red,
+                tags: [
+                    Color::Blue,
+                    Color::Green,
+                    Color::Magenta,
+                    Color::Red,
+                    Color::Cyan,
+                    Color::DarkGray,
+                ],
+                fresh: [
+                    Color::Indexed(120),
+                    Color::Indexed(157),
+                    Color::Indexed(255),
+                ],
+            },
+        }
+    }
+}
+
+/// How long to wait for more of the reply when the terminal didn't answer where the cursor is
+const QUERY_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Asks the terminal for its background color, falling back to `$COLORFGBG`.
+/// Needs raw mode, call it after the terminal is set up and before reading events
+pub fn detect_background() -> Option<Background> {
+    query_background()
+        .ok()
+        .flatten()
+        .or_else(|| colorfgbg_background(&std::env::var("COLORFGBG").ok()?))
+}
+
+/// Sends OSC 11, terminals answer with `ESC ] 11 ; rgb:rrrr/gggg/bbbb` and a terminator.
+/// A cursor position request goes right after it. Every terminal answers that one, and in
+/// order, so once its answer is in so is any OSC 11 reply, however slow the connection.
+/// Whatever arrived before it is read here rather than taken for keys by the app
+fn query_background() -> io::Result<Option<Background>> {
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]11;?\x1b\\")?;
+    stdout.flush()?;
+    // crossterm keeps the keys it reads while waiting for the position, they're read below
+    let wait = match cursor::position() {
+        Ok(_) => Duration::ZERO,
+        Err(_) => QUERY_TIMEOUT,
+    };
+
+    // crossterm doesn't know OSC replies, they arrive as a run of key events
+    let mut response = String::new();
+    let mut background = None;
+    while event::poll(wait)? {
+        if let Event::Key(key) = event::read()? {
+            if let KeyCode::Char(c) = key.code {
+                response.push(c);
+            }
+            background = background.or_else(|| osc_background(&response));
+        }
+    }
+    Ok(background)
+}
+
+/// The background of an OSC 11 reply, once all three components have arrived
+pub fn osc_background(response: &str) -> Option<Background> {
+    let (_, rgb) = response.split_once("rgb:")?;
+    let components = rgb
+        .split('/')
+        .map(|component| {
+            let digits = component
+                .chars()
+                .take_while(char::is_ascii_hexdigit)
+                .collect::<String>();
+            let max = 16_f64.powi(digits.len() as i32) - 1.0;
+            u32::from_str_radix(&digits, 16)
+                .ok()
+                .map(|value| value as f64 / max)
+        })
+        .collect::<Option<Vec<_>>>()?;
+    // the last component is only complete once something other than a digit follows it
+    if components.len() != 3 || rgb.ends_with(|c: char| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let luminance = 0.2126 * components[0] + 0.7152 * components[1] + 0.0722 * components[2];
+    Some(if luminance > 0.5 {
+        Background::Light
+    } else {
+        Background::Dark
+    })
+}
+
+/// `$COLORFGBG` is `fg;bg` with ansi color numbers, set by rxvt, konsole and others
+pub fn colorfgbg_background(value: &str) -> Option<Background> {
+    let background = value.rsplit(';').next()?.parse::<u8>().ok()?;
+    Some(match background {
+        7 | 9..=15 => Background::Light,
+        _ => Background::Dark,
+    })
+}
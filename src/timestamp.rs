@@ -0,0 +1,113 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, NaiveTime, TimeZone, Utc};
+
+/// Minutes either side of the time when `±N` isn't given
+const DEFAULT_AROUND_MINUTES: i64 = 15;
+
+/// Times without a zone are read as UTC, the same as the viewer shows them
+const NAIVE_FORMATS: [&str; 6] = [
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M",
+    "%Y/%m/%d %H:%M:%S%.f",
+    "%d/%b/%Y:%H:%M:%S",
+];
+
+/// The window `±N` minutes around a pasted time like `2024-05-01T03:14:07Z ±10`. The
+/// time can be ISO 8601, epoch seconds or millis, an access log's `10/Oct/2024:13:55:36
+/// +0000`, syslog's `Oct 10 13:55:36` or just `03:14:07`, the last two the most recent
+/// time they could be. The window ends at `now` at the latest
+pub fn parse_around(text: &str, now: i64) -> Result<(i64, i64), String> {
+    let text = text.trim();
+    let (time, minutes) = match text.rsplit_once(char::is_whitespace) {
+        Some((time, window)) => match parse_minutes(window) {
+            Some(minutes) => (time.trim(), minutes),
+            None => (text, DEFAULT_AROUND_MINUTES),
+        },
+        None => (text, DEFAULT_AROUND_MINUTES),
+    };
+    let timestamp =
+        parse_timestamp(time, now).ok_or_else(|| format!("can't read a time from '{}'", time))?;
+    if timestamp > now {
+        return Err(format!("{} is in the future", time));
+    }
+    let around = minutes * 60 * 1000;
+    Ok((timestamp - around, (timestamp + around).min(now)))
+}
+
+/// `±10`, `+-10` or `+/-10m`
+fn parse_minutes(text: &str) -> Option<i64> {
+    let minutes = ["±", "+-", "+/-"]
+        .iter()
+        .find_map(|sign| text.strip_prefix(sign))?;
+    let minutes = minutes.strip_suffix('m').unwrap_or(minutes);
+    minutes.parse().ok().filter(|minutes| *minutes > 0)
+}
+
+/// Milliseconds since the epoch of `text`, `now` fills in the parts it leaves out
+pub fn parse_timestamp(text: &str, now: i64) -> Option<i64> {
+    let text = text
+        .trim()
+        .trim_matches(|c| matches!(c, '[' | ']' | '"' | '\''));
+    if let Some(epoch) = parse_epoch(text) {
+        return Some(epoch);
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Some(time.timestamp_millis());
+    }
+    // an access log's time with its offset
+    if let Ok(time) = DateTime::parse_from_str(text, "%d/%b/%Y:%H:%M:%S %z") {
+        return Some(time.timestamp_millis());
+    }
+    if let Some(time) = NAIVE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+    {
+        return Some(time.and_utc().timestamp_millis());
+    }
+    let now = Utc.timestamp_millis_opt(now).single()?;
+    parse_syslog(text, now).or_else(|| parse_time_of_day(text, now))
+}
+
+/// Seconds up to the year 5138, millis after that. Anything shorter than 1973 in seconds
+/// is more likely a typo than a time
+fn parse_epoch(text: &str) -> Option<i64> {
+    let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+    let digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    if whole.len() < 9 || !digits(whole) || !digits(fraction) {
+        return None;
+    }
+    let whole: i64 = whole.parse().ok()?;
+    if whole >= 100_000_000_000 {
+        return Some(whole);
+    }
+    let millis: i64 = format!("{:0<3}", fraction).get(..3)?.parse().ok()?;
+    Some(whole * 1000 + millis)
+}
+
+/// `Oct 10 13:55:36` in the last year it could have been
+fn parse_syslog(text: &str, now: DateTime<Utc>) -> Option<i64> {
+    // single digit days are padded with a space
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let year = now.year();
+    (0..2)
+        .filter_map(|back| {
+            let with_year = format!("{} {}", year - back, text);
+            NaiveDateTime::parse_from_str(&with_year, "%Y %b %d %H:%M:%S%.f").ok()
+        })
+        .map(|time| time.and_utc())
+        .find(|time| *time <= now)
+        .map(|time| time.timestamp_millis())
+}
+
+/// `03:14:07` or `03:14` today, or yesterday when that's still to come
+fn parse_time_of_day(text: &str, now: DateTime<Utc>) -> Option<i64> {
+    let time = ["%H:%M:%S%.f", "%H:%M"]
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(text, format).ok())?;
+    let mut at = now.date_naive().and_time(time).and_utc();
+    if at > now {
+        at -= Duration::days(1);
+    }
+    Some(at.timestamp_millis())
+}
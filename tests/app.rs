@@ -0,0 +1,3606 @@
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use futures::{future::BoxFuture, FutureExt};
+use loglog::{
+    app::{App, Screen},
+    backend::{GroupChange, LiveTailSession, LogBackend},
+    config::{
+        compile_pattern, Config, GroupQuery, MessageTimestamp, MessageTimestamps, ViewerQueries,
+    },
+    demo::DemoBackend,
+    headless,
+    keymap::Keymap,
+    logging,
+    output::{OutputFormat, Printer},
+    paths::{self, Dir, Platform},
+    recording::{RecordingBackend, ReplayBackend},
+    settings::{Flags, Settings},
+    shared::{
+        format_elapsed, Account, Alarm, GroupMetadata, LogEvent, LogGroup, LogGroupPage,
+        MetricFilter, QueryCounts, QueryRow, OFFLINE,
+    },
+    state::StateStore,
+    table::Table,
+    theme::{colorfgbg_background, osc_background, Background, Theme},
+};
+use ratatui::{backend::TestBackend, style::Modifier, Terminal};
+
+#[derive(Debug, Default)]
+struct MockBackend {
+    log_groups: Vec<LogGroup>,
+    log_groups_error: Option<String>,
+    /// never finish loading groups, as if the request was still in flight
+    log_groups_pending: bool,
+    /// listed a moment apart after `log_groups`, in the configured region
+    later_pages: Vec<Vec<LogGroup>>,
+    /// fail loading groups as if the network was gone while set
+    offline: Arc<AtomicBool>,
+    /// listing groups in these fails, as in opt-in regions the account hasn't enabled
+    disabled_regions: Vec<String>,
+    /// queries including any of these groups fail
+    unqueryable_log_groups: Vec<String>,
+    logs: Vec<LogEvent>,
+    /// never finish fetching logs, as if the query was still running
+    logs_pending: bool,
+    /// returned instead of `logs` for queries ending more than an hour ago
+    previous_logs: Vec<LogEvent>,
+    /// returned instead of `logs` from the second fetch on, when set
+    refreshed_logs: Vec<LogEvent>,
+    fetches: AtomicUsize,
+    /// changing these groups fails
+    unchangeable_log_groups: Vec<String>,
+    /// the metric filters on every group
+    metric_filters: Vec<MetricFilter>,
+    /// unmasked messages by `@ptr`
+    unmasked: Vec<(String, String)>,
+    /// every field of an event by `@ptr`
+    log_records: Vec<(String, QueryRow)>,
+    /// fields indexed on every group
+    field_indexes: Vec<String>,
+    /// returned by `run_query` when set, instead of a row per event in `logs`
+    query_rows: Vec<QueryRow>,
+    /// returned for a configured viewer query when set, instead of an event showing the query
+    query_logs: Vec<LogEvent>,
+    query_counts: QueryCounts,
+    alarms: Vec<Alarm>,
+    /// groups publishing the metric of every alarm
+    alarm_log_groups: Vec<String>,
+    accounts: Vec<Account>,
+    /// the account switched to last
+    switched: Mutex<Option<Account>>,
+    /// listed instead of `log_groups` once switched to another account
+    account_log_groups: Vec<LogGroup>,
+    /// by group name, groups not listed have none
+    group_metadata: Vec<(String, GroupMetadata)>,
+    metadata_fetches: Arc<AtomicUsize>,
+    /// sent by live tails one after the other, tails fail when there are none
+    tail_batches: Vec<Vec<LogEvent>>,
+    /// every tailed batch is a sample
+    tail_sampled: bool,
+    /// the filter pattern of every live tail started
+    tail_filters: Mutex<Vec<String>>,
+    /// the range of every fetch of logs
+    fetched_ranges: Mutex<Vec<(i64, i64)>>,
+    /// the region and query of every fetch of logs
+    fetched_queries: Mutex<Vec<(Option<String>, Option<String>)>>,
+}
+
+/// Sends its batches a moment apart, then waits as if nothing more was logged
+struct MockTail {
+    batches: Vec<Vec<LogEvent>>,
+    sampled: bool,
+}
+
+impl LiveTailSession for MockTail {
+    fn next_events(&mut self) -> BoxFuture<'_, Result<Option<Vec<LogEvent>>, String>> {
+        if self.batches.is_empty() {
+            return futures::future::pending().boxed();
+        }
+        let batch = self.batches.remove(0);
+        async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Ok(Some(batch))
+        }
+        .boxed()
+    }
+
+    fn is_sampled(&self) -> bool {
+        self.sampled
+    }
+}
+
+impl LogBackend for MockBackend {
+    fn regions(&self) -> Vec<String> {
+        vec!["eu-west-1".to_string(), "us-east-1".to_string()]
+    }
+
+    fn fetch_log_groups(
+        &self,
+        region: Option<String>,
+        next_token: Option<String>,
+    ) -> BoxFuture<'_, Result<LogGroupPage, String>> {
+        if let Some(page) = next_token.and_then(|token| token.parse::<usize>().ok()) {
+            let result = Ok(LogGroupPage {
+                log_groups: self.later_pages[page].clone(),
+                next_token: (page + 1 < self.later_pages.len()).then(|| (page + 1).to_string()),
+            });
+            return async move {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                result
+            }
+            .boxed();
+        }
+        if self.log_groups_pending {
+            return futures::future::pending().boxed();
+        }
+        if self.offline.load(Ordering::SeqCst) {
+            return futures::future::ready(Err(OFFLINE.to_string())).boxed();
+        }
+        if region
+            .as_ref()
+            .is_some_and(|region| self.disabled_regions.contains(region))
+        {
+            let error = "the security token included in the request is invalid".to_string();
+            return futures::future::ready(Err(error)).boxed();
+        }
+        let log_groups = if self.switched.lock().unwrap().is_some() {
+            &self.account_log_groups
+        } else {
+            &self.log_groups
+        };
+        let result = match &self.log_groups_error {
+            Some(error) => Err(error.clone()),
+            None => Ok(LogGroupPage {
+                log_groups: log_groups
+                    .iter()
+                    .filter(|group| region.is_none() || group.region() == region.as_deref())
+                    .cloned()
+                    .collect(),
+                next_token: (region.is_none() && !self.later_pages.is_empty())
+                    .then(|| "0".to_string()),
+            }),
+        };
+        async move { result }.boxed()
+    }
+
+    fn fetch_logs(
+        &self,
+        region: Option<String>,
+        _log_group_names: Vec<String>,
+        query: Option<String>,
+        start: i64,
+        end: i64,
+    ) -> BoxFuture<'_, Result<Vec<LogEvent>, String>> {
+        self.fetched_ranges.lock().unwrap().push((start, end));
+        self.fetched_queries
+            .lock()
+            .unwrap()
+            .push((region, query.clone()));
+        if self.logs_pending {
+            return futures::future::pending().boxed();
+        }
+        // a configured query comes back as its only event, to show which one ran
+        if let Some(query) = query {
+            let logs = match self.query_logs.is_empty() {
+                true => vec![event(&query)],
+                false => self.query_logs.clone(),
+            };
+            return async move { Ok(logs) }.boxed();
+        }
+        let an_hour_ago = chrono::Utc::now().timestamp_millis() - 3600 * 1000;
+        let logs = if end < an_hour_ago {
+            self.previous_logs.clone()
+        } else if self.fetches.fetch_add(1, Ordering::Relaxed) > 0
+            && !self.refreshed_logs.is_empty()
+        {
+            self.refreshed_logs.clone()
+        } else {
+            self.logs.clone()
+        };
+        async move { Ok(logs) }.boxed()
+    }
+
+    fn filter_logs(
+        &self,
+        region: Option<String>,
+        log_group_names: Vec<String>,
+        _log_stream_name_prefix: String,
+        start: i64,
+        end: i64,
+    ) -> BoxFuture<'_, Result<Vec<LogEvent>, String>> {
+        self.fetch_logs(region, log_group_names, None, start, end)
+    }
+
+    fn run_query(
+        &self,
+        _region: Option<String>,
+        log_group_names: Vec<String>,
+        query: String,
+        _start: i64,
+        _end: i64,
+    ) -> BoxFuture<'_, Result<Vec<QueryRow>, String>> {
+        if log_group_names
+            .iter()
+            .any(|name| self.unqueryable_log_groups.contains(name))
+        {
+            return async { Err("access denied".to_string()) }.boxed();
+        }
+        if !self.query_rows.is_empty() {
+            let rows = self.query_rows.clone();
+            return async move { Ok(rows) }.boxed();
+        }
+        let rows = self
+            .logs
+            .iter()
+            .map(|event| {
+                vec![
+                    ("query".to_string(), query.clone()),
+                    ("@message".to_string(), event.message.to_string()),
+                ]
+            })
+            .collect();
+        async move { Ok(rows) }.boxed()
+    }
+
+    fn start_live_tail(
+        &self,
+        _region: Option<String>,
+        _log_group_arns: Vec<String>,
+        _log_stream_name_prefix: String,
+        filter_pattern: String,
+    ) -> BoxFuture<'_, Result<Box<dyn LiveTailSession>, String>> {
+        self.tail_filters.lock().unwrap().push(filter_pattern);
+        if self.tail_batches.is_empty() {
+            return async { Err("live tail is not mocked".to_string()) }.boxed();
+        }
+        let tail = MockTail {
+            batches: self.tail_batches.clone(),
+            sampled: self.tail_sampled,
+        };
+        futures::future::ready(Ok(Box::new(tail) as Box<dyn LiveTailSession>)).boxed()
+    }
+
+    fn change_log_group(
+        &self,
+        log_group: LogGroup,
+        _change: GroupChange,
+    ) -> BoxFuture<'_, Result<(), String>> {
+        let result = if self.offline.load(Ordering::SeqCst) {
+            Err(OFFLINE.to_string())
+        } else if self.unchangeable_log_groups.contains(&log_group.name) {
+            Err("access denied".to_string())
+        } else {
+            Ok(())
+        };
+        async move { result }.boxed()
+    }
+
+    fn fetch_metric_filters(
+        &self,
+        _region: Option<String>,
+        _log_group_name: String,
+    ) -> BoxFuture<'_, Result<Vec<MetricFilter>, String>> {
+        let filters = self.metric_filters.clone();
+        async move { Ok(filters) }.boxed()
+    }
+
+    /// Only plain terms, which is all the tests use
+    fn test_metric_filter(
+        &self,
+        _region: Option<String>,
+        pattern: String,
+        messages: Vec<String>,
+    ) -> BoxFuture<'_, Result<Vec<usize>, String>> {
+        let matches = messages
+            .iter()
+            .enumerate()
+            .filter(|(_, message)| message.contains(pattern.trim_matches('"')))
+            .map(|(index, _)| index)
+            .collect();
+        async move { Ok(matches) }.boxed()
+    }
+
+    fn fetch_field_indexes(
+        &self,
+        _region: Option<String>,
+        _log_group_name: String,
+    ) -> BoxFuture<'_, Result<Vec<String>, String>> {
+        let fields = self.field_indexes.clone();
+        async move { Ok(fields) }.boxed()
+    }
+
+    fn fetch_alarms(&self, _region: Option<String>) -> BoxFuture<'_, Result<Vec<Alarm>, String>> {
+        let alarms = self.alarms.clone();
+        async move { Ok(alarms) }.boxed()
+    }
+
+    fn fetch_metric_filter_groups(
+        &self,
+        _region: Option<String>,
+        _metric_name: String,
+        _namespace: String,
+    ) -> BoxFuture<'_, Result<Vec<String>, String>> {
+        let names = self.alarm_log_groups.clone();
+        async move { Ok(names) }.boxed()
+    }
+
+    fn fetch_metric_alarms(
+        &self,
+        _region: Option<String>,
+        metric_name: String,
+        namespace: String,
+    ) -> BoxFuture<'_, Result<Vec<Alarm>, String>> {
+        let alarms = self
+            .alarms
+            .iter()
+            .filter(|alarm| {
+                alarm.metric_name.as_ref() == Some(&metric_name)
+                    && alarm.namespace.as_ref() == Some(&namespace)
+            })
+            .cloned()
+            .collect();
+        async move { Ok(alarms) }.boxed()
+    }
+
+    fn fetch_unmasked(
+        &self,
+        _region: Option<String>,
+        pointer: String,
+    ) -> BoxFuture<'_, Result<String, String>> {
+        let message = self
+            .unmasked
+            .iter()
+            .find(|(unmasked, _)| *unmasked == pointer)
+            .map(|(_, message)| message.clone())
+            .ok_or_else(|| "AccessDeniedException".to_string());
+        async move { message }.boxed()
+    }
+
+    fn fetch_log_record(
+        &self,
+        _region: Option<String>,
+        pointer: String,
+    ) -> BoxFuture<'_, Result<QueryRow, String>> {
+        let fields = self
+            .log_records
+            .iter()
+            .find(|(record, _)| *record == pointer)
+            .map(|(_, fields)| fields.clone())
+            .ok_or_else(|| "ResourceNotFoundException".to_string());
+        async move { fields }.boxed()
+    }
+
+    fn fetch_accounts(&self) -> BoxFuture<'_, Result<Vec<Account>, String>> {
+        futures::future::ready(Ok(self.accounts.clone())).boxed()
+    }
+
+    fn switch_account(&self, account: Account) -> BoxFuture<'_, Result<(), String>> {
+        *self.switched.lock().unwrap() = Some(account);
+        futures::future::ready(Ok(())).boxed()
+    }
+
+    fn fetch_group_metadata(
+        &self,
+        _region: Option<String>,
+        log_group_name: String,
+        _log_group_arn: String,
+    ) -> BoxFuture<'_, Result<GroupMetadata, String>> {
+        self.metadata_fetches.fetch_add(1, Ordering::SeqCst);
+        let metadata = self
+            .group_metadata
+            .iter()
+            .find(|(name, _)| *name == log_group_name)
+            .map(|(_, metadata)| metadata.clone())
+            .unwrap_or_default();
+        futures::future::ready(Ok(metadata)).boxed()
+    }
+
+    fn query_counts(&self) -> QueryCounts {
+        self.query_counts
+    }
+}
+
+fn group(name: &str) -> LogGroup {
+    LogGroup {
+        name: name.to_string(),
+        arn: format!("arn:aws:logs:eu-west-1:123456789012:log-group:{}", name),
+        ..Default::default()
+    }
+}
+
+fn event(message: &str) -> LogEvent {
+    LogEvent {
+        timestamp: 0,
+        message: message.into(),
+        log_group: "/aws/lambda/checkout".into(),
+        log_stream: "stream".into(),
+        ..Default::default()
+    }
+}
+
+fn app_with(backend: MockBackend) -> App {
+    let mut app = App::new(Arc::new(backend));
+    app.start();
+    app
+}
+
+/// Loads the mocked groups, then waits for the page and the loaded state to arrive
+async fn load_groups(app: &mut App) {
+    next_message(app).await;
+    next_message(app).await;
+}
+
+async fn next_message(app: &mut App) {
+    tokio::time::timeout(Duration::from_secs(1), app.handle_next_message())
+        .await
+        .expect("no message from background task");
+}
+
+/// Waits for the background search to catch up with what was typed
+async fn search_results(app: &mut App) {
+    while app.is_search_pending() {
+        next_message(app).await;
+    }
+}
+
+fn press(app: &mut App, code: KeyCode) {
+    app.handle_event(&Event::Key(KeyEvent::new(code, KeyModifiers::NONE)));
+}
+
+fn render(app: &mut App) -> String {
+    render_sized(app, 80, 12)
+}
+
+fn render_sized(app: &mut App, width: u16, height: u16) -> String {
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    terminal.draw(|frame| app.draw(frame)).unwrap();
+    let buffer = terminal.backend().buffer();
+    buffer
+        .content()
+        .chunks(buffer.area.width as usize)
+        .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The screen as the terminal shows it, wide characters take up two cells, for snapshots
+fn snapshot(app: &mut App) -> String {
+    let mut terminal = Terminal::new(TestBackend::new(80, 12)).unwrap();
+    terminal.draw(|frame| app.draw(frame)).unwrap();
+    terminal.backend().to_string()
+}
+
+fn mock_backend() -> MockBackend {
+    MockBackend {
+        log_groups: vec![group("/aws/lambda/checkout"), group("/service/dev/api")],
+        logs: vec![event("payment accepted"), event("order shipped")],
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn loads_log_groups() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+
+    let screen = render(&mut app);
+    assert!(screen.contains("/aws/lambda/checkout"));
+    assert!(screen.contains("/service/dev/api"));
+    assert!(screen.contains("Loaded"));
+}
+
+#[tokio::test]
+async fn search_filters_log_groups() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+
+    press(&mut app, KeyCode::Char('/'));
+    for c in "checkout".chars() {
+        press(&mut app, KeyCode::Char(c));
+    }
+    search_results(&mut app).await;
+
+    let screen = render(&mut app);
+    assert!(screen.contains("/checkout"));
+    assert!(screen.contains("/aws/lambda/checkout"));
+    assert!(!screen.contains("/service/dev/api"));
+    assert!(screen.contains("1 / 2 matches"));
+
+    for c in "zzz".chars() {
+        press(&mut app, KeyCode::Char(c));
+    }
+    search_results(&mut app).await;
+    assert!(render(&mut app).contains("no matches for 'checkoutzzz'"));
+}
+
+#[tokio::test]
+async fn group_selection_stays_within_search_results() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Char('/'));
+    for c in "checkout".chars() {
+        press(&mut app, KeyCode::Char(c));
+    }
+    search_results(&mut app).await;
+
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    assert_eq!(app.current_screen(), Screen::LogViewer);
+}
+
+#[tokio::test]
+async fn selecting_a_group_shows_its_logs() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    let screen = render(&mut app);
+    assert!(screen.contains("/aws/lambda/checkout"));
+    assert!(screen.contains("payment accepted"));
+    assert!(screen.contains("order shipped"));
+}
+
+#[tokio::test]
+async fn esc_in_viewer_returns_to_group_list() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    press(&mut app, KeyCode::Esc);
+
+    assert!(!app.should_quit());
+    assert_eq!(app.current_screen(), Screen::LogGroups);
+    let screen = render(&mut app);
+    assert!(screen.contains("Log Groups"));
+    assert!(screen.contains("/service/dev/api"));
+}
+
+#[tokio::test]
+async fn esc_pops_one_screen_at_a_time() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    assert_eq!(app.current_screen(), Screen::LogDetail);
+    assert!(render(&mut app).contains("order shipped"));
+
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.current_screen(), Screen::LogViewer);
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.current_screen(), Screen::LogGroups);
+    press(&mut app, KeyCode::Esc);
+    assert!(app.should_quit());
+}
+
+#[tokio::test]
+async fn logs_arriving_after_esc_are_dropped() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    // let the fetch finish, its logs are queued but not applied yet
+    for _ in 0..10 {
+        tokio::task::yield_now().await;
+    }
+    press(&mut app, KeyCode::Esc);
+    next_message(&mut app).await;
+
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(screen.contains("Loading"));
+    assert!(!screen.contains("payment accepted"));
+
+    next_message(&mut app).await;
+    assert!(render(&mut app).contains("payment accepted"));
+}
+
+#[tokio::test]
+async fn results_of_an_earlier_run_are_dropped() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    for _ in 0..10 {
+        tokio::task::yield_now().await;
+    }
+    // rerun while the first run's logs are still queued
+    press(&mut app, KeyCode::Char('R'));
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(screen.contains("Loading"));
+    assert!(!screen.contains("payment accepted"));
+
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(screen.contains("payment accepted"));
+    assert!(screen.contains("rerun: 2 new since the last run"));
+}
+
+#[tokio::test]
+async fn rerunning_keeps_the_selected_event() {
+    let at = |timestamp, message| LogEvent {
+        timestamp,
+        ..event(message)
+    };
+    let mut app = app_with(MockBackend {
+        logs: vec![at(1, "payment accepted"), at(2, "order shipped")],
+        refreshed_logs: vec![
+            at(1, "payment accepted"),
+            at(2, "order shipped"),
+            at(3, "refund issued"),
+            at(4, "order cancelled"),
+        ],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    press(&mut app, KeyCode::Up);
+    press(&mut app, KeyCode::Char('R'));
+    next_message(&mut app).await;
+    assert!(render(&mut app).contains("order cancelled"));
+
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(screen.contains("payment accepted"));
+    assert!(!screen.contains("refund issued"));
+}
+
+#[tokio::test]
+async fn events_returned_by_several_queries_show_once() {
+    let with_pointer = |message, pointer: &str| LogEvent {
+        pointer: Some(pointer.into()),
+        ..event(message)
+    };
+    let mut app = app_with(MockBackend {
+        log_groups: vec![
+            group("/aws/lambda/checkout"),
+            LogGroup {
+                arn: "arn:aws:logs:us-east-1:123456789012:log-group:/service/dev/api".to_string(),
+                ..group("/service/dev/api")
+            },
+        ],
+        logs: vec![
+            with_pointer("payment accepted", "ptr-1"),
+            with_pointer("payment accepted", "ptr-2"),
+        ],
+        ..Default::default()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Char(' '));
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Char(' '));
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    // each region's query returned both events, they're only shown once
+    let screen = render(&mut app);
+    assert_eq!(screen.matches("payment accepted").count(), 2);
+}
+
+#[tokio::test]
+async fn esc_ends_a_search_before_leaving_the_screen() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Char('/'));
+    press(&mut app, KeyCode::Char('q'));
+    press(&mut app, KeyCode::Esc);
+    search_results(&mut app).await;
+    assert!(!app.should_quit());
+    assert!(!render(&mut app).contains("/q"));
+
+    press(&mut app, KeyCode::Enter);
+    // results of the search typed above may still be on their way from the search thread
+    while !render(&mut app).contains("order shipped") {
+        next_message(&mut app).await;
+    }
+    press(&mut app, KeyCode::Char('w'));
+    press(&mut app, KeyCode::Char('*'));
+    assert!(render(&mut app).contains("search: shipped"));
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.current_screen(), Screen::LogViewer);
+    assert!(!render(&mut app).contains("search:"));
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.current_screen(), Screen::LogGroups);
+}
+
+#[tokio::test]
+async fn shows_error_when_groups_fail_to_load() {
+    let mut app = app_with(MockBackend {
+        log_groups_error: Some("AccessDenied".to_string()),
+        ..Default::default()
+    });
+    next_message(&mut app).await;
+
+    let screen = render(&mut app);
+    assert!(screen.contains("AccessDenied"));
+}
+
+#[tokio::test]
+async fn q_quits() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Char('q'));
+    assert!(app.should_quit());
+}
+
+#[tokio::test]
+async fn asks_before_quitting_while_loading() {
+    let mut app = app_with(MockBackend {
+        log_groups_pending: true,
+        ..Default::default()
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    press(&mut app, KeyCode::Char('q'));
+    assert!(!app.should_quit());
+    assert!(render(&mut app).contains("quit anyway?"));
+
+    press(&mut app, KeyCode::Char('n'));
+    assert!(!app.should_quit());
+    assert!(!render(&mut app).contains("quit anyway?"));
+
+    press(&mut app, KeyCode::Char('q'));
+    press(&mut app, KeyCode::Char('y'));
+    assert!(app.should_quit());
+}
+
+#[tokio::test]
+async fn status_bar_lists_running_tasks() {
+    let mut app = app_with(MockBackend {
+        log_groups_pending: true,
+        ..Default::default()
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(render(&mut app).contains("● loading groups"));
+
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!render(&mut app).contains("loading groups"));
+}
+
+#[tokio::test]
+async fn e_jumps_between_errors() {
+    let mut app = app_with(MockBackend {
+        logs: vec![
+            event("[ERROR] first failure"),
+            event("all good"),
+            event(r#"{"level":"error","msg":"second failure"}"#),
+            event("still fine"),
+        ],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    // the newest event is selected, E walks back to older errors
+    press(&mut app, KeyCode::Char('E'));
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    assert!(render(&mut app).contains("second failure"));
+
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Char('E'));
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    assert!(render(&mut app).contains("first failure"));
+
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Char('e'));
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    assert!(render(&mut app).contains("second failure"));
+}
+
+#[tokio::test]
+async fn f_filters_by_token_under_cursor() {
+    let mut app = app_with(MockBackend {
+        logs: vec![
+            event("start req-1"),
+            event("start req-2"),
+            event("end req-1"),
+            event("end req-2"),
+        ],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    // the newest event "end req-2" is selected, w moves onto the request id
+    press(&mut app, KeyCode::Char('w'));
+    press(&mut app, KeyCode::Char('f'));
+    let screen = render(&mut app);
+    assert!(screen.contains("start req-2"));
+    assert!(!screen.contains("req-1"));
+    assert!(screen.contains("filter: req-2"));
+
+    press(&mut app, KeyCode::Char('F'));
+    assert!(render(&mut app).contains("end req-1"));
+}
+
+#[tokio::test]
+async fn star_searches_in_the_background() {
+    let mut app = app_with(MockBackend {
+        logs: vec![
+            event("start req-1"),
+            event("start req-2"),
+            event("end req-1"),
+            event("end req-2"),
+        ],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    // jumps straight away, the match count follows once the worker is done
+    press(&mut app, KeyCode::Char('w'));
+    press(&mut app, KeyCode::Char('*'));
+    assert!(render(&mut app).contains("search: req-2 (…)"));
+    search_results(&mut app).await;
+    assert!(render(&mut app).contains("search: req-2 (2 matches)"));
+
+    press(&mut app, KeyCode::Char('n'));
+    assert!(!app.is_search_pending());
+    assert!(render(&mut app).contains("search: req-2 (2 matches)"));
+
+    // only the matched text is highlighted within the row
+    let mut terminal = Terminal::new(TestBackend::new(80, 12)).unwrap();
+    terminal.draw(|frame| app.draw(frame)).unwrap();
+    let buffer = terminal.backend().buffer();
+    let row = buffer
+        .content()
+        .chunks(80)
+        .find(|row| {
+            row.iter()
+                .map(|cell| cell.symbol())
+                .collect::<String>()
+                .contains("end req-2")
+        })
+        .unwrap();
+    let start = row.iter().position(|cell| cell.symbol() == "e").unwrap();
+    let bold = |cell: &ratatui::buffer::Cell| cell.modifier.contains(Modifier::BOLD);
+    assert!(!bold(&row[start]));
+    assert!(row[start + 4..start + 9].iter().all(bold));
+}
+
+#[tokio::test]
+async fn events_over_the_memory_cap_spill_to_disk() {
+    let mut app = app_with(MockBackend {
+        logs: (1..=5).map(|n| event(&format!("event {}", n))).collect(),
+        ..mock_backend()
+    });
+    app.set_memory_events(2);
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    // the oldest three are read back from the temp file to draw them
+    let screen = render(&mut app);
+    assert!(screen.contains("3 on disk"));
+    for n in 1..=5 {
+        assert!(screen.contains(&format!("event {}", n)));
+    }
+
+    press(&mut app, KeyCode::Char('/'));
+    press(&mut app, KeyCode::Char('1'));
+    press(&mut app, KeyCode::Enter);
+    let screen = render(&mut app);
+    assert!(screen.contains("event 1"));
+    assert!(!screen.contains("event 2"));
+}
+
+#[tokio::test]
+async fn m_tests_a_metric_filter_on_loaded_events() {
+    let mut app = app_with(MockBackend {
+        metric_filters: vec![MetricFilter {
+            pattern: "\"shipped\"".to_string(),
+            ..Default::default()
+        }],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    press(&mut app, KeyCode::Char('m'));
+    next_message(&mut app).await;
+    assert!(render(&mut app).contains("metric filter:   (↑↓ 1 existing)"));
+
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    assert!(render(&mut app).contains("metric filter: \"shipped\" (testing…)"));
+    next_message(&mut app).await;
+    assert!(render(&mut app).contains("metric filter: \"shipped\" (1 of the last 2 match)"));
+
+    press(&mut app, KeyCode::Char('m'));
+    for _ in 0.."\"shipped\"".len() {
+        press(&mut app, KeyCode::Backspace);
+    }
+    press(&mut app, KeyCode::Enter);
+    assert!(!render(&mut app).contains("metric filter:"));
+}
+
+#[tokio::test]
+async fn shift_u_unmasks_a_data_protected_event() {
+    let mut app = app_with(MockBackend {
+        log_groups: vec![LogGroup {
+            data_protected: true,
+            ..group("/aws/lambda/checkout")
+        }],
+        logs: vec![LogEvent {
+            pointer: Some("ptr-1".into()),
+            ..event("card ****")
+        }],
+        unmasked: vec![("ptr-1".to_string(), "card 4111".to_string())],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(screen.contains("[masked]"), "{screen}");
+
+    press(&mut app, KeyCode::Char('U'));
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(screen.contains("card 4111"));
+    assert!(!screen.contains("[masked]"));
+}
+
+#[tokio::test]
+async fn viewer_footer_shows_where_the_selected_event_came_from() {
+    let mut app = app_with(MockBackend {
+        logs: vec![LogEvent {
+            ingestion_time: Some(2000),
+            event_id: Some("evt-1".into()),
+            ..event("payment accepted")
+        }],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    let screen = render(&mut app);
+    assert!(
+        screen.contains("stream stream · ingested 1970-01-01T00:00:02+00:00, 2s after · id evt-1"),
+        "{screen}"
+    );
+}
+
+#[tokio::test]
+async fn f_in_the_detail_shows_every_field() {
+    let mut app = app_with(MockBackend {
+        logs: vec![LogEvent {
+            pointer: Some("ptr-1".into()),
+            ..event("payment accepted")
+        }],
+        log_records: vec![(
+            "ptr-1".to_string(),
+            vec![
+                ("@message".to_string(), "payment accepted".to_string()),
+                ("orderId".to_string(), "A-1042".to_string()),
+            ],
+        )],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    assert_eq!(app.current_screen(), Screen::LogDetail);
+
+    press(&mut app, KeyCode::Char('f'));
+    assert!(render(&mut app).contains("loading fields…"));
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(screen.contains("orderId   A-1042"), "{screen}");
+
+    // back to the message
+    press(&mut app, KeyCode::Char('f'));
+    assert!(!render(&mut app).contains("A-1042"));
+}
+
+#[tokio::test]
+async fn indexed_fields_show_in_group_info_and_filter_hints() {
+    let mut app = app_with(MockBackend {
+        field_indexes: vec!["requestId".to_string()],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+
+    press(&mut app, KeyCode::Char('i'));
+    assert!(render(&mut app).contains("loading…"));
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(screen.contains("never expires"), "{screen}");
+    assert!(screen.contains("indexes     requestId"));
+    press(&mut app, KeyCode::Esc);
+    assert!(!render(&mut app).contains("indexes"));
+
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    press(&mut app, KeyCode::Char('/'));
+    next_message(&mut app).await;
+    for c in "requestId".chars() {
+        press(&mut app, KeyCode::Char(c));
+    }
+    let screen = render(&mut app);
+    assert!(screen.contains("requestId is indexed"), "{screen}");
+}
+
+#[tokio::test]
+async fn field_stats_summarise_numeric_fields() {
+    let mut app = app_with(MockBackend {
+        logs: vec![
+            event(r#"{"msg":"done","durationMs":10}"#),
+            event(r#"{"msg":"done","durationMs":30}"#),
+            event("request done durationMs=20 status=200"),
+        ],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    press(&mut app, KeyCode::Char('#'));
+    assert!(render(&mut app).contains("status"));
+    press(&mut app, KeyCode::Enter);
+    let screen = render(&mut app);
+    assert!(screen.contains("count 3"), "{screen}");
+    assert!(screen.contains("min 10  max 30"));
+    assert!(screen.contains("p50 20  p95 30"));
+
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Esc);
+    assert!(!render(&mut app).contains("Field stats"));
+}
+
+#[tokio::test]
+async fn c_compares_with_the_previous_day() {
+    let mut app = app_with(MockBackend {
+        logs: vec![event("[ERROR] payment failed"), event("order shipped")],
+        previous_logs: vec![event("order shipped")],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    press(&mut app, KeyCode::Char('c'));
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(
+        screen.contains("previous day: 1 events, 0 errors"),
+        "{screen}"
+    );
+    assert!(screen.contains("last day: 2 events, 1 errors"));
+
+    press(&mut app, KeyCode::Char('c'));
+    assert!(!render(&mut app).contains("previous day"));
+}
+
+#[tokio::test]
+async fn v_opens_a_second_viewer_side_by_side() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    press(&mut app, KeyCode::Char('v'));
+    assert_eq!(app.current_screen(), Screen::LogGroups);
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    assert_eq!(app.current_screen(), Screen::LogViewer);
+    let screen = render(&mut app);
+    assert!(screen.contains("/aws/lambda/checkout"));
+    assert!(screen.contains("/service/dev/api"));
+
+    // esc closes the split before leaving the viewer
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.current_screen(), Screen::LogViewer);
+    assert!(!render(&mut app).contains("/service/dev/api"));
+}
+
+#[tokio::test]
+async fn z_expands_multi_line_events() {
+    let mut app = app_with(MockBackend {
+        logs: vec![event(
+            "panic: boom\n  at checkout::pay\n  at checkout::main",
+        )],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    let screen = render(&mut app);
+    assert!(screen.contains("panic: boom ▸ 2 more lines"));
+    assert!(!screen.contains("checkout::pay"));
+
+    press(&mut app, KeyCode::Char('z'));
+    let screen = render(&mut app);
+    assert!(screen.contains("at checkout::pay"));
+    assert!(screen.contains("at checkout::main"));
+
+    press(&mut app, KeyCode::Char('z'));
+    assert!(!render(&mut app).contains("checkout::pay"));
+}
+
+#[tokio::test]
+async fn large_messages_are_truncated_until_opened() {
+    let payload = format!("request body\n{}", "x".repeat(40 * 1024));
+    let mut app = app_with(MockBackend {
+        logs: vec![event(&payload)],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    let screen = render(&mut app);
+    assert!(screen.contains("request body … 40 KB, enter to expand"));
+
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    assert_eq!(app.current_screen(), Screen::LogDetail);
+}
+
+#[tokio::test]
+async fn group_list_scrolls_through_many_groups() {
+    let mut app = app_with(MockBackend {
+        log_groups: (0..1000)
+            .map(|i| group(&format!("/service/{:04}", i)))
+            .collect(),
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    assert!(render(&mut app).contains("/service/0000"));
+
+    for _ in 0..999 {
+        press(&mut app, KeyCode::Down);
+    }
+    let screen = render(&mut app);
+    assert!(screen.contains("/service/0999"));
+    assert!(!screen.contains("/service/0000"));
+}
+
+#[tokio::test]
+async fn all_regions_lists_groups_from_every_region() {
+    let mut app = App::new(Arc::new(MockBackend {
+        log_groups: vec![
+            group("/aws/lambda/checkout"),
+            LogGroup {
+                name: "/aws/lambda/billing".to_string(),
+                arn: "arn:aws:logs:us-east-1:123456789012:log-group:/aws/lambda/billing"
+                    .to_string(),
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    }));
+    app.set_all_regions(true);
+    app.start();
+    // a page per region, then the loaded state
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    let screen = render(&mut app);
+    assert!(screen.contains("Loaded"));
+    assert!(screen.contains("us-east-1"));
+    assert!(screen.contains("eu-west-1"));
+    assert!(screen.contains("/aws/lambda/billing"));
+}
+
+#[tokio::test]
+async fn digits_open_visible_rows() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+    assert!(render(&mut app).contains("2   /service/dev/api"));
+
+    press(&mut app, KeyCode::Char('2'));
+    next_message(&mut app).await;
+    assert_eq!(app.current_screen(), Screen::LogViewer);
+    assert!(render(&mut app).contains("/service/dev/api"));
+}
+
+#[tokio::test]
+async fn remembers_the_last_opened_group() {
+    let path = std::env::temp_dir().join(format!("loglog-state-{}.json", std::process::id()));
+    let store = StateStore::new(Some(path.clone()));
+
+    let mut app = App::new(Arc::new(mock_backend()));
+    app.set_state_store(store.clone(), false);
+    app.start();
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Char('2'));
+    next_message(&mut app).await;
+
+    let mut app = App::new(Arc::new(mock_backend()));
+    app.set_state_store(store, true);
+    app.start();
+    load_groups(&mut app).await;
+    next_message(&mut app).await;
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(app.current_screen(), Screen::LogViewer);
+    assert!(render(&mut app).contains("/service/dev/api"));
+}
+
+#[tokio::test]
+async fn exclude_patterns_hide_groups_until_toggled() {
+    let mut app = App::new(Arc::new(mock_backend()));
+    app.set_exclude_patterns(vec![
+        compile_pattern("*/dev/*").unwrap(),
+        compile_pattern("/^RDSOS/").unwrap(),
+    ]);
+    app.start();
+    load_groups(&mut app).await;
+
+    let screen = render(&mut app);
+    assert!(screen.contains("/aws/lambda/checkout"));
+    assert!(!screen.contains("/service/dev/api"));
+    assert!(screen.contains("1 hidden, H to show"));
+
+    press(&mut app, KeyCode::Char('H'));
+    assert!(render(&mut app).contains("/service/dev/api"));
+}
+
+#[tokio::test]
+async fn x_hides_a_group_across_runs() {
+    let path = std::env::temp_dir().join(format!("loglog-hidden-{}.json", std::process::id()));
+    let store = StateStore::new(Some(path.clone()));
+
+    let mut app = App::new(Arc::new(mock_backend()));
+    app.set_state_store(store.clone(), false);
+    app.start();
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Char('x'));
+    next_message(&mut app).await;
+    assert!(!render(&mut app).contains("/aws/lambda/checkout"));
+
+    let mut app = App::new(Arc::new(mock_backend()));
+    app.set_state_store(store, false);
+    app.start();
+    load_groups(&mut app).await;
+    std::fs::remove_file(path).unwrap();
+    let screen = render(&mut app);
+    assert!(!screen.contains("/aws/lambda/checkout"));
+    assert!(screen.contains("1 hidden, H to show"));
+
+    press(&mut app, KeyCode::Char('H'));
+    assert!(render(&mut app).contains("/aws/lambda/checkout"));
+}
+
+#[tokio::test]
+async fn bulk_delete_reports_each_group() {
+    let mut app = app_with(MockBackend {
+        unchangeable_log_groups: vec!["/service/dev/api".to_string()],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Char(' '));
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Char(' '));
+
+    press(&mut app, KeyCode::Char('b'));
+    assert!(render(&mut app).contains("Change 2 groups"));
+    press(&mut app, KeyCode::Char('d'));
+    assert!(render(&mut app).contains("Delete 2 groups"));
+    press(&mut app, KeyCode::Char('y'));
+
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(!screen.contains("/aws/lambda/checkout"));
+    assert!(screen.contains("/service/dev/api: access denied"));
+}
+
+#[tokio::test]
+async fn the_bulk_menu_follows_overridden_bindings_and_edits_like_a_prompt() {
+    let overrides = [("bulk.retention".to_string(), vec!["R".to_string()])].into();
+    let mut app = App::new(Arc::new(mock_backend()));
+    app.set_keymap(Keymap::with_overrides(&overrides).unwrap());
+    app.start();
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Char(' '));
+    press(&mut app, KeyCode::Char('b'));
+    assert!(render(&mut app).contains("Change 1 groups: R retention, t tag, d delete"));
+    press(&mut app, KeyCode::Char('r'));
+    assert!(!render(&mut app).contains("Retention in days"));
+
+    press(&mut app, KeyCode::Char('R'));
+    press(&mut app, KeyCode::Char('1'));
+    press(&mut app, KeyCode::Char('4'));
+    press(&mut app, KeyCode::Left);
+    press(&mut app, KeyCode::Backspace);
+    app.handle_event(&Event::Paste("3".to_string()));
+    assert!(render(&mut app).contains("Retention in days: 34"));
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    assert!(render(&mut app).contains("/aws/lambda/checkout: done"));
+}
+
+#[tokio::test]
+async fn a_stream_prefix_fetch_says_when_it_stopped_paging() {
+    let mut app = app_with(MockBackend {
+        logs: (0..10_000)
+            .map(|n| event(&format!("event {}", n)))
+            .collect(),
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    assert!(!render_sized(&mut app, 160, 12).contains("only the first"));
+
+    press(&mut app, KeyCode::Char('s'));
+    press(&mut app, KeyCode::Char('w'));
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    assert!(render_sized(&mut app, 160, 12)
+        .contains("only the first 10,000 events of /aws/lambda/checkout were fetched"));
+}
+
+#[tokio::test]
+async fn viewer_warns_when_the_range_outlasts_retention() {
+    let mut app = app_with(MockBackend {
+        log_groups: vec![LogGroup {
+            stored_bytes: Some(1024_i64.pow(3)),
+            retention_in_days: Some(1),
+            ..group("/aws/lambda/checkout")
+        }],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(screen.contains("1.0 GB stored, 1d retention"), "{screen}");
+    assert!(!screen.contains("older events are gone"));
+
+    press(&mut app, KeyCode::Char('c'));
+    let screen = render(&mut app);
+    assert!(
+        screen.contains("1d retention, older events are gone"),
+        "{screen}"
+    );
+}
+
+#[tokio::test]
+async fn a_picked_window_longer_than_retention_warns() {
+    let mut app = app_with(MockBackend {
+        log_groups: vec![LogGroup {
+            retention_in_days: Some(7),
+            ..group("/aws/lambda/checkout")
+        }],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Char('t'));
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    // a week of a group that keeps a week is all there
+    assert!(!render(&mut app).contains("older events are gone"));
+
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Char('t'));
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(
+        screen.contains("7d retention, older events are gone"),
+        "{screen}"
+    );
+}
+
+#[tokio::test]
+async fn the_comparison_is_the_window_just_before_a_longer_one() {
+    let backend = Arc::new(MockBackend {
+        previous_logs: vec![event("card declined")],
+        ..mock_backend()
+    });
+    let mut app = App::new(backend.clone());
+    app.start();
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Char('t'));
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    press(&mut app, KeyCode::Char('c'));
+    next_message(&mut app).await;
+    let screen = render_sized(&mut app, 120, 12);
+    assert!(
+        screen.contains("previous window: 1 events, 0 errors"),
+        "{screen}"
+    );
+    assert!(screen.contains("this window: 2 events"), "{screen}");
+    let ranges = backend.fetched_ranges.lock().unwrap().clone();
+    let week = 7 * 24 * 3600 * 1000;
+    assert_eq!(ranges[0].1 - ranges[0].0, week);
+    assert_eq!(ranges[1].1 - ranges[1].0, week);
+    assert!((ranges[1].1 - ranges[0].0).abs() < 1000, "{ranges:?}");
+}
+
+#[tokio::test]
+async fn expensive_queries_ask_first() {
+    let mut app = app_with(MockBackend {
+        log_groups: vec![LogGroup {
+            stored_bytes: Some(5 * 1024_i64.pow(4)),
+            retention_in_days: Some(7),
+            ..group("/aws/lambda/checkout")
+        }],
+        logs: vec![event("payment accepted")],
+        ..Default::default()
+    });
+    app.set_confirm_query_bytes(100 * 1024_i64.pow(3));
+    load_groups(&mut app).await;
+
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(screen.contains("731.4 GB"));
+    assert!(!screen.contains("payment accepted"));
+
+    press(&mut app, KeyCode::Char('n'));
+    assert!(!render(&mut app).contains("731.4 GB"));
+    press(&mut app, KeyCode::Char('r'));
+    press(&mut app, KeyCode::Char('y'));
+    next_message(&mut app).await;
+    assert!(render(&mut app).contains("payment accepted"));
+}
+
+#[tokio::test]
+async fn headless_commands_print_groups_and_rows() {
+    let backend = mock_backend();
+    let mut out = vec![];
+    let printer = Printer::new(&mut out, OutputFormat::Text, None);
+    headless::list_groups(&backend, vec![None], printer, vec![])
+        .await
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "/aws/lambda/checkout\n/service/dev/api\n"
+    );
+
+    let mut out = vec![];
+    let since_ms = headless::parse_since("2h").unwrap();
+    assert_eq!(since_ms, 2 * 3600 * 1000);
+    headless::query(
+        &backend,
+        "/aws/lambda/checkout".to_string(),
+        "stats count(*)".to_string(),
+        since_ms,
+        None,
+        Printer::new(&mut out, OutputFormat::Text, None),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "query=stats count(*) @message=payment accepted\nquery=stats count(*) @message=order shipped\n"
+    );
+    assert!(headless::parse_since("2 weeks").is_err());
+}
+
+#[tokio::test]
+async fn search_counts_matches_in_every_group() {
+    let backend = MockBackend {
+        query_rows: vec![
+            vec![
+                (
+                    "@log".to_string(),
+                    "123456789012:/aws/lambda/checkout".to_string(),
+                ),
+                ("matches".to_string(), "3".to_string()),
+            ],
+            vec![
+                (
+                    "@log".to_string(),
+                    "123456789012:/service/dev/api".to_string(),
+                ),
+                ("matches".to_string(), "7".to_string()),
+            ],
+        ],
+        ..mock_backend()
+    };
+    let mut out = vec![];
+    headless::search(
+        &backend,
+        vec![None],
+        "timeout".to_string(),
+        headless::parse_since("15m").unwrap(),
+        Printer::new(&mut out, OutputFormat::Text, None),
+        vec![],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "7 /service/dev/api\n3 /aws/lambda/checkout\n"
+    );
+}
+
+#[tokio::test]
+async fn search_prints_what_it_could_search_and_reports_the_rest() {
+    let backend = MockBackend {
+        log_groups: vec![
+            group("/aws/lambda/checkout"),
+            LogGroup {
+                arn: "arn:aws:logs:us-east-1:123456789012:log-group:/aws/lambda/refunds"
+                    .to_string(),
+                ..group("/aws/lambda/refunds")
+            },
+        ],
+        disabled_regions: vec!["ap-east-1".to_string()],
+        unqueryable_log_groups: vec!["/aws/lambda/refunds".to_string()],
+        query_rows: vec![vec![
+            (
+                "@log".to_string(),
+                "123456789012:/aws/lambda/checkout".to_string(),
+            ),
+            ("matches".to_string(), "3".to_string()),
+        ]],
+        ..mock_backend()
+    };
+    let regions = ["eu-west-1", "us-east-1", "ap-east-1"]
+        .map(|region| Some(region.to_string()))
+        .to_vec();
+    let (mut out, mut errors) = (vec![], vec![]);
+    headless::search(
+        &backend,
+        regions,
+        "timeout".to_string(),
+        headless::parse_since("15m").unwrap(),
+        Printer::new(&mut out, OutputFormat::Text, None),
+        &mut errors,
+    )
+    .await
+    .unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "3 /aws/lambda/checkout\n");
+    assert_eq!(
+        String::from_utf8(errors).unwrap(),
+        "ap-east-1: the security token included in the request is invalid\n\
+         us-east-1: /aws/lambda/refunds: access denied\n"
+    );
+}
+
+#[tokio::test]
+async fn listing_every_region_skips_the_ones_that_fail() {
+    let backend = MockBackend {
+        disabled_regions: vec!["ap-east-1".to_string()],
+        ..mock_backend()
+    };
+    let regions = vec![Some("eu-west-1".to_string()), Some("ap-east-1".to_string())];
+    let (mut out, mut errors) = (vec![], vec![]);
+    let printer = Printer::new(&mut out, OutputFormat::Text, None);
+    headless::list_groups(&backend, regions, printer, &mut errors)
+        .await
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "/aws/lambda/checkout\n/service/dev/api\n"
+    );
+    assert_eq!(
+        String::from_utf8(errors).unwrap(),
+        "ap-east-1: the security token included in the request is invalid\n"
+    );
+
+    let printer = Printer::new(vec![], OutputFormat::Text, None);
+    let regions = vec![Some("ap-east-1".to_string())];
+    assert!(headless::list_groups(&backend, regions, printer, vec![])
+        .await
+        .is_err());
+}
+
+#[test]
+fn query_placeholders_are_filled_from_values() {
+    let query = "filter requestId = '$requestId' and @message like /$$/ | limit $limit $requestId";
+    assert_eq!(headless::placeholders(query), vec!["requestId", "limit"]);
+    let values = [
+        ("requestId".to_string(), "abc".to_string()),
+        ("limit".to_string(), "20".to_string()),
+    ];
+    assert_eq!(
+        headless::fill_placeholders(query, &values).unwrap(),
+        "filter requestId = 'abc' and @message like /$/ | limit 20 abc"
+    );
+    assert_eq!(
+        headless::fill_placeholders(query, &values[1..]),
+        Err("no value for $requestId, pass --var requestId=...".to_string())
+    );
+}
+
+#[tokio::test]
+async fn split_queries_run_every_piece_in_order() {
+    assert_eq!(
+        headless::split_range(0, 25, 10),
+        vec![(0, 10), (10, 20), (20, 25)]
+    );
+
+    let backend = MockBackend {
+        logs: vec![event("payment accepted")],
+        ..mock_backend()
+    };
+    let mut out = vec![];
+    headless::query(
+        &backend,
+        "/aws/lambda/checkout".to_string(),
+        "fields @message".to_string(),
+        headless::parse_since("3d").unwrap(),
+        Some(headless::parse_since("1d").unwrap()),
+        Printer::new(&mut out, OutputFormat::Text, None),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "query=fields @message @message=payment accepted\n".repeat(3)
+    );
+}
+
+async fn print_groups(backend: &MockBackend, format: OutputFormat, fields: &[&str]) -> String {
+    let mut out = vec![];
+    let fields = (!fields.is_empty()).then(|| fields.iter().map(|f| f.to_string()).collect());
+    let printer = Printer::new(&mut out, format, fields);
+    headless::list_groups(backend, vec![None], printer, vec![])
+        .await
+        .unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[tokio::test]
+async fn headless_output_formats_keep_field_order() {
+    let backend = mock_backend();
+    assert_eq!(
+        print_groups(&backend, OutputFormat::Csv, &["region", "name"]).await,
+        "region,name\neu-west-1,/aws/lambda/checkout\neu-west-1,/service/dev/api\n"
+    );
+    assert_eq!(
+        print_groups(&backend, OutputFormat::Ndjson, &["name", "missing"]).await,
+        "{\"name\":\"/aws/lambda/checkout\",\"missing\":null}\n{\"name\":\"/service/dev/api\",\"missing\":null}\n"
+    );
+    let json = print_groups(&backend, OutputFormat::Json, &[]).await;
+    let groups: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(groups[1]["name"], "/service/dev/api");
+    assert!(json.find("\"name\"").unwrap() < json.find("\"arn\"").unwrap());
+}
+
+#[test]
+fn settings_prefer_flags_then_config_then_environment() {
+    let env = |name: &str| match name {
+        "LOGLOG_REGION" => Some("us-east-1".to_string()),
+        "LOGLOG_PROFILE" => Some("ops".to_string()),
+        "LOGLOG_SINCE" => Some("7d".to_string()),
+        "LOGLOG_OUTPUT" => Some("ndjson".to_string()),
+        _ => None,
+    };
+    let config = Config {
+        region: Some("eu-west-1".to_string()),
+        ..Default::default()
+    };
+    let flags = Flags {
+        profile: Some("dev".to_string()),
+        ..Default::default()
+    };
+
+    let settings = Settings::resolve(flags, &config, env).unwrap();
+    assert_eq!(settings.profile.as_deref(), Some("dev"));
+    assert_eq!(settings.region.as_deref(), Some("eu-west-1"));
+    assert_eq!(settings.since, "7d");
+    assert_eq!(settings.query, None);
+    assert_eq!(settings.output, OutputFormat::Ndjson);
+
+    let settings = Settings::resolve(Flags::default(), &Config::default(), |_| None).unwrap();
+    assert_eq!(settings.since, "1h");
+    assert_eq!(settings.output, OutputFormat::Text);
+    assert!(
+        Settings::resolve(Flags::default(), &Config::default(), |name| {
+            (name == "LOGLOG_OUTPUT").then(|| "xml".to_string())
+        })
+        .is_err()
+    );
+}
+
+#[test]
+fn directories_follow_each_platform() {
+    let env = |name: &str| match name {
+        "HOME" => Some("/home/me".into()),
+        "XDG_STATE_HOME" => Some("/var/state".into()),
+        "APPDATA" => Some("C:\\Roaming".into()),
+        _ => None,
+    };
+    let resolve = |dir, platform| paths::resolve(dir, platform, env).unwrap();
+    assert_eq!(
+        resolve(Dir::Config, Platform::Linux),
+        Path::new("/home/me/.config/loglog")
+    );
+    assert_eq!(
+        resolve(Dir::State, Platform::Linux),
+        Path::new("/var/state/loglog")
+    );
+    assert_eq!(
+        resolve(Dir::Cache, Platform::MacOs),
+        Path::new("/home/me/Library/Caches/loglog")
+    );
+    assert_eq!(
+        resolve(Dir::Config, Platform::Windows),
+        Path::new("C:\\Roaming").join("loglog")
+    );
+    assert_eq!(paths::resolve(Dir::Cache, Platform::Windows, env), None);
+}
+
+#[tokio::test]
+async fn help_and_keys_follow_overridden_bindings() {
+    let overrides = [("groups.open".to_string(), vec!["o".to_string()])].into();
+    let keymap = Keymap::with_overrides(&overrides).unwrap();
+    let mut out = vec![];
+    headless::keys(&keymap, Printer::new(&mut out, OutputFormat::Text, None)).unwrap();
+    assert!(String::from_utf8(out).unwrap().contains("groups open o\n"));
+
+    let mut app = App::new(Arc::new(mock_backend()));
+    app.set_keymap(keymap);
+    app.start();
+    load_groups(&mut app).await;
+
+    press(&mut app, KeyCode::Char('?'));
+    let screen = render(&mut app);
+    let open_line = screen
+        .lines()
+        .find(|line| line.contains("open the marked groups"))
+        .unwrap();
+    assert!(open_line.contains("│o "));
+    press(&mut app, KeyCode::Char('x'));
+    press(&mut app, KeyCode::Enter);
+    assert!(!render(&mut app).contains("payment accepted"));
+    press(&mut app, KeyCode::Char('o'));
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    assert!(render(&mut app).contains("payment accepted"));
+    assert!(Keymap::with_overrides(&[("viewer.fly".to_string(), vec![])].into()).is_err());
+}
+
+#[test]
+fn overrides_may_not_take_a_key_bound_in_the_same_context() {
+    let clash = [("viewer.search_token".to_string(), vec!["/".to_string()])].into();
+    assert_eq!(
+        Keymap::with_overrides(&clash).unwrap_err(),
+        "/ is bound to both viewer.search_token and viewer.filter"
+    );
+
+    // the key is free once the action holding it moves elsewhere, or in another context
+    let swapped = [
+        ("viewer.search_token".to_string(), vec!["/".to_string()]),
+        ("viewer.filter".to_string(), vec!["*".to_string()]),
+        ("detail.inspect".to_string(), vec!["/".to_string()]),
+    ]
+    .into();
+    assert!(Keymap::with_overrides(&swapped).is_ok());
+}
+
+#[tokio::test]
+async fn help_scrolls_to_bindings_below_the_fold() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Char('?'));
+    let screen = render_sized(&mut app, 120, 12);
+    assert!(screen.contains("move down"));
+    assert!(!screen.contains("answer yes when asked to confirm"));
+
+    for _ in 0..40 {
+        press(&mut app, KeyCode::Char('j'));
+    }
+    let screen = render_sized(&mut app, 120, 12);
+    assert!(screen.contains("switch to another profile or an account role of an SSO session"));
+    assert!(screen.contains("answer yes when asked to confirm"));
+    press(&mut app, KeyCode::Char('k'));
+    let screen = render_sized(&mut app, 120, 12);
+    assert!(
+        !screen.contains("answer yes when asked to confirm"),
+        "{screen}"
+    );
+    assert!(screen.contains("Keys"));
+    press(&mut app, KeyCode::Esc);
+    assert!(!render(&mut app).contains("Keys"));
+}
+
+#[tokio::test]
+async fn shift_r_reruns_and_reports_new_events() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    press(&mut app, KeyCode::Char('R'));
+    next_message(&mut app).await;
+    assert!(render(&mut app).contains("rerun: nothing new"));
+    press(&mut app, KeyCode::Char('r'));
+    next_message(&mut app).await;
+    assert!(!render(&mut app).contains("rerun:"));
+}
+
+#[tokio::test]
+async fn viewer_shows_the_scroll_position() {
+    let mut app = app_with(MockBackend {
+        logs: (1..=1200).map(|n| event(&format!("event {}", n))).collect(),
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    assert!(render(&mut app).contains("line 1,200 / 1,200 (100%)"));
+    press(&mut app, KeyCode::Char('k'));
+    assert!(render(&mut app).contains("line 1,199 / 1,200 (99%)"));
+}
+
+#[tokio::test]
+async fn error_summary_charts_errors_per_hour() {
+    let hour_ago = (chrono::Utc::now() - chrono::Duration::hours(1))
+        .format("%Y-%m-%d %H:00:00.000")
+        .to_string();
+    let mut app = App::new(Arc::new(MockBackend {
+        query_rows: vec![vec![
+            ("bin(1h)".to_string(), hour_ago),
+            ("errors".to_string(), "12".to_string()),
+        ]],
+        ..mock_backend()
+    }));
+    app.set_error_summary(true);
+    app.start();
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(
+        screen.contains("errors/h ▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁█▁ 12"),
+        "{screen}"
+    );
+}
+
+#[tokio::test]
+async fn running_queries_show_their_elapsed_time() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    assert!(render(&mut app).contains("Loading 0s"));
+    next_message(&mut app).await;
+    assert!(!render(&mut app).contains("Loading"));
+
+    assert_eq!(format_elapsed(Duration::from_secs(65)), "1m 05s");
+    assert_eq!(format_elapsed(Duration::from_secs(3660)), "1h 01m");
+}
+
+#[test]
+fn detects_the_terminal_background() {
+    assert_eq!(
+        osc_background("]11;rgb:ffff/ffff/ffff\\"),
+        Some(Background::Light)
+    );
+    assert_eq!(
+        osc_background("]11;rgb:1e1e/1e1e/2e2e\\"),
+        Some(Background::Dark)
+    );
+    // still waiting for the rest of the blue component
+    assert_eq!(osc_background("]11;rgb:ffff/ffff/ff"), None);
+    assert_eq!(colorfgbg_background("0;15"), Some(Background::Light));
+    assert_eq!(colorfgbg_background("15;default;0"), Some(Background::Dark));
+    assert_eq!(colorfgbg_background("default"), None);
+}
+
+#[tokio::test]
+async fn selection_follows_searching_and_filtering() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Char('/'));
+    for c in "api".chars() {
+        press(&mut app, KeyCode::Char(c));
+    }
+    search_results(&mut app).await;
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    assert!(render(&mut app).contains("/service/dev/api"));
+
+    press(&mut app, KeyCode::Char('k'));
+    press(&mut app, KeyCode::Char('/'));
+    for c in "pay".chars() {
+        press(&mut app, KeyCode::Char(c));
+    }
+    press(&mut app, KeyCode::Enter);
+    assert!(render(&mut app).contains("line 1 / 1"));
+    press(&mut app, KeyCode::Char('F'));
+    assert!(render(&mut app).contains("line 1 / 2"));
+}
+
+#[tokio::test]
+async fn slash_filters_by_text_or_regex() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    press(&mut app, KeyCode::Char('/'));
+    for c in "/pay.*ted/".chars() {
+        press(&mut app, KeyCode::Char(c));
+    }
+    press(&mut app, KeyCode::Enter);
+    let screen = render(&mut app);
+    assert!(screen.contains("payment accepted"));
+    assert!(!screen.contains("order shipped"));
+    assert!(screen.contains("filter: /pay.*ted/"));
+
+    press(&mut app, KeyCode::Char('/'));
+    for _ in 0.."/pay.*ted/".len() {
+        press(&mut app, KeyCode::Backspace);
+    }
+    for c in "/(/".chars() {
+        press(&mut app, KeyCode::Char(c));
+    }
+    press(&mut app, KeyCode::Enter);
+    assert!(render(&mut app).contains("invalid regex"));
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Char('F'));
+    assert!(render(&mut app).contains("order shipped"));
+}
+
+#[tokio::test]
+async fn inputs_edit_at_the_cursor() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    let ctrl = |app: &mut App, c| {
+        app.handle_event(&Event::Key(KeyEvent::new(
+            KeyCode::Char(c),
+            KeyModifiers::CONTROL,
+        )))
+    };
+
+    press(&mut app, KeyCode::Char('/'));
+    for c in "shipped".chars() {
+        press(&mut app, KeyCode::Char(c));
+    }
+    press(&mut app, KeyCode::Home);
+    for c in "order ".chars() {
+        press(&mut app, KeyCode::Char(c));
+    }
+    press(&mut app, KeyCode::End);
+    press(&mut app, KeyCode::Enter);
+    let screen = render(&mut app);
+    assert!(screen.contains("filter: order shipped"));
+    assert!(!screen.contains("payment accepted"));
+
+    // ctrl+w deletes a word at a time
+    press(&mut app, KeyCode::Char('/'));
+    ctrl(&mut app, 'w');
+    ctrl(&mut app, 'w');
+    for c in "payment".chars() {
+        press(&mut app, KeyCode::Char(c));
+    }
+    press(&mut app, KeyCode::Left);
+    press(&mut app, KeyCode::Delete);
+    press(&mut app, KeyCode::Enter);
+    let screen = render(&mut app);
+    assert!(screen.contains("filter: paymen ("), "{screen}");
+    assert!(screen.contains("payment accepted"));
+}
+
+#[tokio::test]
+async fn inputs_move_over_whole_graphemes() {
+    let mut app = app_with(MockBackend {
+        // an e followed by a combining accent, typed as two characters
+        logs: vec![event("xe\u{301}"), event("other")],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    press(&mut app, KeyCode::Char('/'));
+    press(&mut app, KeyCode::Char('e'));
+    press(&mut app, KeyCode::Char('\u{301}'));
+    press(&mut app, KeyCode::Left);
+    press(&mut app, KeyCode::Char('x'));
+    press(&mut app, KeyCode::Enter);
+    let screen = render(&mut app);
+    assert!(screen.contains("line 1 / 1"), "{screen}");
+    assert!(!screen.contains("other"));
+
+    // ctrl+alt is AltGr, it types instead of being ignored like ctrl
+    press(&mut app, KeyCode::Char('/'));
+    app.handle_event(&Event::Key(KeyEvent::new(
+        KeyCode::Char('@'),
+        KeyModifiers::CONTROL | KeyModifiers::ALT,
+    )));
+    assert!(render(&mut app).contains("filter: xe\u{301}@"));
+}
+
+#[tokio::test]
+async fn viewer_runs_the_query_configured_for_the_group() {
+    let mut app = app_with(mock_backend());
+    let config = Config {
+        viewer_query: Some("fields @message".to_string()),
+        group_queries: vec![GroupQuery {
+            groups: "/aws/lambda/*".to_string(),
+            query: "fields @timestamp, @message | sort @timestamp desc".to_string(),
+        }],
+        ..Config::default()
+    };
+    app.set_viewer_queries(ViewerQueries::from_config(&config).unwrap());
+    load_groups(&mut app).await;
+
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(screen.contains("fields @timestamp, @message | sort @timestamp desc"));
+    assert!(!screen.contains("payment accepted"));
+
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(screen.contains("fields @message"));
+    assert!(!screen.contains("sort @timestamp"));
+}
+
+#[tokio::test]
+async fn picked_fields_replace_the_fields_of_the_query() {
+    let mut app = app_with(MockBackend {
+        logs: vec![event(r#"{"level":"info","duration":12}"#)],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    press(&mut app, KeyCode::Char('Q'));
+    let screen = render(&mut app);
+    assert!(screen.contains("[x] @timestamp"), "{screen}");
+    assert!(screen.contains("[ ] @requestId"));
+    assert!(screen.contains("[ ] level"));
+
+    for _ in 0..4 {
+        press(&mut app, KeyCode::Down);
+    }
+    press(&mut app, KeyCode::Char(' '));
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    assert!(render(&mut app).contains("fields @timestamp, @message, @requestId"));
+}
+
+#[tokio::test]
+async fn picked_fields_are_shown_beside_the_message_as_logged() {
+    let mut app = app_with(MockBackend {
+        logs: vec![LogEvent {
+            fields: vec![("@requestId".into(), "abc".into())],
+            ..event(r#"{"level":"info","duration":12}"#)
+        }],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    assert!(render(&mut app).contains(r#"@requestId=abc {"level":"info","duration":12}"#));
+
+    // the message still reads as json
+    press(&mut app, KeyCode::Char('#'));
+    let screen = render(&mut app);
+    assert!(!screen.contains("no numeric fields"), "{screen}");
+    press(&mut app, KeyCode::Enter);
+    assert!(render(&mut app).contains("min 12  max 12"));
+}
+
+#[tokio::test]
+async fn the_field_picker_follows_overridden_bindings() {
+    let overrides = [("viewer.mark".to_string(), vec!["x".to_string()])].into();
+    let mut app = App::new(Arc::new(MockBackend {
+        logs: vec![event(r#"{"level":"info","duration":12}"#)],
+        ..mock_backend()
+    }));
+    app.set_keymap(Keymap::with_overrides(&overrides).unwrap());
+    app.start();
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    press(&mut app, KeyCode::Char('Q'));
+    assert!(render(&mut app).contains("x to pick, enter to run"));
+    for _ in 0..4 {
+        press(&mut app, KeyCode::Down);
+    }
+    press(&mut app, KeyCode::Char(' '));
+    assert!(render(&mut app).contains("[ ] @requestId"));
+    press(&mut app, KeyCode::Char('x'));
+    assert!(render(&mut app).contains("[x] @requestId"));
+}
+
+#[tokio::test]
+async fn field_stats_follow_overridden_bindings() {
+    let overrides = [
+        ("viewer.open".to_string(), vec!["o".to_string()]),
+        ("viewer.field_stats".to_string(), vec!["%".to_string()]),
+    ]
+    .into();
+    let mut app = App::new(Arc::new(MockBackend {
+        logs: vec![event(r#"{"duration":12,"size":3}"#)],
+        ..mock_backend()
+    }));
+    app.set_keymap(Keymap::with_overrides(&overrides).unwrap());
+    app.start();
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    press(&mut app, KeyCode::Char('%'));
+    assert!(render(&mut app).contains("o to pick, esc to close"));
+    press(&mut app, KeyCode::Char('j'));
+    press(&mut app, KeyCode::Enter);
+    assert!(!render(&mut app).contains("min 3  max 3"));
+    press(&mut app, KeyCode::Char('o'));
+    assert!(render(&mut app).contains("min 3  max 3"));
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Char('%'));
+    assert!(!render(&mut app).contains("Field stats"));
+}
+
+#[tokio::test]
+async fn wrapped_messages_show_what_they_wrap() {
+    let mut app = app_with(MockBackend {
+        logs: vec![
+            event(
+                r#"{"log":"payment accepted\n","stream":"stdout","time":"2024-01-01T10:00:00Z"}"#,
+            ),
+            event(r#"{"_aws":{"Timestamp":1,"CloudWatchMetrics":[]},"latency":12}"#),
+        ],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(screen.contains("payment accepted"), "{screen}");
+    assert!(screen.contains(r#"{"latency":12}"#));
+    assert!(!screen.contains("stdout"));
+    assert!(!screen.contains("CloudWatchMetrics"));
+
+    press(&mut app, KeyCode::Up);
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(screen.contains("logged as"), "{screen}");
+    assert!(screen.contains(r#""stream":"stdout""#));
+}
+
+#[tokio::test]
+async fn detail_lays_out_embedded_metrics() {
+    let emf = r#"{"_aws":{"Timestamp":1,"CloudWatchMetrics":[{"Namespace":"Checkout","Dimensions":[["Service","Operation"]],"Metrics":[{"Name":"Latency","Unit":"Milliseconds"},{"Name":"Retries"}]}]},"Service":"payments","Operation":"charge","Latency":[12,15],"Retries":0}"#;
+    let mut app = app_with(MockBackend {
+        logs: vec![event(emf)],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+
+    let screen = render(&mut app);
+    assert!(screen.contains("metrics in Checkout"), "{screen}");
+    assert!(screen.contains("Latency  12, 15 Milliseconds"));
+    assert!(screen.contains("Retries  0 "));
+    assert!(screen.contains("by Service=payments, Operation=charge"));
+    assert!(!screen.contains("CloudWatchMetrics"));
+}
+
+#[tokio::test]
+async fn events_keep_the_order_the_query_sorted_them_in() {
+    let at = |timestamp, message| LogEvent {
+        timestamp,
+        ..event(message)
+    };
+    let mut app = app_with(MockBackend {
+        query_logs: vec![
+            at(2000, "latency=9"),
+            at(1000, "latency=5"),
+            at(3000, "latency=1"),
+        ],
+        ..mock_backend()
+    });
+    app.set_viewer_queries(
+        ViewerQueries::from_config(&Config {
+            viewer_query: Some("fields @message | sort latency desc | limit 20".to_string()),
+            ..Config::default()
+        })
+        .unwrap(),
+    );
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    let screen = render(&mut app);
+    assert!(screen.contains("sorted by the query, limit 20"), "{screen}");
+    let position = |message| screen.find(message).unwrap();
+    assert!(position("latency=9") < position("latency=5"));
+    assert!(position("latency=5") < position("latency=1"));
+}
+
+#[tokio::test]
+async fn status_bar_counts_running_and_queued_queries() {
+    let mut app = app_with(MockBackend {
+        query_counts: QueryCounts {
+            queued: 1,
+            running: 2,
+        },
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    assert!(render(&mut app).contains("insights 2 running, 1 queued"));
+
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+    assert!(!render(&mut app).contains("insights"));
+}
+
+#[tokio::test]
+async fn debug_log_records_what_the_ui_did() {
+    let dir = std::env::temp_dir().join(format!("loglog-debug-{}", std::process::id()));
+    let path = dir.join("loglog.log");
+    logging::init(&path, true).unwrap();
+
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    let log = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(log.contains("opened screen screen=LogViewer"), "{log}");
+    assert!(log.contains("queued task task=\"querying logs\""));
+    assert!(log.contains("loaded events pane=Main events=2"));
+}
+
+#[tokio::test]
+async fn f12_toggles_the_debug_overlay() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    press(&mut app, KeyCode::F(12));
+    let screen = render(&mut app);
+    assert!(screen.contains("Debug"), "{screen}");
+    assert!(screen.contains("tasks     0 running, 0 queued"));
+    assert!(screen.contains("queues    groups 0, viewer 0"));
+    assert!(screen.contains("events    2, "));
+
+    press(&mut app, KeyCode::F(12));
+    assert!(!render(&mut app).contains("Debug"));
+}
+
+#[tokio::test]
+async fn debug_overlay_times_each_part_of_the_frame() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::F(12));
+    render(&mut app);
+
+    let screen = render(&mut app);
+    let frame_line = screen
+        .lines()
+        .find(|line| line.contains("frame "))
+        .unwrap_or_else(|| panic!("{screen}"));
+    assert!(frame_line.contains(": groups "), "{frame_line}");
+    assert!(frame_line.contains(", status bar "));
+}
+
+#[tokio::test]
+async fn demo_backend_serves_groups_and_events_without_aws() {
+    let mut app = App::new(Arc::new(DemoBackend::default()));
+    app.start();
+    load_groups(&mut app).await;
+    let screen = render(&mut app);
+    assert!(screen.contains("/aws/apigateway/storefront"));
+    assert!(screen.contains("/secure/customers"));
+
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(screen.contains("/aws/lambda/checkout"));
+    assert!(screen.contains(r#""requestId":"#));
+}
+
+#[tokio::test]
+async fn replays_the_responses_of_a_recorded_session() {
+    let path = std::env::temp_dir().join(format!("loglog-recording-{}.jsonl", std::process::id()));
+    let recorder = RecordingBackend::create(Arc::new(mock_backend()), &path).unwrap();
+    let mut app = App::new(Arc::new(recorder));
+    app.start();
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    let recorded = render(&mut app);
+
+    let replay = ReplayBackend::load(&path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    let mut app = App::new(Arc::new(replay));
+    app.start();
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    let replayed = render(&mut app);
+    assert!(replayed.contains("payment accepted"));
+    assert_eq!(replayed, recorded);
+}
+
+#[tokio::test]
+async fn snapshots_of_the_group_list() {
+    let mut app = app_with(MockBackend {
+        log_groups_pending: true,
+        ..mock_backend()
+    });
+    insta::assert_snapshot!("groups_loading", snapshot(&mut app));
+
+    let mut app = app_with(MockBackend {
+        log_groups_error: Some("AccessDeniedException: not allowed".to_string()),
+        ..mock_backend()
+    });
+    next_message(&mut app).await;
+    insta::assert_snapshot!("groups_error", snapshot(&mut app));
+
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Char('/'));
+    for c in "lmbd".chars() {
+        press(&mut app, KeyCode::Char(c));
+    }
+    search_results(&mut app).await;
+    insta::assert_snapshot!("groups_search", snapshot(&mut app));
+}
+
+#[tokio::test]
+async fn snapshots_of_the_viewer() {
+    let mut app = app_with(MockBackend {
+        logs: vec![
+            event(&format!(
+                "request failed: {}",
+                "upstream timed out ".repeat(8)
+            )),
+            event("用户 ✅ logged in from Zürich 👩‍💻"),
+            event("payment accepted"),
+        ],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    insta::assert_snapshot!("viewer_long_and_unicode_lines", snapshot(&mut app));
+
+    press(&mut app, KeyCode::Char('/'));
+    for c in "Zürich".chars() {
+        press(&mut app, KeyCode::Char(c));
+    }
+    press(&mut app, KeyCode::Enter);
+    insta::assert_snapshot!("viewer_filter", snapshot(&mut app));
+}
+
+#[test]
+fn snapshot_of_the_table() {
+    let events = vec![
+        event("short"),
+        event(&"a very long line that is cut at the edge ".repeat(3)),
+        event("日本語のメッセージ"),
+    ];
+    let table = Table::new(events);
+    let mut terminal = Terminal::new(TestBackend::new(40, 5)).unwrap();
+    terminal
+        .draw(|frame| frame.render_widget(&table, frame.area()))
+        .unwrap();
+    insta::assert_snapshot!("table", terminal.backend());
+}
+
+#[tokio::test]
+async fn asks_for_a_bigger_terminal_when_too_small() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+    for (width, height) in [(30, 12), (80, 5), (1, 1), (0, 0)] {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        if width == 0 {
+            continue;
+        }
+        let screen = terminal.backend().to_string();
+        assert!(!screen.contains("/aws/lambda/checkout"));
+        if width >= 30 {
+            assert!(screen.contains("terminal too small"), "{screen}");
+            assert!(screen.contains("40x8"), "{screen}");
+        }
+    }
+    assert!(render(&mut app).contains("/aws/lambda/checkout"));
+}
+
+#[tokio::test]
+async fn window_title_names_the_open_groups() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+    assert_eq!(app.window_title(), "loglog");
+
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    assert_eq!(app.window_title(), "loglog — /aws/lambda/checkout [24h]");
+
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.window_title(), "loglog");
+}
+
+#[tokio::test]
+async fn long_queries_notify_when_the_terminal_is_in_the_background() {
+    let mut app = app_with(mock_backend());
+    app.set_query_notifications(Some(Duration::ZERO), false);
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    assert_eq!(app.take_desktop_notification(), None);
+
+    app.handle_event(&Event::FocusLost);
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    let notification = app.take_desktop_notification().unwrap();
+    assert!(notification.starts_with("loglog: /aws/lambda/checkout [24h] finished after"));
+    assert_eq!(app.take_desktop_notification(), None);
+
+    app.set_query_notifications(None, true);
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    assert_eq!(app.take_desktop_notification(), None);
+}
+
+#[tokio::test]
+async fn background_queries_land_in_the_inbox() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    press(&mut app, KeyCode::Char('B'));
+    assert_eq!(app.current_screen(), Screen::LogGroups);
+    press(&mut app, KeyCode::Char('I'));
+    assert!(render(&mut app).contains("running"));
+    press(&mut app, KeyCode::Esc);
+
+    next_message(&mut app).await;
+    assert_eq!(app.current_screen(), Screen::LogGroups);
+    assert!(render(&mut app).contains("background query finished"));
+    press(&mut app, KeyCode::Char('I'));
+    assert!(render(&mut app).contains("events in"));
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.current_screen(), Screen::LogViewer);
+    assert!(render(&mut app).contains("order shipped"));
+
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Char('I'));
+    assert!(render(&mut app).contains("nothing running in the background"));
+}
+
+#[tokio::test]
+async fn background_queries_leave_room_for_loading_groups() {
+    let mut app = app_with(MockBackend {
+        logs_pending: true,
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    for _ in 0..4 {
+        press(&mut app, KeyCode::Enter);
+        next_message(&mut app).await;
+        // let the query take its slot before it's sent to the background
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        press(&mut app, KeyCode::Char('B'));
+    }
+
+    press(&mut app, KeyCode::Char('r'));
+    assert!(render(&mut app).contains("Loading"));
+    while render(&mut app).contains("Loading") {
+        next_message(&mut app).await;
+    }
+    assert!(render(&mut app).contains("/aws/lambda/checkout"));
+}
+
+#[tokio::test]
+async fn exports_the_queries_and_bookmarks_of_the_investigation() {
+    let dir = std::env::temp_dir().join(format!("loglog-investigation-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut app = app_with(mock_backend());
+    app.set_export_dir(dir.clone());
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Char('X'));
+    assert!(render(&mut app).contains("nothing to export yet"));
+
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    press(&mut app, KeyCode::Char('M'));
+    app.handle_event(&Event::Paste("shipped twice".to_string()));
+    press(&mut app, KeyCode::Enter);
+    assert!(render(&mut app).contains("bookmarked"));
+    press(&mut app, KeyCode::Char('X'));
+    assert!(render(&mut app).contains(".md and its .json"));
+
+    let files = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<_>>();
+    let markdown = files
+        .iter()
+        .find(|path| path.extension().is_some_and(|ext| ext == "md"))
+        .unwrap();
+    let markdown = std::fs::read_to_string(markdown).unwrap();
+    assert!(markdown.contains("1. `/aws/lambda/checkout` from"));
+    assert!(markdown.contains("2 events"));
+    assert!(markdown.contains("> shipped twice\n\n```\norder shipped\n```"));
+    let json = files
+        .iter()
+        .find(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .unwrap();
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(json).unwrap()).unwrap();
+    assert_eq!(json["bookmarks"][0]["message"], "order shipped");
+    assert_eq!(json["bookmarks"][0]["note"], "shipped twice");
+    assert_eq!(json["queries"][0]["events"], 2);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn bookmarks_pane_lists_and_edits_notes() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Char('K'));
+    assert!(render(&mut app).contains("nothing bookmarked yet"));
+    press(&mut app, KeyCode::Esc);
+
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    press(&mut app, KeyCode::Char('M'));
+    assert!(render(&mut app).contains("Note:"));
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Char('k'));
+    press(&mut app, KeyCode::Char('M'));
+    app.handle_event(&Event::Paste("first payment".to_string()));
+    press(&mut app, KeyCode::Enter);
+
+    press(&mut app, KeyCode::Char('K'));
+    let screen = render(&mut app);
+    assert!(screen.contains("/aws/lambda/checkout  order shipped"));
+    assert!(screen.contains("/aws/lambda/checkout  first payment"));
+    press(&mut app, KeyCode::Enter);
+    app.handle_event(&Event::Paste("shipped late".to_string()));
+    press(&mut app, KeyCode::Enter);
+    assert!(render(&mut app).contains("/aws/lambda/checkout  shipped late"));
+    press(&mut app, KeyCode::Char('x'));
+    let screen = render(&mut app);
+    assert!(!screen.contains("shipped late"));
+    assert!(screen.contains("first payment"));
+
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Char('M'));
+    assert!(render(&mut app).contains("bookmark removed"));
+}
+
+#[tokio::test]
+async fn bookmarks_follow_overridden_bindings_and_show_the_note_cursor() {
+    let overrides = [("bookmarks.remove".to_string(), vec!["d".to_string()])].into();
+    let mut app = App::new(Arc::new(mock_backend()));
+    app.set_keymap(Keymap::with_overrides(&overrides).unwrap());
+    app.start();
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    press(&mut app, KeyCode::Char('M'));
+    app.handle_event(&Event::Paste("shipped".to_string()));
+
+    // the cursor sits after the note typed so far
+    let mut terminal = Terminal::new(TestBackend::new(80, 12)).unwrap();
+    terminal.draw(|frame| app.draw(frame)).unwrap();
+    let buffer = terminal.backend().buffer();
+    let row = buffer
+        .content()
+        .chunks(80)
+        .find(|row| {
+            row.iter()
+                .map(|cell| cell.symbol())
+                .collect::<String>()
+                .contains("Note: shipped")
+        })
+        .unwrap();
+    let start = row.iter().position(|cell| cell.symbol() == "N").unwrap();
+    assert!(row[start + 13].modifier.contains(Modifier::REVERSED));
+    press(&mut app, KeyCode::Enter);
+
+    press(&mut app, KeyCode::Char('K'));
+    assert!(render(&mut app).contains("d to remove"));
+    press(&mut app, KeyCode::Char('x'));
+    assert!(render(&mut app).contains("shipped"));
+    press(&mut app, KeyCode::Char('d'));
+    assert!(render(&mut app).contains("nothing bookmarked yet"));
+}
+
+#[tokio::test]
+async fn alarms_open_the_logs_around_when_they_went_off() {
+    let three_hours_ago = chrono::Utc::now().timestamp_millis() - 3 * 3600 * 1000;
+    let mut app = app_with(MockBackend {
+        alarms: vec![Alarm {
+            name: "checkout-errors".to_string(),
+            metric_name: Some("CheckoutErrors".to_string()),
+            namespace: Some("Shop".to_string()),
+            reason: "Threshold Crossed".to_string(),
+            state_updated: three_hours_ago,
+            ..Default::default()
+        }],
+        alarm_log_groups: vec!["/aws/lambda/checkout".to_string()],
+        previous_logs: vec![event("card declined")],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Char('A'));
+    next_message(&mut app).await;
+    assert_eq!(app.current_screen(), Screen::Alarms);
+    let screen = render(&mut app);
+    assert!(screen.contains("checkout-errors"));
+    assert!(screen.contains("Shop/CheckoutErrors"));
+    assert!(screen.contains("3h 00m ago"));
+
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    assert_eq!(app.current_screen(), Screen::LogViewer);
+    // the window ends a quarter hour after the alarm, so the older events come back
+    assert!(render(&mut app).contains("card declined"));
+    assert!(app.window_title().ends_with("UTC]"));
+
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.current_screen(), Screen::Alarms);
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    assert!(render(&mut app).contains("order shipped"));
+    assert!(app.window_title().ends_with("[24h]"));
+}
+
+#[tokio::test]
+async fn group_info_lists_metric_filters_and_jumps_to_their_alarms() {
+    let alarm = |name: &str, state: &str| Alarm {
+        name: name.to_string(),
+        metric_name: Some("CheckoutErrors".to_string()),
+        namespace: Some("Shop".to_string()),
+        state: state.to_string(),
+        state_updated: chrono::Utc::now().timestamp_millis(),
+        ..Default::default()
+    };
+    let mut app = app_with(MockBackend {
+        metric_filters: vec![MetricFilter {
+            name: "checkout-errors".to_string(),
+            pattern: "ERROR".to_string(),
+            metric_name: Some("CheckoutErrors".to_string()),
+            namespace: Some("Shop".to_string()),
+        }],
+        // only the first is going off, the other is listed once it's jumped to
+        alarms: vec![alarm("pager", "ALARM"), alarm("dashboard", "OK")],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+
+    press(&mut app, KeyCode::Char('i'));
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(
+        screen.contains("checkout-errors  ERROR → Shop/CheckoutErrors"),
+        "{screen}"
+    );
+    assert!(screen.contains("alarm pager alarm"));
+    assert!(screen.contains("alarm dashboard ok"));
+
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    assert_eq!(app.current_screen(), Screen::Alarms);
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(screen.contains("🚨 dashboard (ok)"), "{screen}");
+    assert!(!screen.contains("🚨 pager"));
+}
+
+#[tokio::test]
+async fn at_queries_the_minutes_around_a_pasted_time() {
+    let mut app = app_with(MockBackend {
+        previous_logs: vec![event("card declined")],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    press(&mut app, KeyCode::Char('@'));
+    let screen = render(&mut app);
+    assert!(screen.contains("around:   (a time ±minutes"), "{screen}");
+    app.handle_event(&Event::Paste("half past three".to_string()));
+    press(&mut app, KeyCode::Enter);
+    assert!(render(&mut app).contains("can't read a time from 'half past three'"));
+    press(&mut app, KeyCode::Esc);
+
+    // an access log's time, the window ends long enough ago for the older events to come back
+    let three_hours_ago = chrono::Utc::now() - chrono::Duration::hours(3);
+    let pasted = three_hours_ago.format("[%d/%b/%Y:%H:%M:%S +0000] ±10");
+    press(&mut app, KeyCode::Char('@'));
+    app.handle_event(&Event::Paste(pasted.to_string()));
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    assert!(render(&mut app).contains("card declined"));
+    assert!(app.window_title().ends_with("UTC]"));
+
+    press(&mut app, KeyCode::Char('@'));
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    assert!(render(&mut app).contains("order shipped"));
+    assert!(app.window_title().ends_with("[24h]"));
+}
+
+#[tokio::test]
+async fn a_pasted_time_older_than_retention_warns() {
+    let mut app = app_with(MockBackend {
+        log_groups: vec![LogGroup {
+            retention_in_days: Some(7),
+            ..group("/aws/lambda/checkout")
+        }],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    assert!(!render(&mut app).contains("older events are gone"));
+
+    let weeks_ago = chrono::Utc::now() - chrono::Duration::days(21);
+    press(&mut app, KeyCode::Char('@'));
+    app.handle_event(&Event::Paste(weeks_ago.to_rfc3339()));
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(
+        screen.contains("7d retention, older events are gone"),
+        "{screen}"
+    );
+}
+
+#[tokio::test]
+async fn detail_shows_the_time_in_the_message_next_to_the_timestamp() {
+    let mut app = app_with(MockBackend {
+        logs: vec![LogEvent {
+            timestamp: 4_000,
+            ..event("[01/Jan/1970:00:00:01 +0000] checkout took 3s")
+        }],
+        ..mock_backend()
+    });
+    let config = Config {
+        message_timestamps: vec![MessageTimestamp {
+            groups: "/aws/lambda/*".to_string(),
+            pattern: r"^\[([^\]]+)\]".to_string(),
+            format: Some("%d/%b/%Y:%H:%M:%S %z".to_string()),
+        }],
+        ..Default::default()
+    };
+    app.set_message_timestamps(MessageTimestamps::from_config(&config).unwrap());
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(
+        screen.contains("logged 1970-01-01T00:00:01+00:00 (@timestamp 3s later)"),
+        "{screen}"
+    );
+
+    let config = Config {
+        message_timestamps: vec![MessageTimestamp {
+            pattern: "no capture".to_string(),
+            ..config.message_timestamps[0].clone()
+        }],
+        ..Default::default()
+    };
+    assert_eq!(
+        MessageTimestamps::from_config(&config).unwrap_err(),
+        "no capture has no capture group for the time"
+    );
+}
+
+#[tokio::test]
+async fn events_logged_out_of_order_are_marked_and_can_be_sorted() {
+    let logged = |timestamp: i64, message: &str| LogEvent {
+        timestamp,
+        ..event(message)
+    };
+    let mut app = app_with(MockBackend {
+        // ingested in order, but the second was buffered by its agent for a while
+        logs: vec![
+            logged(10_000, "1970-01-01T00:00:05Z charged"),
+            logged(20_000, "1970-01-01T00:00:03Z reserved"),
+            logged(30_000, "1970-01-01T00:00:09Z shipped"),
+        ],
+        ..mock_backend()
+    });
+    let config = Config {
+        message_timestamps: vec![MessageTimestamp {
+            groups: "*".to_string(),
+            pattern: r"^(\S+)".to_string(),
+            format: None,
+        }],
+        ..Default::default()
+    };
+    app.set_message_timestamps(MessageTimestamps::from_config(&config).unwrap());
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(
+        screen.contains("1 out of order, O to sort by time"),
+        "{screen}"
+    );
+    assert!(screen.contains("↑1970-01-01T00:00:03Z reserved"));
+
+    press(&mut app, KeyCode::Char('O'));
+    let screen = render(&mut app);
+    assert!(!screen.contains("out of order"));
+    assert!(!screen.contains('↑'));
+    assert!(screen.find("reserved") < screen.find("charged"));
+}
+
+#[tokio::test]
+async fn the_tail_keeps_rows_sorted_by_time_and_leaves_out_ones_already_shown() {
+    let logged = |timestamp: i64, message: &str| LogEvent {
+        timestamp,
+        ..event(message)
+    };
+    let mut app = app_with(MockBackend {
+        logs: vec![
+            logged(10_000, "1970-01-01T00:00:05Z charged"),
+            logged(20_000, "1970-01-01T00:00:03Z reserved"),
+            logged(30_000, "1970-01-01T00:00:09Z shipped"),
+        ],
+        // the tail catches up on the one before, then one logged between the others arrives
+        tail_batches: vec![vec![
+            logged(20_000, "1970-01-01T00:00:03Z reserved"),
+            logged(40_000, "1970-01-01T00:00:04Z refunded"),
+        ]],
+        ..mock_backend()
+    });
+    let config = Config {
+        message_timestamps: vec![MessageTimestamp {
+            groups: "*".to_string(),
+            pattern: r"^(\S+)".to_string(),
+            format: None,
+        }],
+        ..Default::default()
+    };
+    app.set_message_timestamps(MessageTimestamps::from_config(&config).unwrap());
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    press(&mut app, KeyCode::Char('O'));
+    press(&mut app, KeyCode::Char('t'));
+    while !render(&mut app).contains("refunded") {
+        next_message(&mut app).await;
+    }
+
+    let screen = render(&mut app);
+    assert_eq!(screen.matches("reserved").count(), 1, "{screen}");
+    let order = ["reserved", "refunded", "charged", "shipped"].map(|text| screen.find(text));
+    assert!(order.is_sorted(), "{screen}");
+}
+
+#[tokio::test]
+async fn e_shows_when_the_oldest_events_of_each_group_expire() {
+    let now = chrono::Utc::now().timestamp_millis();
+    let day = 24 * 3600 * 1000;
+    let mut app = app_with(MockBackend {
+        log_groups: vec![
+            LogGroup {
+                retention_in_days: Some(30),
+                creation_time: Some(now - 400 * day),
+                ..group("/aws/lambda/checkout")
+            },
+            LogGroup {
+                retention_in_days: Some(14),
+                creation_time: Some(now - 2 * day - 1000),
+                ..group("/aws/lambda/payments")
+            },
+            group("/service/dev/api"),
+        ],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    assert!(!render(&mut app).contains("(30d)"));
+
+    press(&mut app, KeyCode::Char('e'));
+    let screen = render(&mut app);
+    assert!(screen.contains("now (30d)"), "{screen}");
+    assert!(screen.contains("in 11d (14d)"));
+    assert!(screen.contains("never"));
+}
+
+#[tokio::test]
+async fn m_fetches_tags_and_the_newest_event_for_the_groups_on_screen() {
+    let now = chrono::Utc::now().timestamp_millis();
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let mut app = app_with(MockBackend {
+        log_groups: (0..30)
+            .map(|i| group(&format!("/service/api-{:02}", i)))
+            .collect(),
+        group_metadata: vec![(
+            "/service/api-00".to_string(),
+            GroupMetadata {
+                last_event: Some(now - 5 * 60 * 1000),
+                tags: vec![("team".to_string(), "checkout".to_string())],
+            },
+        )],
+        metadata_fetches: fetches.clone(),
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    render(&mut app);
+    assert_eq!(fetches.load(Ordering::SeqCst), 0);
+
+    press(&mut app, KeyCode::Char('m'));
+    while render(&mut app).contains('…') {
+        next_message(&mut app).await;
+    }
+    let screen = render(&mut app);
+    assert!(screen.contains("team=checkout"), "{screen}");
+    assert!(screen.contains("5m 00s ago"));
+    assert!(screen.contains("never"));
+    // only the rows on screen, not all 30 groups
+    assert!(fetches.load(Ordering::SeqCst) <= 12);
+}
+
+#[tokio::test]
+async fn live_tail_shows_its_event_rate_and_when_it_samples() {
+    let mut app = app_with(MockBackend {
+        tail_batches: vec![
+            vec![event("cart updated"), event("coupon applied")],
+            vec![event("refund issued")],
+        ],
+        tail_sampled: true,
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    press(&mut app, KeyCode::Char('t'));
+    while !render(&mut app).contains("refund issued") {
+        next_message(&mut app).await;
+    }
+    let screen = render(&mut app);
+    assert!(screen.contains("tailing 3.0/s, sampled"), "{screen}");
+}
+
+#[tokio::test]
+async fn p_pauses_the_live_tail_and_shows_what_arrived_meanwhile_once_resumed() {
+    let mut app = app_with(MockBackend {
+        tail_batches: vec![vec![event("cart updated")], vec![event("refund issued")]],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    press(&mut app, KeyCode::Char('t'));
+    while !render(&mut app).contains("cart updated") {
+        next_message(&mut app).await;
+    }
+
+    press(&mut app, KeyCode::Char('p'));
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(!screen.contains("refund issued"), "{screen}");
+    assert!(screen.contains("paused, 1 new"));
+
+    press(&mut app, KeyCode::Char('p'));
+    let screen = render(&mut app);
+    assert!(screen.contains("refund issued"), "{screen}");
+    assert!(!screen.contains("paused"));
+}
+
+#[tokio::test]
+async fn what_a_closed_viewer_tailed_stays_out_of_the_next_group() {
+    let mut app = app_with(MockBackend {
+        tail_batches: vec![vec![event("cart updated")]],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    press(&mut app, KeyCode::Char('t'));
+    // the batch is sent but still queued when the viewer is left
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    press(&mut app, KeyCode::Esc);
+
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(screen.contains("/service/dev/api"), "{screen}");
+    assert!(!screen.contains("cart updated"), "{screen}");
+}
+
+#[tokio::test]
+async fn tailed_events_are_highlighted_as_they_arrive() {
+    let mut app = app_with(MockBackend {
+        tail_batches: vec![vec![event("cart updated")]],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    press(&mut app, KeyCode::Char('t'));
+    while !render(&mut app).contains("cart updated") {
+        next_message(&mut app).await;
+    }
+    // the selected row has its own background
+    press(&mut app, KeyCode::Up);
+
+    let mut terminal = Terminal::new(TestBackend::new(80, 12)).unwrap();
+    terminal.draw(|frame| app.draw(frame)).unwrap();
+    let buffer = terminal.backend().buffer();
+    let background = |text: &str| {
+        let row = buffer
+            .content()
+            .chunks(80)
+            .find(|row| {
+                row.iter()
+                    .map(|cell| cell.symbol())
+                    .collect::<String>()
+                    .contains(text)
+            })
+            .unwrap();
+        row[2].bg
+    };
+    let fresh = Theme::default().fresh;
+    assert!(fresh.contains(&background("cart updated")));
+    assert!(!fresh.contains(&background("payment accepted")));
+}
+
+#[tokio::test]
+async fn t_filters_the_live_tail_on_the_server_and_restarts_it_when_changed() {
+    let backend = Arc::new(MockBackend {
+        tail_batches: vec![vec![event("cart updated")]],
+        ..mock_backend()
+    });
+    let mut app = App::new(backend.clone());
+    app.start();
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    press(&mut app, KeyCode::Char('T'));
+    for c in "ERROR".chars() {
+        press(&mut app, KeyCode::Char(c));
+    }
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    press(&mut app, KeyCode::Char('T'));
+    for c in " -timeout".chars() {
+        press(&mut app, KeyCode::Char(c));
+    }
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+
+    assert_eq!(
+        *backend.tail_filters.lock().unwrap(),
+        vec!["ERROR".to_string(), "ERROR -timeout".to_string()]
+    );
+    let screen = render(&mut app);
+    assert!(screen.contains("tail filter: ERROR -timeout"), "{screen}");
+    assert!(screen.contains("tailing"));
+}
+
+#[tokio::test]
+async fn gutter_marks_bookmarked_errors_and_folded_events() {
+    let mut app = app_with(MockBackend {
+        logs: vec![
+            event("ERROR payment declined"),
+            event("panic: boom\n  at checkout"),
+            event("order shipped"),
+        ],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(screen.contains("│!▌ERROR payment declined"), "{screen}");
+    assert!(screen.contains("│▸▌panic: boom"));
+    assert!(screen.contains("│ ▌order shipped"));
+
+    press(&mut app, KeyCode::Char('M'));
+    press(&mut app, KeyCode::Enter);
+    assert!(render(&mut app).contains("│★▌order shipped"));
+    press(&mut app, KeyCode::Char('M'));
+    assert!(render(&mut app).contains("│ ▌order shipped"));
+}
+
+#[tokio::test]
+async fn the_selection_stays_on_screen_when_the_terminal_is_resized() {
+    let mut app = app_with(MockBackend {
+        log_groups: (0..30)
+            .map(|i| group(&format!("/service/api-{:02}", i)))
+            .collect(),
+        logs: (0..40)
+            .map(|i| LogEvent {
+                timestamp: i,
+                ..event(&format!("event {:02}", i))
+            })
+            .collect(),
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    for _ in 0..25 {
+        press(&mut app, KeyCode::Down);
+    }
+    assert!(render_sized(&mut app, 80, 40).contains("/service/api-25"));
+    for (width, height) in [(80, 10), (40, 8), (100, 24)] {
+        let screen = render_sized(&mut app, width, height);
+        assert!(
+            screen.contains("/service/api-25"),
+            "{width}x{height}\n{screen}"
+        );
+    }
+
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    for _ in 0..30 {
+        press(&mut app, KeyCode::Up);
+    }
+    for (width, height) in [(80, 40), (80, 10), (40, 8), (100, 24)] {
+        let screen = render_sized(&mut app, width, height);
+        assert!(screen.contains("event 09"), "{width}x{height}\n{screen}");
+    }
+}
+
+#[test]
+fn a_selected_row_taller_than_the_table_keeps_the_cursor_line_in_view() {
+    let lines = (0..10)
+        .map(|i| format!("line {}", i))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut table = Table::new(vec![event("older"), event(&lines)]);
+    table.toggle_fold();
+    for _ in 0..8 {
+        table.cursor_next_word();
+        table.cursor_next_word();
+    }
+    let mut terminal = Terminal::new(TestBackend::new(30, 4)).unwrap();
+    terminal
+        .draw(|frame| frame.render_widget(&table, frame.area()))
+        .unwrap();
+    let screen = format!("{:?}", terminal.backend());
+    assert!(screen.contains("line 8"), "{screen}");
+    assert!(!screen.contains("line 0"));
+}
+
+#[tokio::test]
+async fn groups_open_while_later_pages_are_still_loading() {
+    let mut app = app_with(MockBackend {
+        later_pages: vec![
+            vec![group("/service/prod/api")],
+            vec![group("/service/prod/worker")],
+        ],
+        ..mock_backend()
+    });
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(screen.contains("Loading, 2 so far"), "{screen}");
+
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    assert_eq!(app.current_screen(), Screen::LogViewer);
+    // the later pages keep arriving while the group is open
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    press(&mut app, KeyCode::Esc);
+    let mut screen = render(&mut app);
+    while !screen.contains("Loaded") {
+        next_message(&mut app).await;
+        screen = render(&mut app);
+    }
+    assert!(screen.contains("/service/prod/worker"), "{screen}");
+}
+
+#[tokio::test]
+async fn the_viewer_shows_what_it_queries_until_results_arrive() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    assert_eq!(app.current_screen(), Screen::LogViewer);
+    let screen = render(&mut app);
+    assert!(screen.contains("querying /aws/lambda/checkout"), "{screen}");
+    assert!(screen.contains("over the last 24h, 0s so far"), "{screen}");
+    assert!(screen.contains("fields @timestamp, @message"), "{screen}");
+
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(screen.contains("payment accepted"), "{screen}");
+    assert!(!screen.contains("querying"), "{screen}");
+}
+
+#[tokio::test]
+async fn t_picks_how_far_back_opening_a_group_queries() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+
+    press(&mut app, KeyCode::Char('t'));
+    let screen = render(&mut app);
+    assert!(screen.contains("Time range"), "{screen}");
+    assert!(screen.contains("last 15m"), "{screen}");
+    for _ in 0..3 {
+        press(&mut app, KeyCode::Up);
+    }
+    press(&mut app, KeyCode::Enter);
+    assert!(render(&mut app).contains("opens the last 1h, t to change"));
+
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    assert!(render(&mut app).contains("over the last 1h"));
+    assert_eq!(app.window_title(), "loglog — /aws/lambda/checkout [1h]");
+}
+
+#[tokio::test]
+async fn l_lists_the_queries_run_and_runs_one_again() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Char('L'));
+    assert!(render(&mut app).contains("no queries run yet"));
+    press(&mut app, KeyCode::Esc);
+
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    press(&mut app, KeyCode::Esc);
+
+    press(&mut app, KeyCode::Char('L'));
+    let screen = render_sized(&mut app, 160, 12);
+    assert!(screen.contains("History"), "{screen}");
+    let newest = screen.find("/service/dev/api  last 24h  2 events in 0s");
+    let oldest = screen.find("/aws/lambda/checkout  last 24h  2 events in 0s");
+    assert!(newest.is_some() && newest < oldest, "{screen}");
+
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.current_screen(), Screen::LogViewer);
+    assert_eq!(app.window_title(), "loglog — /aws/lambda/checkout [24h]");
+    next_message(&mut app).await;
+    assert!(render(&mut app).contains("payment accepted"));
+}
+
+#[tokio::test]
+async fn the_history_runs_a_query_again_with_its_fields_and_filter() {
+    let backend = Arc::new(MockBackend {
+        logs: vec![event(r#"{"level":"info","duration":12}"#)],
+        ..mock_backend()
+    });
+    let mut app = App::new(backend.clone());
+    app.start();
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+
+    // query A filters on "info" and picks @requestId
+    press(&mut app, KeyCode::Char('/'));
+    for c in "info".chars() {
+        press(&mut app, KeyCode::Char(c));
+    }
+    press(&mut app, KeyCode::Enter);
+    press(&mut app, KeyCode::Char('Q'));
+    for _ in 0..4 {
+        press(&mut app, KeyCode::Down);
+    }
+    press(&mut app, KeyCode::Char(' '));
+    press(&mut app, KeyCode::Enter);
+    while render(&mut app).contains("Loading") {
+        next_message(&mut app).await;
+    }
+    let last_query = || {
+        backend
+            .fetched_queries
+            .lock()
+            .unwrap()
+            .last()
+            .unwrap()
+            .1
+            .clone()
+    };
+    let query_a = last_query();
+    assert!(query_a.as_deref().unwrap().contains("@requestId"));
+
+    // then the filter goes and the fields change again
+    press(&mut app, KeyCode::Char('F'));
+    press(&mut app, KeyCode::Char('Q'));
+    press(&mut app, KeyCode::Char(' '));
+    press(&mut app, KeyCode::Enter);
+    while render(&mut app).contains("Loading") {
+        next_message(&mut app).await;
+    }
+    assert_ne!(last_query(), query_a);
+    assert!(!render(&mut app).contains("filter: info"));
+    press(&mut app, KeyCode::Esc);
+
+    press(&mut app, KeyCode::Char('L'));
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    while backend.fetched_queries.lock().unwrap().len() < 4 {
+        next_message(&mut app).await;
+    }
+    assert_eq!(last_query(), query_a);
+    assert!(render(&mut app).contains("filter: info"));
+}
+
+#[tokio::test]
+async fn the_history_runs_a_query_again_in_the_region_it_ran_in() {
+    let backend = Arc::new(MockBackend {
+        log_groups: vec![
+            group("/aws/lambda/checkout"),
+            LogGroup {
+                arn: "arn:aws:logs:us-east-1:123456789012:log-group:/aws/lambda/checkout"
+                    .to_string(),
+                ..group("/aws/lambda/checkout")
+            },
+        ],
+        ..mock_backend()
+    });
+    let mut app = App::new(backend.clone());
+    app.start();
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    press(&mut app, KeyCode::Esc);
+
+    press(&mut app, KeyCode::Char('L'));
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    let fetched = backend.fetched_queries.lock().unwrap();
+    assert_eq!(fetched.len(), 2);
+    assert!(fetched
+        .iter()
+        .all(|(region, _)| region.as_deref() == Some("us-east-1")));
+}
+
+#[tokio::test]
+async fn p_switches_to_an_sso_account_and_lists_its_groups() {
+    let sso_account = Account::Sso {
+        session: "work".to_string(),
+        account_id: "111111111111".to_string(),
+        account_name: "shop-production".to_string(),
+        role_name: "ReadOnly".to_string(),
+    };
+    let mut app = app_with(MockBackend {
+        accounts: vec![Account::Profile("default".to_string()), sso_account],
+        account_log_groups: vec![group("/aws/lambda/billing")],
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+
+    press(&mut app, KeyCode::Char('P'));
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(screen.contains("profile  default"));
+    assert!(screen.contains("work  shop-production (111111111111)  ReadOnly"));
+
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    load_groups(&mut app).await;
+    let screen = render(&mut app);
+    assert!(!screen.contains("Accounts"));
+    assert!(screen.contains("/aws/lambda/billing"));
+    assert!(!screen.contains("/service/dev/api"));
+    assert!(screen.contains("switched to work"));
+}
+
+#[tokio::test(start_paused = true)]
+async fn groups_load_again_once_the_network_is_back() {
+    let offline = Arc::new(AtomicBool::new(true));
+    let mut app = app_with(MockBackend {
+        offline: offline.clone(),
+        ..mock_backend()
+    });
+    next_message(&mut app).await;
+    let screen = render(&mut app);
+    assert!(screen.contains("you appear to be offline"));
+    assert!(screen.contains("offline, retrying"));
+
+    // still offline on the first retry, the second gets through
+    tokio::time::sleep(Duration::from_secs(6)).await;
+    offline.store(false, Ordering::SeqCst);
+    tokio::time::timeout(Duration::from_secs(10), app.handle_next_message())
+        .await
+        .expect("never came back online");
+    load_groups(&mut app).await;
+    let screen = render(&mut app);
+    assert!(screen.contains("/aws/lambda/checkout"));
+    assert!(screen.contains("back online"));
+    assert!(!screen.contains("offline, retrying"));
+}
+
+#[tokio::test(start_paused = true)]
+async fn bulk_changes_carry_on_once_the_network_is_back() {
+    let offline = Arc::new(AtomicBool::new(false));
+    let mut app = app_with(MockBackend {
+        offline: offline.clone(),
+        ..mock_backend()
+    });
+    load_groups(&mut app).await;
+    press(&mut app, KeyCode::Char(' '));
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Char(' '));
+    press(&mut app, KeyCode::Char('b'));
+    press(&mut app, KeyCode::Char('t'));
+    for c in "team=payments".chars() {
+        press(&mut app, KeyCode::Char(c));
+    }
+    offline.store(true, Ordering::SeqCst);
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    let screen = render_sized(&mut app, 120, 12);
+    assert!(
+        screen.contains("2 groups left to change once back online"),
+        "{screen}"
+    );
+
+    tokio::time::sleep(Duration::from_secs(6)).await;
+    offline.store(false, Ordering::SeqCst);
+    tokio::time::timeout(Duration::from_secs(10), app.handle_next_message())
+        .await
+        .expect("never came back online");
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    assert!(render(&mut app).contains("/service/dev/api: done"));
+}
+
+#[tokio::test]
+async fn pastes_land_in_the_open_input_on_one_line() {
+    let mut app = app_with(mock_backend());
+    load_groups(&mut app).await;
+    app.handle_event(&Event::Paste("checkout".to_string()));
+    // nothing is typing, the paste is dropped
+    assert!(!render(&mut app).contains("matches"));
+
+    press(&mut app, KeyCode::Char('/'));
+    app.handle_event(&Event::Paste("lambda/\ncheckout\n".to_string()));
+    search_results(&mut app).await;
+    assert!(render(&mut app).contains("/lambda/ checkout"));
+    press(&mut app, KeyCode::Esc);
+
+    press(&mut app, KeyCode::Enter);
+    next_message(&mut app).await;
+    next_message(&mut app).await;
+    press(&mut app, KeyCode::Char('/'));
+    app.handle_event(&Event::Paste("order\r\nshipped".to_string()));
+    press(&mut app, KeyCode::Enter);
+    let screen = render(&mut app);
+    assert!(screen.contains("filter: order shipped"));
+    assert!(!screen.contains("payment accepted"));
+}
@@ -0,0 +1,137 @@
+use std::sync::Arc;
+
+use loglog::{pattern::Pattern, shared::LogEvent, table::Table};
+use proptest::prelude::*;
+use ratatui::{buffer::Buffer, layout::Rect, widgets::Widget};
+
+const MESSAGES: [&str; 5] = [
+    "payment accepted",
+    "order shipped",
+    "stack trace\n  at checkout\n  at main",
+    "",
+    "用户 logged in 👩‍💻",
+];
+
+const FILTERS: [&str; 3] = ["order", "at", "nothing matches this"];
+
+#[derive(Debug, Clone)]
+enum Op {
+    ScrollUp(Option<usize>),
+    ScrollDown(Option<usize>),
+    SelectOldest,
+    SelectNewest,
+    /// events by timestamp and index into `MESSAGES`
+    Append(Vec<(i64, usize)>),
+    SetData(Vec<(i64, usize)>),
+    /// appends every event already there, as a tail catching up does
+    AppendAgain,
+    SortByTime,
+    Filter(usize),
+    ClearFilter,
+    Clear,
+    Render(u16, u16),
+}
+
+fn events(events: &[(i64, usize)]) -> Vec<LogEvent> {
+    events
+        .iter()
+        .map(|&(timestamp, message)| LogEvent {
+            timestamp,
+            message: MESSAGES[message].into(),
+            log_stream: "stream".into(),
+            ..Default::default()
+        })
+        .collect()
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    let batch = prop::collection::vec((0..50i64, 0..MESSAGES.len()), 0..20);
+    prop_oneof![
+        prop::option::of(0..30usize).prop_map(Op::ScrollUp),
+        prop::option::of(0..30usize).prop_map(Op::ScrollDown),
+        Just(Op::SelectOldest),
+        Just(Op::SelectNewest),
+        batch.clone().prop_map(Op::Append),
+        batch.prop_map(Op::SetData),
+        Just(Op::AppendAgain),
+        Just(Op::SortByTime),
+        (0..FILTERS.len()).prop_map(Op::Filter),
+        Just(Op::ClearFilter),
+        Just(Op::Clear),
+        (0..30u16, 0..8u16).prop_map(|(width, height)| Op::Render(width, height)),
+    ]
+}
+
+/// Renders `table` into a `width` by `height` area, true when the selected row was drawn
+fn shows_selection(table: &Table, width: u16, height: u16) -> bool {
+    let area = Rect::new(0, 0, width, height);
+    let mut buffer = Buffer::empty(area);
+    table.render(area, &mut buffer);
+    buffer
+        .content()
+        .iter()
+        .any(|cell| cell.bg == table.theme.selection)
+}
+
+proptest! {
+    #[test]
+    fn selection_stays_on_a_shown_event(ops in prop::collection::vec(op(), 1..40)) {
+        let mut table = Table::new(vec![]);
+        let mut filter: Option<&str> = None;
+        for op in ops {
+            match op {
+                Op::ScrollUp(by) => table.scroll_up(by),
+                Op::ScrollDown(by) => table.scroll_down(by),
+                Op::SelectOldest => table.select_oldest(),
+                Op::SelectNewest => table.select_newest(),
+                Op::Append(batch) => table.append(events(&batch)),
+                // left unsorted, as a query sorting by something else returns them
+                Op::SetData(batch) => table.set_data(events(&batch)),
+                Op::AppendAgain => {
+                    let len = table.len();
+                    table.append(table.events().collect());
+                    prop_assert_eq!(table.len(), len);
+                }
+                Op::SortByTime => table.sort_by_time(),
+                Op::Filter(index) => {
+                    let source = FILTERS[index];
+                    filter = Some(source);
+                    let pattern = Pattern::parse(source).unwrap();
+                    table.set_filter(source.to_string(), Arc::new(pattern));
+                }
+                Op::ClearFilter => {
+                    filter = None;
+                    table.clear_filter();
+                }
+                Op::Clear => {
+                    filter = None;
+                    table.clear();
+                }
+                Op::Render(width, height) => {
+                    let shown = table.selected_position().is_some();
+                    let drawn = shows_selection(&table, width, height);
+                    prop_assert_eq!(drawn, shown && width > 0 && height > 0);
+                }
+            }
+
+            // however the rows are ordered, lookups rely on this
+            prop_assert!(table.events().is_sorted_by_key(|event| event.timestamp));
+            let shown = table
+                .events()
+                .filter(|event| filter.is_none_or(|filter| event.message.contains(filter)))
+                .count();
+            prop_assert_eq!(table.selected_position().is_some(), shown > 0);
+            if let Some(label) = table.position_label() {
+                let (line, total) = label
+                    .trim_start_matches("line ")
+                    .split_once(" (")
+                    .and_then(|(position, _)| position.split_once(" / "))
+                    .unwrap();
+                let line: usize = line.replace(',', "").parse().unwrap();
+                let total: usize = total.replace(',', "").parse().unwrap();
+                prop_assert_eq!(total, shown);
+                prop_assert!((1..=total).contains(&line));
+            }
+        }
+    }
+}